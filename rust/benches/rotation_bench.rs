@@ -1,32 +1,13 @@
 use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
-use rotate_cli::{rotate_right, square_len};
+use rotate_cli::fixtures::{Dataset, Pattern, matrix};
+use rotate_cli::json::JsonStyle;
+use rotate_cli::processor::RotateRight;
+use rotate_cli::{
+    RotateOptions, RotationPlan, RowBuffers, RowProcessor, Strategy, json, parse,
+    process_json_array, process_json_bytes, rotate_right, rotate_right_with, square_len,
+};
 use std::hint::black_box as std_black_box;
 
-/// Generate test data for different matrix sizes and patterns
-fn generate_matrix_data(n: usize, pattern: &str) -> Vec<i32> {
-    let size = n * n;
-    match pattern {
-        "sequential" => (1..=size as i32).collect(),
-        "random" => {
-            use std::collections::hash_map::DefaultHasher;
-            use std::hash::{Hash, Hasher};
-            (0..size)
-                .map(|i| {
-                    let mut hasher = DefaultHasher::new();
-                    i.hash(&mut hasher);
-                    (hasher.finish() % 10000) as i32
-                })
-                .collect()
-        }
-        "repeated" => vec![42; size],
-        "negative" => (1..=size as i32).map(|x| -x).collect(),
-        "mixed" => (1..=size as i32)
-            .map(|x| if x % 2 == 0 { x } else { -x })
-            .collect(),
-        _ => (1..=size as i32).collect(),
-    }
-}
-
 /// Benchmark core rotation algorithm with different matrix sizes
 fn bench_rotation_sizes(c: &mut Criterion) {
     let mut group = c.benchmark_group("rotation_by_size");
@@ -34,7 +15,7 @@ fn bench_rotation_sizes(c: &mut Criterion) {
     // Test different matrix sizes
     for &n in &[1, 2, 3, 4, 5, 8, 10, 16, 25, 50, 100] {
         let size = n * n;
-        let data = generate_matrix_data(n, "sequential");
+        let data = matrix(n, Pattern::Sequential);
 
         group.throughput(Throughput::Elements(size as u64));
         group.bench_with_input(
@@ -58,12 +39,12 @@ fn bench_rotation_patterns(c: &mut Criterion) {
     let n = 10; // 10x10 matrix
     let size = n * n;
 
-    for pattern in &["sequential", "random", "repeated", "negative", "mixed"] {
-        let data = generate_matrix_data(n, pattern);
+    for pattern in Pattern::ALL {
+        let data = matrix(n, pattern);
 
         group.throughput(Throughput::Elements(size as u64));
         group.bench_with_input(
-            BenchmarkId::new("rotate_pattern", pattern),
+            BenchmarkId::new("rotate_pattern", pattern.name()),
             &data,
             |b, input| {
                 b.iter(|| {
@@ -106,7 +87,7 @@ fn bench_square_len(c: &mut Criterion) {
 fn bench_multiple_rotations(c: &mut Criterion) {
     let mut group = c.benchmark_group("multiple_rotations");
     let n = 10;
-    let data = generate_matrix_data(n, "sequential");
+    let data = matrix(n, Pattern::Sequential);
 
     for &rotations in &[1, 4, 8, 16, 32] {
         group.bench_with_input(
@@ -180,13 +161,173 @@ fn bench_csv_processing(c: &mut Criterion) {
     group.finish();
 }
 
+/// Read and rotate a whole CSV file: the pipeline's reused `ByteRecord`, which only decodes
+/// the json field, against a `StringRecord` per row, which validates every field as UTF-8
+fn bench_csv_reading(c: &mut Criterion) {
+    use csv::{ByteRecord, ReaderBuilder, StringRecord};
+
+    let mut group = c.benchmark_group("csv_reading");
+    let mut input = Vec::new();
+    Dataset::default().write_csv(&mut input).unwrap();
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    group.bench_function("string_record", |b| {
+        let mut buffers = RowBuffers::new();
+        b.iter(|| {
+            let mut rdr = ReaderBuilder::new().from_reader(black_box(input.as_slice()));
+            for record in rdr.records() {
+                let record: StringRecord = record.unwrap();
+                let valid = process_json_array(&record[1], JsonStyle::Compact, &mut buffers);
+                std_black_box((&record[0], valid, &buffers.json));
+            }
+        });
+    });
+
+    group.bench_function("byte_record", |b| {
+        let mut buffers = RowBuffers::new();
+        let mut record = ByteRecord::new();
+        b.iter(|| {
+            let mut rdr = ReaderBuilder::new().from_reader(black_box(input.as_slice()));
+            while rdr.read_byte_record(&mut record).unwrap() {
+                let valid = process_json_bytes(&record[1], JsonStyle::Compact, &mut buffers);
+                std_black_box((&record[0], valid, &buffers.json));
+            }
+        });
+    });
+    group.finish();
+}
+
+/// Benchmark per-row output serialization: serde_json String vs itoa into a reused buffer
+fn bench_json_serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("json_serialization");
+
+    for &n in &[3, 10, 50, 100] {
+        let data: Vec<i64> = (1..=(n * n) as i64).map(|x| x * 7919 - 500_000).collect();
+
+        group.throughput(Throughput::Elements((n * n) as u64));
+        group.bench_with_input(
+            BenchmarkId::new("serde_json_to_string", format!("{}x{}", n, n)),
+            &data,
+            |b, input| {
+                b.iter(|| {
+                    let result = serde_json::to_string(black_box(input)).unwrap();
+                    std_black_box(result);
+                });
+            },
+        );
+
+        let mut buffer = Vec::new();
+        group.bench_with_input(
+            BenchmarkId::new("itoa_reused_buffer", format!("{}x{}", n, n)),
+            &data,
+            |b, input| {
+                b.iter(|| {
+                    buffer.clear();
+                    json::write_array(&mut buffer, black_box(input));
+                    std_black_box(&buffer);
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+/// The output array of one table, one `itoa` call per element against blocks of elements
+/// with the small magnitudes copied from a table, for the values of `matrix` and for
+/// large ones
+fn bench_number_formatting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("number_formatting");
+
+    for &n in &[10, 100] {
+        let sequential: Vec<i64> = matrix(n, Pattern::Sequential)
+            .into_iter()
+            .map(i64::from)
+            .collect();
+        let large: Vec<i64> = (1..=(n * n) as i64).map(|x| x * 7919 - 500_000).collect();
+        group.throughput(Throughput::Elements((n * n) as u64));
+        for (values, data) in [("sequential", &sequential), ("large", &large)] {
+            let mut buffer = Vec::new();
+            let id = format!("{n}x{n}/{values}");
+            group.bench_with_input(BenchmarkId::new("itoa", &id), data, |b, input| {
+                b.iter(|| {
+                    buffer.clear();
+                    json::write_array(&mut buffer, black_box(input));
+                    std_black_box(&buffer);
+                });
+            });
+            group.bench_with_input(BenchmarkId::new("batched", &id), data, |b, input| {
+                b.iter(|| {
+                    buffer.clear();
+                    json::write_array_batched(&mut buffer, black_box(input), b",");
+                    std_black_box(&buffer);
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Whole output rows through a csv writer: the pipeline's reused buffer handed to
+/// `write_field`, against a `serde_json::to_string` String per row
+fn bench_csv_writing(c: &mut Criterion) {
+    use csv::{Writer, WriterBuilder};
+
+    const ROWS: usize = 100;
+    let mut group = c.benchmark_group("csv_writing");
+    let writer = || WriterBuilder::new().from_writer(Vec::with_capacity(1 << 20));
+    let mut id = itoa::Buffer::new();
+    let mut finish = |wtr: &mut Writer<Vec<u8>>, row: usize, json: &[u8]| {
+        wtr.write_field(id.format(row)).unwrap();
+        wtr.write_field(json).unwrap();
+        wtr.write_field("true").unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
+    };
+
+    for &n in &[10, 100] {
+        let data: Vec<i64> = (1..=(n * n) as i64).map(|x| x * 7919 - 500_000).collect();
+        group.throughput(Throughput::Elements((ROWS * n * n) as u64));
+        group.bench_with_input(
+            BenchmarkId::new("serde_json_to_string", format!("{}x{}", n, n)),
+            &data,
+            |b, input| {
+                b.iter(|| {
+                    let mut wtr = writer();
+                    for row in 0..ROWS {
+                        let json = serde_json::to_string(black_box(input)).unwrap();
+                        finish(&mut wtr, row, json.as_bytes());
+                    }
+                    std_black_box(wtr.into_inner().unwrap());
+                });
+            },
+        );
+
+        let mut buffer = Vec::new();
+        group.bench_with_input(
+            BenchmarkId::new("buffer_write_field", format!("{}x{}", n, n)),
+            &data,
+            |b, input| {
+                b.iter(|| {
+                    let mut wtr = writer();
+                    for row in 0..ROWS {
+                        buffer.clear();
+                        json::write_array_styled(&mut buffer, black_box(input), JsonStyle::Compact);
+                        finish(&mut wtr, row, &buffer);
+                    }
+                    std_black_box(wtr.into_inner().unwrap());
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
 /// Benchmark memory allocation patterns
 fn bench_memory_patterns(c: &mut Criterion) {
     let mut group = c.benchmark_group("memory_allocation");
 
     // Test in-place vs cloning approaches
     let n = 20;
-    let data = generate_matrix_data(n, "sequential");
+    let data = matrix(n, Pattern::Sequential);
 
     group.bench_function("in_place_rotation", |b| {
         b.iter(|| {
@@ -250,7 +391,7 @@ fn bench_scaling(c: &mut Criterion) {
     ];
 
     for (n, label) in sizes {
-        let data = generate_matrix_data(n, "sequential");
+        let data = matrix(n, Pattern::Sequential);
         let elements = (n * n) as u64;
 
         group.throughput(Throughput::Elements(elements));
@@ -265,13 +406,166 @@ fn bench_scaling(c: &mut Criterion) {
     group.finish();
 }
 
+/// Compare the layer walk with the alternative strategies behind `RotateOptions::strategy`
+fn bench_rotation_strategies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rotation_strategies");
+
+    for &n in &[2, 4, 10, 25, 100, 500] {
+        let data = matrix(n, Pattern::Sequential);
+        group.throughput(Throughput::Elements((n * n) as u64));
+        for strategy in Strategy::ALL {
+            let options = RotateOptions { strategy };
+            group.bench_with_input(
+                BenchmarkId::new(format!("{strategy:?}"), format!("{}x{}", n, n)),
+                &data,
+                |b, input| {
+                    let mut data = input.clone();
+                    b.iter(|| {
+                        rotate_right_with(black_box(&mut data), options).unwrap();
+                    });
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+/// Many tables of one size rotated by `RotationPlan`, planned once, against calling
+/// `rotate_right` once per step on each table
+fn bench_rotation_plan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rotation_plan");
+    const TABLES: usize = 1000;
+
+    for &n in &[4, 10, 25] {
+        let tables = vec![matrix(n, Pattern::Sequential); TABLES];
+        group.throughput(Throughput::Elements((TABLES * n * n) as u64));
+        for steps in [1, 3] {
+            let id = format!("{n}x{n}/{steps}_steps");
+            group.bench_with_input(
+                BenchmarkId::new("rotate_right", &id),
+                &tables,
+                |b, input| {
+                    let mut tables = input.clone();
+                    b.iter(|| {
+                        for table in &mut tables {
+                            for _ in 0..steps {
+                                rotate_right(black_box(table)).unwrap();
+                            }
+                        }
+                    });
+                },
+            );
+            group.bench_with_input(BenchmarkId::new("plan", &id), &tables, |b, input| {
+                let plan = RotationPlan::new(n, steps);
+                let mut tables = input.clone();
+                b.iter(|| {
+                    for table in &mut tables {
+                        plan.apply(black_box(table)).unwrap();
+                    }
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Compare the `json` column parsers on the same mix of rows
+fn bench_row_parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("row_parsing");
+    // Shaped like `input-samples/large.csv`: tables from 1x1 to 50x50, one in ten invalid
+    let rows: Vec<String> = Dataset::default().rows().map(|row| row.json).collect();
+    let bytes: usize = rows.iter().map(String::len).sum();
+    group.throughput(Throughput::Bytes(bytes as u64));
+
+    group.bench_function("serde_json", |b| {
+        let mut numbers = Vec::new();
+        b.iter(|| {
+            for row in &rows {
+                std_black_box(parse::parse_numbers(black_box(row), &mut numbers));
+            }
+        });
+    });
+
+    group.bench_function("fast", |b| {
+        let mut numbers = Vec::new();
+        b.iter(|| {
+            for row in &rows {
+                let parsed =
+                    parse::parse_numbers_fast(black_box(row), &mut numbers, Default::default());
+                std_black_box(parsed.is_ok());
+            }
+        });
+    });
+
+    #[cfg(feature = "simd-json")]
+    group.bench_function("simd_json", |b| {
+        let mut parser = parse::SimdParser::new();
+        let mut numbers = Vec::new();
+        b.iter(|| {
+            for row in &rows {
+                std_black_box(parser.parse_numbers(black_box(row), &mut numbers));
+            }
+        });
+    });
+    group.finish();
+}
+
+/// Per-row cost of a `--script` transform on top of the plain rotation
+fn bench_script(c: &mut Criterion) {
+    let mut group = c.benchmark_group("script");
+    let rows: Vec<String> = Dataset::default().rows().map(|row| row.json).collect();
+    group.throughput(Throughput::Elements(rows.len() as u64));
+
+    let mut bench = |name: &str, processor: &dyn RowProcessor| {
+        group.bench_function(name, |b| {
+            let mut buffers = RowBuffers::new();
+            b.iter(|| {
+                for row in &rows {
+                    std_black_box(processor.process(
+                        black_box(row.as_bytes()),
+                        JsonStyle::Compact,
+                        &mut buffers,
+                    ));
+                }
+            });
+        });
+    };
+    bench("rotate", &RotateRight);
+
+    #[cfg(feature = "script")]
+    {
+        use rotate_cli::script::ScriptProcessor;
+
+        let identity = ScriptProcessor::compile("fn transform(table, n) { table }").unwrap();
+        bench("rhai_identity", &identity);
+        let scale_and_mask = ScriptProcessor::compile(
+            "fn transform(table, n) {
+                let out = table.map(|x| x * 10);
+                for i in 0..n { out[i * n + i] = 0; }
+                out
+            }",
+        )
+        .unwrap();
+        bench("rhai_scale_and_mask", &scale_and_mask);
+    }
+    group.finish();
+}
+
 criterion_group!(
     rotation_benches,
     bench_rotation_sizes,
     bench_rotation_patterns,
+    bench_rotation_strategies,
+    bench_rotation_plan,
     bench_square_len,
     bench_multiple_rotations,
     bench_csv_processing,
+    bench_csv_reading,
+    bench_json_serialization,
+    bench_number_formatting,
+    bench_csv_writing,
+    bench_row_parsing,
+    bench_script,
     bench_memory_patterns,
     bench_edge_cases,
     bench_scaling
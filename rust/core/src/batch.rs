@@ -0,0 +1,60 @@
+//! Rotation of many independent tables in one call.
+//!
+//! With the `rayon` feature the tables are spread over rayon's global thread pool; without
+//! it they are rotated one after the other, so `no_std` and WASM builds get the same API.
+
+use crate::{RotationError, rotate_right};
+use alloc::vec::Vec;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Rotates every table in `tables` one step clockwise, as [`rotate_right`] does, and
+/// returns one result per table, in order.
+///
+/// A table that is empty or not square is left as it is and gets its error; the others are
+/// rotated regardless. Small batches are not worth the threads: the parallel version pays
+/// off once a batch holds a few thousand cells in total.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::{RotationError, rotate_batch};
+///
+/// let mut tables = vec![vec![1, 2, 3, 4], vec![1, 2, 3], vec![5]];
+/// let results = rotate_batch(&mut tables);
+/// assert!(results[0].is_ok() && results[2].is_ok());
+/// assert!(matches!(results[1], Err(RotationError::NotSquare)));
+/// assert_eq!(tables, vec![vec![3, 1, 4, 2], vec![1, 2, 3], vec![5]]);
+/// ```
+pub fn rotate_batch<T: Copy + Send>(tables: &mut [Vec<T>]) -> Vec<Result<(), RotationError>> {
+    #[cfg(feature = "rayon")]
+    let tables = tables.par_iter_mut();
+    #[cfg(not(feature = "rayon"))]
+    let tables = tables.iter_mut();
+    tables.map(|table| rotate_right(table)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec;
+
+    #[test]
+    fn test_batch_matches_rotate_right() {
+        let mut tables: Vec<Vec<u32>> = (0..200).map(|len| (0..len).collect()).collect();
+        let results = rotate_batch(&mut tables);
+        assert_eq!(results.len(), 200);
+        for (len, (table, result)) in tables.iter().zip(&results).enumerate() {
+            let mut expected: Vec<u32> = (0..len as u32).collect();
+            let expected_result = rotate_right(&mut expected);
+            assert_eq!(table, &expected, "table of {len}");
+            assert_eq!(result.is_ok(), expected_result.is_ok(), "table of {len}");
+        }
+        assert!(rotate_batch::<i32>(&mut []).is_empty());
+        assert!(matches!(
+            rotate_batch::<i32>(&mut [vec![]])[..],
+            [Err(RotationError::Empty)]
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+//! Ring shift within square blocks of a table, for maps edited chunk by chunk.
+
+use crate::{Grid, RotationError, check_side, rotate_grid};
+
+/// `side × side` block of a row-major table of side `n`, with its top-left cell at
+/// (`row`, `col`).
+struct Block<'a, T> {
+    data: &'a mut [T],
+    n: usize,
+    row: usize,
+    col: usize,
+    side: usize,
+}
+
+impl<T: Copy> Grid for Block<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn side(&self) -> usize {
+        self.side
+    }
+
+    #[inline]
+    fn get(&self, row: usize, col: usize) -> T {
+        self.data[(self.row + row) * self.n + self.col + col]
+    }
+
+    #[inline]
+    fn set(&mut self, row: usize, col: usize, value: T) {
+        self.data[(self.row + row) * self.n + self.col + col] = value;
+    }
+}
+
+/// Splits the `n`×`n` table in `data` into `block_size`×`block_size` blocks and shifts the
+/// rings of each block one step clockwise, as [`rotate_right`](crate::rotate_right) does for
+/// the whole table. Returns the number of blocks rotated.
+///
+/// Blocks are laid out from the top-left corner; when `block_size` does not divide `n`, the
+/// partial blocks along the right and bottom edges are left untouched, as by
+/// `image::apply_tiled`. A `block_size` of 0 rotates nothing.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::rotate_blocks;
+///
+/// let mut data = vec![
+///      1,  2,  3,  4,
+///      5,  6,  7,  8,
+///      9, 10, 11, 12,
+///     13, 14, 15, 16,
+/// ];
+/// assert_eq!(rotate_blocks(&mut data, 4, 2).unwrap(), 4);
+/// assert_eq!(data, vec![
+///      5,  1,  7,  3,
+///      6,  2,  8,  4,
+///     13,  9, 15, 11,
+///     14, 10, 16, 12,
+/// ]);
+/// ```
+///
+/// # Errors
+///
+/// [`RotationError::Empty`] for an empty table, and [`RotationError::NotSquare`] if
+/// `data.len()` is not `n * n`.
+pub fn rotate_blocks<T: Copy>(
+    data: &mut [T],
+    n: usize,
+    block_size: usize,
+) -> Result<usize, RotationError> {
+    check_side(data, n)?;
+    if block_size == 0 {
+        return Ok(0);
+    }
+    let blocks = n / block_size;
+    for row in 0..blocks {
+        for col in 0..blocks {
+            rotate_grid(&mut Block {
+                data: &mut *data,
+                n,
+                row: row * block_size,
+                col: col * block_size,
+                side: block_size,
+            });
+        }
+    }
+    Ok(blocks * blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rotate_right;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_blocks_match_rotate_right() {
+        let (n, size) = (7, 3);
+        let mut data: Vec<i32> = (0..49).collect();
+        assert_eq!(rotate_blocks(&mut data, n, size).unwrap(), 4);
+        for row in 0..n {
+            for col in 0..n {
+                let cell = data[row * n + col];
+                if row >= 6 || col >= 6 {
+                    assert_eq!(cell, (row * n + col) as i32, "edge cell ({row}, {col})");
+                    continue;
+                }
+                // Each block, cut out and rotated on its own
+                let (top, left) = (row / size * size, col / size * size);
+                let mut block: Vec<i32> = (0..size * size)
+                    .map(|i| ((top + i / size) * n + left + i % size) as i32)
+                    .collect();
+                rotate_right(&mut block).unwrap();
+                assert_eq!(cell, block[(row - top) * size + col - left]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_sizes() {
+        let table: Vec<i32> = (0..16).collect();
+        let mut whole = table.clone();
+        rotate_right(&mut whole).unwrap();
+        let mut data = table.clone();
+        assert_eq!(rotate_blocks(&mut data, 4, 4).unwrap(), 1);
+        assert_eq!(data, whole);
+
+        for size in [0, 1, 5] {
+            let mut data = table.clone();
+            rotate_blocks(&mut data, 4, size).unwrap();
+            assert_eq!(data, table, "blocks of {size}");
+        }
+        assert!(matches!(
+            rotate_blocks(&mut [1, 2, 3], 2, 1),
+            Err(RotationError::NotSquare)
+        ));
+    }
+}
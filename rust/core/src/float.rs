@@ -0,0 +1,215 @@
+//! Floating-point tables.
+//!
+//! The rotation only moves values, so `f32` and `f64` tables rotate like any other: every
+//! bit pattern, NaN payloads and `-0.0` included, comes out unchanged. What needs care is
+//! comparing them, since `NaN != NaN` and `0.1 + 0.2 != 0.3`:
+//!
+//! - [`Total`] orders floats with `total_cmp`, so NaN equals itself and float tables work
+//!   with the checks that need `Eq` or `Ord` ([`testkit`](crate::testkit),
+//!   [`rotation_distance`](crate::rotation_distance), the symmetry tests)
+//! - [`approx_eq`] compares two tables within a [`Tolerance`], as `rotate_cli compare` does
+//!   for result files
+
+use core::cmp::Ordering;
+
+/// A float compared by [`total_cmp`](f64::total_cmp): NaN equals NaN with the same bits and
+/// sorts after every number, and `-0.0` sorts before `0.0`.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::{Total, rotation_distance};
+///
+/// let from = [1.0, f64::NAN, 3.0, 4.0];
+/// let to = [3.0, 1.0, 4.0, f64::NAN];
+/// assert_eq!(rotation_distance(&from, &to), None); // NaN != NaN
+/// assert_eq!(rotation_distance(Total::slice(&from), Total::slice(&to)), Some(1));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(transparent)]
+pub struct Total<T>(pub T);
+
+impl<T> Total<T> {
+    /// Views a float table as [`Total`] values, without copying.
+    pub fn slice(values: &[T]) -> &[Total<T>] {
+        // SAFETY: Total is repr(transparent) over T
+        unsafe { &*(values as *const [T] as *const [Total<T>]) }
+    }
+
+    /// Views a mutable float table as [`Total`] values, without copying.
+    pub fn slice_mut(values: &mut [T]) -> &mut [Total<T>] {
+        // SAFETY: Total is repr(transparent) over T
+        unsafe { &mut *(values as *mut [T] as *mut [Total<T>]) }
+    }
+}
+
+macro_rules! total {
+    ($float:ty) => {
+        impl PartialEq for Total<$float> {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+
+        impl Eq for Total<$float> {}
+
+        impl PartialOrd for Total<$float> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Total<$float> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+    };
+}
+
+total!(f32);
+total!(f64);
+
+/// When two numbers count as equal: within `absolute` of each other, or within `relative`
+/// times the larger magnitude. The default is exact equality.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Tolerance {
+    pub absolute: f64,
+    pub relative: f64,
+}
+
+impl Tolerance {
+    /// Whether `a` and `b` are equal within the tolerance. Two NaNs are equal, and an
+    /// infinity only equals the same infinity, whatever the tolerance.
+    pub fn approx_eq(self, a: f64, b: f64) -> bool {
+        if a.is_nan() || b.is_nan() {
+            return a.is_nan() && b.is_nan();
+        }
+        if a.is_infinite() || b.is_infinite() {
+            return a == b;
+        }
+        let allowed = self.absolute.max(self.relative * a.abs().max(b.abs()));
+        a == b || (a - b).abs() <= allowed
+    }
+}
+
+/// Whether two tables have the same length and are equal element by element within
+/// `tolerance` (see [`Tolerance::approx_eq`]).
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::{Tolerance, approx_eq};
+///
+/// let tolerance = Tolerance { absolute: 1e-9, relative: 0.0 };
+/// assert!(approx_eq(&[0.1 + 0.2, f64::NAN], &[0.3, f64::NAN], tolerance));
+/// assert!(!approx_eq(&[0.1 + 0.2], &[0.3], Tolerance::default()));
+/// assert!(approx_eq(&[1.5f32], &[1.5f32], Tolerance::default()));
+/// ```
+pub fn approx_eq<T: Copy + Into<f64>>(a: &[T], b: &[T], tolerance: Tolerance) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(&x, &y)| tolerance.approx_eq(x.into(), y.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Matrix, Rotatable, is_ring_constant, rotate_left, rotate_right, testkit};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn bits(values: &[f64]) -> Vec<u64> {
+        values.iter().map(|x| x.to_bits()).collect()
+    }
+
+    #[test]
+    fn test_float_rotation_keeps_bits() {
+        let nan = f64::from_bits(0x7ff8_dead_beef_0001);
+        let table = vec![
+            nan,
+            -0.0,
+            1.5,
+            f64::INFINITY,
+            -f64::NAN,
+            0.0,
+            f64::MIN,
+            1e-310,
+            2.0,
+        ];
+        let mut data = table.clone();
+        rotate_right(&mut data).unwrap();
+        assert_eq!(
+            bits(&data),
+            bits(&[
+                f64::INFINITY,
+                nan,
+                -0.0,
+                f64::MIN,
+                -f64::NAN,
+                1.5,
+                1e-310,
+                2.0,
+                0.0
+            ])
+        );
+        rotate_left(&mut data).unwrap();
+        assert_eq!(bits(&data), bits(&table));
+
+        let mut m = Matrix::from_vec(vec![1.0f32, f32::NAN, -0.0, 4.0]).unwrap();
+        m.rotate_right().unwrap();
+        assert_eq!(m.as_slice()[0].to_bits(), (-0.0f32).to_bits());
+        assert!(m.as_slice()[3].is_nan());
+    }
+
+    #[test]
+    fn test_total_with_testkit() {
+        let table = [
+            f64::NAN,
+            0.5,
+            -0.0,
+            0.0,
+            f64::NEG_INFINITY,
+            3.0,
+            3.0,
+            f64::NAN,
+            1.0,
+        ];
+        let table = Total::slice(&table);
+        testkit::check_rotate_right(table, |data| rotate_right(data).unwrap()).unwrap();
+        testkit::check_ring_period(table, |data| rotate_right(data).unwrap()).unwrap();
+
+        assert_eq!(Total(f64::NAN), Total(f64::NAN));
+        assert!(Total(-0.0f32) < Total(0.0f32));
+        assert!(Total(f64::INFINITY) < Total(f64::NAN));
+        assert!(is_ring_constant(Total::slice(&[f64::NAN; 4])).unwrap());
+
+        let mut data = [1.0, 2.0, 3.0, 4.0];
+        rotate_right(Total::slice_mut(&mut data)).unwrap();
+        assert_eq!(data, [3.0, 1.0, 4.0, 2.0]);
+    }
+
+    #[test]
+    fn test_tolerance() {
+        let exact = Tolerance::default();
+        assert!(exact.approx_eq(1.0, 1.0));
+        assert!(exact.approx_eq(-0.0, 0.0));
+        assert!(!exact.approx_eq(1.0, 1.0 + f64::EPSILON));
+        assert!(exact.approx_eq(f64::NAN, -f64::NAN));
+        assert!(!exact.approx_eq(f64::NAN, 0.0));
+
+        let loose = Tolerance {
+            absolute: 0.5,
+            relative: 0.01,
+        };
+        assert!(loose.approx_eq(1.0, 1.4));
+        assert!(loose.approx_eq(1000.0, 1009.0));
+        assert!(!loose.approx_eq(1000.0, 1011.0));
+        assert!(!loose.approx_eq(f64::INFINITY, f64::MAX));
+        assert!(!loose.approx_eq(f64::INFINITY, f64::NEG_INFINITY));
+
+        assert!(!approx_eq(&[1.0], &[1.0, 2.0], loose));
+    }
+}
@@ -0,0 +1,210 @@
+//! [`image`](::image) integration (`image` feature).
+//!
+//! A square `ImageBuffer` is a table of pixels, so [`Rotatable`] and [`Transform`] apply to
+//! it directly; the ring shift moves every pixel one step clockwise around its ring, which
+//! makes the algorithm easy to verify by eye. [`apply_tiled`] treats a picture of any shape
+//! as a grid of square tiles and transforms each tile on its own.
+
+use crate::matrix::{flip_horizontal_grid, flip_vertical_grid, square_side, transpose_grid};
+use crate::{Grid, Rotatable, RotationError, Transform, rotate_grid, rotate_grid_left};
+use ::image::{ImageBuffer, Pixel};
+use core::ops::{Deref, DerefMut};
+
+/// Square region of an image buffer viewed as a grid of pixels.
+struct Region<'a, P: Pixel, C> {
+    image: &'a mut ImageBuffer<P, C>,
+    x: u32,
+    y: u32,
+    n: usize,
+}
+
+impl<P, C> Grid for Region<'_, P, C>
+where
+    P: Pixel + Copy,
+    C: Deref<Target = [P::Subpixel]> + DerefMut,
+{
+    type Item = P;
+
+    #[inline]
+    fn side(&self) -> usize {
+        self.n
+    }
+
+    #[inline]
+    fn get(&self, row: usize, col: usize) -> P {
+        *self
+            .image
+            .get_pixel(self.x + col as u32, self.y + row as u32)
+    }
+
+    #[inline]
+    fn set(&mut self, row: usize, col: usize, value: P) {
+        self.image
+            .put_pixel(self.x + col as u32, self.y + row as u32, value);
+    }
+}
+
+impl<P, C> Rotatable for Region<'_, P, C>
+where
+    P: Pixel + Copy,
+    C: Deref<Target = [P::Subpixel]> + DerefMut,
+{
+    fn rotate_right(&mut self) -> Result<(), RotationError> {
+        rotate_grid(self);
+        Ok(())
+    }
+
+    fn rotate_left(&mut self) -> Result<(), RotationError> {
+        rotate_grid_left(self);
+        Ok(())
+    }
+
+    fn transpose(&mut self) -> Result<(), RotationError> {
+        transpose_grid(self);
+        Ok(())
+    }
+
+    fn flip_horizontal(&mut self) -> Result<(), RotationError> {
+        flip_horizontal_grid(self);
+        Ok(())
+    }
+
+    fn flip_vertical(&mut self) -> Result<(), RotationError> {
+        flip_vertical_grid(self);
+        Ok(())
+    }
+}
+
+/// The whole image as one table; fails unless it is square.
+fn whole<P, C>(image: &mut ImageBuffer<P, C>) -> Result<Region<'_, P, C>, RotationError>
+where
+    P: Pixel,
+    C: Deref<Target = [P::Subpixel]>,
+{
+    let n = square_side(image.height() as usize, image.width() as usize)?;
+    Ok(Region {
+        image,
+        x: 0,
+        y: 0,
+        n,
+    })
+}
+
+/// Implemented for square images of any pixel type, e.g. `RgbaImage` or `GrayImage`.
+impl<P, C> Rotatable for ImageBuffer<P, C>
+where
+    P: Pixel + Copy,
+    C: Deref<Target = [P::Subpixel]> + DerefMut,
+{
+    fn rotate_right(&mut self) -> Result<(), RotationError> {
+        whole(self)?.rotate_right()
+    }
+
+    fn rotate_left(&mut self) -> Result<(), RotationError> {
+        whole(self)?.rotate_left()
+    }
+
+    fn transpose(&mut self) -> Result<(), RotationError> {
+        whole(self)?.transpose()
+    }
+
+    fn flip_horizontal(&mut self) -> Result<(), RotationError> {
+        whole(self)?.flip_horizontal()
+    }
+
+    fn flip_vertical(&mut self) -> Result<(), RotationError> {
+        whole(self)?.flip_vertical()
+    }
+}
+
+/// Applies `transforms` in order to every full `tile × tile` square of the image.
+///
+/// Tiles are laid out from the top-left corner; a partial row or column of tiles at the
+/// right or bottom edge is left untouched. Returns the number of tiles transformed.
+///
+/// # Examples
+///
+/// ```
+/// use image::{GrayImage, Luma};
+/// use rotate_core::Transform;
+/// use rotate_core::image::apply_tiled;
+///
+/// let mut img = GrayImage::from_fn(4, 2, |x, _| Luma([x as u8]));
+/// assert_eq!(apply_tiled(&mut img, 2, &[Transform::FlipHorizontal]), 2);
+/// assert_eq!(img.get_pixel(0, 0), &Luma([1]));
+/// assert_eq!(img.get_pixel(2, 0), &Luma([3]));
+/// ```
+pub fn apply_tiled<P, C>(
+    image: &mut ImageBuffer<P, C>,
+    tile: u32,
+    transforms: &[Transform],
+) -> usize
+where
+    P: Pixel + Copy,
+    C: Deref<Target = [P::Subpixel]> + DerefMut,
+{
+    if tile == 0 {
+        return 0;
+    }
+    let (cols, rows) = (image.width() / tile, image.height() / tile);
+    for ty in 0..rows {
+        for tx in 0..cols {
+            let mut region = Region {
+                image: &mut *image,
+                x: tx * tile,
+                y: ty * tile,
+                n: tile as usize,
+            };
+            for transform in transforms {
+                // Regions are square by construction, so transforms cannot fail
+                let _ = transform.apply(&mut region);
+            }
+        }
+    }
+    (cols * rows) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    use ::image::{GrayImage, Luma};
+
+    fn gray(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| Luma([(y * width + x) as u8]))
+    }
+
+    fn pixels(image: &GrayImage) -> Vec<u8> {
+        image.pixels().map(|p| p.0[0]).collect()
+    }
+
+    #[test]
+    fn test_matches_flat_functions() {
+        for t in Transform::ALL {
+            let mut image = gray(4, 4);
+            t.apply(&mut image).unwrap();
+
+            let mut expected: Vec<u8> = (0..16).collect();
+            t.apply(expected.as_mut_slice()).unwrap();
+            assert_eq!(pixels(&image), expected, "{t}");
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_square() {
+        let mut image = gray(3, 2);
+        assert!(matches!(
+            image.rotate_right(),
+            Err(RotationError::NotSquare)
+        ));
+    }
+
+    #[test]
+    fn test_tiles_skip_partial_edges() {
+        let mut image = gray(5, 2);
+        assert_eq!(apply_tiled(&mut image, 2, &[Transform::RotateRight]), 2);
+        // 2×2 tiles [0,1,5,6] and [2,3,7,8] rotate; column 4 is a partial tile
+        assert_eq!(pixels(&image), [5, 0, 7, 2, 4, 6, 1, 8, 3, 9]);
+    }
+}
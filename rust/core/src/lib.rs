@@ -0,0 +1,791 @@
+//! Rotation of square tables stored as flat, row-major arrays: every element moves one
+//! position clockwise around its ring.
+//!
+//! This is the core of `rotate_cli` without any of its I/O: no CSV, JSON or command-line
+//! dependencies, and `#![no_std]` (it only needs `alloc`), so it fits lean services,
+//! embedded targets and WASM. The optional `ndarray`, `nalgebra` and `image` features add
+//! [`Rotatable`] for those crates' types, and `rayon` runs [`rotate_batch`] in parallel.
+
+#![no_std]
+
+extern crate alloc;
+#[cfg(any(test, feature = "ndarray", feature = "nalgebra", feature = "image"))]
+extern crate std;
+
+use core::error::Error;
+use core::fmt;
+
+pub use batch::rotate_batch;
+pub use blocks::rotate_blocks;
+pub use float::{Tolerance, Total, approx_eq};
+pub use matrix::{Matrix, Rotatable};
+pub use plan::{RotationPlan, rotation_permutation};
+pub use rings::{extract_ring, is_rotation_of, ring_map, rotation_distance, write_ring};
+pub use roll::{roll_cols, roll_rows};
+pub use strategy::{RotateOptions, Strategy, rotate_right_with};
+pub use symmetry::{is_ring_constant, is_rotationally_symmetric, is_symmetric};
+pub use transform::Transform;
+
+mod batch;
+mod blocks;
+mod float;
+#[cfg(feature = "image")]
+pub mod image;
+mod matrix;
+#[cfg(feature = "nalgebra")]
+pub mod nalgebra;
+#[cfg(feature = "ndarray")]
+pub mod ndarray;
+mod plan;
+mod rings;
+mod roll;
+mod strategy;
+mod symmetry;
+pub mod testkit;
+mod transform;
+
+/// Custom error type for rotation operations.
+#[derive(Debug)]
+pub enum RotationError {
+    NotSquare,
+    Empty,
+}
+
+impl fmt::Display for RotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotationError::NotSquare => write!(f, "Array length is not a perfect square"),
+            RotationError::Empty => write!(f, "Array is empty"),
+        }
+    }
+}
+
+impl Error for RotationError {}
+
+/// Returns `Some(n)` if `len` is a perfect square (n × n), else `None`.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::square_len;
+///
+/// assert_eq!(square_len(4), Some(2));
+/// assert_eq!(square_len(9), Some(3));
+/// assert_eq!(square_len(5), None);
+/// ```
+pub fn square_len(len: usize) -> Option<usize> {
+    if len == 0 {
+        return Some(0);
+    }
+
+    let n = len.isqrt();
+    if n * n == len { Some(n) } else { None }
+}
+
+/// Why an array of `len` elements cannot hold a table, with the nearest lengths that can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShapeError {
+    pub len: usize,
+    /// Side of the largest non-empty table shorter than `len`; 0 if there is none
+    pub smaller: usize,
+    /// Side of the smallest table longer than `len`
+    pub larger: usize,
+}
+
+impl ShapeError {
+    /// Elements to remove for a `smaller`×`smaller` table; `None` for an empty array.
+    pub fn to_remove(&self) -> Option<usize> {
+        (self.smaller > 0).then(|| self.len - self.smaller * self.smaller)
+    }
+
+    /// Elements to add for a `larger`×`larger` table.
+    pub fn to_add(&self) -> usize {
+        // (s + 1)² - len without overflow, as len - s² is at most 2s
+        2 * self.smaller + 1 - (self.len - self.smaller * self.smaller)
+    }
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (add, larger) = (self.to_add(), self.larger);
+        match self.to_remove() {
+            None => write!(f, "empty: add {add} for {larger}×{larger}"),
+            Some(remove) => {
+                let (len, smaller) = (self.len, self.smaller);
+                write!(
+                    f,
+                    "{len} elements: remove {remove} for {smaller}×{smaller} or add {add} for \
+                     {larger}×{larger}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for ShapeError {}
+
+impl From<ShapeError> for RotationError {
+    fn from(error: ShapeError) -> Self {
+        match error.len {
+            0 => RotationError::Empty,
+            _ => RotationError::NotSquare,
+        }
+    }
+}
+
+/// The side of the table an array of `len` elements holds, or what it would take to hold
+/// one: the nearest smaller and larger tables and how many elements each is away.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::validate_table;
+///
+/// assert_eq!(validate_table(9), Ok(3));
+///
+/// let error = validate_table(15).unwrap_err();
+/// assert_eq!((error.smaller, error.to_remove(), error.larger, error.to_add()), (3, Some(6), 4, 1));
+/// assert_eq!(error.to_string(), "15 elements: remove 6 for 3×3 or add 1 for 4×4");
+/// assert_eq!(validate_table(0).unwrap_err().to_string(), "empty: add 1 for 1×1");
+/// ```
+pub fn validate_table(len: usize) -> Result<usize, ShapeError> {
+    let n = len.isqrt();
+    if len > 0 && n * n == len {
+        return Ok(n);
+    }
+    Err(ShapeError {
+        len,
+        smaller: n,
+        larger: n + 1,
+    })
+}
+
+/// Checks that `data` holds a non-empty `n`×`n` table, for functions that take the side.
+pub(crate) fn check_side<T>(data: &[T], n: usize) -> Result<(), RotationError> {
+    if n == 0 || data.is_empty() {
+        return Err(RotationError::Empty);
+    }
+    if n.checked_mul(n) != Some(data.len()) {
+        return Err(RotationError::NotSquare);
+    }
+    Ok(())
+}
+
+/// Rotates an N×N matrix by shifting each element one position clockwise around its ring.
+///
+/// This uses the canonical "layer walk" algorithm that processes each concentric ring
+/// from outside to inside. Each ring is rotated by walking clockwise:
+/// top row → right column → bottom row → left column
+///
+/// The input array represents a square table read row-by-row:
+/// - `[40, 20, 90, 10]` represents a 2×2 table: `[[40, 20], [90, 10]]`
+/// - After one-step clockwise shift: `[[90, 40], [10, 20]]` → `[90, 40, 10, 20]`
+///
+/// # Complexity
+/// - Time: O(N²) - touches each element exactly once
+/// - Space: O(1) - uses only two temporary variables
+///
+/// # Arguments
+///
+/// * `data` - Mutable slice containing the table elements
+///
+/// # Returns
+///
+/// * `Ok(())` - Success
+/// * `Err(RotationError)` - If the array is empty or not a perfect square
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::rotate_right;
+///
+/// let mut data = vec![40, 20, 90, 10];
+/// rotate_right(&mut data).unwrap();
+/// assert_eq!(data, vec![90, 40, 10, 20]);
+/// ```
+pub fn rotate_right<T: Copy>(data: &mut [T]) -> Result<(), RotationError> {
+    let len = data.len();
+
+    if len == 0 {
+        return Err(RotationError::Empty);
+    }
+
+    let n = square_len(len).ok_or(RotationError::NotSquare)?;
+
+    rotate_grid(&mut Flat { data, n });
+    Ok(())
+}
+
+/// [`rotate_right`] for a table the caller has already validated, without the length checks.
+///
+/// Cells are accessed without bounds checks. Debug builds still assert every index against
+/// `n`, so a bug in the index math fails the test suite instead of corrupting memory.
+///
+/// # Safety
+///
+/// `data.len()` must equal `n * n`.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::{rotate_right_unchecked, square_len};
+///
+/// let mut data = vec![40, 20, 90, 10];
+/// let n = square_len(data.len()).unwrap();
+/// // SAFETY: n * n == data.len() by definition of square_len
+/// unsafe { rotate_right_unchecked(&mut data, n) };
+/// assert_eq!(data, vec![90, 40, 10, 20]);
+/// ```
+pub unsafe fn rotate_right_unchecked<T: Copy>(data: &mut [T], n: usize) {
+    debug_assert_eq!(
+        n.checked_mul(n),
+        Some(data.len()),
+        "a {n}×{n} table needs {} elements",
+        n.saturating_mul(n)
+    );
+    rotate_grid(&mut Unchecked { data, n });
+}
+
+/// Shifts each element one position counter-clockwise around its ring, undoing
+/// [`rotate_right`].
+///
+/// Same validation and complexity as [`rotate_right`].
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::rotate_left;
+///
+/// let mut data = vec![90, 40, 10, 20];
+/// rotate_left(&mut data).unwrap();
+/// assert_eq!(data, vec![40, 20, 90, 10]);
+/// ```
+pub fn rotate_left<T: Copy>(data: &mut [T]) -> Result<(), RotationError> {
+    if data.is_empty() {
+        return Err(RotationError::Empty);
+    }
+    let n = square_len(data.len()).ok_or(RotationError::NotSquare)?;
+
+    rotate_grid_left(&mut Flat { data, n });
+    Ok(())
+}
+
+/// Number of cells in ring `layer` (0 is the outermost) of an n × n table: `4(m - 1)` for
+/// a ring of side `m`, and 1 for the center of an odd table. Rotating a ring that many times
+/// restores it.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::ring_len;
+///
+/// assert_eq!(ring_len(4, 0), 12);
+/// assert_eq!(ring_len(4, 1), 4);
+/// assert_eq!(ring_len(5, 2), 1);
+/// ```
+///
+/// # Panics
+///
+/// If `layer` is not a ring of the table (`2 * layer >= n`).
+pub fn ring_len(n: usize, layer: usize) -> usize {
+    assert!(2 * layer < n, "table of side {n} has no ring {layer}");
+    let side = n - 2 * layer;
+    if side == 1 { 1 } else { 4 * (side - 1) }
+}
+
+/// Checks that [`rotate_right`] brings every ring of `data` back after `ring_len(n, layer)`
+/// steps, leaving `data` itself untouched.
+///
+/// This is a cheap end-to-end canary for a rotation backend: it needs no reference output,
+/// only the table, and costs about `4 * n` rotations. `rotate_cli --self-check` runs it on
+/// a sample of rows; [`testkit::check_ring_period`] runs the same check against any other
+/// rotation function.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::verify_rotation_identity;
+///
+/// assert!(verify_rotation_identity(&[1, 2, 3, 4, 5, 6, 7, 8, 9]).is_ok());
+/// assert!(verify_rotation_identity(&[1, 2, 3]).is_err());
+/// ```
+pub fn verify_rotation_identity<T>(data: &[T]) -> Result<(), testkit::Violation>
+where
+    T: Copy + PartialEq + fmt::Debug,
+{
+    testkit::check_ring_period(data, |table| {
+        rotate_right(table).expect("check_ring_period only passes square tables")
+    })
+}
+
+/// Cell access to an n × n table, independent of its memory layout.
+///
+/// The ring walk and the other in-place transforms are written against this trait, so
+/// flat slices, [`Matrix`] and strided views from other crates share one implementation.
+pub(crate) trait Grid {
+    type Item: Copy;
+
+    /// Side length `n`.
+    fn side(&self) -> usize;
+    fn get(&self, row: usize, col: usize) -> Self::Item;
+    fn set(&mut self, row: usize, col: usize, value: Self::Item);
+}
+
+/// Row-major flat slice viewed as a grid.
+pub(crate) struct Flat<'a, T> {
+    pub(crate) data: &'a mut [T],
+    pub(crate) n: usize,
+}
+
+impl<T: Copy> Grid for Flat<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn side(&self) -> usize {
+        self.n
+    }
+
+    #[inline]
+    fn get(&self, row: usize, col: usize) -> T {
+        self.data[idx(self.n, row, col)]
+    }
+
+    #[inline]
+    fn set(&mut self, row: usize, col: usize, value: T) {
+        self.data[idx(self.n, row, col)] = value;
+    }
+}
+
+/// Row-major flat slice of exactly `n * n` cells, accessed without bounds checks.
+///
+/// Only constructed by [`rotate_right_unchecked`], whose caller guarantees the length.
+struct Unchecked<'a, T> {
+    data: &'a mut [T],
+    n: usize,
+}
+
+impl<T: Copy> Grid for Unchecked<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn side(&self) -> usize {
+        self.n
+    }
+
+    #[inline]
+    fn get(&self, row: usize, col: usize) -> T {
+        let i = idx(self.n, row, col);
+        debug_assert!(i < self.data.len());
+        // SAFETY: row, col < n and data.len() == n * n, so i < data.len()
+        unsafe { *self.data.get_unchecked(i) }
+    }
+
+    #[inline]
+    fn set(&mut self, row: usize, col: usize, value: T) {
+        let i = idx(self.n, row, col);
+        debug_assert!(i < self.data.len());
+        // SAFETY: as in `get`
+        unsafe { *self.data.get_unchecked_mut(i) = value };
+    }
+}
+
+/// Rotates every ring of a grid one position clockwise.
+pub(crate) fn rotate_grid<G: Grid>(grid: &mut G) {
+    let n = grid.side();
+
+    // Handle trivial cases
+    if n <= 1 {
+        return;
+    }
+
+    // Process each concentric ring from outside to inside
+    for layer in 0..n / 2 {
+        rotate_ring_clockwise(grid, n, layer);
+    }
+}
+
+/// Rotates every ring of a grid one position counter-clockwise.
+pub(crate) fn rotate_grid_left<G: Grid>(grid: &mut G) {
+    let n = grid.side();
+    if n <= 1 {
+        return;
+    }
+    for layer in 0..n / 2 {
+        rotate_ring_counterclockwise(grid, n, layer);
+    }
+}
+
+/// Rotates a single ring of the matrix one position clockwise using in-place swaps.
+///
+/// This is the core of the canonical layer-walk algorithm. It walks around the ring
+/// in clockwise order, swapping elements with a temporary variable.
+fn rotate_ring_clockwise<G: Grid>(grid: &mut G, n: usize, layer: usize) {
+    debug_assert!(
+        layer < n / 2,
+        "a table of side {n} has no ring {layer} to walk"
+    );
+    let first = layer;
+    let last = n - 1 - layer;
+
+    // Save the element that will be overwritten first (top-left of the ring)
+    let mut prev = grid.get(first + 1, first); // Element below top-left
+
+    // Top row: left → right
+    for col in first..=last {
+        let temp = grid.get(first, col);
+        grid.set(first, col, prev);
+        prev = temp;
+    }
+
+    // Right column: top+1 → bottom
+    for row in (first + 1)..=last {
+        let temp = grid.get(row, last);
+        grid.set(row, last, prev);
+        prev = temp;
+    }
+
+    // Bottom row: right-1 → left
+    for col in (first..last).rev() {
+        let temp = grid.get(last, col);
+        grid.set(last, col, prev);
+        prev = temp;
+    }
+
+    // Left column: bottom-1 → top+1
+    for row in ((first + 1)..last).rev() {
+        let temp = grid.get(row, first);
+        grid.set(row, first, prev);
+        prev = temp;
+    }
+}
+
+/// Mirror image of [`rotate_ring_clockwise`]: walks the ring counter-clockwise.
+fn rotate_ring_counterclockwise<G: Grid>(grid: &mut G, n: usize, layer: usize) {
+    debug_assert!(
+        layer < n / 2,
+        "a table of side {n} has no ring {layer} to walk"
+    );
+    let first = layer;
+    let last = n - 1 - layer;
+
+    // Element right of top-left, which moves into the corner
+    let mut prev = grid.get(first, first + 1);
+
+    // Left column: top → bottom
+    for row in first..=last {
+        let temp = grid.get(row, first);
+        grid.set(row, first, prev);
+        prev = temp;
+    }
+
+    // Bottom row: left+1 → right
+    for col in (first + 1)..=last {
+        let temp = grid.get(last, col);
+        grid.set(last, col, prev);
+        prev = temp;
+    }
+
+    // Right column: bottom-1 → top
+    for row in (first..last).rev() {
+        let temp = grid.get(row, last);
+        grid.set(row, last, prev);
+        prev = temp;
+    }
+
+    // Top row: right-1 → left+1
+    for col in ((first + 1)..last).rev() {
+        let temp = grid.get(first, col);
+        grid.set(first, col, prev);
+        prev = temp;
+    }
+}
+
+/// Converts 2D table coordinates (row, col) to 1D array index.
+///
+/// For an N×N table stored row-by-row in a flat array:
+/// `index = row * n + col`
+///
+/// Debug builds check that the cell lies inside the table and that the index does not
+/// overflow, which the unchecked grid relies on.
+#[inline]
+fn idx(n: usize, row: usize, col: usize) -> usize {
+    debug_assert!(
+        row < n && col < n,
+        "cell ({row}, {col}) is outside a {n}×{n} table"
+    );
+    debug_assert!(
+        row.checked_mul(n)
+            .and_then(|i| i.checked_add(col))
+            .is_some()
+    );
+    row * n + col
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_square_len() {
+        assert_eq!(square_len(0), Some(0));
+        assert_eq!(square_len(1), Some(1));
+        assert_eq!(square_len(4), Some(2));
+        assert_eq!(square_len(9), Some(3));
+        assert_eq!(square_len(16), Some(4));
+        assert_eq!(square_len(25), Some(5));
+
+        // Non-perfect squares
+        assert_eq!(square_len(2), None);
+        assert_eq!(square_len(3), None);
+        assert_eq!(square_len(5), None);
+        assert_eq!(square_len(8), None);
+        assert_eq!(square_len(10), None);
+    }
+
+    #[test]
+    fn test_validate_table() {
+        for len in 0..1000 {
+            match validate_table(len) {
+                Ok(n) => assert_eq!(square_len(len), Some(n)),
+                Err(error) => {
+                    assert!(len == 0 || square_len(len).is_none());
+                    let remove = error.to_remove().unwrap_or(len);
+                    assert_eq!(len - remove, error.smaller * error.smaller);
+                    assert_eq!(len + error.to_add(), error.larger * error.larger);
+                    assert_eq!(error.larger, error.smaller + 1);
+                }
+            }
+        }
+        let error = validate_table(usize::MAX).unwrap_err();
+        assert_eq!(error.to_add(), 1);
+        assert!(matches!(
+            RotationError::from(validate_table(0).unwrap_err()),
+            RotationError::Empty
+        ));
+    }
+
+    #[test]
+    fn test_rotate_1x1_stays_same() {
+        // Original: [42]  →  After: [42]
+        // Single element matrices don't change
+        // Expected: vec![42]
+        let mut data = vec![42];
+        rotate_right(&mut data).unwrap();
+        assert_eq!(data, vec![42]);
+    }
+
+    #[test]
+    fn test_rotate_2x2() {
+        // Original:      After 1-step clockwise:
+        // [1, 2]     →   [3, 1]
+        // [3, 4]         [4, 2]
+        //
+        // Ring walk: 1→2→4→3 becomes 3→1→2→4
+        // Expected: vec![3, 1, 4, 2]
+        let mut data = vec![1, 2, 3, 4];
+        rotate_right(&mut data).unwrap();
+        assert_eq!(data, vec![3, 1, 4, 2]);
+    }
+
+    #[test]
+    fn test_rotate_3x3() {
+        // Original:        After 1-step clockwise:
+        // [1, 2, 3]    →   [4, 1, 2]
+        // [4, 5, 6]        [7, 5, 3]
+        // [7, 8, 9]        [8, 9, 6]
+        //
+        // Outer ring: 1→2→3→6→9→8→7→4 becomes 4→1→2→3→6→9→8→7
+        // Center: 5 stays 5 (unchanged)
+        // Expected: vec![4, 1, 2, 7, 5, 3, 8, 9, 6]
+        let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+        rotate_right(&mut data).unwrap();
+        assert_eq!(data, vec![4, 1, 2, 7, 5, 3, 8, 9, 6]);
+    }
+
+    #[test]
+    fn test_rotate_4x4() {
+        // Original:              After 1-step clockwise:
+        // [ 1,  2,  3,  4]   →   [ 5,  1,  2,  3]
+        // [ 5,  6,  7,  8]       [ 9, 10,  6,  4]
+        // [ 9, 10, 11, 12]       [13, 11,  7,  8]
+        // [13, 14, 15, 16]       [14, 15, 16, 12]
+        //
+        // Outer ring: 1→2→3→4→8→12→16→15→14→13→9→5 becomes 5→1→2→3→4→8→12→16→15→14→13→9
+        // Inner ring: 6→7→11→10 becomes 10→6→7→11
+        // Expected: vec![5, 1, 2, 3, 9, 10, 6, 4, 13, 11, 7, 8, 14, 15, 16, 12]
+        let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        rotate_right(&mut data).unwrap();
+        let expected = vec![5, 1, 2, 3, 9, 10, 6, 4, 13, 11, 7, 8, 14, 15, 16, 12];
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_rotate_empty_array() {
+        let mut data: Vec<i32> = vec![];
+        assert!(matches!(rotate_right(&mut data), Err(RotationError::Empty)));
+    }
+
+    #[test]
+    fn test_rotate_non_square() {
+        let mut data = vec![1, 2, 3]; // Length 3 is not a perfect square
+        assert!(matches!(
+            rotate_right(&mut data),
+            Err(RotationError::NotSquare)
+        ));
+    }
+
+    #[test]
+    fn test_rotate_with_negatives() {
+        // Original:         After 1-step clockwise:
+        // [-1, -2]      →   [-3, -1]
+        // [-3, -4]          [-4, -2]
+        //
+        // Ring: -1→-2→-4→-3 becomes -3→-1→-2→-4
+        // Expected: vec![-3, -1, -4, -2]
+        let mut data = vec![-1, -2, -3, -4];
+        rotate_right(&mut data).unwrap();
+        assert_eq!(data, vec![-3, -1, -4, -2]);
+    }
+
+    #[test]
+    fn test_rotate_with_zeros() {
+        let mut data = vec![0, 0, 0, 0];
+        rotate_right(&mut data).unwrap();
+        assert_eq!(data, vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_rotate_ring_size_times_identity() {
+        // For a 3x3, the outer ring has 8 elements, so rotating 8 times should return to original
+        let original = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let mut data = original.clone();
+
+        // Rotate 8 times (size of outer ring) should return to original
+        for _ in 0..8 {
+            rotate_right(&mut data).unwrap();
+        }
+
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn test_rotate_5x5() {
+        // Original:                    After 1-step clockwise:
+        // [ 1,  2,  3,  4,  5]     →   [ 6,  1,  2,  3,  4]
+        // [ 6,  7,  8,  9, 10]         [11, 12,  7,  8,  5]
+        // [11, 12, 13, 14, 15]         [16, 17, 13,  9, 10]
+        // [16, 17, 18, 19, 20]         [21, 18, 19, 14, 15]
+        // [21, 22, 23, 24, 25]         [22, 23, 24, 25, 20]
+        //
+        // Outer ring: 1→2→3→4→5→10→15→20→25→24→23→22→21→16→11→6 becomes 6→1→2→3→4→5→10→15→20→25→24→23→22→21→16→11
+        // Inner ring: 7→8→9→14→19→18→17→12 becomes 12→7→8→9→14→19→18→17
+        // Center: 13 stays 13 (unchanged)
+        // Expected: vec![6, 1, 2, 3, 4, 11, 12, 7, 8, 5, 16, 17, 13, 9, 10, 21, 18, 19, 14, 15, 22, 23, 24, 25, 20]
+        let mut data = (1..=25).collect::<Vec<_>>();
+        rotate_right(&mut data).unwrap();
+        let expected = vec![
+            6, 1, 2, 3, 4, 11, 12, 7, 8, 5, 16, 17, 13, 9, 10, 21, 18, 19, 14, 15, 22, 23, 24, 25,
+            20,
+        ];
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_rotate_10x10() {
+        // Original 10×10 (1-100):           After 1-step clockwise:
+        // [ 1  2  3  4  5  6  7  8  9 10]   [11  1  2  3  4  5  6  7  8  9]
+        // [11 12 13 14 15 16 17 18 19 20]   [21 22 12 13 14 15 16 17 18 10]
+        // [21 22 23 24 25 26 27 28 29 30] → [31 32 33 23 24 25 26 27 19 20]
+        // [31 32 33 34 35 36 37 38 39 40]   [41 42 43 44 34 35 36 28 29 30]
+        // [41 42 43 44 45 46 47 48 49 50]   [51 52 53 54 55 45 37 38 39 40]
+        // [51 52 53 54 55 56 57 58 59 60]   [61 62 63 64 56 46 47 48 49 50]
+        // [61 62 63 64 65 66 67 68 69 70]   [71 72 73 65 66 67 57 58 59 60]
+        // [71 72 73 74 75 76 77 78 79 80]   [81 82 74 75 76 77 78 68 69 70]
+        // [81 82 83 84 85 86 87 88 89 90]   [91 83 84 85 86 87 88 89 79 80]
+        // [91 92 93 94 95 96 97 98 99100]   [92 93 94 95 96 97 98 99100 90]
+        //
+        // Four concentric rings all shift one position clockwise:
+        // Ring 0 (outer): 1→2→...→10→20→...→100→99→...→91→81→...→11 becomes 11→1→...→9→10→...→90→100→...→92→91→...→21
+        // Ring 1: 12→13→...→19→29→...→99→98→...→92→82→...→22 becomes 22→12→...→18→19→...→89→99→...→93→92→...→32
+        // Ring 2: 23→24→...→28→38→...→98→97→...→93→83→...→33 becomes 33→23→...→27→28→...→88→98→...→94→93→...→43
+        // Ring 3 (inner): 34→35→36→37→47→57→67→76→75→74→64→54→44→45→46→56→66→65→55 becomes 44→34→35→36→37→47→57→67→76→75→74→64→54→55→56→66→65→45→46
+        //
+        // Since 10 is even, no center element stays fixed - all elements move
+        // Expected: vec![11,1,2,3,4,5,6,7,8,9, 21,22,12,13,14,15,16,17,18,10, ...]
+        let mut data = (1..=100).collect::<Vec<u32>>();
+        rotate_right(&mut data).unwrap();
+        let expected = vec![
+            11, 1, 2, 3, 4, 5, 6, 7, 8, 9, 21, 22, 12, 13, 14, 15, 16, 17, 18, 10, 31, 32, 33, 23,
+            24, 25, 26, 27, 19, 20, 41, 42, 43, 44, 34, 35, 36, 28, 29, 30, 51, 52, 53, 54, 55, 45,
+            37, 38, 39, 40, 61, 62, 63, 64, 56, 46, 47, 48, 49, 50, 71, 72, 73, 65, 66, 67, 57, 58,
+            59, 60, 81, 82, 74, 75, 76, 77, 78, 68, 69, 70, 91, 83, 84, 85, 86, 87, 88, 89, 79, 80,
+            92, 93, 94, 95, 96, 97, 98, 99, 100, 90,
+        ];
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_rotate_left_undoes_rotate_right() {
+        // [1, 2]  →  [2, 4]
+        // [3, 4]     [1, 3]
+        let mut data = vec![1, 2, 3, 4];
+        rotate_left(&mut data).unwrap();
+        assert_eq!(data, vec![2, 4, 1, 3]);
+
+        for n in 1..=7 {
+            let original: Vec<u32> = (0..n * n).collect();
+            let mut data = original.clone();
+            rotate_right(&mut data).unwrap();
+            rotate_left(&mut data).unwrap();
+            assert_eq!(data, original, "{n}x{n}");
+        }
+        assert!(matches!(
+            rotate_left::<i32>(&mut []),
+            Err(RotationError::Empty)
+        ));
+        assert!(matches!(
+            rotate_left(&mut [1, 2]),
+            Err(RotationError::NotSquare)
+        ));
+    }
+
+    #[test]
+    fn test_ring_len() {
+        assert_eq!(ring_len(1, 0), 1);
+        assert_eq!(ring_len(2, 0), 4);
+        assert_eq!(ring_len(10, 4), 4);
+        let total: usize = (0..=3).map(|layer| ring_len(7, layer)).sum();
+        assert_eq!(total, 49);
+    }
+
+    #[test]
+    fn test_rotate_right_unchecked_agrees() {
+        for n in 0..=10 {
+            let mut checked: Vec<i32> = (0..(n * n) as i32).collect();
+            let mut unchecked = checked.clone();
+            if n > 0 {
+                rotate_right(&mut checked).unwrap();
+            }
+            // SAFETY: the table has n * n elements
+            unsafe { rotate_right_unchecked(&mut unchecked, n) };
+            assert_eq!(unchecked, checked, "n = {n}");
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "needs 9 elements")]
+    fn test_rotate_right_unchecked_asserts_length() {
+        // SAFETY: not upheld on purpose; the debug assertion stops it before any access
+        unsafe { rotate_right_unchecked(&mut [1, 2, 3, 4], 3) };
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "cell (2, 0) is outside a 2×2 table")]
+    fn test_idx_asserts_bounds() {
+        idx(2, 2, 0);
+    }
+}
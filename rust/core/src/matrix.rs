@@ -0,0 +1,288 @@
+//! Owned square matrices and the [`Rotatable`] operations.
+//!
+//! [`Matrix`] is a validated n × n table stored row by row, so it converts to and from the
+//! flat arrays the CSV format uses without copying. [`Rotatable`] is implemented for it and,
+//! behind the matching features, for matrix types of other crates.
+
+use crate::{Flat, Grid, RotationError, rotate_grid, rotate_grid_left, square_len};
+use alloc::vec::Vec;
+use core::ops::{Index, IndexMut};
+
+/// In-place transforms of a square table.
+///
+/// Every operation fails with [`RotationError::Empty`] or [`RotationError::NotSquare`] if the
+/// value is not a non-empty square; types that are square by construction never fail.
+pub trait Rotatable {
+    /// Shifts every element one position clockwise around its ring.
+    fn rotate_right(&mut self) -> Result<(), RotationError>;
+
+    /// Shifts every element one position counter-clockwise, undoing
+    /// [`rotate_right`](Self::rotate_right).
+    fn rotate_left(&mut self) -> Result<(), RotationError>;
+
+    /// Mirrors the table across its main diagonal.
+    fn transpose(&mut self) -> Result<(), RotationError>;
+
+    /// Mirrors the table left to right.
+    fn flip_horizontal(&mut self) -> Result<(), RotationError>;
+
+    /// Mirrors the table top to bottom.
+    fn flip_vertical(&mut self) -> Result<(), RotationError>;
+}
+
+/// Non-empty n × n matrix stored in row-major order.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::{Matrix, Rotatable};
+///
+/// let mut m = Matrix::from_vec(vec![1, 2, 3, 4]).unwrap();
+/// m.rotate_right().unwrap();
+/// assert_eq!(m[(0, 0)], 3);
+/// assert_eq!(m.into_vec(), vec![3, 1, 4, 2]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Matrix<T> {
+    n: usize,
+    data: Vec<T>,
+}
+
+impl<T> Matrix<T> {
+    /// Wraps a flat row-major vector whose length is a non-zero perfect square.
+    pub fn from_vec(data: Vec<T>) -> Result<Self, RotationError> {
+        if data.is_empty() {
+            return Err(RotationError::Empty);
+        }
+        let n = square_len(data.len()).ok_or(RotationError::NotSquare)?;
+        Ok(Self { n, data })
+    }
+
+    /// Side length.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Elements in row-major order.
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    /// Mutable elements in row-major order.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+
+    /// Returns the flat row-major vector.
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
+    /// Element at `(row, col)`, or `None` if out of bounds.
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        (row < self.n && col < self.n).then(|| &self.data[row * self.n + col])
+    }
+
+    /// Iterates over the rows as slices.
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.data.chunks(self.n)
+    }
+}
+
+impl<T> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        self.get(row, col).expect("matrix index out of bounds")
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        assert!(row < self.n && col < self.n, "matrix index out of bounds");
+        &mut self.data[row * self.n + col]
+    }
+}
+
+impl<T> TryFrom<Vec<T>> for Matrix<T> {
+    type Error = RotationError;
+
+    fn try_from(data: Vec<T>) -> Result<Self, RotationError> {
+        Self::from_vec(data)
+    }
+}
+
+impl<T> From<Matrix<T>> for Vec<T> {
+    fn from(matrix: Matrix<T>) -> Self {
+        matrix.data
+    }
+}
+
+impl<T: Copy> Rotatable for Matrix<T> {
+    fn rotate_right(&mut self) -> Result<(), RotationError> {
+        rotate_grid(&mut self.grid());
+        Ok(())
+    }
+
+    fn rotate_left(&mut self) -> Result<(), RotationError> {
+        rotate_grid_left(&mut self.grid());
+        Ok(())
+    }
+
+    fn transpose(&mut self) -> Result<(), RotationError> {
+        transpose_grid(&mut self.grid());
+        Ok(())
+    }
+
+    fn flip_horizontal(&mut self) -> Result<(), RotationError> {
+        flip_horizontal_grid(&mut self.grid());
+        Ok(())
+    }
+
+    fn flip_vertical(&mut self) -> Result<(), RotationError> {
+        flip_vertical_grid(&mut self.grid());
+        Ok(())
+    }
+}
+
+impl<T: Copy> Matrix<T> {
+    fn grid(&mut self) -> Flat<'_, T> {
+        Flat {
+            data: &mut self.data,
+            n: self.n,
+        }
+    }
+}
+
+/// Flat row-major tables, validated on every call like [`rotate_right`](crate::rotate_right).
+///
+/// Slices have an inherent `rotate_right(k)`, so call these through the trait
+/// (`Rotatable::rotate_right(data)`) or a [`Transform`](crate::Transform).
+impl<T: Copy> Rotatable for [T] {
+    fn rotate_right(&mut self) -> Result<(), RotationError> {
+        crate::rotate_right(self)
+    }
+
+    fn rotate_left(&mut self) -> Result<(), RotationError> {
+        crate::rotate_left(self)
+    }
+
+    fn transpose(&mut self) -> Result<(), RotationError> {
+        transpose_grid(&mut flat(self)?);
+        Ok(())
+    }
+
+    fn flip_horizontal(&mut self) -> Result<(), RotationError> {
+        flip_horizontal_grid(&mut flat(self)?);
+        Ok(())
+    }
+
+    fn flip_vertical(&mut self) -> Result<(), RotationError> {
+        flip_vertical_grid(&mut flat(self)?);
+        Ok(())
+    }
+}
+
+fn flat<T>(data: &mut [T]) -> Result<Flat<'_, T>, RotationError> {
+    if data.is_empty() {
+        return Err(RotationError::Empty);
+    }
+    let n = square_len(data.len()).ok_or(RotationError::NotSquare)?;
+    Ok(Flat { data, n })
+}
+
+/// Checks that a `rows × cols` table is a non-empty square and returns its side.
+#[cfg(any(feature = "ndarray", feature = "nalgebra", feature = "image"))]
+pub(crate) fn square_side(rows: usize, cols: usize) -> Result<usize, RotationError> {
+    if rows == 0 || cols == 0 {
+        Err(RotationError::Empty)
+    } else if rows != cols {
+        Err(RotationError::NotSquare)
+    } else {
+        Ok(rows)
+    }
+}
+
+fn swap<G: Grid>(grid: &mut G, a: (usize, usize), b: (usize, usize)) {
+    let value = grid.get(a.0, a.1);
+    grid.set(a.0, a.1, grid.get(b.0, b.1));
+    grid.set(b.0, b.1, value);
+}
+
+pub(crate) fn transpose_grid<G: Grid>(grid: &mut G) {
+    let n = grid.side();
+    for row in 0..n {
+        for col in row + 1..n {
+            swap(grid, (row, col), (col, row));
+        }
+    }
+}
+
+pub(crate) fn flip_horizontal_grid<G: Grid>(grid: &mut G) {
+    let n = grid.side();
+    for row in 0..n {
+        for col in 0..n / 2 {
+            swap(grid, (row, col), (row, n - 1 - col));
+        }
+    }
+}
+
+pub(crate) fn flip_vertical_grid<G: Grid>(grid: &mut G) {
+    let n = grid.side();
+    for row in 0..n / 2 {
+        for col in 0..n {
+            swap(grid, (row, col), (n - 1 - row, col));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn matrix3() -> Matrix<i32> {
+        Matrix::from_vec((1..=9).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_from_vec_validates() {
+        assert!(matches!(
+            Matrix::<i32>::from_vec(vec![]),
+            Err(RotationError::Empty)
+        ));
+        assert!(matches!(
+            Matrix::from_vec(vec![1, 2, 3]),
+            Err(RotationError::NotSquare)
+        ));
+        let m = matrix3();
+        assert_eq!(m.n(), 3);
+        assert_eq!(m[(1, 2)], 6);
+        assert_eq!(m.get(3, 0), None);
+        assert_eq!(m.rows().nth(2), Some(&[7, 8, 9][..]));
+    }
+
+    #[test]
+    fn test_rotate_matches_slice_rotation() {
+        let mut m = matrix3();
+        m.rotate_right().unwrap();
+        let mut flat: Vec<i32> = (1..=9).collect();
+        crate::rotate_right(&mut flat).unwrap();
+        assert_eq!(m.into_vec(), flat);
+    }
+
+    #[test]
+    fn test_transpose_and_flips() {
+        let mut m = matrix3();
+        m.transpose().unwrap();
+        assert_eq!(m.as_slice(), [1, 4, 7, 2, 5, 8, 3, 6, 9]);
+
+        let mut m = matrix3();
+        m.flip_horizontal().unwrap();
+        assert_eq!(m.as_slice(), [3, 2, 1, 6, 5, 4, 9, 8, 7]);
+
+        let mut m = matrix3();
+        m.flip_vertical().unwrap();
+        assert_eq!(m.as_slice(), [7, 8, 9, 4, 5, 6, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,177 @@
+//! [`nalgebra`](::nalgebra) integration (`nalgebra` feature).
+//!
+//! [`Rotatable`] (and therefore [`Transform`](crate::Transform)) works on every nalgebra
+//! matrix with mutable storage: `DMatrix<T>`, fixed-size `SMatrix<T, N, N>` and views such
+//! as `m.view_mut((1, 1), (2, 2))`. Cells are addressed by `(row, col)`, so nalgebra's
+//! column-major layout is transparent and the result matches the flat row-major functions.
+//!
+//! nalgebra matrices have an inherent `transpose()` that returns a copy; use
+//! `Rotatable::transpose(&mut m)` or `Transform::Transpose` for the in-place version.
+
+use crate::matrix::{flip_horizontal_grid, flip_vertical_grid, square_side, transpose_grid};
+use crate::{Grid, Matrix, Rotatable, RotationError, rotate_grid, rotate_grid_left};
+use ::nalgebra::{DMatrix, Dim, RawStorage, RawStorageMut, Scalar};
+
+/// A square nalgebra matrix viewed as a grid.
+struct Cells<'a, T, R, C, S>(&'a mut ::nalgebra::Matrix<T, R, C, S>);
+
+impl<T, R, C, S> Grid for Cells<'_, T, R, C, S>
+where
+    T: Copy,
+    R: Dim,
+    C: Dim,
+    S: RawStorageMut<T, R, C>,
+{
+    type Item = T;
+
+    #[inline]
+    fn side(&self) -> usize {
+        self.0.nrows()
+    }
+
+    #[inline]
+    fn get(&self, row: usize, col: usize) -> T {
+        self.0[(row, col)]
+    }
+
+    #[inline]
+    fn set(&mut self, row: usize, col: usize, value: T) {
+        self.0[(row, col)] = value;
+    }
+}
+
+/// Wraps the matrix after checking that it is a non-empty square.
+fn cells<T, R, C, S>(
+    matrix: &mut ::nalgebra::Matrix<T, R, C, S>,
+) -> Result<Cells<'_, T, R, C, S>, RotationError>
+where
+    T: Copy,
+    R: Dim,
+    C: Dim,
+    S: RawStorageMut<T, R, C>,
+{
+    square_side(matrix.nrows(), matrix.ncols())?;
+    Ok(Cells(matrix))
+}
+
+/// Implemented for `DMatrix<T>`, `SMatrix<T, N, N>` and mutable views.
+///
+/// # Examples
+///
+/// ```
+/// use nalgebra::{DMatrix, Matrix3};
+/// use rotate_core::{Rotatable, Transform};
+///
+/// let mut m = Matrix3::new(1, 2, 3, 4, 5, 6, 7, 8, 9);
+/// m.rotate_right().unwrap();
+/// assert_eq!(m, Matrix3::new(4, 1, 2, 7, 5, 3, 8, 9, 6));
+///
+/// let mut d = DMatrix::from_row_slice(2, 2, &[1, 2, 3, 4]);
+/// Transform::Transpose.apply(&mut d).unwrap();
+/// assert_eq!(d, DMatrix::from_row_slice(2, 2, &[1, 3, 2, 4]));
+/// ```
+impl<T, R, C, S> Rotatable for ::nalgebra::Matrix<T, R, C, S>
+where
+    T: Copy,
+    R: Dim,
+    C: Dim,
+    S: RawStorageMut<T, R, C>,
+{
+    fn rotate_right(&mut self) -> Result<(), RotationError> {
+        rotate_grid(&mut cells(self)?);
+        Ok(())
+    }
+
+    fn rotate_left(&mut self) -> Result<(), RotationError> {
+        rotate_grid_left(&mut cells(self)?);
+        Ok(())
+    }
+
+    fn transpose(&mut self) -> Result<(), RotationError> {
+        transpose_grid(&mut cells(self)?);
+        Ok(())
+    }
+
+    fn flip_horizontal(&mut self) -> Result<(), RotationError> {
+        flip_horizontal_grid(&mut cells(self)?);
+        Ok(())
+    }
+
+    fn flip_vertical(&mut self) -> Result<(), RotationError> {
+        flip_vertical_grid(&mut cells(self)?);
+        Ok(())
+    }
+}
+
+impl<T: Scalar> From<Matrix<T>> for DMatrix<T> {
+    fn from(matrix: Matrix<T>) -> Self {
+        let n = matrix.n();
+        DMatrix::from_row_iterator(n, n, matrix.into_vec())
+    }
+}
+
+/// Copies any nalgebra matrix or view in row-major order.
+impl<T, R, C, S> TryFrom<&::nalgebra::Matrix<T, R, C, S>> for Matrix<T>
+where
+    T: Scalar,
+    R: Dim,
+    C: Dim,
+    S: RawStorage<T, R, C>,
+{
+    type Error = RotationError;
+
+    fn try_from(matrix: &::nalgebra::Matrix<T, R, C, S>) -> Result<Self, RotationError> {
+        let n = square_side(matrix.nrows(), matrix.ncols())?;
+        let data = (0..n)
+            .flat_map(|row| (0..n).map(move |col| matrix[(row, col)].clone()))
+            .collect();
+        Matrix::from_vec(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use crate::Transform;
+    use ::nalgebra::{SMatrix, dmatrix};
+
+    #[test]
+    fn test_matches_flat_functions() {
+        for t in Transform::ALL {
+            let flat: Vec<i32> = (1..=16).collect();
+            let mut expected = flat.clone();
+            t.apply(expected.as_mut_slice()).unwrap();
+
+            let mut d = DMatrix::from_row_slice(4, 4, &flat);
+            t.apply(&mut d).unwrap();
+            assert_eq!(Matrix::try_from(&d).unwrap().into_vec(), expected, "{t}");
+
+            let mut s = SMatrix::<i32, 4, 4>::from_row_slice(&flat);
+            t.apply(&mut s).unwrap();
+            assert_eq!(Matrix::try_from(&s).unwrap().into_vec(), expected, "{t}");
+        }
+    }
+
+    #[test]
+    fn test_views_and_conversions() {
+        let mut d = dmatrix![1, 2, 3; 4, 5, 6; 7, 8, 9];
+        d.view_mut((1, 1), (2, 2)).rotate_right().unwrap();
+        assert_eq!(d, dmatrix![1, 2, 3; 4, 8, 5; 7, 9, 6]);
+
+        let m = Matrix::from_vec(vec![1, 2, 3, 4]).unwrap();
+        let d = DMatrix::from(m.clone());
+        assert_eq!(d, dmatrix![1, 2; 3, 4]);
+        assert_eq!(Matrix::try_from(&d).unwrap(), m);
+    }
+
+    #[test]
+    fn test_rejects_non_square() {
+        let mut d = DMatrix::<i32>::zeros(2, 3);
+        assert!(matches!(d.rotate_right(), Err(RotationError::NotSquare)));
+        let mut d = DMatrix::<i32>::zeros(0, 0);
+        assert!(matches!(d.flip_vertical(), Err(RotationError::Empty)));
+    }
+}
@@ -0,0 +1,191 @@
+//! [`ndarray`](::ndarray) integration (`ndarray` feature).
+//!
+//! [`Rotatable`] works directly on any mutable 2-D array, including non-contiguous views
+//! such as `a.slice_mut(s![.., ..;2])` or transposed views: cells are addressed through the
+//! view's strides, so nothing is flattened or copied. [`Matrix`] converts to and from
+//! `Array2`.
+
+use crate::matrix::{flip_horizontal_grid, flip_vertical_grid, square_side, transpose_grid};
+use crate::{Grid, Matrix, Rotatable, RotationError, rotate_grid, rotate_grid_left};
+use ::ndarray::{Array2, ArrayBase, Data, DataMut, Ix2};
+
+/// A square 2-D array viewed as a grid.
+struct Strided<'a, S: DataMut>(&'a mut ArrayBase<S, Ix2>);
+
+impl<S> Grid for Strided<'_, S>
+where
+    S: DataMut,
+    S::Elem: Copy,
+{
+    type Item = S::Elem;
+
+    #[inline]
+    fn side(&self) -> usize {
+        self.0.nrows()
+    }
+
+    #[inline]
+    fn get(&self, row: usize, col: usize) -> S::Elem {
+        self.0[[row, col]]
+    }
+
+    #[inline]
+    fn set(&mut self, row: usize, col: usize, value: S::Elem) {
+        self.0[[row, col]] = value;
+    }
+}
+
+impl<S> Strided<'_, S>
+where
+    S: DataMut,
+    S::Elem: Copy,
+{
+    fn check(&self) -> Result<(), RotationError> {
+        square_side(self.0.nrows(), self.0.ncols()).map(drop)
+    }
+}
+
+/// Implemented for `Array2<T>`, `ArrayViewMut2<T>` and other mutable 2-D arrays.
+///
+/// # Examples
+///
+/// ```
+/// use ndarray::{array, s};
+/// use rotate_core::Rotatable;
+///
+/// let mut a = array![[1, 0, 2], [0, 0, 0], [3, 0, 4]];
+/// // Rotate the 2×2 table formed by every other row and column, in place
+/// a.slice_mut(s![..;2, ..;2]).rotate_right().unwrap();
+/// assert_eq!(a, array![[3, 0, 1], [0, 0, 0], [4, 0, 2]]);
+/// ```
+impl<S> Rotatable for ArrayBase<S, Ix2>
+where
+    S: DataMut,
+    S::Elem: Copy,
+{
+    fn rotate_right(&mut self) -> Result<(), RotationError> {
+        let mut grid = Strided(self);
+        grid.check()?;
+        rotate_grid(&mut grid);
+        Ok(())
+    }
+
+    fn rotate_left(&mut self) -> Result<(), RotationError> {
+        let mut grid = Strided(self);
+        grid.check()?;
+        rotate_grid_left(&mut grid);
+        Ok(())
+    }
+
+    fn transpose(&mut self) -> Result<(), RotationError> {
+        let mut grid = Strided(self);
+        grid.check()?;
+        transpose_grid(&mut grid);
+        Ok(())
+    }
+
+    fn flip_horizontal(&mut self) -> Result<(), RotationError> {
+        let mut grid = Strided(self);
+        grid.check()?;
+        flip_horizontal_grid(&mut grid);
+        Ok(())
+    }
+
+    fn flip_vertical(&mut self) -> Result<(), RotationError> {
+        let mut grid = Strided(self);
+        grid.check()?;
+        flip_vertical_grid(&mut grid);
+        Ok(())
+    }
+}
+
+impl<T> From<Matrix<T>> for Array2<T> {
+    fn from(matrix: Matrix<T>) -> Self {
+        let n = matrix.n();
+        Array2::from_shape_vec((n, n), matrix.into_vec()).expect("matrix is n × n")
+    }
+}
+
+/// Copies any 2-D array or view in logical row-major order, whatever its memory layout.
+impl<S, T> TryFrom<&ArrayBase<S, Ix2>> for Matrix<T>
+where
+    S: Data<Elem = T>,
+    T: Clone,
+{
+    type Error = RotationError;
+
+    fn try_from(array: &ArrayBase<S, Ix2>) -> Result<Self, RotationError> {
+        square_side(array.nrows(), array.ncols())?;
+        Matrix::from_vec(array.iter().cloned().collect())
+    }
+}
+
+impl<T: Clone> TryFrom<Array2<T>> for Matrix<T> {
+    type Error = RotationError;
+
+    fn try_from(array: Array2<T>) -> Result<Self, RotationError> {
+        Matrix::try_from(&array)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use ::ndarray::{Array, array, s};
+
+    #[test]
+    fn test_rotate_matches_flat_rotation() {
+        let mut a = Array::from_shape_vec((4, 4), (1..=16).collect()).unwrap();
+        a.rotate_right().unwrap();
+
+        let mut flat: Vec<i32> = (1..=16).collect();
+        crate::rotate_right(&mut flat).unwrap();
+        assert_eq!(a.iter().copied().collect::<Vec<_>>(), flat);
+    }
+
+    #[test]
+    fn test_respects_strides() {
+        // A transposed view rotates the logical (transposed) table
+        let mut a = array![[1, 2], [3, 4]];
+        a.view_mut().reversed_axes().rotate_right().unwrap();
+        // View [[1, 3], [2, 4]] rotates to [[2, 1], [4, 3]]; stored transposed back
+        assert_eq!(a, array![[2, 4], [1, 3]]);
+
+        let mut a = Array::from_shape_vec((3, 6), (0..18).collect()).unwrap();
+        a.slice_mut(s![.., ..;2]).flip_vertical().unwrap();
+        assert_eq!(a.row(0).to_vec(), [12, 1, 14, 3, 16, 5]);
+        assert_eq!(a.row(2).to_vec(), [0, 13, 2, 15, 4, 17]);
+    }
+
+    #[test]
+    fn test_transforms_match_matrix() {
+        let m = Matrix::from_vec((1..=9).collect::<Vec<i32>>()).unwrap();
+
+        let mut expected = m.clone();
+        expected.transpose().unwrap();
+        let mut a = Array2::from(m.clone());
+        a.transpose().unwrap();
+        assert_eq!(Matrix::try_from(a).unwrap(), expected);
+
+        let mut expected = m.clone();
+        expected.flip_horizontal().unwrap();
+        let mut a = Array2::from(m);
+        a.flip_horizontal().unwrap();
+        assert_eq!(Matrix::try_from(&a).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_rejects_non_square() {
+        let mut a = Array2::<i32>::zeros((2, 3));
+        assert!(matches!(a.rotate_right(), Err(RotationError::NotSquare)));
+        let mut a = Array2::<i32>::zeros((0, 0));
+        assert!(matches!(a.transpose(), Err(RotationError::Empty)));
+        assert!(matches!(
+            Matrix::try_from(Array2::<i32>::zeros((3, 2))),
+            Err(RotationError::NotSquare)
+        ));
+    }
+}
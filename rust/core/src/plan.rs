@@ -0,0 +1,198 @@
+//! Rotations precomputed for one table size.
+//!
+//! [`rotate_right`](crate::rotate_right) works out the ring geometry on every call and moves
+//! each element one step, so `k` steps take `k` passes. A [`RotationPlan`] works out the
+//! index cycles of `k` steps once per size, and then rotates any number of tables of that
+//! size in a single pass each. For one step the layer walk is faster on all but the
+//! smallest tables (`cargo bench --bench rotation_bench -- rotation_plan`).
+//!
+//! [`rotation_permutation`] exports the same index mapping, for systems that gather by
+//! index (SQL, GPU kernels, spreadsheets) and should not port the ring walk.
+
+use crate::strategy::ring_indices;
+use crate::{RotationError, check_side};
+use alloc::vec::Vec;
+
+/// The rotation of an `n`×`n` table by `steps` clockwise steps (counter-clockwise for
+/// negative `steps`) as an index mapping: the rotated table is `dst[i] = src[perm[i]]`.
+///
+/// Indices are 0-based and row-major, as in the flat tables everywhere else in this crate.
+/// Rings are shifted by `steps` modulo their length, so a mapping can be computed for any
+/// number of steps without rotating anything.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::{rotate_right, rotation_permutation};
+///
+/// let perm = rotation_permutation(2, 1);
+/// assert_eq!(perm, vec![2, 0, 3, 1]);
+///
+/// let src = vec![40, 20, 90, 10];
+/// let dst: Vec<i32> = perm.iter().map(|&i| src[i]).collect();
+/// let mut rotated = src.clone();
+/// rotate_right(&mut rotated).unwrap();
+/// assert_eq!(dst, rotated);
+/// ```
+pub fn rotation_permutation(n: usize, steps: isize) -> Vec<usize> {
+    let mut source: Vec<usize> = (0..n * n).collect();
+    let mut ring = Vec::with_capacity(4 * n);
+    for layer in 0..n / 2 {
+        ring.clear();
+        ring.extend(ring_indices(n, layer));
+        let len = ring.len();
+        let shift = steps.rem_euclid(len as isize) as usize;
+        for (k, &dest) in ring.iter().enumerate() {
+            source[dest] = ring[(k + len - shift) % len];
+        }
+    }
+    source
+}
+
+/// `steps` ring shifts of `n`×`n` tables, precomputed.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::RotationPlan;
+///
+/// let plan = RotationPlan::new(2, 3);
+/// let mut tables = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+/// for table in &mut tables {
+///     plan.apply(table).unwrap();
+/// }
+/// assert_eq!(tables, vec![vec![2, 4, 1, 3], vec![6, 8, 5, 7]]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotationPlan {
+    n: usize,
+    source: Vec<usize>,
+    /// One cell of every cycle of `source` longer than one cell
+    cycles: Vec<usize>,
+}
+
+impl RotationPlan {
+    /// Plans `steps` clockwise steps of [`rotate_right`](crate::rotate_right) for tables of
+    /// side `n`; negative `steps` go counter-clockwise, as [`rotate_left`](crate::rotate_left).
+    pub fn new(n: usize, steps: isize) -> Self {
+        let source = rotation_permutation(n, steps);
+        let mut seen = alloc::vec![false; source.len()];
+        let mut cycles = Vec::new();
+        for start in 0..source.len() {
+            if seen[start] || source[start] == start {
+                continue;
+            }
+            cycles.push(start);
+            let mut i = start;
+            while !seen[i] {
+                seen[i] = true;
+                i = source[i];
+            }
+        }
+        RotationPlan { n, source, cycles }
+    }
+
+    /// Side length of the tables the plan is for.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// The planned rotation as an index mapping; see [`rotation_permutation`].
+    pub fn permutation(&self) -> &[usize] {
+        &self.source
+    }
+
+    /// Rotates `data` in place, moving every element once and allocating nothing.
+    ///
+    /// # Errors
+    ///
+    /// [`RotationError::Empty`] for an empty table, and [`RotationError::NotSquare`] if
+    /// `data.len()` is not `n * n`.
+    pub fn apply<T: Copy>(&self, data: &mut [T]) -> Result<(), RotationError> {
+        check_side(data, self.n)?;
+        for &start in &self.cycles {
+            let first = data[start];
+            let mut i = start;
+            loop {
+                let from = self.source[i];
+                if from == start {
+                    data[i] = first;
+                    break;
+                }
+                data[i] = data[from];
+                i = from;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the rotation of `src` to `dst` in one gather pass, leaving `src` as it is.
+    ///
+    /// # Errors
+    ///
+    /// As [`apply`](Self::apply), for either table.
+    pub fn apply_to<T: Copy>(&self, src: &[T], dst: &mut [T]) -> Result<(), RotationError> {
+        check_side(src, self.n)?;
+        check_side(dst, self.n)?;
+        for (value, &from) in dst.iter_mut().zip(&self.source) {
+            *value = src[from];
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{rotate_left, rotate_right};
+    use alloc::vec;
+
+    #[test]
+    fn test_plan_matches_repeated_rotations() {
+        for n in 1..=7 {
+            let table: Vec<i32> = (0..(n * n) as i32).collect();
+            for steps in -30isize..=30 {
+                let mut expected = table.clone();
+                for _ in 0..steps.unsigned_abs() {
+                    if steps > 0 {
+                        rotate_right(&mut expected).unwrap();
+                    } else {
+                        rotate_left(&mut expected).unwrap();
+                    }
+                }
+                let plan = RotationPlan::new(n, steps);
+
+                let mut data = table.clone();
+                plan.apply(&mut data).unwrap();
+                assert_eq!(data, expected, "{n}x{n} by {steps}");
+
+                let mut dst = vec![0; n * n];
+                plan.apply_to(&table, &mut dst).unwrap();
+                assert_eq!(dst, expected, "{n}x{n} by {steps} into dst");
+
+                let perm = rotation_permutation(n, steps);
+                assert_eq!(perm, plan.permutation());
+                let gathered: Vec<i32> = perm.iter().map(|&i| table[i]).collect();
+                assert_eq!(gathered, expected, "{n}x{n} by {steps} gathered");
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_validates() {
+        let plan = RotationPlan::new(3, 1);
+        assert!(matches!(
+            plan.apply(&mut [1, 2, 3, 4]),
+            Err(RotationError::NotSquare)
+        ));
+        assert!(matches!(
+            plan.apply_to(&[0; 9], &mut [0; 4]),
+            Err(RotationError::NotSquare)
+        ));
+        assert!(matches!(
+            RotationPlan::new(0, 1).apply::<i32>(&mut []),
+            Err(RotationError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,421 @@
+//! Ring-by-ring processing of a table.
+//!
+//! The rotation shifts every ring by one; [`ring_map`] hands each ring to a closure as a
+//! contiguous slice instead, so any per-ring operation (reversing, sorting, shifting by more
+//! than one) reuses the same ring walk. [`extract_ring`] and [`write_ring`] do the same for a
+//! single ring, with the caller holding it in between. [`rotation_distance`] goes the other
+//! way and finds how many steps turn one table into another.
+
+use crate::strategy::ring_indices;
+use crate::{RotationError, check_side, ring_len, square_len};
+use alloc::vec::Vec;
+
+/// Calls `f` with the layer and the elements of each ring of the `n`×`n` table in `data`,
+/// and writes the ring back.
+///
+/// Rings go from the outermost (layer 0) inwards, and their elements run clockwise from the
+/// top-left corner, as in [`testkit::ring`](crate::testkit::ring). The center of an odd
+/// table is passed as a ring of one element. Each ring is copied into one scratch buffer,
+/// reused for all rings.
+///
+/// # Examples
+///
+/// Shifting every ring by one is [`rotate_right`](crate::rotate_right):
+///
+/// ```
+/// use rotate_core::ring_map;
+///
+/// let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+/// ring_map(&mut data, 3, |_, ring| ring.rotate_right(1)).unwrap();
+/// assert_eq!(data, vec![4, 1, 2, 7, 5, 3, 8, 9, 6]);
+///
+/// // Sort the outer ring only
+/// let mut data = vec![9, 1, 8, 2, 0, 7, 3, 6, 4];
+/// ring_map(&mut data, 3, |layer, ring| {
+///     if layer == 0 {
+///         ring.sort();
+///     }
+/// })
+/// .unwrap();
+/// assert_eq!(data, vec![1, 2, 3, 9, 0, 4, 8, 7, 6]);
+/// ```
+///
+/// # Errors
+///
+/// [`RotationError::Empty`] for an empty table, and [`RotationError::NotSquare`] if
+/// `data.len()` is not `n * n`.
+pub fn ring_map<T, F>(data: &mut [T], n: usize, mut f: F) -> Result<(), RotationError>
+where
+    T: Copy,
+    F: FnMut(usize, &mut [T]),
+{
+    check_side(data, n)?;
+    let mut ring = Vec::with_capacity(4 * n);
+    for layer in 0..n.div_ceil(2) {
+        ring.clear();
+        ring.extend(ring_indices(n, layer).map(|i| data[i]));
+        f(layer, &mut ring);
+        for (i, value) in ring_indices(n, layer).zip(&ring) {
+            data[i] = *value;
+        }
+    }
+    Ok(())
+}
+
+/// Copies ring `layer` of the `n`×`n` table in `data`, clockwise from its top-left corner.
+///
+/// [`write_ring`] puts it back, so a ring can be saved, changed elsewhere and restored.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::extract_ring;
+///
+/// let data = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+/// assert_eq!(extract_ring(&data, 3, 0).unwrap(), vec![1, 2, 3, 6, 9, 8, 7, 4]);
+/// assert_eq!(extract_ring(&data, 3, 1).unwrap(), vec![5]);
+/// ```
+///
+/// # Errors
+///
+/// As [`ring_map`].
+///
+/// # Panics
+///
+/// If `layer` is not a ring of the table (`2 * layer >= n`).
+pub fn extract_ring<T: Copy>(data: &[T], n: usize, layer: usize) -> Result<Vec<T>, RotationError> {
+    check_side(data, n)?;
+    let mut ring = Vec::with_capacity(ring_len(n, layer));
+    ring.extend(ring_indices(n, layer).map(|i| data[i]));
+    Ok(ring)
+}
+
+/// Overwrites ring `layer` of the `n`×`n` table in `data` with `ring`, in the order
+/// [`extract_ring`] returns it; the other rings are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::{extract_ring, write_ring};
+///
+/// let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+/// let mut ring = extract_ring(&data, 3, 0).unwrap();
+/// ring.rotate_right(2);
+/// write_ring(&mut data, 3, 0, &ring).unwrap();
+/// assert_eq!(data, vec![7, 4, 1, 8, 5, 2, 9, 6, 3]);
+/// ```
+///
+/// # Errors
+///
+/// As [`ring_map`].
+///
+/// # Panics
+///
+/// If `layer` is not a ring of the table, or `ring.len()` is not
+/// [`ring_len(n, layer)`](crate::ring_len).
+pub fn write_ring<T: Copy>(
+    data: &mut [T],
+    n: usize,
+    layer: usize,
+    ring: &[T],
+) -> Result<(), RotationError> {
+    check_side(data, n)?;
+    let len = ring_len(n, layer);
+    assert_eq!(
+        ring.len(),
+        len,
+        "ring {layer} of a {n}×{n} table has {len} elements"
+    );
+    for (i, value) in ring_indices(n, layer).zip(ring) {
+        data[i] = *value;
+    }
+    Ok(())
+}
+
+/// Whether [`rotate_right`](crate::rotate_right), applied some number of times to
+/// `original`, gives `candidate`. See [`rotation_distance`].
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::is_rotation_of;
+///
+/// assert!(is_rotation_of(&[3, 1, 4, 2], &[1, 2, 3, 4]));
+/// assert!(!is_rotation_of(&[2, 1, 3, 4], &[1, 2, 3, 4]));
+/// ```
+pub fn is_rotation_of<T: PartialEq>(candidate: &[T], original: &[T]) -> bool {
+    rotation_distance(original, candidate).is_some()
+}
+
+/// The smallest `k` such that `k` calls of [`rotate_right`](crate::rotate_right) turn
+/// `from` into `to`, or `None` if there is none.
+///
+/// Every ring moves by the same `k`, so `k` has to match the shift of each ring modulo the
+/// ring's period (its length, or less if its elements repeat); the answer is found ring by
+/// ring in linear time rather than by rotating `from` until it matches. A table rotated
+/// with `rotate_left` is found too, at the matching number of clockwise steps.
+///
+/// `None` is also returned when the tables differ in size or are not non-empty squares, and
+/// when the smallest `k` does not fit in a `usize` (only possible for large tables, where the
+/// periods of the rings multiply out).
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::rotation_distance;
+///
+/// let from = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+/// assert_eq!(rotation_distance(&from, &from), Some(0));
+/// assert_eq!(rotation_distance(&from, &[4, 1, 2, 7, 5, 3, 8, 9, 6]), Some(1));
+/// // One step counter-clockwise
+/// assert_eq!(rotation_distance(&from, &[2, 3, 6, 1, 5, 9, 4, 7, 8]), Some(7));
+/// assert_eq!(rotation_distance(&from, &[1, 2, 3, 4, 6, 5, 7, 8, 9]), None);
+/// ```
+pub fn rotation_distance<T: PartialEq>(from: &[T], to: &[T]) -> Option<usize> {
+    if from.is_empty() || from.len() != to.len() {
+        return None;
+    }
+    let n = square_len(from.len())?;
+    if n % 2 == 1 && from[n * n / 2] != to[n * n / 2] {
+        return None;
+    }
+    // The steps that fit every ring so far: k ≡ shift (mod period), where a period too
+    // large to count leaves `shift` as the only candidate
+    let (mut shift, mut period) = (0u128, Some(1u128));
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    for layer in 0..n / 2 {
+        a.clear();
+        a.extend(ring_indices(n, layer).map(|i| &from[i]));
+        b.clear();
+        b.extend(ring_indices(n, layer).map(|i| &to[i]));
+        let (ring_shift, ring_period) = ring_shift(&a, &b)?;
+        let (ring_shift, ring_period) = (ring_shift as u128, ring_period as u128);
+        match period {
+            Some(m) => (shift, period) = combine(shift, m, ring_shift, ring_period)?,
+            None if shift % ring_period == ring_shift => {}
+            None => return None,
+        }
+    }
+    usize::try_from(shift).ok()
+}
+
+/// The smallest right shift of ring `a` that gives ring `b`, and the period of `b` under
+/// shifts, found with the Knuth-Morris-Pratt failure function of `b`.
+fn ring_shift<T: PartialEq>(a: &[&T], b: &[&T]) -> Option<(usize, usize)> {
+    let len = b.len();
+    let mut fail = alloc::vec![0; len];
+    let mut k = 0;
+    for i in 1..len {
+        while k > 0 && b[i] != b[k] {
+            k = fail[k - 1];
+        }
+        if b[i] == b[k] {
+            k += 1;
+        }
+        fail[i] = k;
+    }
+    let period = match len - fail[len - 1] {
+        p if len.is_multiple_of(p) => p,
+        _ => len,
+    };
+
+    // Search b in a + a: a match at `start` means b is a shifted right by len - start
+    let mut k = 0;
+    for i in 0..2 * len - 1 {
+        let x = a[i % len];
+        while k > 0 && x != b[k] {
+            k = fail[k - 1];
+        }
+        if x == b[k] {
+            k += 1;
+        }
+        if k == len {
+            let start = i + 1 - len;
+            return Some(((len - start) % period, period));
+        }
+    }
+    None
+}
+
+/// Merges `k ≡ a1 (mod m1)` and `k ≡ a2 (mod m2)`, with `a1 < m1` and `a2 < m2`, into one
+/// congruence. The solution is `None` if they contradict each other or it overflows, and
+/// the modulus is `None` if it overflows.
+fn combine(a1: u128, m1: u128, a2: u128, m2: u128) -> Option<(u128, Option<u128>)> {
+    let g = gcd(m1, m2);
+    let diff = (a2 + m2 - a1 % m2) % m2;
+    if !diff.is_multiple_of(g) {
+        return None;
+    }
+    // k = a1 + m1 * t, where (m1 / g) * t ≡ diff / g (mod m2 / g)
+    let m = m2 / g;
+    let t = (diff / g) * mod_inverse((m1 / g) % m, m) % m;
+    let k = a1.checked_add(m1.checked_mul(t)?)?;
+    Some((k, (m1 / g).checked_mul(m2)))
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// The inverse of `a` modulo `m`, for coprime `a` and `m` no larger than a ring length.
+fn mod_inverse(a: u128, m: u128) -> u128 {
+    let (mut r0, mut r1) = (m as i128, a as i128);
+    let (mut t0, mut t1) = (0i128, 1i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, t0 - q * t1);
+    }
+    t0.rem_euclid(m as i128) as u128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{rotate_left, rotate_right, testkit};
+    use alloc::vec;
+
+    #[test]
+    fn test_ring_map_shift() {
+        for n in 1..=9 {
+            let table: Vec<i32> = (0..n * n).collect();
+
+            let mut expected = table.clone();
+            rotate_right(&mut expected).unwrap();
+            let mut data = table.clone();
+            ring_map(&mut data, n as usize, |_, ring| ring.rotate_right(1)).unwrap();
+            assert_eq!(data, expected, "rotate right {n}x{n}");
+
+            let mut expected = table.clone();
+            rotate_left(&mut expected).unwrap();
+            let mut data = table.clone();
+            ring_map(&mut data, n as usize, |_, ring| ring.rotate_left(1)).unwrap();
+            assert_eq!(data, expected, "rotate left {n}x{n}");
+        }
+    }
+
+    #[test]
+    fn test_ring_map_layers() {
+        let n = 5;
+        let mut data: Vec<i32> = (0..25).collect();
+        let mut layers = vec![];
+        ring_map(&mut data, n, |layer, ring| {
+            assert_eq!(ring.len(), ring_len(n, layer));
+            layers.push(layer);
+            ring.reverse();
+        })
+        .unwrap();
+        assert_eq!(layers, vec![0, 1, 2]);
+        let original: Vec<i32> = (0..25).collect();
+        for layer in 0..3 {
+            let mut ring = testkit::ring(&original, n, layer);
+            ring.reverse();
+            assert_eq!(testkit::ring(&data, n, layer), ring);
+        }
+    }
+
+    #[test]
+    fn test_extract_write_ring() {
+        let n = 6;
+        let original: Vec<i32> = (0..36).collect();
+        let mut data = original.clone();
+        for layer in 0..3 {
+            let ring = extract_ring(&data, n, layer).unwrap();
+            assert_eq!(ring, testkit::ring(&original, n, layer));
+            let zeros = vec![0; ring.len()];
+            write_ring(&mut data, n, layer, &zeros).unwrap();
+            assert!(
+                extract_ring(&data, n, layer)
+                    .unwrap()
+                    .iter()
+                    .all(|&x| x == 0)
+            );
+            write_ring(&mut data, n, layer, &ring).unwrap();
+            assert_eq!(data, original);
+        }
+        assert!(matches!(
+            extract_ring(&[1, 2, 3], 2, 0),
+            Err(RotationError::NotSquare)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "ring 1 of a 4×4 table has 4 elements")]
+    fn test_write_ring_length() {
+        let mut data = [0; 16];
+        write_ring(&mut data, 4, 1, &[1, 2, 3]).unwrap();
+    }
+
+    #[test]
+    fn test_rotation_distance() {
+        for n in 1..=7 {
+            let from: Vec<i32> = (0..n * n).collect();
+            let mut to = from.clone();
+            for k in 0..30 {
+                let expected = (0..=k).find(|&j| {
+                    let mut data = from.clone();
+                    (0..j).for_each(|_| rotate_right(&mut data).unwrap());
+                    data == to
+                });
+                assert_eq!(
+                    rotation_distance(&from, &to),
+                    expected,
+                    "{n}x{n}, {k} steps"
+                );
+                assert!(is_rotation_of(&to, &from));
+                rotate_right(&mut to).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotation_distance_periods() {
+        // Shifting the outer ring (12 elements) by 1 and the inner one (4 elements) by 2
+        // needs k ≡ 1 (mod 12) and k ≡ 2 (mod 4), which no k satisfies
+        let from: Vec<i32> = (0..16).collect();
+        let mut to = from.clone();
+        ring_map(&mut to, 4, |layer, ring| ring.rotate_right(layer + 1)).unwrap();
+        assert_eq!(rotation_distance(&from, &to), None);
+        // A repeating ring only constrains k modulo its period: 3 steps shift an outer ring
+        // of alternating elements as much as 1 does
+        let mut from = [0; 16];
+        write_ring(&mut from, 4, 0, &[1, 2].repeat(6)).unwrap();
+        write_ring(&mut from, 4, 1, &[5, 6, 7, 8]).unwrap();
+        let mut to = from;
+        ring_map(&mut to, 4, |layer, ring| {
+            ring.rotate_right(if layer == 0 { 1 } else { 3 })
+        })
+        .unwrap();
+        assert_eq!(rotation_distance(&from, &to), Some(3));
+
+        // The periods of the 100 rings of a 200×200 table multiply out past u128
+        let from: Vec<i32> = (0..200 * 200).collect();
+        let mut to = from.clone();
+        (0..3).for_each(|_| rotate_right(&mut to).unwrap());
+        assert_eq!(rotation_distance(&from, &to), Some(3));
+
+        assert_eq!(rotation_distance(&[1, 2, 3, 4], &[1, 2, 3]), None);
+        assert_eq!(rotation_distance::<i32>(&[], &[]), None);
+        assert_eq!(rotation_distance(&[5], &[6]), None);
+    }
+
+    #[test]
+    fn test_ring_map_validates() {
+        assert!(matches!(
+            ring_map::<i32, _>(&mut [], 0, |_, _| {}),
+            Err(RotationError::Empty)
+        ));
+        assert!(matches!(
+            ring_map(&mut [1, 2, 3, 4], 3, |_, _| {}),
+            Err(RotationError::NotSquare)
+        ));
+        assert!(matches!(
+            ring_map(&mut [1, 2, 3], 0, |_, _| {}),
+            Err(RotationError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+//! Cyclic shifts of whole rows and columns, as numpy's `roll` along one axis.
+//!
+//! Unlike the ring shift these move every row (or column) as a unit: rolling rows by `k`
+//! moves row `i` to row `i + k`, wrapping around at the bottom. Both are in place and
+//! allocation-free, using [`slice::rotate_right`].
+
+use crate::{RotationError, check_side};
+
+/// Shifts the rows of the `n`×`n` table in `data` down by `k`, wrapping around; a negative
+/// `k` shifts them up. Same as `numpy.roll(table, k, axis=0)`.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::roll_rows;
+///
+/// let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+/// roll_rows(&mut data, 3, 1).unwrap();
+/// assert_eq!(data, vec![7, 8, 9, 1, 2, 3, 4, 5, 6]);
+/// roll_rows(&mut data, 3, -2).unwrap();
+/// assert_eq!(data, vec![4, 5, 6, 7, 8, 9, 1, 2, 3]);
+/// ```
+///
+/// # Errors
+///
+/// [`RotationError::Empty`] for an empty table, and [`RotationError::NotSquare`] if
+/// `data.len()` is not `n * n`.
+pub fn roll_rows<T>(data: &mut [T], n: usize, k: isize) -> Result<(), RotationError> {
+    check_side(data, n)?;
+    data.rotate_right(wrap(k, n) * n);
+    Ok(())
+}
+
+/// Shifts the columns of the `n`×`n` table in `data` right by `k`, wrapping around; a
+/// negative `k` shifts them left. Same as `numpy.roll(table, k, axis=1)`.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::roll_cols;
+///
+/// let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+/// roll_cols(&mut data, 3, 1).unwrap();
+/// assert_eq!(data, vec![3, 1, 2, 6, 4, 5, 9, 7, 8]);
+/// ```
+///
+/// # Errors
+///
+/// As [`roll_rows`].
+pub fn roll_cols<T>(data: &mut [T], n: usize, k: isize) -> Result<(), RotationError> {
+    check_side(data, n)?;
+    let k = wrap(k, n);
+    for row in data.chunks_exact_mut(n) {
+        row.rotate_right(k);
+    }
+    Ok(())
+}
+
+/// `k` modulo `n`, as a right shift in `0..n`.
+fn wrap(k: isize, n: usize) -> usize {
+    let shift = k.unsigned_abs() % n;
+    if k >= 0 || shift == 0 {
+        shift
+    } else {
+        n - shift
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_roll() {
+        let n = 4;
+        let table: Vec<usize> = (0..n * n).collect();
+        for k in -9isize..=9 {
+            let shift = k.rem_euclid(n as isize) as usize;
+
+            let mut data = table.clone();
+            roll_rows(&mut data, n, k).unwrap();
+            for (i, &x) in data.iter().enumerate() {
+                let (row, col) = (i / n, i % n);
+                assert_eq!(x, table[(row + n - shift) % n * n + col], "rows by {k}");
+            }
+
+            let mut data = table.clone();
+            roll_cols(&mut data, n, k).unwrap();
+            for (i, &x) in data.iter().enumerate() {
+                let (row, col) = (i / n, i % n);
+                assert_eq!(x, table[row * n + (col + n - shift) % n], "cols by {k}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_roll_validates() {
+        assert!(matches!(
+            roll_rows::<i32>(&mut [], 0, 1),
+            Err(RotationError::Empty)
+        ));
+        assert!(matches!(
+            roll_cols(&mut [1, 2, 3], 2, 1),
+            Err(RotationError::NotSquare)
+        ));
+        assert!(matches!(roll_rows(&mut [1], 1, isize::MIN), Ok(())));
+    }
+}
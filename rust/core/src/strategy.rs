@@ -0,0 +1,134 @@
+//! Alternative implementations of the one-step ring shift.
+//!
+//! [`rotate_right`](crate::rotate_right) always uses the in-place layer walk. The other
+//! strategies produce identical results and exist so the benchmark claim that the layer walk
+//! is the fastest approach is backed by code (`cargo bench --bench rotation_bench --
+//! rotation_strategies`); select one with [`RotateOptions::strategy`].
+
+use crate::plan::rotation_permutation;
+use crate::{Flat, RotationError, rotate_grid, square_len};
+use alloc::vec::Vec;
+
+/// Algorithm used by [`rotate_right_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Strategy {
+    /// Walk each ring clockwise, carrying one element along (in place, no allocation)
+    #[default]
+    LayerWalk,
+    /// Copy each ring into a buffer, rotate the buffer with [`slice::rotate_right`] and write
+    /// it back (one allocation per call)
+    RingBuffer,
+    /// Compute the source index of every cell, then gather from a copy of the table (two
+    /// allocations per call)
+    Permutation,
+}
+
+impl Strategy {
+    /// Every strategy, in declaration order.
+    pub const ALL: [Strategy; 3] = [
+        Strategy::LayerWalk,
+        Strategy::RingBuffer,
+        Strategy::Permutation,
+    ];
+}
+
+/// Options for [`rotate_right_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RotateOptions {
+    pub strategy: Strategy,
+}
+
+/// Like [`rotate_right`](crate::rotate_right), with a choice of algorithm.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::{RotateOptions, Strategy, rotate_right_with};
+///
+/// let options = RotateOptions { strategy: Strategy::Permutation };
+/// let mut data = vec![40, 20, 90, 10];
+/// rotate_right_with(&mut data, options).unwrap();
+/// assert_eq!(data, vec![90, 40, 10, 20]);
+/// ```
+pub fn rotate_right_with<T: Copy>(
+    data: &mut [T],
+    options: RotateOptions,
+) -> Result<(), RotationError> {
+    if data.is_empty() {
+        return Err(RotationError::Empty);
+    }
+    let n = square_len(data.len()).ok_or(RotationError::NotSquare)?;
+
+    match options.strategy {
+        Strategy::LayerWalk => rotate_grid(&mut Flat { data, n }),
+        Strategy::RingBuffer => ring_buffer(data, n),
+        Strategy::Permutation => permutation(data, n),
+    }
+    Ok(())
+}
+
+/// Flat indices of ring `layer` in clockwise order, starting at its top-left corner.
+pub(crate) fn ring_indices(n: usize, layer: usize) -> impl Iterator<Item = usize> {
+    let first = layer;
+    let last = n - 1 - layer;
+    let top = (first..=last).map(move |col| first * n + col);
+    let right = (first + 1..=last).map(move |row| row * n + last);
+    let bottom = (first..last).rev().map(move |col| last * n + col);
+    let left = (first + 1..last).rev().map(move |row| row * n + first);
+    top.chain(right).chain(bottom).chain(left)
+}
+
+fn ring_buffer<T: Copy>(data: &mut [T], n: usize) {
+    let mut ring = Vec::with_capacity(4 * n);
+    for layer in 0..n / 2 {
+        ring.clear();
+        ring.extend(ring_indices(n, layer).map(|i| data[i]));
+        ring.rotate_right(1);
+        for (i, value) in ring_indices(n, layer).zip(&ring) {
+            data[i] = *value;
+        }
+    }
+}
+
+fn permutation<T: Copy>(data: &mut [T], n: usize) {
+    let source = rotation_permutation(n, 1);
+    let original = data.to_vec();
+    for (value, &from) in data.iter_mut().zip(&source) {
+        *value = original[from];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rotate_right;
+
+    #[test]
+    fn test_strategies_agree() {
+        for n in 1..=12 {
+            let mut expected: Vec<i32> = (0..n * n).collect();
+            rotate_right(&mut expected).unwrap();
+            for strategy in Strategy::ALL {
+                let mut data: Vec<i32> = (0..n * n).collect();
+                rotate_right_with(&mut data, RotateOptions { strategy }).unwrap();
+                assert_eq!(data, expected, "{strategy:?} for {n}x{n}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_strategies_validate() {
+        for strategy in Strategy::ALL {
+            let options = RotateOptions { strategy };
+            assert!(matches!(
+                rotate_right_with::<i32>(&mut [], options),
+                Err(RotationError::Empty)
+            ));
+            assert!(matches!(
+                rotate_right_with(&mut [1, 2, 3], options),
+                Err(RotationError::NotSquare)
+            ));
+        }
+    }
+}
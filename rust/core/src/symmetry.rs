@@ -0,0 +1,198 @@
+//! Symmetry tests for square tables.
+//!
+//! Each test is a free function on flat row-major slices, validated like
+//! [`rotate_right`](crate::rotate_right), and a method on [`Matrix`]. Nothing is copied or
+//! rotated: the tests compare each cell with its image and stop at the first mismatch, so
+//! they are cheap enough to run on every tile before deduplicating.
+
+use crate::{Matrix, RotationError, square_len};
+
+/// Side of the non-empty square table in `data`.
+fn side<T>(data: &[T]) -> Result<usize, RotationError> {
+    if data.is_empty() {
+        return Err(RotationError::Empty);
+    }
+    square_len(data.len()).ok_or(RotationError::NotSquare)
+}
+
+/// Whether the table equals its transpose.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::is_symmetric;
+///
+/// assert!(is_symmetric(&[1, 2, 2, 5]).unwrap());
+/// assert!(!is_symmetric(&[1, 2, 3, 4]).unwrap());
+/// ```
+///
+/// # Errors
+///
+/// [`RotationError::Empty`] or [`RotationError::NotSquare`] if `data` is not a non-empty
+/// square.
+pub fn is_symmetric<T: PartialEq>(data: &[T]) -> Result<bool, RotationError> {
+    Ok(symmetric(data, side(data)?))
+}
+
+/// Whether turning the table by `1 / order` of a full turn about its center (a quarter turn
+/// for `order` 4, a half turn for 2) leaves it unchanged. Every table has order 1.
+///
+/// These are the geometric turns of the whole table, not the ring shift of
+/// [`rotate_right`](crate::rotate_right); see [`is_ring_constant`] for that.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::is_rotationally_symmetric;
+///
+/// let pinwheel = [1, 2, 1, 2, 0, 2, 1, 2, 1];
+/// assert!(is_rotationally_symmetric(&pinwheel, 4).unwrap());
+/// let stripes = [1, 2, 3, 4, 5, 4, 3, 2, 1];
+/// assert!(is_rotationally_symmetric(&stripes, 2).unwrap());
+/// assert!(!is_rotationally_symmetric(&stripes, 4).unwrap());
+/// ```
+///
+/// # Errors
+///
+/// As [`is_symmetric`].
+///
+/// # Panics
+///
+/// If `order` is not 1, 2 or 4, the only turns that map a square grid onto itself.
+pub fn is_rotationally_symmetric<T: PartialEq>(
+    data: &[T],
+    order: usize,
+) -> Result<bool, RotationError> {
+    Ok(rotationally_symmetric(data, side(data)?, order))
+}
+
+/// Whether every ring holds a single repeated value, i.e. whether
+/// [`rotate_right`](crate::rotate_right) leaves the table unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::is_ring_constant;
+///
+/// assert!(is_ring_constant(&[7, 7, 7, 7, 0, 7, 7, 7, 7]).unwrap());
+/// assert!(!is_ring_constant(&[7, 7, 7, 7, 0, 7, 7, 7, 1]).unwrap());
+/// ```
+///
+/// # Errors
+///
+/// As [`is_symmetric`].
+pub fn is_ring_constant<T: PartialEq>(data: &[T]) -> Result<bool, RotationError> {
+    Ok(ring_constant(data, side(data)?))
+}
+
+impl<T: PartialEq> Matrix<T> {
+    /// [`is_symmetric`] for a matrix.
+    pub fn is_symmetric(&self) -> bool {
+        symmetric(self.as_slice(), self.n())
+    }
+
+    /// [`is_rotationally_symmetric`] for a matrix.
+    ///
+    /// # Panics
+    ///
+    /// If `order` is not 1, 2 or 4.
+    pub fn is_rotationally_symmetric(&self, order: usize) -> bool {
+        rotationally_symmetric(self.as_slice(), self.n(), order)
+    }
+
+    /// [`is_ring_constant`] for a matrix.
+    pub fn is_ring_constant(&self) -> bool {
+        ring_constant(self.as_slice(), self.n())
+    }
+}
+
+fn symmetric<T: PartialEq>(data: &[T], n: usize) -> bool {
+    (0..n).all(|row| (row + 1..n).all(|col| data[row * n + col] == data[col * n + row]))
+}
+
+fn rotationally_symmetric<T: PartialEq>(data: &[T], n: usize, order: usize) -> bool {
+    let quarter = match order {
+        1 => return true,
+        2 => false,
+        4 => true,
+        _ => panic!("a square has no rotational symmetry of order {order}"),
+    };
+    (0..n).all(|row| {
+        (0..n).all(|col| {
+            // Where the cell comes from after the turn
+            let (from_row, from_col) = if quarter {
+                (n - 1 - col, row)
+            } else {
+                (n - 1 - row, n - 1 - col)
+            };
+            data[row * n + col] == data[from_row * n + from_col]
+        })
+    })
+}
+
+fn ring_constant<T: PartialEq>(data: &[T], n: usize) -> bool {
+    (0..n).all(|row| {
+        (0..n).all(|col| {
+            let layer = row.min(col).min(n - 1 - row).min(n - 1 - col);
+            data[row * n + col] == data[layer * n + layer]
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Rotatable, rotate_right};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_symmetry_matches_transforms() {
+        // Compare against the transforms on tables with few distinct values, so that some of
+        // them are symmetric
+        for n in 1..=4 {
+            for seed in 0..200u32 {
+                let data: Vec<u32> = (0..n * n)
+                    .map(|i| (seed.wrapping_mul(2654435761) >> (i % 16 * 2)) % 2)
+                    .collect();
+                let matrix = Matrix::from_vec(data.clone()).unwrap();
+
+                let mut transposed = data.clone();
+                Rotatable::transpose(transposed.as_mut_slice()).unwrap();
+                assert_eq!(matrix.is_symmetric(), transposed == data);
+
+                // A half turn is both flips; a quarter turn is a transpose and a flip
+                let mut half = data.clone();
+                Rotatable::flip_horizontal(half.as_mut_slice()).unwrap();
+                Rotatable::flip_vertical(half.as_mut_slice()).unwrap();
+                assert_eq!(matrix.is_rotationally_symmetric(2), half == data);
+                let mut quarter = transposed;
+                Rotatable::flip_horizontal(quarter.as_mut_slice()).unwrap();
+                assert_eq!(matrix.is_rotationally_symmetric(4), quarter == data);
+                assert!(matrix.is_rotationally_symmetric(1));
+
+                let mut shifted = data.clone();
+                rotate_right(&mut shifted).unwrap();
+                assert_eq!(matrix.is_ring_constant(), shifted == data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_symmetry_validates() {
+        assert!(matches!(
+            is_symmetric::<i32>(&[]),
+            Err(RotationError::Empty)
+        ));
+        assert!(matches!(
+            is_ring_constant(&[1, 2, 3]),
+            Err(RotationError::NotSquare)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "no rotational symmetry of order 3")]
+    fn test_rotational_symmetry_order() {
+        is_rotationally_symmetric(&[1, 2, 3, 4], 3).unwrap();
+    }
+}
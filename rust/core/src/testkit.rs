@@ -0,0 +1,219 @@
+//! Invariants of the ring rotation, for testing this crate and its bindings.
+//!
+//! Each check takes the rotation under test as a closure, so the same properties can be run
+//! against [`rotate_right`](crate::rotate_right), a [`Rotatable`](crate::Rotatable) type, or
+//! a binding that calls into WASM or Python:
+//!
+//! ```
+//! use rotate_core::testkit;
+//!
+//! let table: Vec<i64> = (0..25).collect();
+//! testkit::check_rotate_right(&table, |data| rotate_core::rotate_right(data).unwrap()).unwrap();
+//! testkit::check_inverse(
+//!     &table,
+//!     |data| rotate_core::rotate_right(data).unwrap(),
+//!     |data| rotate_core::rotate_left(data).unwrap(),
+//! )
+//! .unwrap();
+//! ```
+//!
+//! The checks return a [`Violation`] describing the first broken property instead of
+//! panicking, so they also fit property-testing frameworks that shrink failing inputs.
+
+use crate::strategy::ring_indices;
+use crate::{ring_len, square_len};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug};
+
+/// A broken invariant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation(pub String);
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl core::error::Error for Violation {}
+
+/// Side length of `table`, which must be a non-empty square.
+fn side<T>(table: &[T]) -> Result<usize, Violation> {
+    match square_len(table.len()) {
+        Some(n) if n > 0 => Ok(n),
+        _ => Err(Violation(format!(
+            "test input of length {} is not a non-empty square table",
+            table.len()
+        ))),
+    }
+}
+
+/// Elements of ring `layer` in clockwise order, starting at its top-left corner.
+pub fn ring<T: Copy>(table: &[T], n: usize, layer: usize) -> Vec<T> {
+    ring_indices(n, layer).map(|i| table[i]).collect()
+}
+
+/// The expected result of one clockwise step, computed independently of the layer walk.
+pub fn reference_rotate_right<T: Copy>(table: &[T]) -> Vec<T> {
+    let n = square_len(table.len()).expect("table must be square");
+    let mut out = table.to_vec();
+    for layer in 0..n / 2 {
+        let indices: Vec<usize> = ring_indices(n, layer).collect();
+        for (k, &dest) in indices.iter().enumerate() {
+            out[dest] = table[indices[(k + indices.len() - 1) % indices.len()]];
+        }
+    }
+    out
+}
+
+/// `after` holds the same elements as `before`, with the same multiplicities.
+pub fn check_same_elements<T: Ord + Clone + Debug>(
+    before: &[T],
+    after: &[T],
+) -> Result<(), Violation> {
+    let mut before = before.to_vec();
+    let mut after = after.to_vec();
+    before.sort();
+    after.sort();
+    if before == after {
+        Ok(())
+    } else {
+        Err(Violation(format!(
+            "elements changed: {before:?} became {after:?} (sorted)"
+        )))
+    }
+}
+
+/// One application of `rotate` matches [`reference_rotate_right`] and keeps the elements.
+pub fn check_rotate_right<T, F>(table: &[T], mut rotate: F) -> Result<(), Violation>
+where
+    T: Copy + Ord + Debug,
+    F: FnMut(&mut [T]),
+{
+    side(table)?;
+    let mut data = table.to_vec();
+    rotate(&mut data);
+    check_same_elements(table, &data)?;
+    let expected = reference_rotate_right(table);
+    if data != expected {
+        return Err(Violation(format!(
+            "rotating {table:?} gave {data:?}, expected {expected:?}"
+        )));
+    }
+    check_ring_period(table, rotate)
+}
+
+/// Rotating `ring_len(n, layer)` times restores ring `layer`, for every ring.
+pub fn check_ring_period<T, F>(table: &[T], mut rotate: F) -> Result<(), Violation>
+where
+    T: Copy + PartialEq + Debug,
+    F: FnMut(&mut [T]),
+{
+    let n = side(table)?;
+    let mut data = table.to_vec();
+    let mut steps = 0;
+    // Rings are visited from the inside out, so their periods only grow
+    for layer in (0..n.div_ceil(2)).rev() {
+        while steps < ring_len(n, layer) {
+            rotate(&mut data);
+            steps += 1;
+        }
+        // Inner rings are back at the start after every multiple of their period
+        let expected = ring(table, n, layer);
+        let actual = ring(&data, n, layer);
+        if actual != expected {
+            return Err(Violation(format!(
+                "ring {layer} is {actual:?} after {steps} rotations, expected {expected:?}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// `rotate_left` undoes `rotate_right`, and the other way around.
+pub fn check_inverse<T, R, L>(
+    table: &[T],
+    mut rotate_right: R,
+    mut rotate_left: L,
+) -> Result<(), Violation>
+where
+    T: Copy + PartialEq + Debug,
+    R: FnMut(&mut [T]),
+    L: FnMut(&mut [T]),
+{
+    side(table)?;
+    let mut data = table.to_vec();
+    rotate_right(&mut data);
+    rotate_left(&mut data);
+    if data != table {
+        return Err(Violation(format!(
+            "left after right turned {table:?} into {data:?}"
+        )));
+    }
+    rotate_left(&mut data);
+    rotate_right(&mut data);
+    if data != table {
+        return Err(Violation(format!(
+            "right after left turned {table:?} into {data:?}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Matrix, Rotatable, rotate_left, rotate_right};
+    use proptest::prelude::*;
+
+    /// Square tables from 1×1 to 12×12 with small values, so duplicates occur.
+    fn table() -> impl Strategy<Value = Vec<i32>> {
+        (1usize..=12).prop_flat_map(|n| prop::collection::vec(-5i32..5, n * n))
+    }
+
+    proptest! {
+        #[test]
+        fn prop_rotate_right(table in table()) {
+            check_rotate_right(&table, |data| rotate_right(data).unwrap())?;
+        }
+
+        #[test]
+        fn prop_left_inverts_right(table in table()) {
+            check_inverse(
+                &table,
+                |data| rotate_right(data).unwrap(),
+                |data| rotate_left(data).unwrap(),
+            )?;
+            check_same_elements(&table, &{
+                let mut data = table.clone();
+                rotate_left(&mut data).unwrap();
+                data
+            })?;
+        }
+
+        #[test]
+        fn prop_matrix_matches_slice(table in table()) {
+            check_rotate_right(&table, |data| {
+                let mut m = Matrix::from_vec(data.to_vec()).unwrap();
+                m.rotate_right().unwrap();
+                data.copy_from_slice(m.as_slice());
+            })?;
+        }
+    }
+
+    #[test]
+    fn test_detects_broken_rotation() {
+        let table: Vec<i32> = (0..9).collect();
+        // Reversing keeps the elements but is not the ring shift
+        let error = check_rotate_right(&table, |data| data.reverse()).unwrap_err();
+        assert!(error.0.starts_with("rotating [0, 1, 2"), "{error}");
+        let error = check_rotate_right(&table, |data| data[0] = 100).unwrap_err();
+        assert!(error.0.starts_with("elements changed"), "{error}");
+        let error = check_inverse(&table, |_| {}, |data| data.swap(0, 1)).unwrap_err();
+        assert!(error.0.starts_with("left after right"), "{error}");
+        assert!(check_rotate_right(&[1, 2, 3], |_| {}).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Transforms as values.
+//!
+//! [`Transform`] names one [`Rotatable`] operation, so a sequence of operations can be
+//! parsed from the command line, stored next to the data it was applied to, and replayed on
+//! any supported matrix type.
+
+use crate::{Rotatable, RotationError};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+/// One in-place operation on a square table.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_core::{Matrix, Transform};
+///
+/// let mut m = Matrix::from_vec(vec![1, 2, 3, 4]).unwrap();
+/// for t in ["rotate-right", "flip-vertical"] {
+///     t.parse::<Transform>().unwrap().apply(&mut m).unwrap();
+/// }
+/// assert_eq!(m.into_vec(), vec![4, 2, 3, 1]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transform {
+    /// [`Rotatable::rotate_right`]
+    RotateRight,
+    /// [`Rotatable::rotate_left`]
+    RotateLeft,
+    /// [`Rotatable::transpose`]
+    Transpose,
+    /// [`Rotatable::flip_horizontal`]
+    FlipHorizontal,
+    /// [`Rotatable::flip_vertical`]
+    FlipVertical,
+}
+
+impl Transform {
+    /// Every transform, in declaration order.
+    pub const ALL: [Transform; 5] = [
+        Transform::RotateRight,
+        Transform::RotateLeft,
+        Transform::Transpose,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+    ];
+
+    /// Applies the transform to `target` in place.
+    pub fn apply<R: Rotatable + ?Sized>(self, target: &mut R) -> Result<(), RotationError> {
+        match self {
+            Transform::RotateRight => target.rotate_right(),
+            Transform::RotateLeft => target.rotate_left(),
+            Transform::Transpose => target.transpose(),
+            Transform::FlipHorizontal => target.flip_horizontal(),
+            Transform::FlipVertical => target.flip_vertical(),
+        }
+    }
+
+    /// The transform that undoes this one: [`RotateLeft`](Transform::RotateLeft) for
+    /// [`RotateRight`](Transform::RotateRight) and back; the transpose and the flips are their
+    /// own inverses.
+    ///
+    /// To undo a sequence, apply the inverses in reverse order:
+    ///
+    /// ```
+    /// use rotate_core::Transform;
+    ///
+    /// let applied = [Transform::RotateRight, Transform::FlipVertical];
+    /// let mut data = vec![1, 2, 3, 4];
+    /// for t in applied {
+    ///     t.apply(data.as_mut_slice()).unwrap();
+    /// }
+    /// for t in applied.iter().rev() {
+    ///     t.invert().apply(data.as_mut_slice()).unwrap();
+    /// }
+    /// assert_eq!(data, vec![1, 2, 3, 4]);
+    /// ```
+    pub fn invert(self) -> Transform {
+        match self {
+            Transform::RotateRight => Transform::RotateLeft,
+            Transform::RotateLeft => Transform::RotateRight,
+            t @ (Transform::Transpose | Transform::FlipHorizontal | Transform::FlipVertical) => t,
+        }
+    }
+
+    /// Restores `target` to what it was before this transform was applied to it.
+    pub fn undo<R: Rotatable + ?Sized>(self, target: &mut R) -> Result<(), RotationError> {
+        self.invert().apply(target)
+    }
+
+    /// Kebab-case name, as accepted by [`FromStr`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Transform::RotateRight => "rotate-right",
+            Transform::RotateLeft => "rotate-left",
+            Transform::Transpose => "transpose",
+            Transform::FlipHorizontal => "flip-horizontal",
+            Transform::FlipVertical => "flip-vertical",
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Transform::ALL
+            .into_iter()
+            .find(|t| t.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Transform::ALL.iter().map(|t| t.name()).collect();
+                format!("unknown transform '{s}' (expected {})", names.join(", "))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Matrix;
+    use std::string::ToString;
+
+    #[test]
+    fn test_names_round_trip() {
+        for t in Transform::ALL {
+            assert_eq!(t.to_string().parse::<Transform>(), Ok(t));
+        }
+        assert!("rotate".parse::<Transform>().is_err());
+    }
+
+    #[test]
+    fn test_apply_to_slice_and_matrix() {
+        for t in Transform::ALL {
+            let mut flat: Vec<i32> = (1..=9).collect();
+            t.apply(flat.as_mut_slice()).unwrap();
+
+            let mut m = Matrix::from_vec((1..=9).collect::<Vec<i32>>()).unwrap();
+            t.apply(&mut m).unwrap();
+            assert_eq!(m.as_slice(), flat, "{t}");
+        }
+    }
+
+    #[test]
+    fn test_undo() {
+        let original: Vec<i32> = (1..=16).collect();
+        for t in Transform::ALL {
+            let mut m = Matrix::from_vec(original.clone()).unwrap();
+            t.apply(&mut m).unwrap();
+            t.undo(&mut m).unwrap();
+            assert_eq!(m.as_slice(), original, "{t}");
+            assert_eq!(t.invert().invert(), t);
+        }
+    }
+
+    #[test]
+    fn test_apply_validates_slices() {
+        let mut data = [1, 2, 3];
+        assert!(matches!(
+            Transform::Transpose.apply(&mut data[..]),
+            Err(RotationError::NotSquare)
+        ));
+    }
+}
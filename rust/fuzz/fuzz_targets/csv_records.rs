@@ -0,0 +1,52 @@
+//! Whole CSV inputs through the record path: reading with the CLI's reader settings,
+//! processing the `json` field of every record and writing the output rows.
+//!
+//! Quoting abuse, ragged records and invalid UTF-8 must never panic, and every record with
+//! at least two fields must produce exactly one output record whose id is unchanged.
+
+#![no_main]
+
+use csv::{ReaderBuilder, WriterBuilder};
+use libfuzzer_sys::fuzz_target;
+use rotate_cli::json::JsonStyle;
+use rotate_cli::{RowBuffers, process_json_bytes};
+
+fuzz_target!(|data: &[u8]| {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(data);
+    let mut wtr = WriterBuilder::new().from_writer(Vec::new());
+    let mut buffers = RowBuffers::new();
+    let mut ids = Vec::new();
+
+    for record in rdr.byte_records() {
+        // In-memory reads only fail on I/O, which cannot happen here
+        let record = record.unwrap();
+        if record.len() < 2 {
+            continue;
+        }
+        let is_valid = process_json_bytes(&record[1], JsonStyle::Compact, &mut buffers);
+        wtr.write_record([
+            &record[0],
+            &buffers.json[..],
+            if is_valid { &b"true"[..] } else { b"false" },
+        ])
+        .unwrap();
+        ids.push(record[0].to_vec());
+    }
+
+    // The output is always well-formed CSV with one row per processed record
+    let output = wtr.into_inner().unwrap();
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(&output[..]);
+    let mut rows = 0;
+    for (record, id) in rdr.byte_records().zip(&ids) {
+        let record = record.unwrap();
+        assert_eq!(record.len(), 3);
+        assert_eq!(&record[0], &id[..]);
+        rows += 1;
+    }
+    assert_eq!(rows, ids.len());
+});
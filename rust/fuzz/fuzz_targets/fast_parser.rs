@@ -0,0 +1,30 @@
+//! `parse::parse_numbers_fast` against the serde visitor it stands in for.
+//!
+//! The first input byte picks the element limit (none for 0), the rest is the cell. Both
+//! parsers must give the same result and the same numbers, whatever the bytes.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rotate_cli::parse::{self, Limits};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&max_len, cell)) = data.split_first() else {
+        return;
+    };
+    let Ok(json_text) = std::str::from_utf8(cell) else {
+        return;
+    };
+    let limits = Limits {
+        max_len: (max_len > 0).then_some(usize::from(max_len)),
+        ..Limits::default()
+    };
+
+    let (mut fast, mut serde) = (Vec::new(), Vec::new());
+    assert_eq!(
+        parse::parse_numbers_fast(json_text, &mut fast, limits),
+        parse::parse_numbers_limited(json_text, &mut serde, limits),
+        "{json_text:?}"
+    );
+    assert_eq!(fast, serde, "{json_text:?}");
+});
@@ -0,0 +1,46 @@
+//! The `json` column of one row through `process_json_bytes`, the CLI's row processing.
+//!
+//! The first input byte picks the output style, the rest is the cell. Besides not
+//! panicking, a valid row must serialize to an array that parses back to the same number
+//! of elements and turns back into the input with `rotate_left`, and an invalid row must
+//! serialize to `[]`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rotate_cli::json::JsonStyle;
+use rotate_cli::{RowBuffers, parse, process_json_bytes, rotate_left};
+
+const STYLES: [JsonStyle; 3] = [JsonStyle::Compact, JsonStyle::Spaced, JsonStyle::Node];
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&style, cell)) = data.split_first() else {
+        return;
+    };
+    let style = STYLES[usize::from(style) % STYLES.len()];
+
+    let mut buffers = RowBuffers::new();
+    if !process_json_bytes(cell, style, &mut buffers) {
+        assert_eq!(buffers.json, b"[]");
+        return;
+    }
+    let n = buffers.dimension(true).unwrap();
+    assert_eq!(n * n, buffers.numbers.len());
+
+    let mut reparsed = Vec::new();
+    let json = std::str::from_utf8(&buffers.json).unwrap();
+    assert!(parse::parse_numbers(json, &mut reparsed), "{json}");
+    assert_eq!(reparsed.len(), buffers.numbers.len());
+    if style != JsonStyle::Node {
+        // Node rounds beyond 2^53, so only the other styles round-trip exactly
+        assert_eq!(reparsed, buffers.numbers);
+    }
+
+    let mut original = Vec::new();
+    assert!(parse::parse_numbers(
+        std::str::from_utf8(cell).unwrap(),
+        &mut original
+    ));
+    rotate_left(&mut buffers.numbers).unwrap();
+    assert_eq!(buffers.numbers, original);
+});
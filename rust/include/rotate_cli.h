@@ -0,0 +1,40 @@
+/*
+ * C bindings for the rotate_cli rotation core.
+ *
+ * Build the shared library with `cargo build --release` in rust/ and link against
+ * target/release/librotate_cli.so (.dylib on macOS, .dll on Windows).
+ *
+ * Tables are flat, row-major arrays of n * n elements. Rotation shifts every element one
+ * position clockwise around its ring, in place. Errors leave the table untouched.
+ */
+#ifndef ROTATE_CLI_H
+#define ROTATE_CLI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes returned by the rotate_right_* functions. */
+#define ROTATE_OK 0
+#define ROTATE_ERR_NOT_SQUARE (-1) /* len is not a perfect square */
+#define ROTATE_ERR_EMPTY (-2)      /* len is 0 */
+#define ROTATE_ERR_NULL (-3)       /* data is NULL but len is not 0 */
+
+int rotate_right_i64(int64_t *data, size_t len);
+int rotate_right_i32(int32_t *data, size_t len);
+int rotate_right_f64(double *data, size_t len);
+
+/* Side length n of an n x n table with len elements, or 0 if len is not a non-zero square. */
+size_t rotate_square_len(size_t len);
+
+/* Library version, e.g. "0.1.0". The string is static and must not be freed. */
+const char *rotate_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ROTATE_CLI_H */
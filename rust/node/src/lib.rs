@@ -0,0 +1,223 @@
+//! Node.js bindings for the rotation core via N-API.
+//!
+//! Build with `npm run build` in this directory (which runs `napi build --release`); it
+//! produces a `.node` addon plus `index.js`/`index.d.ts` loaders. These entry points are
+//! exported:
+//!
+//! - `rotateRight(table)` rotates a `Float64Array` in place or returns a rotated copy of a
+//!   plain `number[]`, so N-API call overhead can be measured against the pure JS version.
+//! - `rotateBatch(tables)` rotates many tables in one call, on a rayon thread pool.
+//! - `CsvProcessor` consumes CSV input in arbitrary chunks (e.g. from a Node `Transform`
+//!   stream) and returns the finished output rows for each chunk, with exactly the CLI's
+//!   per-row semantics.
+
+use csv::WriterBuilder;
+use csv_core::ReadRecordResult;
+use napi::bindgen_prelude::{Buffer, Either, Float64Array};
+use napi::{Error, Result};
+use napi_derive::napi;
+use rotate_cli::{json, parse, rotate_batch as rotate_tables, rotate_right as rotate_table};
+
+/// Rotates a square table one step clockwise.
+///
+/// Typed arrays are rotated in place and returned; plain arrays are copied, rotated and the
+/// copy is returned. Throws if the table is empty or its length is not a perfect square.
+#[napi(ts_return_type = "number[] | Float64Array")]
+pub fn rotate_right(
+    #[napi(ts_arg_type = "number[] | Float64Array")] table: Either<Vec<f64>, Float64Array>,
+) -> Result<Either<Vec<f64>, Float64Array>> {
+    match table {
+        Either::A(mut values) => {
+            rotate_table(&mut values).map_err(to_napi_error)?;
+            Ok(Either::A(values))
+        }
+        Either::B(mut values) => {
+            rotate_table(&mut values).map_err(to_napi_error)?;
+            Ok(Either::B(values))
+        }
+    }
+}
+
+/// Rotates every table of `tables` one step clockwise, spreading them over a thread pool.
+///
+/// Returns the rotated tables in order, with `null` in place of each table that is empty or
+/// not square, so one bad table does not fail the whole batch.
+#[napi(ts_return_type = "Array<number[] | null>")]
+pub fn rotate_batch(tables: Vec<Vec<f64>>) -> Vec<Option<Vec<f64>>> {
+    let mut tables = tables;
+    let results = rotate_tables(&mut tables);
+    tables
+        .into_iter()
+        .zip(results)
+        .map(|(table, result)| result.ok().map(|()| table))
+        .collect()
+}
+
+fn to_napi_error(e: rotate_cli::RotationError) -> Error {
+    Error::from_reason(e.to_string())
+}
+
+/// Incremental CSV processor: `id,json` rows in, `id,json,is_valid` rows out.
+#[napi]
+pub struct CsvProcessor {
+    reader: csv_core::Reader,
+    /// Field bytes of the record being parsed
+    record: Vec<u8>,
+    record_len: usize,
+    /// Field end offsets of the record being parsed
+    ends: Vec<usize>,
+    ends_len: usize,
+    /// Whether the input header record has been consumed
+    header_done: bool,
+    /// Whether the output header has been emitted
+    header_written: bool,
+    finished: bool,
+    numbers: Vec<i64>,
+    json: Vec<u8>,
+    rows: i64,
+    valid: i64,
+}
+
+#[napi]
+impl CsvProcessor {
+    #[napi(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            reader: csv_core::Reader::new(),
+            record: vec![0; 1024],
+            record_len: 0,
+            ends: vec![0; 8],
+            ends_len: 0,
+            header_done: false,
+            header_written: false,
+            finished: false,
+            numbers: Vec::new(),
+            json: Vec::new(),
+            rows: 0,
+            valid: 0,
+        }
+    }
+
+    /// Feeds the next chunk of input and returns the output for every record it completed.
+    ///
+    /// The first call's output starts with the `id,json,is_valid` header.
+    #[napi]
+    pub fn push(&mut self, chunk: Buffer) -> Result<Buffer> {
+        if self.finished {
+            return Err(Error::from_reason("push() called after finish()"));
+        }
+        let mut out = Vec::new();
+        if !chunk.is_empty() {
+            self.feed(&chunk, &mut out)?;
+        }
+        Ok(out.into())
+    }
+
+    /// Signals end of input and returns the output for a final unterminated record.
+    #[napi]
+    pub fn finish(&mut self) -> Result<Buffer> {
+        let mut out = Vec::new();
+        if !self.finished {
+            // An empty input tells csv-core that the stream is over
+            self.feed(&[], &mut out)?;
+            self.finished = true;
+        }
+        Ok(out.into())
+    }
+
+    /// Number of data rows written so far.
+    #[napi(getter)]
+    pub fn rows(&self) -> i64 {
+        self.rows
+    }
+
+    /// Number of rows whose table was rotated.
+    #[napi(getter)]
+    pub fn valid(&self) -> i64 {
+        self.valid
+    }
+
+    fn feed(&mut self, mut input: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        let at_eof = input.is_empty();
+        let mut wtr = WriterBuilder::new().has_headers(false).from_writer(out);
+        if !self.header_written && !at_eof {
+            wtr.write_record(["id", "json", "is_valid"])
+                .map_err(to_csv_error)?;
+            self.header_written = true;
+        }
+
+        loop {
+            let (result, read, written, ends) = self.reader.read_record(
+                input,
+                &mut self.record[self.record_len..],
+                &mut self.ends[self.ends_len..],
+            );
+            input = &input[read..];
+            self.record_len += written;
+            self.ends_len += ends;
+
+            match result {
+                ReadRecordResult::InputEmpty => {
+                    if !at_eof {
+                        break;
+                    }
+                }
+                ReadRecordResult::OutputFull => {
+                    let len = self.record.len() * 2;
+                    self.record.resize(len, 0);
+                }
+                ReadRecordResult::OutputEndsFull => {
+                    let len = self.ends.len() * 2;
+                    self.ends.resize(len, 0);
+                }
+                ReadRecordResult::Record => {
+                    self.write_record(&mut wtr)?;
+                    self.record_len = 0;
+                    self.ends_len = 0;
+                }
+                ReadRecordResult::End => break,
+            }
+        }
+
+        wtr.flush().map_err(|e| Error::from_reason(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Rotates the record just parsed and appends its output row.
+    fn write_record<W: std::io::Write>(&mut self, wtr: &mut csv::Writer<W>) -> Result<()> {
+        if !self.header_done {
+            self.header_done = true;
+            return Ok(());
+        }
+        let ends = &self.ends[..self.ends_len];
+        if ends.len() < 2 {
+            return Ok(());
+        }
+        let id = &self.record[..ends[0]];
+        let json_text = &self.record[ends[0]..ends[1]];
+
+        let is_valid = std::str::from_utf8(json_text)
+            .is_ok_and(|text| parse::parse_numbers(text, &mut self.numbers))
+            && rotate_table(&mut self.numbers).is_ok();
+        self.json.clear();
+        if is_valid {
+            json::write_array(&mut self.json, &self.numbers);
+        } else {
+            self.json.extend_from_slice(b"[]");
+        }
+
+        self.rows += 1;
+        self.valid += i64::from(is_valid);
+        wtr.write_record([
+            id,
+            self.json.as_slice(),
+            if is_valid { b"true" } else { b"false" },
+        ])
+        .map_err(to_csv_error)
+    }
+}
+
+fn to_csv_error(e: csv::Error) -> Error {
+    Error::from_reason(e.to_string())
+}
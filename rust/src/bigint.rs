@@ -0,0 +1,126 @@
+//! `--bigint`: tables of integers of any size, such as the 256-bit values of cryptographic
+//! test vectors, which do not fit the `i64` tables of the other processors.
+//!
+//! Every element is parsed into a [`BigInt`] and written back with all its digits, so a
+//! value comes out exactly as it went in (`-0` as `0`, `2.0` as `2`). Whole numbers with a
+//! zero fraction are accepted as in the default mode; exponents such as `1e3` are not, since
+//! `1e400` would take more memory to expand than the text it came from.
+
+use crate::RowBuffers;
+use crate::json::JsonStyle;
+use crate::processor::{Processor, RowProcessor};
+use crate::tokens::{parse_values, write_processed};
+use num_bigint::BigInt;
+
+/// A built-in [`Processor`] applied to tables of arbitrary-size integers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BigIntProcessor {
+    processor: Processor,
+}
+
+impl BigIntProcessor {
+    pub fn new(processor: Processor) -> Self {
+        BigIntProcessor { processor }
+    }
+}
+
+impl RowProcessor for BigIntProcessor {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        match parse_values(json, buffers, parse_integer) {
+            Some(values) => write_processed(self.processor, &values, style, buffers),
+            None => false,
+        }
+    }
+}
+
+/// The integer a JSON value stands for, if it is a number with no fraction and no exponent.
+fn parse_integer(token: &str) -> Option<BigInt> {
+    // serde_json has checked the grammar, so only numbers parse, and `+` cannot occur
+    let (whole, fraction) = token.split_once('.').unwrap_or((token, ""));
+    if !fraction.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    whole.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::row::InvalidReason;
+
+    const P256: &str =
+        "115792089210356248762697774997256364154404427017396017016706651010281418325057";
+
+    fn process(processor: Processor, json: &str, style: JsonStyle) -> (String, bool) {
+        let mut buffers = RowBuffers::with_max_n(Some(3));
+        let valid = BigIntProcessor::new(processor).process(json.as_bytes(), style, &mut buffers);
+        (String::from_utf8(buffers.json).unwrap(), valid)
+    }
+
+    #[test]
+    fn test_bigint_processors() {
+        let table = format!("[1, -{P256}, 3, {P256}]");
+        assert_eq!(
+            process(Processor::Rotate, &table, JsonStyle::Compact),
+            (format!("[3,1,{P256},-{P256}]"), true)
+        );
+        assert_eq!(
+            process(Processor::Transpose, &table, JsonStyle::Spaced),
+            (format!("[1, 3, -{P256}, {P256}]"), true)
+        );
+        assert_eq!(
+            process(Processor::Validate, &table, JsonStyle::Node),
+            (format!("[1,-{P256},3,{P256}]"), true)
+        );
+        assert_eq!(
+            process(
+                Processor::Rotate,
+                "[ -0 , 2.000, 9007199254740993, 4 ]",
+                JsonStyle::Compact
+            ),
+            ("[9007199254740993,0,4,2]".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_bigint_invalid_tables() {
+        let cases = [
+            ("[1, 2, 3]", InvalidReason::NotSquare),
+            ("[]", InvalidReason::Empty),
+            ("[1, 2, 3, 4.5]", InvalidReason::Malformed),
+            ("[1, 2, 3, 1e3]", InvalidReason::Malformed),
+            ("[1, 2, 3, \"4\"]", InvalidReason::Malformed),
+            ("[1, 2, 3, null]", InvalidReason::Malformed),
+            ("[1, 2, 3, 4", InvalidReason::Malformed),
+            ("{\"a\": 1}", InvalidReason::Malformed),
+            (
+                "[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16]",
+                InvalidReason::TooLarge,
+            ),
+        ];
+        for (json, reason) in cases {
+            let mut buffers = RowBuffers::with_max_n(Some(3));
+            let valid = BigIntProcessor::default().process(
+                json.as_bytes(),
+                JsonStyle::Compact,
+                &mut buffers,
+            );
+            assert!(!valid, "{json}");
+            assert_eq!(buffers.json, b"[]", "{json}");
+            assert_eq!(buffers.invalid_reason(valid), Some(reason), "{json}");
+        }
+    }
+
+    #[test]
+    fn test_bigint_leaves_index_table() {
+        let mut buffers = RowBuffers::new();
+        let valid = BigIntProcessor::default().process(
+            format!("[{P256}, 2, 3, 4]").as_bytes(),
+            JsonStyle::Compact,
+            &mut buffers,
+        );
+        assert_eq!(buffers.numbers, [2, 0, 3, 1]);
+        assert_eq!(buffers.dimension(valid), Some(2));
+    }
+}
@@ -0,0 +1,238 @@
+//! `--max-memory`: an approximate cap on the bytes the [`pipeline`](crate::pipeline) holds.
+//!
+//! Two kinds of memory are tracked. Batches in flight (the records read, then their
+//! serialized rows, including those the writer holds back to restore input order) are
+//! *queued*: the reader waits before reading more while they would push the total past the
+//! limit, so a slow writer slows the reader down instead of growing the queues. The
+//! per-worker [`RowBuffers`](crate::RowBuffers) are *pinned*: they grow to fit the largest
+//! table seen so far, and a table that would take them past the limit fails the run with an
+//! error naming its record instead of letting the process get OOM-killed. Spare buffers that
+//! a worker frees again are unpinned.
+//!
+//! Only these buffers are counted, not the allocator's overhead or the rest of the
+//! process, so leave some headroom below the real limit.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Condvar, Mutex, PoisonError};
+
+/// Bytes charged for a batch per byte of input: the records, then the output rows.
+pub const BATCH_FACTOR: usize = 2;
+
+/// Shared accounting of the pipeline's buffers against a limit.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    queued: usize,
+    pinned: usize,
+    closed: bool,
+}
+
+impl MemoryBudget {
+    /// A budget of `limit` bytes; `None` never waits or fails.
+    pub fn new(limit: Option<usize>) -> Self {
+        MemoryBudget {
+            limit: limit.unwrap_or(usize::MAX),
+            state: Mutex::default(),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Reserves `bytes` for a batch, waiting while other batches hold the memory it needs.
+    ///
+    /// Fails if the batch could not fit even with nothing else queued. Returns at once after
+    /// [`close`](Self::close).
+    pub fn reserve(&self, bytes: usize) -> Result<(), BudgetError> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        while !state.closed && state.queued > 0 && self.exceeded(&state, bytes) {
+            state = self
+                .changed
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        if !state.closed && self.exceeded(&state, bytes) {
+            return Err(BudgetError::Batch {
+                bytes,
+                limit: self.limit,
+                pinned: state.pinned,
+            });
+        }
+        state.queued += bytes;
+        Ok(())
+    }
+
+    /// Returns the memory of a batch reserved with [`reserve`](Self::reserve).
+    pub fn release(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.queued = state.queued.saturating_sub(bytes);
+        self.changed.notify_all();
+    }
+
+    /// Records that row buffers grew by `bytes`; fails if that takes the total past the limit
+    /// even once every queued batch is gone.
+    pub fn pin(&self, bytes: usize) -> Result<(), BudgetError> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.pinned = state.pinned.saturating_add(bytes);
+        if state.pinned > self.limit {
+            return Err(BudgetError::Buffers {
+                pinned: state.pinned,
+                limit: self.limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the bytes of row buffers recorded with [`pin`](Self::pin) once they are freed.
+    pub fn unpin(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.pinned = state.pinned.saturating_sub(bytes);
+        self.changed.notify_all();
+    }
+
+    /// Wakes a waiting reader for good, once nothing will be released anymore.
+    pub fn close(&self) {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .closed = true;
+        self.changed.notify_all();
+    }
+
+    fn exceeded(&self, state: &State, bytes: usize) -> bool {
+        state
+            .queued
+            .saturating_add(state.pinned)
+            .saturating_add(bytes)
+            > self.limit
+    }
+}
+
+/// Memory that does not fit in the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetError {
+    /// A batch that would not fit even with nothing else queued
+    Batch {
+        bytes: usize,
+        limit: usize,
+        pinned: usize,
+    },
+    /// Row buffers that grew past the limit
+    Buffers { pinned: usize, limit: usize },
+}
+
+impl fmt::Display for BudgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            BudgetError::Batch {
+                bytes,
+                limit,
+                pinned,
+            } => write!(
+                f,
+                "a batch of {} does not fit in the --max-memory budget of {} ({} held by row \
+                 buffers)",
+                format_bytes(bytes),
+                format_bytes(limit),
+                format_bytes(pinned)
+            ),
+            BudgetError::Buffers { pinned, limit } => write!(
+                f,
+                "row buffers need {}, more than the --max-memory budget of {}",
+                format_bytes(pinned),
+                format_bytes(limit)
+            ),
+        }
+    }
+}
+
+impl Error for BudgetError {}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`.
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_reserve_waits_for_release() {
+        let budget = Arc::new(MemoryBudget::new(Some(100)));
+        budget.reserve(60).unwrap();
+        let waiter = {
+            let budget = Arc::clone(&budget);
+            thread::spawn(move || budget.reserve(60))
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert!(!waiter.is_finished());
+        budget.release(60);
+        waiter.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_errors() {
+        let budget = MemoryBudget::new(Some(100));
+        assert_eq!(
+            budget.reserve(150).unwrap_err().to_string(),
+            "a batch of 150 B does not fit in the --max-memory budget of 100 B \
+             (0 B held by row buffers)"
+        );
+        budget.pin(80).unwrap();
+        assert!(budget.reserve(30).is_err());
+        assert_eq!(
+            budget.pin(40).unwrap_err().to_string(),
+            "row buffers need 120 B, more than the --max-memory budget of 100 B"
+        );
+        let unlimited = MemoryBudget::new(None);
+        unlimited.pin(usize::MAX).unwrap();
+        unlimited.reserve(1 << 40).unwrap();
+    }
+
+    #[test]
+    fn test_unpin() {
+        let budget = MemoryBudget::new(Some(100));
+        budget.pin(80).unwrap();
+        budget.unpin(80);
+        budget.pin(90).unwrap();
+        budget.reserve(10).unwrap();
+    }
+
+    #[test]
+    fn test_close_wakes_the_reader() {
+        let budget = Arc::new(MemoryBudget::new(Some(100)));
+        budget.reserve(100).unwrap();
+        let waiter = {
+            let budget = Arc::clone(&budget);
+            thread::spawn(move || budget.reserve(1))
+        };
+        budget.close();
+        waiter.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(2 << 30), "2.0 GiB");
+    }
+}
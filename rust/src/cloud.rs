@@ -0,0 +1,134 @@
+//! Object-store (S3 / GCS) input and output.
+//!
+//! With the `object-store` feature, `s3://bucket/key.csv` and `gs://bucket/key.csv` URIs are
+//! accepted wherever a file path is. Credentials and region are taken from the standard
+//! environment variables (`AWS_ACCESS_KEY_ID`, `AWS_REGION`, `GOOGLE_SERVICE_ACCOUNT`, ...).
+//!
+//! The object-store client is async, so a small Tokio runtime drives it in the background
+//! while the CSV pipeline keeps its synchronous `Read`/`Write` view through `SyncIoBridge`.
+
+/// Returns `true` if the location names an object in S3 or GCS.
+pub fn is_object_uri(location: &str) -> bool {
+    location.starts_with("s3://") || location.starts_with("gs://")
+}
+
+#[cfg(feature = "object-store")]
+pub use imp::{ObjectWriter, create_writer, open_reader};
+
+#[cfg(feature = "object-store")]
+mod imp {
+    use futures::TryStreamExt;
+    use object_store::aws::AmazonS3Builder;
+    use object_store::buffered::BufWriter;
+    use object_store::gcp::GoogleCloudStorageBuilder;
+    use object_store::path::Path;
+    use object_store::{ObjectStore, ObjectStoreExt, ObjectStoreScheme};
+    use std::io::{self, Read, Write};
+    use std::sync::{Arc, OnceLock};
+    use tokio::runtime::{Builder, Runtime};
+    use tokio_util::io::{StreamReader, SyncIoBridge};
+    use url::Url;
+
+    /// Streams an object's body without downloading it first.
+    pub fn open_reader(uri: &str) -> io::Result<Box<dyn Read + Send>> {
+        let (store, path) = resolve(uri)?;
+        let rt = runtime();
+
+        let result = rt.block_on(store.get(&path)).map_err(io::Error::other)?;
+        let stream = result.into_stream().map_err(io::Error::other);
+        let reader = SyncIoBridge::new_with_handle(StreamReader::new(stream), rt.handle().clone());
+
+        Ok(Box::new(reader))
+    }
+
+    /// Creates a writer that uploads to the object using multipart uploads.
+    ///
+    /// Parts are uploaded in the background as the buffer fills, so memory use stays bounded
+    /// regardless of output size. The upload only becomes visible after [`ObjectWriter::finish`].
+    pub fn create_writer(uri: &str) -> io::Result<ObjectWriter> {
+        let (store, path) = resolve(uri)?;
+        let writer = BufWriter::new(store, path);
+
+        Ok(ObjectWriter {
+            inner: SyncIoBridge::new_with_handle(writer, runtime().handle().clone()),
+        })
+    }
+
+    /// Synchronous handle to an in-progress object upload.
+    pub struct ObjectWriter {
+        inner: SyncIoBridge<BufWriter>,
+    }
+
+    impl ObjectWriter {
+        /// Uploads any buffered data and completes the multipart upload.
+        pub fn finish(mut self) -> io::Result<()> {
+            self.inner.shutdown()
+        }
+    }
+
+    impl Write for ObjectWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    /// Builds the store for a URI's bucket and returns it with the object path.
+    fn resolve(uri: &str) -> io::Result<(Arc<dyn ObjectStore>, Path)> {
+        let url = Url::parse(uri).map_err(|e| invalid_uri(uri, e))?;
+        let (scheme, path) = ObjectStoreScheme::parse(&url).map_err(|e| invalid_uri(uri, e))?;
+
+        let store: Arc<dyn ObjectStore> = match scheme {
+            ObjectStoreScheme::AmazonS3 => Arc::new(
+                AmazonS3Builder::from_env()
+                    .with_url(uri)
+                    .build()
+                    .map_err(io::Error::other)?,
+            ),
+            ObjectStoreScheme::GoogleCloudStorage => Arc::new(
+                GoogleCloudStorageBuilder::from_env()
+                    .with_url(uri)
+                    .build()
+                    .map_err(io::Error::other)?,
+            ),
+            other => return Err(invalid_uri(uri, format!("unsupported scheme {other:?}"))),
+        };
+
+        Ok((store, path))
+    }
+
+    fn invalid_uri(uri: &str, reason: impl std::fmt::Display) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid object URI {uri}: {reason}"),
+        )
+    }
+
+    /// Shared background runtime driving all object-store I/O.
+    fn runtime() -> &'static Runtime {
+        static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+        RUNTIME.get_or_init(|| {
+            Builder::new_multi_thread()
+                .worker_threads(2)
+                .enable_all()
+                .build()
+                .expect("failed to start object-store runtime")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_object_uri() {
+        assert!(is_object_uri("s3://bucket/key.csv"));
+        assert!(is_object_uri("gs://bucket/dir/key.csv"));
+        assert!(!is_object_uri("https://example.com/key.csv"));
+        assert!(!is_object_uri("s3-results.csv"));
+    }
+}
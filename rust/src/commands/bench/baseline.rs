@@ -0,0 +1,130 @@
+//! Named results of earlier `rotate_cli bench` runs, for regression checks in CI.
+//!
+//! `--save-baseline NAME` stores the summary as `DIR/NAME.json`; `--compare-baseline NAME`
+//! loads it and fails when the mean wall time grew by more than `--threshold` percent.
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Summary of one benchmark, as stored on disk. Times are in seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub command: String,
+    pub rows: u64,
+    pub runs: usize,
+    pub mean: f64,
+    pub stddev: Option<f64>,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Baseline {
+    fn path(dir: &Path, name: &str) -> PathBuf {
+        dir.join(format!("{name}.json"))
+    }
+
+    pub fn save(&self, dir: &Path, name: &str) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("cannot create {}: {e}", dir.display()))?;
+        let path = Self::path(dir, name);
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json + "\n")
+            .map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn load(dir: &Path, name: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let path = Self::path(dir, name);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("cannot read baseline {}: {e}", path.display()))?;
+        Ok(serde_json::from_str(&text)
+            .map_err(|e| format!("invalid baseline {}: {e}", path.display()))?)
+    }
+}
+
+/// Change of the mean wall time against a baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub name: String,
+    pub baseline: f64,
+    pub current: f64,
+    /// Largest allowed slowdown in percent
+    pub threshold: f64,
+}
+
+impl Comparison {
+    /// Relative change in percent; positive is slower.
+    pub fn change(&self) -> f64 {
+        (self.current / self.baseline - 1.0) * 100.0
+    }
+
+    pub fn is_regression(&self) -> bool {
+        self.change() > self.threshold
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "baseline     {}: {:.3} s -> {:.3} s ({:+.1}%, threshold {}%)",
+            self.name,
+            self.baseline,
+            self.current,
+            self.change(),
+            self.threshold
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let baseline = Baseline {
+            command: "rotate_cli in.csv".to_string(),
+            rows: 10,
+            runs: 3,
+            mean: 0.5,
+            stddev: Some(0.01),
+            median: 0.5,
+            min: 0.49,
+            max: 0.51,
+        };
+        let path = baseline.save(&dir.path().join("nested"), "main").unwrap();
+        assert!(path.ends_with("nested/main.json"));
+        assert_eq!(
+            Baseline::load(&dir.path().join("nested"), "main").unwrap(),
+            baseline
+        );
+        let missing = Baseline::load(dir.path(), "other").unwrap_err();
+        assert!(missing.to_string().starts_with("cannot read baseline"));
+    }
+
+    #[test]
+    fn test_comparison() {
+        let comparison = Comparison {
+            name: "main".to_string(),
+            baseline: 0.5,
+            current: 0.53,
+            threshold: 5.0,
+        };
+        assert!(comparison.is_regression());
+        assert_eq!(
+            comparison.to_string(),
+            "baseline     main: 0.500 s -> 0.530 s (+6.0%, threshold 5%)"
+        );
+        let faster = Comparison {
+            current: 0.4,
+            ..comparison
+        };
+        assert!(!faster.is_regression());
+        assert!((faster.change() + 20.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,810 @@
+//! `rotate_cli bench INPUT.csv`: measure in-process runs of the pipeline.
+//!
+//! The input is processed exactly like `rotate_cli INPUT.csv` but the output is discarded,
+//! so the numbers cover reading, parsing, rotation and serialization only. Besides wall time
+//! and throughput the report includes CPU time and peak RSS (Unix only) and, when built with
+//! the `count-allocs` feature, the number of heap allocations.
+//!
+//! Like hyperfine, `--warmup` runs are discarded and `--runs` runs are summarized with their
+//! mean, standard deviation, median and range, plus a 95% confidence interval of the mean.
+//! Runs whose modified z-score (based on the median absolute deviation) exceeds 3.5 are
+//! rejected as outliers unless `--keep-outliers` is given. `--export-json` writes
+//! hyperfine-compatible JSON, so existing analysis scripts can read the results.
+//!
+//! `--save-baseline NAME` and `--compare-baseline NAME` turn the command into a performance
+//! gate: the second fails when the mean wall time regressed beyond `--threshold` (see
+//! [`baseline`]).
+//!
+//! `--by-size` additionally groups the valid rows by table size and measures each group on
+//! its own, for a throughput-versus-size curve. `--report FILE` writes the results as JSON,
+//! CSV or a self-contained HTML page with charts (see [`crate::report`]). `--profile out.svg`
+//! records a flamegraph of the measured runs (see [`profile`]).
+
+use crate::report::{Report, Section};
+use crate::stats::{Counters, Resources, Summary};
+use crate::{RowFormat, default_threads, input, pipeline};
+use baseline::{Baseline, Comparison};
+use clap::Args;
+use csv::{ReaderBuilder, WriterBuilder};
+use profile::Profiler;
+use rotate_cli::json::JsonStyle;
+use rotate_cli::processor::RotateRight;
+use rotate_cli::{parse, square_len};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+mod baseline;
+mod profile;
+
+/// Modified z-score above which a run counts as an outlier (Iglewicz and Hoaglin).
+const OUTLIER_Z_SCORE: f64 = 3.5;
+
+/// Two-sided 95% critical values of Student's t distribution for 1 to 30 degrees of freedom.
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Path or http(s) URL of the input CSV file with columns 'id' and 'json'
+    input: String,
+
+    /// Number of worker threads rotating rows (defaults to the number of CPU cores)
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+
+    /// Also produce the `n` column (see the top-level --emit-dimension)
+    #[arg(long)]
+    emit_dimension: bool,
+
+    /// Output array style (see the top-level --json-style)
+    #[arg(long, value_name = "STYLE", default_value_t = JsonStyle::Compact)]
+    json_style: JsonStyle,
+
+    /// Unmeasured runs before the measured ones, to warm up caches
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    warmup: u32,
+
+    /// Measured runs
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    runs: u32,
+
+    /// Include outlier runs in the statistics instead of rejecting them
+    #[arg(long)]
+    keep_outliers: bool,
+
+    /// Write the results as hyperfine-compatible JSON to FILE
+    #[arg(long, value_name = "FILE")]
+    export_json: Option<PathBuf>,
+
+    /// Store the results as baseline NAME
+    #[arg(long, value_name = "NAME")]
+    save_baseline: Option<String>,
+
+    /// Compare with baseline NAME and fail if the mean wall time regressed
+    #[arg(long, value_name = "NAME")]
+    compare_baseline: Option<String>,
+
+    /// Largest allowed slowdown against the baseline, in percent
+    #[arg(long, value_name = "PERCENT", default_value_t = 5.0)]
+    threshold: f64,
+
+    /// Directory of the stored baselines
+    #[arg(long, value_name = "DIR", default_value = "target/bench-baselines")]
+    baseline_dir: PathBuf,
+
+    /// Also measure the valid rows of each table size separately
+    #[arg(long)]
+    by_size: bool,
+
+    /// Write a report to FILE (.json, .csv or .html); may be repeated
+    #[arg(long, value_name = "FILE")]
+    report: Vec<PathBuf>,
+
+    /// Write a flamegraph (SVG) of the measured runs to FILE (requires the `profile` feature)
+    #[arg(long, value_name = "FILE")]
+    profile: Option<PathBuf>,
+}
+
+impl BenchArgs {
+    /// The equivalent CLI invocation, used as the `command` of exported results.
+    fn command(&self) -> String {
+        let mut command = format!("rotate_cli {}", self.input);
+        if let Some(threads) = self.threads {
+            let _ = write!(command, " --threads {threads}");
+        }
+        if self.emit_dimension {
+            command.push_str(" --emit-dimension");
+        }
+        if self.json_style != JsonStyle::Compact {
+            let _ = write!(command, " --json-style {}", self.json_style);
+        }
+        command
+    }
+}
+
+/// What one run processed and what it cost.
+struct Measurement {
+    summary: Summary,
+    resources: Resources,
+}
+
+/// All measured runs and which of them were rejected.
+struct Benchmark {
+    warmup: u32,
+    runs: Vec<Measurement>,
+    /// Indices into `runs`
+    outliers: Vec<usize>,
+}
+
+impl Benchmark {
+    fn kept(&self) -> impl Iterator<Item = &Measurement> {
+        self.runs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.outliers.contains(i))
+            .map(|(_, run)| run)
+    }
+
+    /// Wall times of the kept runs in seconds.
+    fn times(&self) -> Vec<f64> {
+        self.kept()
+            .map(|run| run.summary.elapsed.as_secs_f64())
+            .collect()
+    }
+
+    /// Mean user and system CPU time of the kept runs, if measured.
+    fn mean_cpu(&self) -> Option<(Duration, Duration)> {
+        let cpu: Vec<_> = self
+            .kept()
+            .map(|run| run.resources.usage.cpu)
+            .collect::<Option<_>>()?;
+        let runs = cpu.len() as u32;
+        let user = cpu.iter().map(|cpu| cpu.user).sum::<Duration>() / runs;
+        let system = cpu.iter().map(|cpu| cpu.system).sum::<Duration>() / runs;
+        Some((user, system))
+    }
+
+    fn baseline(&self, command: &str) -> Baseline {
+        let times = self.times();
+        let statistics = Statistics::of(&times);
+        Baseline {
+            command: command.to_string(),
+            rows: self.runs[0].summary.rows,
+            runs: times.len(),
+            mean: statistics.mean,
+            stddev: statistics.stddev,
+            median: statistics.median,
+            min: statistics.min,
+            max: statistics.max,
+        }
+    }
+}
+
+pub fn run(args: BenchArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Loaded first, so a missing baseline fails before the runs and before it is replaced
+    // by `--save-baseline` with the same name
+    let compare_to = match &args.compare_baseline {
+        Some(name) => Some((name, Baseline::load(&args.baseline_dir, name)?)),
+        None => None,
+    };
+    for _ in 0..args.warmup {
+        measure(&args)?;
+    }
+    let profiler = args
+        .profile
+        .as_ref()
+        .map(|_| Profiler::start())
+        .transpose()?;
+    let runs = (0..args.runs)
+        .map(|_| measure(&args))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let (Some(profiler), Some(path)) = (profiler, &args.profile) {
+        profiler.finish(path)?;
+        log::info!("Wrote flamegraph to {}", path.display());
+    }
+    let outliers = if args.keep_outliers {
+        Vec::new()
+    } else {
+        let times: Vec<f64> = runs
+            .iter()
+            .map(|run| run.summary.elapsed.as_secs_f64())
+            .collect();
+        outliers(&times)
+    };
+    let benchmark = Benchmark {
+        warmup: args.warmup,
+        runs,
+        outliers,
+    };
+
+    print!("{}", report(&benchmark));
+    let sizes = if args.by_size {
+        let sizes = measure_sizes(&args)?;
+        print!("{}", sizes_report(&sizes));
+        sizes
+    } else {
+        Vec::new()
+    };
+    for path in &args.report {
+        structured_report(&args.command(), &benchmark, &sizes).write(path)?;
+    }
+    if let Some(path) = &args.export_json {
+        let json = serde_json::to_string_pretty(&hyperfine_json(&args.command(), &benchmark))?;
+        std::fs::write(path, json + "\n")
+            .map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+    }
+
+    let current = benchmark.baseline(&args.command());
+    let comparison = compare_to.map(|(name, baseline)| {
+        if baseline.command != current.command {
+            log::warn!(
+                "baseline {name} was recorded for `{}`, not `{}`",
+                baseline.command,
+                current.command
+            );
+        }
+        Comparison {
+            name: name.clone(),
+            baseline: baseline.mean,
+            current: current.mean,
+            threshold: args.threshold,
+        }
+    });
+    if let Some(name) = &args.save_baseline {
+        let path = current.save(&args.baseline_dir, name)?;
+        log::info!("Saved baseline {name} to {}", path.display());
+    }
+    if let Some(comparison) = comparison {
+        println!("{comparison}");
+        if comparison.is_regression() {
+            return Err(format!(
+                "performance regression: {:+.1}% against baseline {} exceeds {}%",
+                comparison.change(),
+                comparison.name,
+                comparison.threshold
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn measure(args: &BenchArgs) -> Result<Measurement, Box<dyn Error + Send + Sync>> {
+    measure_source(args, || input::open(&args.input))
+}
+
+/// Processes the CSV returned by `open`, which is timed too.
+fn measure_source<R: Read + Send>(
+    args: &BenchArgs,
+    open: impl FnOnce() -> io::Result<R>,
+) -> Result<Measurement, Box<dyn Error + Send + Sync>> {
+    let format = RowFormat {
+        emit_dimension: args.emit_dimension,
+        json_style: args.json_style,
+        ..RowFormat::default()
+    };
+    let workers = args.threads.unwrap_or_else(default_threads);
+    let counters = Counters::default();
+
+    let before = Resources::now();
+    let started = Instant::now();
+    let rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(open()?);
+    pipeline::run(
+        rdr,
+        io::sink(),
+        workers,
+        format,
+        &RotateRight,
+        &counters,
+        None,
+    )?;
+    let elapsed = started.elapsed();
+
+    Ok(Measurement {
+        summary: counters.snapshot(elapsed),
+        resources: Resources::now().since(&before),
+    })
+}
+
+/// Mean throughput of the rows with n×n tables.
+struct SizeResult {
+    n: usize,
+    rows: u64,
+    /// Mean wall time over `--runs` runs
+    elapsed: Duration,
+}
+
+impl SizeResult {
+    fn rows_per_sec(&self) -> f64 {
+        self.rows as f64 / self.elapsed.as_secs_f64()
+    }
+
+    fn cells_per_sec(&self) -> f64 {
+        (self.rows * (self.n * self.n) as u64) as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+fn measure_sizes(args: &BenchArgs) -> Result<Vec<SizeResult>, Box<dyn Error + Send + Sync>> {
+    let mut results = Vec::new();
+    for (n, csv) in split_by_size(&args.input)? {
+        let mut total = Duration::ZERO;
+        let mut rows = 0;
+        for _ in 0..args.runs {
+            let measurement = measure_source(args, || Ok(csv.as_slice()))?;
+            total += measurement.summary.elapsed;
+            rows = measurement.summary.rows;
+        }
+        results.push(SizeResult {
+            n,
+            rows,
+            elapsed: total / args.runs,
+        });
+    }
+    Ok(results)
+}
+
+/// The valid rows of `input` grouped by table side length, each group as a CSV input of
+/// its own.
+fn split_by_size(input: &str) -> Result<BTreeMap<usize, Vec<u8>>, Box<dyn Error + Send + Sync>> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(input::open(input)?);
+    let mut groups = BTreeMap::new();
+    let mut numbers = Vec::new();
+    for record in rdr.byte_records() {
+        let record = record?;
+        let Some(Ok(json_text)) = record.get(1).map(std::str::from_utf8) else {
+            continue;
+        };
+        if !parse::parse_numbers(json_text, &mut numbers) || numbers.is_empty() {
+            continue;
+        }
+        let Some(n) = square_len(numbers.len()) else {
+            continue;
+        };
+        let wtr = groups.entry(n).or_insert_with(|| {
+            let mut wtr = WriterBuilder::new().from_writer(Vec::new());
+            let _ = wtr.write_record(["id", "json"]);
+            wtr
+        });
+        wtr.write_byte_record(&record)?;
+    }
+    groups
+        .into_iter()
+        .map(|(n, wtr)| Ok((n, wtr.into_inner().map_err(|e| e.into_error())?)))
+        .collect()
+}
+
+/// Indices of the times whose modified z-score exceeds [`OUTLIER_Z_SCORE`].
+fn outliers(times: &[f64]) -> Vec<usize> {
+    if times.len() < 3 {
+        return Vec::new();
+    }
+    let center = median(times);
+    let deviations: Vec<f64> = times.iter().map(|time| (time - center).abs()).collect();
+    let mad = median(&deviations);
+    if mad == 0.0 {
+        return Vec::new();
+    }
+    deviations
+        .iter()
+        .enumerate()
+        .filter(|(_, deviation)| 0.6745 * *deviation / mad > OUTLIER_Z_SCORE)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Mean, standard deviation, median, minimum and maximum of a non-empty sample.
+struct Statistics {
+    mean: f64,
+    /// `None` for a single run
+    stddev: Option<f64>,
+    median: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Statistics {
+    fn of(times: &[f64]) -> Self {
+        let n = times.len() as f64;
+        let mean = times.iter().sum::<f64>() / n;
+        let stddev = (times.len() > 1).then(|| {
+            let variance = times.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            variance.sqrt()
+        });
+        Statistics {
+            mean,
+            stddev,
+            median: median(times),
+            min: times.iter().copied().fold(f64::INFINITY, f64::min),
+            max: times.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    /// Half-width of the 95% confidence interval of the mean of `runs` runs.
+    fn ci95(&self, runs: usize) -> Option<f64> {
+        let stddev = self.stddev?;
+        let t = T_95.get(runs - 2).copied().unwrap_or(1.96);
+        Some(t * stddev / (runs as f64).sqrt())
+    }
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+fn report(benchmark: &Benchmark) -> String {
+    let times = benchmark.times();
+    let statistics = Statistics::of(&times);
+    let first = &benchmark.runs[0].summary;
+    let mut out = String::new();
+
+    if benchmark.runs.len() > 1 || benchmark.warmup > 0 {
+        let _ = writeln!(
+            out,
+            "runs         {} ({} outliers rejected, {} warmup)",
+            benchmark.runs.len(),
+            benchmark.outliers.len(),
+            benchmark.warmup
+        );
+    }
+    let _ = writeln!(
+        out,
+        "rows         {} ({} valid, {} invalid)\n\
+         input        {:.2} MB",
+        first.rows,
+        first.valid,
+        first.invalid(),
+        first.input_bytes as f64 / 1e6,
+    );
+    match (statistics.stddev, statistics.ci95(times.len())) {
+        (Some(stddev), Some(ci)) => {
+            let _ = writeln!(
+                out,
+                "wall time    {:.3} s ± {stddev:.3} s (mean ± σ), 95% CI {:.3} s … {:.3} s\n\
+                 median       {:.3} s (min {:.3} s, max {:.3} s)",
+                statistics.mean,
+                statistics.mean - ci,
+                statistics.mean + ci,
+                statistics.median,
+                statistics.min,
+                statistics.max,
+            );
+        }
+        _ => {
+            let _ = writeln!(out, "wall time    {:.3} s", statistics.mean);
+        }
+    }
+    let _ = writeln!(
+        out,
+        "throughput   {:.0} rows/s, {:.2} MB/s",
+        first.rows as f64 / statistics.mean,
+        first.input_bytes as f64 / 1e6 / statistics.mean,
+    );
+
+    let _ = match benchmark.mean_cpu() {
+        Some((user, system)) => writeln!(
+            out,
+            "CPU time     {:.3} s (user {:.3} s, system {:.3} s)",
+            (user + system).as_secs_f64(),
+            user.as_secs_f64(),
+            system.as_secs_f64()
+        ),
+        None => writeln!(out, "CPU time     n/a"),
+    };
+    let max_rss = benchmark
+        .kept()
+        .map(|run| run.resources.usage.max_rss)
+        .max()
+        .flatten();
+    let _ = match max_rss {
+        Some(rss) => writeln!(
+            out,
+            "peak RSS     {:.1} MiB",
+            rss as f64 / (1024.0 * 1024.0)
+        ),
+        None => writeln!(out, "peak RSS     n/a"),
+    };
+    let allocs: Option<Vec<_>> = benchmark.kept().map(|run| run.resources.allocs).collect();
+    let _ = match allocs {
+        Some(allocs) => {
+            let runs = allocs.len() as u64;
+            writeln!(
+                out,
+                "allocations  {} ({:.2} MB) per run",
+                allocs.iter().map(|a| a.allocations).sum::<u64>() / runs,
+                allocs.iter().map(|a| a.bytes).sum::<u64>() as f64 / runs as f64 / 1e6
+            )
+        }
+        None => writeln!(
+            out,
+            "allocations  n/a (build with `--features count-allocs`)"
+        ),
+    };
+    out
+}
+
+fn sizes_report(sizes: &[SizeResult]) -> String {
+    let mut out = String::from("by size\n");
+    for size in sizes {
+        let _ = writeln!(
+            out,
+            "  {:>9} {:>8} rows  {:>10.0} rows/s  {:>8.2} M cells/s",
+            format!("{0}x{0}", size.n),
+            size.rows,
+            size.rows_per_sec(),
+            size.cells_per_sec() / 1e6
+        );
+    }
+    out
+}
+
+/// The results for `--report`.
+fn structured_report(command: &str, benchmark: &Benchmark, sizes: &[SizeResult]) -> Report {
+    let times = benchmark.times();
+    let statistics = Statistics::of(&times);
+    let first = &benchmark.runs[0].summary;
+    let cpu = benchmark
+        .mean_cpu()
+        .map(|(user, system)| (user + system).as_secs_f64());
+    let mib = |rss: Option<u64>| rss.map(|rss| rss as f64 / (1024.0 * 1024.0));
+
+    let mut summary = Section::new(
+        "summary",
+        &[
+            "command",
+            "runs",
+            "outliers",
+            "rows",
+            "valid",
+            "input (MB)",
+            "mean (s)",
+            "stddev (s)",
+            "median (s)",
+            "min (s)",
+            "max (s)",
+            "rows/s",
+            "MB/s",
+            "cpu mean (s)",
+            "peak RSS (MiB)",
+        ],
+    );
+    let max_rss = benchmark
+        .kept()
+        .map(|run| run.resources.usage.max_rss)
+        .max()
+        .flatten();
+    summary.rows.push(vec![
+        json!(command),
+        json!(benchmark.runs.len()),
+        json!(benchmark.outliers.len()),
+        json!(first.rows),
+        json!(first.valid),
+        json!(first.input_bytes as f64 / 1e6),
+        json!(statistics.mean),
+        json!(statistics.stddev),
+        json!(statistics.median),
+        json!(statistics.min),
+        json!(statistics.max),
+        json!(first.rows as f64 / statistics.mean),
+        json!(first.input_bytes as f64 / 1e6 / statistics.mean),
+        json!(cpu),
+        json!(mib(max_rss)),
+    ]);
+
+    let mut runs = Section::new(
+        "runs",
+        &["run", "wall (s)", "cpu (s)", "peak RSS (MiB)", "outlier"],
+    )
+    .with_chart("Wall time per run (s)", "run", "wall (s)");
+    for (i, run) in benchmark.runs.iter().enumerate() {
+        runs.rows.push(vec![
+            json!(i + 1),
+            json!(run.summary.elapsed.as_secs_f64()),
+            json!(run.resources.usage.cpu.map(|cpu| cpu.total().as_secs_f64())),
+            json!(mib(run.resources.usage.max_rss)),
+            json!(benchmark.outliers.contains(&i)),
+        ]);
+    }
+
+    let mut sections = vec![summary, runs];
+    if !sizes.is_empty() {
+        let mut by_size = Section::new(
+            "by size",
+            &["table", "rows", "mean (s)", "rows/s", "cells/s"],
+        )
+        .with_chart("Throughput by table size (cells/s)", "table", "cells/s");
+        for size in sizes {
+            by_size.rows.push(vec![
+                Value::String(format!("{0}x{0}", size.n)),
+                json!(size.rows),
+                json!(size.elapsed.as_secs_f64()),
+                json!(size.rows_per_sec()),
+                json!(size.cells_per_sec()),
+            ]);
+        }
+        sections.push(by_size);
+    }
+    Report {
+        title: format!("rotate_cli bench: {command}"),
+        sections,
+    }
+}
+
+/// Results in the format of `hyperfine --export-json`, with times in seconds.
+fn hyperfine_json(command: &str, benchmark: &Benchmark) -> serde_json::Value {
+    let times = benchmark.times();
+    let statistics = Statistics::of(&times);
+    let (user, system) = benchmark.mean_cpu().unwrap_or_default();
+    let memory: Option<Vec<u64>> = benchmark
+        .kept()
+        .map(|run| run.resources.usage.max_rss)
+        .collect();
+    json!({
+        "results": [{
+            "command": command,
+            "mean": statistics.mean,
+            "stddev": statistics.stddev,
+            "median": statistics.median,
+            "user": user.as_secs_f64(),
+            "system": system.as_secs_f64(),
+            "min": statistics.min,
+            "max": statistics.max,
+            "times": times,
+            "memory_usage_byte": memory,
+            "exit_codes": vec![0; times.len()],
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusage::{CpuTime, Usage};
+    use clap::Parser;
+    use rotate_cli::counting_alloc::AllocStats;
+    use std::io::Write;
+
+    #[derive(Parser)]
+    struct Wrapper {
+        #[command(flatten)]
+        args: BenchArgs,
+    }
+
+    fn measurement(millis: u64) -> Measurement {
+        Measurement {
+            summary: Summary {
+                rows: 4000,
+                valid: 3900,
+                input_bytes: 2_000_000,
+                elapsed: Duration::from_millis(millis),
+            },
+            resources: Resources {
+                usage: Usage {
+                    cpu: Some(CpuTime {
+                        user: Duration::from_millis(millis * 2),
+                        system: Duration::from_millis(millis / 2),
+                    }),
+                    max_rss: Some(12 << 20),
+                },
+                allocs: Some(AllocStats {
+                    allocations: 42,
+                    bytes: 1_500_000,
+                }),
+            },
+        }
+    }
+
+    #[test]
+    fn test_measure() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2]\"\n")
+            .unwrap();
+        let path = file.path().to_str().unwrap();
+        let Wrapper { args } = Wrapper::parse_from(["bench", path, "-j", "2"]);
+
+        let measurement = measure(&args).unwrap();
+        assert_eq!(measurement.summary.rows, 2);
+        assert_eq!(measurement.summary.valid, 1);
+        assert_eq!(measurement.summary.input_bytes, 36);
+        assert_eq!(args.command(), format!("rotate_cli {path} --threads 2"));
+    }
+
+    #[test]
+    fn test_split_by_size() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(
+            b"id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1]\"\n3,\"[1, 2]\"\n4,\"[5, 6, 7, 8]\"\n",
+        )
+        .unwrap();
+        let groups = split_by_size(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(groups.keys().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(groups[&1], b"id,json\n2,[1]\n");
+        assert_eq!(
+            groups[&2],
+            b"id,json\n1,\"[1, 2, 3, 4]\"\n4,\"[5, 6, 7, 8]\"\n"
+        );
+    }
+
+    #[test]
+    fn test_outliers() {
+        assert_eq!(outliers(&[0.10, 0.11, 0.10, 0.30, 0.12, 0.09]), [3]);
+        assert!(outliers(&[0.10, 0.50]).is_empty());
+        assert!(outliers(&[0.10, 0.10, 0.10, 0.50, 0.10]).is_empty());
+    }
+
+    #[test]
+    fn test_statistics() {
+        let statistics = Statistics::of(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(statistics.mean, 2.5);
+        assert_eq!(statistics.median, 2.5);
+        let stddev = statistics.stddev.unwrap();
+        assert!((stddev - 1.2910).abs() < 1e-4);
+        // t(3) = 3.182
+        assert!((statistics.ci95(4).unwrap() - 3.182 * stddev / 2.0).abs() < 1e-9);
+        assert_eq!(Statistics::of(&[1.0]).ci95(1), None);
+    }
+
+    #[test]
+    fn test_report_single_run() {
+        let benchmark = Benchmark {
+            warmup: 0,
+            runs: vec![measurement(500)],
+            outliers: Vec::new(),
+        };
+        assert_eq!(
+            report(&benchmark),
+            "\
+rows         4000 (3900 valid, 100 invalid)
+input        2.00 MB
+wall time    0.500 s
+throughput   8000 rows/s, 4.00 MB/s
+CPU time     1.250 s (user 1.000 s, system 0.250 s)
+peak RSS     12.0 MiB
+allocations  42 (1.50 MB) per run
+"
+        );
+    }
+
+    #[test]
+    fn test_report_and_export_repeated_runs() {
+        let benchmark = Benchmark {
+            warmup: 2,
+            runs: [400, 600, 500, 5000].map(measurement).into(),
+            outliers: vec![3],
+        };
+        let report = report(&benchmark);
+        assert!(report.starts_with("runs         4 (1 outliers rejected, 2 warmup)\n"));
+        assert!(report.contains(
+            "wall time    0.500 s ± 0.100 s (mean ± σ), 95% CI 0.252 s … 0.748 s\n\
+             median       0.500 s (min 0.400 s, max 0.600 s)\n"
+        ));
+
+        let json = hyperfine_json("rotate_cli in.csv", &benchmark);
+        let result = &json["results"][0];
+        assert_eq!(result["command"], "rotate_cli in.csv");
+        assert_eq!(result["times"], json!([0.4, 0.6, 0.5]));
+        assert_eq!(result["mean"], 0.5);
+        assert_eq!(result["exit_codes"], json!([0, 0, 0]));
+        assert_eq!(result["user"], 1.0);
+    }
+}
@@ -0,0 +1,76 @@
+//! `--profile out.svg`: a flamegraph of the measured runs, from pprof's sampling profiler.
+//!
+//! Samples are taken from every thread at [`FREQUENCY`] Hz while the measured runs execute
+//! (warmup runs are left out) and rendered with inferno, so no `perf` or external
+//! flamegraph scripts are needed. Building with the `profile` feature is required.
+
+use std::error::Error;
+use std::path::Path;
+
+/// Sampling frequency; a prime, so samples do not line up with periodic work.
+#[cfg_attr(not(feature = "profile"), allow(dead_code))]
+const FREQUENCY: i32 = 997;
+
+/// A running profiler.
+#[cfg(feature = "profile")]
+pub struct Profiler(pprof::ProfilerGuard<'static>);
+
+#[cfg(feature = "profile")]
+impl Profiler {
+    pub fn start() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(FREQUENCY)
+            // Frames inside these libraries cannot be unwound reliably
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()?;
+        Ok(Profiler(guard))
+    }
+
+    /// Stops sampling and writes the flamegraph as SVG to `path`.
+    pub fn finish(self, path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let report = self.0.report().build()?;
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+        report.flamegraph(file)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "profile"))]
+pub struct Profiler;
+
+#[cfg(not(feature = "profile"))]
+impl Profiler {
+    pub fn start() -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "--profile requires building with `--features profile`",
+        )))
+    }
+
+    pub fn finish(self, _path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "profile"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flamegraph() {
+        let profiler = Profiler::start().unwrap();
+        // Enough work for a few samples
+        let mut data: Vec<u64> = (0..4_000_000).collect();
+        for _ in 0..5 {
+            rotate_cli::rotate_right(&mut data[..4_000_000]).unwrap();
+        }
+        std::hint::black_box(&data);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flamegraph.svg");
+        profiler.finish(&path).unwrap();
+        let svg = std::fs::read_to_string(path).unwrap();
+        assert!(svg.contains("<svg"));
+    }
+}
@@ -0,0 +1,240 @@
+//! `rotate_cli compare`: check that two result files agree, up to a numeric tolerance.
+//!
+//! Rows are compared in file order and must have the same id. The `json` columns are
+//! parsed and compared element by element, so `1.0` matches `1` and `0.30000000000000004`
+//! matches `0.3` within `--tolerance`; every other column must be byte-for-byte equal.
+//! Sort both files first (`rotate_cli sort`) when their row order may differ.
+
+use crate::input;
+use clap::Args;
+use csv::{ByteRecord, ReaderBuilder};
+use rotate_cli::Tolerance;
+use std::error::Error;
+use std::io::Read;
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// Result file (path or URI)
+    left: String,
+
+    /// Result file (path or URI) to compare it with
+    right: String,
+
+    /// Largest absolute difference between two elements that still counts as equal
+    #[arg(long, value_name = "ABS", default_value_t = 0.0)]
+    tolerance: f64,
+
+    /// Largest difference relative to the larger magnitude of the two elements
+    #[arg(long, value_name = "REL", default_value_t = 0.0)]
+    relative_tolerance: f64,
+
+    /// Print at most this many differing rows
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    max_diffs: usize,
+}
+
+pub fn run(args: CompareArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let tolerance = Tolerance {
+        absolute: args.tolerance,
+        relative: args.relative_tolerance,
+    };
+    let mut shown = 0;
+    let comparison = compare(
+        input::open(&args.left)?,
+        input::open(&args.right)?,
+        tolerance,
+        |difference| {
+            if shown < args.max_diffs {
+                eprintln!("{difference}");
+                shown += 1;
+            }
+        },
+    )?;
+
+    if comparison.differing > 0 {
+        return Err(format!(
+            "{} of {} rows differ",
+            comparison.differing, comparison.rows
+        )
+        .into());
+    }
+    eprintln!("{} rows match", comparison.rows);
+    Ok(())
+}
+
+/// Totals of a comparison.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Comparison {
+    /// Rows in the longer file
+    rows: u64,
+    differing: u64,
+}
+
+/// Compares the files row by row, passing a description of each difference to `report`.
+fn compare<A: Read, B: Read>(
+    left: A,
+    right: B,
+    tolerance: Tolerance,
+    mut report: impl FnMut(String),
+) -> Result<Comparison, Box<dyn Error + Send + Sync>> {
+    let mut left = ReaderBuilder::new().from_reader(left);
+    let mut right = ReaderBuilder::new().from_reader(right);
+    let header = left.byte_headers()?.clone();
+    if header != *right.byte_headers()? {
+        return Err(format!(
+            "the headers differ: '{}' vs '{}'",
+            join(&header),
+            join(right.byte_headers()?)
+        )
+        .into());
+    }
+    let json = header.iter().position(|field| field == b"json");
+
+    let mut comparison = Comparison::default();
+    let (mut a, mut b) = (ByteRecord::new(), ByteRecord::new());
+    loop {
+        let (more_left, more_right) = (
+            left.read_byte_record(&mut a)?,
+            right.read_byte_record(&mut b)?,
+        );
+        if !more_left && !more_right {
+            return Ok(comparison);
+        }
+        comparison.rows += 1;
+        let difference = match (more_left, more_right) {
+            (true, false) => Some("missing on the right".to_string()),
+            (false, true) => Some("missing on the left".to_string()),
+            _ => difference(&header, json, &a, &b, tolerance),
+        };
+        if let Some(difference) = difference {
+            comparison.differing += 1;
+            let id = if more_left { &a } else { &b }.get(0).unwrap_or_default();
+            report(format!(
+                "row {} (id {}): {difference}",
+                comparison.rows,
+                String::from_utf8_lossy(id)
+            ));
+        }
+    }
+}
+
+/// What differs between two rows, if anything.
+fn difference(
+    header: &ByteRecord,
+    json: Option<usize>,
+    a: &ByteRecord,
+    b: &ByteRecord,
+    tolerance: Tolerance,
+) -> Option<String> {
+    for (i, name) in header.iter().enumerate() {
+        let (x, y) = (a.get(i).unwrap_or_default(), b.get(i).unwrap_or_default());
+        if Some(i) == json
+            && let (Ok(x), Ok(y)) = (
+                serde_json::from_slice::<Vec<f64>>(x),
+                serde_json::from_slice::<Vec<f64>>(y),
+            )
+        {
+            if x.len() != y.len() {
+                return Some(format!("json has {} vs {} elements", x.len(), y.len()));
+            }
+            if let Some(at) = (0..x.len()).find(|&at| !tolerance.approx_eq(x[at], y[at])) {
+                return Some(format!("json element {at}: {} vs {}", x[at], y[at]));
+            }
+        } else if x != y {
+            return Some(format!(
+                "{}: '{}' vs '{}'",
+                String::from_utf8_lossy(name),
+                String::from_utf8_lossy(x),
+                String::from_utf8_lossy(y)
+            ));
+        }
+    }
+    None
+}
+
+fn join(header: &ByteRecord) -> String {
+    let fields: Vec<_> = header.iter().map(String::from_utf8_lossy).collect();
+    fields.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_compare(left: &str, right: &str, tolerance: Tolerance) -> (Comparison, Vec<String>) {
+        let mut differences = Vec::new();
+        let comparison = compare(left.as_bytes(), right.as_bytes(), tolerance, |difference| {
+            differences.push(difference)
+        })
+        .unwrap();
+        (comparison, differences)
+    }
+
+    #[test]
+    fn test_compare_with_tolerance() {
+        let left = "id,json,is_valid\n1,\"[0.1, 2]\",true\n2,[],false\n";
+        let right = "id,json,is_valid\n1,\"[0.10000000001,2.0]\",true\n2,[],false\n";
+
+        let (comparison, differences) = run_compare(left, right, Tolerance::default());
+        assert_eq!(
+            comparison,
+            Comparison {
+                rows: 2,
+                differing: 1
+            }
+        );
+        assert_eq!(
+            differences,
+            ["row 1 (id 1): json element 0: 0.1 vs 0.10000000001"]
+        );
+
+        let absolute = Tolerance {
+            absolute: 1e-9,
+            ..Tolerance::default()
+        };
+        assert_eq!(run_compare(left, right, absolute).0.differing, 0);
+        let relative = Tolerance {
+            relative: 1e-9,
+            ..Tolerance::default()
+        };
+        assert_eq!(run_compare(left, right, relative).0.differing, 0);
+    }
+
+    #[test]
+    fn test_compare_reports_differences() {
+        let left = "id,json,is_valid\n1,[1],true\n2,\"[1,2,3,4]\",true\n3,x,false\n";
+        let right = "id,json,is_valid\n1,[1],false\n2,[1],true\n3,y,false\n4,[4],true\n";
+        let (comparison, differences) = run_compare(left, right, Tolerance::default());
+        assert_eq!(
+            comparison,
+            Comparison {
+                rows: 4,
+                differing: 4
+            }
+        );
+        assert_eq!(
+            differences,
+            [
+                "row 1 (id 1): is_valid: 'true' vs 'false'",
+                "row 2 (id 2): json has 4 vs 1 elements",
+                "row 3 (id 3): json: 'x' vs 'y'",
+                "row 4 (id 4): missing on the left",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_rejects_different_headers() {
+        let error = compare(
+            "id,json,is_valid\n".as_bytes(),
+            "id,json,is_valid,n\n".as_bytes(),
+            Tolerance::default(),
+            |_| {},
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the headers differ: 'id,json,is_valid' vs 'id,json,is_valid,n'"
+        );
+    }
+}
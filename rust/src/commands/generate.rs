@@ -0,0 +1,101 @@
+//! `rotate_cli generate`: write a reproducible input file (see `rotate_cli::fixtures`).
+//!
+//! The same arguments always produce the same bytes, so a file can be regenerated instead
+//! of checked in. `--expected` also writes what `rotate_cli` outputs for it, which the other
+//! implementations' test suites compare against.
+
+use crate::output::Output;
+use clap::Args;
+use rotate_cli::CsvRotator;
+use rotate_cli::fixtures::{Dataset, SizeDistribution};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Number of rows
+    #[arg(long, default_value_t = 1000)]
+    rows: usize,
+
+    /// Seed of the random number generator
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Table side lengths: N, MIN-MAX (uniform) or N:WEIGHT,N:WEIGHT,...
+    #[arg(long, value_name = "SIZES", default_value = "1-50")]
+    sizes: SizeDistribution,
+
+    /// Share of invalid rows (non-square, empty, non-numeric, fractional or malformed)
+    #[arg(long, value_name = "RATIO", default_value_t = 0.1, value_parser = crate::parse_ratio)]
+    invalid_ratio: f64,
+
+    /// Cells are drawn from -MAX..=MAX
+    #[arg(long, value_name = "MAX", default_value_t = 1000)]
+    max_value: i64,
+
+    /// Write the CSV to this path or URI instead of stdout
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Also write the expected `rotate_cli` output for the generated file to FILE
+    #[arg(long, value_name = "FILE", requires = "output")]
+    expected: Option<PathBuf>,
+}
+
+pub fn run(args: GenerateArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let dataset = Dataset {
+        rows: args.rows,
+        seed: args.seed,
+        sizes: args.sizes,
+        invalid_ratio: args.invalid_ratio,
+        max_value: args.max_value,
+    };
+    let mut output = Output::create(args.output.as_deref())?;
+    dataset.write_csv(&mut output)?;
+    output.finish()?;
+
+    if let (Some(expected), Some(input)) = (&args.expected, &args.output) {
+        write_expected(input, expected)?;
+    }
+    Ok(())
+}
+
+/// Runs the default `rotate_cli INPUT.csv` processing of `input` into `expected`.
+fn write_expected(input: &str, expected: &PathBuf) -> Result<(), Box<dyn Error + Send + Sync>> {
+    CsvRotator::default().process(
+        BufReader::new(File::open(input)?),
+        BufWriter::new(File::create(expected)?),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_with_expected() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.csv");
+        let expected = dir.path().join("expected.csv");
+        run(GenerateArgs {
+            rows: 50,
+            seed: 3,
+            sizes: SizeDistribution::Fixed(2),
+            invalid_ratio: 0.2,
+            max_value: 9,
+            output: Some(input.to_str().unwrap().to_string()),
+            expected: Some(expected.clone()),
+        })
+        .unwrap();
+
+        let input = std::fs::read_to_string(input).unwrap();
+        assert!(input.starts_with("id,json\n1,\"["));
+        let expected = std::fs::read_to_string(expected).unwrap();
+        assert!(expected.starts_with("id,json,is_valid\n1,"));
+        assert_eq!(expected.lines().count(), 51);
+        assert!(expected.contains(",false\n"));
+    }
+}
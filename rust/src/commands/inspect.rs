@@ -0,0 +1,519 @@
+//! `rotate_cli inspect`: guess how to process an unfamiliar CSV.
+//!
+//! Reads the start of the file and reports its encoding, field delimiter and header, which
+//! column holds the tables (the one whose values most often look like JSON arrays) and which
+//! holds the ids, the distribution of table sizes and the reasons of invalid ones, and the
+//! `rotate_cli` command line that processes the file:
+//!
+//! ```text
+//! tables.csv: 1000 rows sampled
+//! encoding:   UTF-8
+//! delimiter:  ';'
+//! header:     table;key
+//! columns:    id = key (2), json = table (1); 100.0% of the sampled tables are JSON arrays
+//! tables:     950 valid, 50 invalid (not_square 50)
+//! sizes:      2×2              400  42.1%
+//!             3×3              550  57.9%
+//! command:    rotate_cli tables.csv --delimiter ';' --columns key,table
+//! ```
+
+use crate::input;
+use clap::Args;
+use csv::{ByteRecord, ReaderBuilder};
+use rotate_cli::{DEFAULT_MAX_N, InvalidReason, RowBuffers, parse_table};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::Read;
+
+/// Bytes read from the start of the file at most.
+const MAX_PREFIX: usize = 16 << 20;
+
+/// Delimiters tried, in order of preference on a tie.
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// Records the delimiter guess looks at.
+const DELIMITER_SAMPLE: usize = 50;
+
+/// Distinct sizes listed one by one; more are grouped by powers of two.
+const MAX_SIZE_LINES: usize = 12;
+
+#[derive(Args)]
+pub struct InspectArgs {
+    /// CSV file (path, URL or URI) to inspect
+    input: String,
+
+    /// Data rows to sample from the start of the file
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    rows: usize,
+}
+
+pub fn run(args: InspectArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut prefix = Vec::new();
+    input::open(&args.input)?
+        .take(MAX_PREFIX as u64 + 1)
+        .read_to_end(&mut prefix)?;
+    let truncated = prefix.len() > MAX_PREFIX;
+    if truncated {
+        // Whole lines only; the record cut off at the end is dropped
+        let end = prefix[..MAX_PREFIX]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |newline| newline + 1);
+        prefix.truncate(end);
+    }
+    let report = inspect(&prefix, args.rows, truncated)?;
+    print!("{}", report.describe(&args.input));
+    Ok(())
+}
+
+/// How the bytes of the file decode, judging by its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Ascii,
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// Neither ASCII nor UTF-8, such as Latin-1 or Windows-1252
+    Other,
+}
+
+impl Encoding {
+    fn guess(prefix: &[u8], truncated: bool) -> Self {
+        if prefix.starts_with(b"\xef\xbb\xbf") {
+            return Encoding::Utf8Bom;
+        }
+        if prefix.starts_with(b"\xff\xfe") {
+            return Encoding::Utf16Le;
+        }
+        if prefix.starts_with(b"\xfe\xff") {
+            return Encoding::Utf16Be;
+        }
+        if prefix.is_ascii() {
+            return Encoding::Ascii;
+        }
+        match std::str::from_utf8(prefix) {
+            Ok(_) => Encoding::Utf8,
+            // A character cut in two by the end of the sample
+            Err(e) if truncated && e.error_len().is_none() => Encoding::Utf8,
+            Err(_) => Encoding::Other,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Ascii => "ASCII",
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with a byte order mark",
+            Encoding::Utf16Le => "UTF-16 (little endian)",
+            Encoding::Utf16Be => "UTF-16 (big endian)",
+            Encoding::Other => "not UTF-8 (Latin-1 or Windows-1252?)",
+        }
+    }
+
+    /// What to do about the encoding before processing the file, if anything.
+    fn advice(self) -> Option<&'static str> {
+        match self {
+            Encoding::Ascii | Encoding::Utf8 => None,
+            Encoding::Utf8Bom => Some(
+                "the byte order mark becomes part of the first header name; \
+                 strip it before selecting that column by name",
+            ),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                Some("convert the file to UTF-8 first, e.g. with `iconv -f UTF-16 -t UTF-8`")
+            }
+            Encoding::Other => Some(
+                "ids are copied byte for byte, but tables must be ASCII; \
+                 convert with e.g. `iconv -f WINDOWS-1252 -t UTF-8` if needed",
+            ),
+        }
+    }
+}
+
+/// What [`inspect`] found out.
+#[derive(Debug, Default)]
+struct Report {
+    encoding: Option<Encoding>,
+    delimiter: u8,
+    /// The first row's fields, if it is a header
+    header: Option<Vec<String>>,
+    /// Data rows sampled
+    rows: u64,
+    /// The sample ended before the file did
+    partial: bool,
+    /// Positions of the id and table columns, if any column holds arrays
+    columns: Option<(usize, usize)>,
+    /// Sampled values of the table column that look like JSON arrays
+    arrays: u64,
+    /// Valid tables by side length
+    sizes: BTreeMap<usize, u64>,
+    invalid: BTreeMap<&'static str, u64>,
+    /// Largest side of the tables `--max-n` rejects by default, if any
+    largest: Option<usize>,
+}
+
+/// Samples up to `rows` data rows of `prefix`, the start of a CSV file.
+fn inspect(prefix: &[u8], rows: usize, truncated: bool) -> Result<Report, csv::Error> {
+    let encoding = Encoding::guess(prefix, truncated);
+    let mut report = Report {
+        encoding: Some(encoding),
+        delimiter: b',',
+        partial: truncated,
+        ..Report::default()
+    };
+    if matches!(encoding, Encoding::Utf16Le | Encoding::Utf16Be) {
+        return Ok(report);
+    }
+
+    report.delimiter = guess_delimiter(prefix);
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(report.delimiter)
+        .flexible(true)
+        .from_reader(prefix);
+    // The header, the sampled rows, and one more to tell whether the file goes on
+    let mut records = Vec::new();
+    for record in rdr.byte_records().take(rows.saturating_add(2)) {
+        records.push(record?);
+    }
+    let Some(first) = records.first() else {
+        return Ok(report);
+    };
+    let has_header = !first.iter().any(looks_like_array);
+    if has_header {
+        report.header = Some(
+            first
+                .iter()
+                .map(|field| String::from_utf8_lossy(field).into_owned())
+                .collect(),
+        );
+    }
+    let start = usize::from(has_header);
+    let end = records.len().min(start + rows);
+    report.partial |= records.len() > end;
+    let data = &records[start..end];
+    report.rows = data.len() as u64;
+
+    let Some(json) = table_column(data) else {
+        return Ok(report);
+    };
+    let id = id_column(report.header.as_deref(), json);
+    report.columns = Some((id, json));
+
+    let mut buffers = RowBuffers::default();
+    for record in data {
+        let field = record.get(json).unwrap_or_default();
+        report.arrays += u64::from(looks_like_array(field));
+        let n = std::str::from_utf8(field)
+            .ok()
+            .and_then(|text| parse_table(text, &mut buffers));
+        match n {
+            Some(n) => {
+                *report.sizes.entry(n).or_default() += 1;
+                if n > DEFAULT_MAX_N {
+                    report.largest = report.largest.max(Some(n));
+                }
+            }
+            None => {
+                let reason = buffers
+                    .invalid_reason(false)
+                    .unwrap_or(InvalidReason::Malformed);
+                *report.invalid.entry(reason.as_str()).or_default() += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// The delimiter that splits the first records into the most rows of the same width.
+fn guess_delimiter(prefix: &[u8]) -> u8 {
+    let score = |delimiter| {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(prefix);
+        let mut widths = BTreeMap::<usize, usize>::new();
+        let mut record = ByteRecord::new();
+        for _ in 0..DELIMITER_SAMPLE {
+            match rdr.read_byte_record(&mut record) {
+                Ok(true) => *widths.entry(record.len()).or_default() += 1,
+                _ => break,
+            }
+        }
+        // Rows of the most common width, if it has at least two fields
+        widths
+            .into_iter()
+            .filter(|&(width, _)| width > 1)
+            .map(|(_, count)| count)
+            .max()
+            .unwrap_or(0)
+    };
+    // `max_by_key` keeps the last maximum, so go from the least to the most preferred
+    DELIMITERS
+        .into_iter()
+        .rev()
+        .max_by_key(|&delimiter| score(delimiter))
+        .unwrap_or(b',')
+}
+
+fn looks_like_array(field: &[u8]) -> bool {
+    let field = field.trim_ascii();
+    field.starts_with(b"[") && field.ends_with(b"]")
+}
+
+/// The column whose values most often look like JSON arrays, if any does.
+fn table_column(records: &[ByteRecord]) -> Option<usize> {
+    let width = records.iter().map(ByteRecord::len).max()?;
+    (0..width)
+        .map(|column| {
+            let arrays = records
+                .iter()
+                .filter(|record| record.get(column).is_some_and(looks_like_array))
+                .count();
+            (column, arrays)
+        })
+        .filter(|&(_, arrays)| arrays > 0)
+        // The first of equally good columns
+        .min_by_key(|&(column, arrays)| (usize::MAX - arrays, column))
+        .map(|(column, _)| column)
+}
+
+/// The column named `id`, or else the first one that does not hold the tables.
+fn id_column(header: Option<&[String]>, json: usize) -> usize {
+    header
+        .and_then(|header| {
+            header
+                .iter()
+                .position(|name| name.trim().eq_ignore_ascii_case("id"))
+        })
+        .filter(|&id| id != json)
+        .unwrap_or(usize::from(json == 0))
+}
+
+impl Report {
+    fn describe(&self, input: &str) -> String {
+        let mut out = String::new();
+        let sampled = match self.partial {
+            true => "sampled",
+            false => "(the whole file)",
+        };
+        let _ = writeln!(out, "{input}: {} rows {sampled}", self.rows);
+        let encoding = self.encoding.unwrap_or(Encoding::Utf8);
+        let _ = writeln!(out, "encoding:   {}", encoding.name());
+        if let Some(advice) = encoding.advice() {
+            let _ = writeln!(out, "            {advice}");
+        }
+        if matches!(encoding, Encoding::Utf16Le | Encoding::Utf16Be) {
+            return out;
+        }
+        let _ = writeln!(out, "delimiter:  {}", delimiter_name(self.delimiter));
+        let delimiter = char::from(self.delimiter).to_string();
+        match &self.header {
+            Some(header) => {
+                let _ = writeln!(out, "header:     {}", header.join(&delimiter));
+            }
+            None => {
+                let _ = writeln!(
+                    out,
+                    "header:     none; the first row holds a table, but rotate_cli always \
+                     skips the first row, so add a header"
+                );
+            }
+        }
+
+        let Some((id, json)) = self.columns else {
+            let _ = writeln!(out, "columns:    no column holds JSON arrays");
+            return out;
+        };
+        let name = |column: usize| match self.header_name(column) {
+            Some(name) => format!("{name} ({})", column + 1),
+            None => format!("column {}", column + 1),
+        };
+        let _ = writeln!(
+            out,
+            "columns:    id = {}, json = {}; {} of the sampled tables are JSON arrays",
+            name(id),
+            name(json),
+            percent(self.arrays, self.rows)
+        );
+
+        let valid: u64 = self.sizes.values().sum();
+        let invalid: Vec<_> = self
+            .invalid
+            .iter()
+            .map(|(reason, count)| format!("{reason} {count}"))
+            .collect();
+        let _ = write!(
+            out,
+            "tables:     {valid} valid, {} invalid",
+            self.rows - valid
+        );
+        if !invalid.is_empty() {
+            let _ = write!(out, " ({})", invalid.join(", "));
+        }
+        out.push('\n');
+        for (i, (sizes, count)) in self.size_lines().into_iter().enumerate() {
+            let label = if i == 0 { "sizes:" } else { "" };
+            let _ = writeln!(
+                out,
+                "{label:<11} {sizes:<13} {count:>6}  {:>5}",
+                percent(count, valid)
+            );
+        }
+
+        let _ = writeln!(out, "command:    {}", self.command(input));
+        out
+    }
+
+    /// The header name of a column, if the file has one.
+    fn header_name(&self, column: usize) -> Option<&str> {
+        self.header.as_ref()?.get(column).map(String::as_str)
+    }
+
+    /// The sizes of the valid tables and their counts, grouped if there are many.
+    fn size_lines(&self) -> Vec<(String, u64)> {
+        if self.sizes.len() <= MAX_SIZE_LINES {
+            return self
+                .sizes
+                .iter()
+                .map(|(n, &count)| (format!("{n}×{n}"), count))
+                .collect();
+        }
+        let mut groups = BTreeMap::<u32, u64>::new();
+        for (&n, &count) in &self.sizes {
+            *groups.entry(n.ilog2()).or_default() += count;
+        }
+        groups
+            .into_iter()
+            .map(|(log, count)| {
+                let (low, high) = (1usize << log, (1usize << log) * 2 - 1);
+                let sizes = match low == high {
+                    true => format!("{low}×{low}"),
+                    false => format!("{low}×{low}–{high}×{high}"),
+                };
+                (sizes, count)
+            })
+            .collect()
+    }
+
+    /// The command line that processes the file as inspected.
+    fn command(&self, input: &str) -> String {
+        let mut command = format!("rotate_cli {}", shell_quote(input));
+        if self.delimiter != b',' {
+            let delimiter = match self.delimiter {
+                b'\t' => "tab".to_string(),
+                other => shell_quote(&char::from(other).to_string()),
+            };
+            let _ = write!(command, " --delimiter {delimiter}");
+        }
+        if let Some((id, json)) = self.columns.filter(|&columns| columns != (0, 1)) {
+            match (self.header_name(id), self.header_name(json)) {
+                (Some(id), Some(json)) => {
+                    let columns = format!("{id},{json}");
+                    let _ = write!(command, " --columns {}", shell_quote(&columns));
+                }
+                _ => command.push_str(
+                    " (after adding a header and moving the id and table to the first two columns)",
+                ),
+            }
+        }
+        if let Some(largest) = self.largest {
+            let _ = write!(command, " --max-n {largest}");
+        }
+        command
+    }
+}
+
+fn delimiter_name(delimiter: u8) -> String {
+    match delimiter {
+        b'\t' => "tab".to_string(),
+        other => format!("'{}'", char::from(other)),
+    }
+}
+
+fn percent(part: u64, whole: u64) -> String {
+    match whole {
+        0 => "-".to_string(),
+        _ => format!("{:.1}%", part as f64 * 100.0 / whole as f64),
+    }
+}
+
+/// `text` as one shell word.
+fn shell_quote(text: &str) -> String {
+    let plain = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:,=+@".contains(c));
+    match plain {
+        true => text.to_string(),
+        false => format!("'{}'", text.replace('\'', r"'\''")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_delimiter() {
+        assert_eq!(
+            guess_delimiter(b"id,json\n1,\"[1, 2, 3, 4]\"\n2,[1]\n"),
+            b','
+        );
+        assert_eq!(
+            guess_delimiter(b"id;json\n1;[1,2,3,4]\n2;[1,2,3,4,5,6,7,8,9]\n3;[1]\n"),
+            b';'
+        );
+        assert_eq!(guess_delimiter(b"id\tjson\n1\t[1, 2]\n"), b'\t');
+        assert_eq!(guess_delimiter(b"just one column\n"), b',');
+    }
+
+    #[test]
+    fn test_guess_encoding() {
+        assert_eq!(Encoding::guess(b"id,json", false), Encoding::Ascii);
+        assert_eq!(Encoding::guess("id,é".as_bytes(), false), Encoding::Utf8);
+        assert_eq!(Encoding::guess(b"id,\xc3", true), Encoding::Utf8);
+        assert_eq!(Encoding::guess(b"id,\xe9", false), Encoding::Other);
+        assert_eq!(Encoding::guess(b"\xef\xbb\xbfid", false), Encoding::Utf8Bom);
+        assert_eq!(Encoding::guess(b"\xff\xfei\0d\0", false), Encoding::Utf16Le);
+    }
+
+    #[test]
+    fn test_inspect() {
+        let input = b"table;key;note\n[1,2,3,4];a;x\n[1,2,3];b;y\n[5];c;z\nnope;d;w\n";
+        let report = inspect(input, 1000, false).unwrap();
+        assert_eq!(report.columns, Some((1, 0)));
+        assert_eq!(
+            report.describe("in put.csv"),
+            "in put.csv: 4 rows (the whole file)\n\
+             encoding:   ASCII\n\
+             delimiter:  ';'\n\
+             header:     table;key;note\n\
+             columns:    id = key (2), json = table (1); 75.0% of the sampled tables are JSON arrays\n\
+             tables:     2 valid, 2 invalid (malformed 1, not_square 1)\n\
+             sizes:      1×1                1  50.0%\n\
+             \x20           2×2                1  50.0%\n\
+             command:    rotate_cli 'in put.csv' --delimiter ';' --columns key,table\n"
+        );
+
+        // A sample of the first rows, and the usual layout needs no flags
+        let report = inspect(b"id,json\n1,[1]\n2,[2]\n3,[3]\n", 2, false).unwrap();
+        assert_eq!((report.rows, report.partial), (2, true));
+        assert_eq!(report.command("in.csv"), "rotate_cli in.csv");
+    }
+
+    #[test]
+    fn test_size_groups() {
+        let report = Report {
+            sizes: (1..=20).map(|n| (n, 1)).collect(),
+            ..Report::default()
+        };
+        let lines = report.size_lines();
+        assert_eq!(lines[0], ("1×1".to_string(), 1));
+        assert_eq!(lines[2], ("4×4–7×7".to_string(), 4));
+        assert_eq!(lines[4], ("16×16–31×31".to_string(), 5));
+    }
+}
@@ -0,0 +1,270 @@
+//! `rotate_cli kafka`: rotate tables flowing through Kafka topics.
+//!
+//! Each message of `--in-topic` carries a JSON array as its payload; the rotated array (or
+//! `[]`) is produced to `--out-topic` with the same key and an `is_valid` header, plus an
+//! `error_reason` header such as `too_large` (see `--max-n`) for invalid tables. Offsets
+//! are committed only after every result of a batch has been acknowledged by the brokers,
+//! so a crash replays the uncommitted batch instead of losing it (at-least-once delivery).
+//! On SIGTERM or SIGINT the batch in progress is finished, delivered and committed before
+//! the process exits.
+
+use clap::Args;
+use rotate_cli::json::JsonStyle;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+#[derive(Args)]
+#[cfg_attr(not(feature = "kafka"), allow(dead_code))]
+pub struct KafkaArgs {
+    /// Bootstrap servers, e.g. `localhost:9092`
+    #[arg(long)]
+    brokers: String,
+
+    /// Topic to consume tables from
+    #[arg(long)]
+    in_topic: String,
+
+    /// Topic to produce rotated tables to
+    #[arg(long)]
+    out_topic: String,
+
+    /// Consumer group id; offsets are committed per group
+    #[arg(long, default_value = "rotate_cli")]
+    group: String,
+
+    /// Messages processed and acknowledged between offset commits
+    #[arg(long, default_value_t = 500)]
+    batch_size: usize,
+
+    /// Exit after no message arrived for SECONDS (runs until interrupted otherwise)
+    #[arg(long, value_name = "SECONDS", value_parser = crate::parse_seconds)]
+    idle_timeout: Option<std::time::Duration>,
+
+    #[command(flatten)]
+    limits: super::RowLimits,
+
+    /// Output array style (see the top-level --json-style)
+    #[arg(long, value_name = "STYLE", default_value_t = JsonStyle::Compact)]
+    json_style: JsonStyle,
+
+    /// Print rows/sec throughput to stderr when done
+    #[arg(long)]
+    stats: bool,
+
+    /// Serve Prometheus metrics on HOST:PORT at /metrics
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<std::net::SocketAddr>,
+}
+
+/// Next offset to commit for every partition seen in the current batch.
+#[derive(Default)]
+#[cfg_attr(not(feature = "kafka"), allow(dead_code))]
+struct Offsets(BTreeMap<(String, i32), i64>);
+
+#[cfg_attr(not(feature = "kafka"), allow(dead_code))]
+impl Offsets {
+    /// Records that the message at `offset` has been processed.
+    fn processed(&mut self, topic: &str, partition: i32, offset: i64) {
+        let next = self.0.entry((topic.to_string(), partition)).or_default();
+        *next = (*next).max(offset + 1);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(feature = "kafka")]
+pub fn run(args: KafkaArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    use super::rotate_payload;
+    use crate::metrics::Metrics;
+    use crate::shutdown::Shutdown;
+    use crate::stats::{self, Counters};
+    use rdkafka::config::ClientConfig;
+    use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+    use rdkafka::message::{Header, Message, OwnedHeaders};
+    use rdkafka::producer::{BaseProducer, BaseRecord, Producer};
+    use rdkafka::types::RDKafkaErrorCode;
+    use rdkafka::{Offset, TopicPartitionList};
+    use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
+
+    let consumer: BaseConsumer = ClientConfig::new()
+        .set("bootstrap.servers", &args.brokers)
+        .set("group.id", &args.group)
+        .set("enable.auto.commit", "false")
+        .set("auto.offset.reset", "earliest")
+        .create()?;
+    consumer.subscribe(&[&args.in_topic])?;
+    let producer: BaseProducer<delivery::Context> = ClientConfig::new()
+        .set("bootstrap.servers", &args.brokers)
+        .set("enable.idempotence", "true")
+        .create_with_context(delivery::Context::default())?;
+
+    let poll_interval = Duration::from_millis(100);
+    let idle_timeout = args.idle_timeout;
+    let counters = Counters::default();
+    let started = Instant::now();
+    let mut last_message = Instant::now();
+    let mut buffers = args.limits.buffers();
+    let metrics = Metrics::serve(args.metrics_addr)?;
+    let shutdown = Shutdown::install()?;
+
+    while !shutdown.requested() {
+        let mut offsets = Offsets::default();
+        let (mut rows, mut valid) = (0, 0);
+        // Consumed messages stay in flight until their offsets are committed
+        let mut in_flight = metrics.as_deref().map(|metrics| metrics.in_flight(0));
+        while rows < args.batch_size as u64 && !shutdown.requested() {
+            let Some(message) = consumer.poll(poll_interval) else {
+                break;
+            };
+            let message = message?;
+            let row_started = Instant::now();
+            let is_valid = rotate_payload(message.payload(), args.json_style, &mut buffers);
+            if let (Some(metrics), Some(in_flight)) = (&metrics, &mut in_flight) {
+                metrics.record_row(is_valid, row_started.elapsed());
+                in_flight.add(1);
+            }
+            let mut headers = OwnedHeaders::new().insert(Header {
+                key: "is_valid",
+                value: Some(if is_valid { "true" } else { "false" }),
+            });
+            if let Some(reason) = buffers.invalid_reason(is_valid) {
+                headers = headers.insert(Header {
+                    key: "error_reason",
+                    value: Some(reason.as_str()),
+                });
+            }
+
+            let mut record = BaseRecord::to(&args.out_topic)
+                .payload(&buffers.json)
+                .headers(headers);
+            if let Some(key) = message.key() {
+                record = record.key(key);
+            }
+            // Wait for room in the producer queue instead of dropping the result
+            while let Err((e, returned)) = producer.send(record) {
+                if e.rdkafka_error_code() != Some(RDKafkaErrorCode::QueueFull) {
+                    return Err(e.into());
+                }
+                producer.poll(poll_interval);
+                record = returned;
+            }
+
+            offsets.processed(message.topic(), message.partition(), message.offset());
+            rows += 1;
+            valid += u64::from(is_valid);
+        }
+
+        if offsets.is_empty() {
+            if idle_timeout.is_some_and(|timeout| last_message.elapsed() >= timeout) {
+                break;
+            }
+            producer.poll(Duration::ZERO);
+            continue;
+        }
+        last_message = Instant::now();
+
+        // Commit only once every result of the batch is acknowledged
+        producer.flush(Duration::from_secs(30))?;
+        let failed = producer.context().failed.swap(0, Ordering::Relaxed);
+        if failed > 0 {
+            return Err(format!(
+                "{failed} result(s) could not be delivered; offsets were not committed"
+            )
+            .into());
+        }
+        let mut list = TopicPartitionList::new();
+        for ((topic, partition), next) in &offsets.0 {
+            list.add_partition_offset(topic, *partition, Offset::Offset(*next))?;
+        }
+        consumer.commit(&list, CommitMode::Sync)?;
+        counters.add_rows(rows, valid);
+    }
+
+    if args.stats {
+        stats::print(counters.snapshot(started.elapsed()));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "kafka")]
+mod delivery {
+    use rdkafka::ClientContext;
+    use rdkafka::producer::{DeliveryResult, ProducerContext};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Producer context counting results the brokers did not acknowledge.
+    #[derive(Default)]
+    pub struct Context {
+        pub failed: AtomicU64,
+    }
+
+    impl ClientContext for Context {}
+
+    impl ProducerContext for Context {
+        type DeliveryOpaque = ();
+
+        fn delivery(&self, result: &DeliveryResult<'_>, _: ()) {
+            if let Err((e, _)) = result {
+                log::error!("Failed to deliver result: {e}");
+                self.failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "kafka"))]
+pub fn run(args: KafkaArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "cannot connect to {}: the kafka subcommand requires building with `--features kafka`",
+            args.brokers
+        ),
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_idle_timeout_must_be_positive() {
+        let args = [
+            "rotate_cli",
+            "kafka",
+            "--brokers=b",
+            "--in-topic=in",
+            "--out-topic=out",
+        ];
+        for value in [
+            "--idle-timeout=-1",
+            "--idle-timeout=NaN",
+            "--idle-timeout=1e300",
+        ] {
+            let error = crate::Cli::try_parse_from(args.into_iter().chain([value]))
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        }
+        assert!(crate::Cli::try_parse_from(args.into_iter().chain(["--idle-timeout=5"])).is_ok());
+    }
+
+    #[test]
+    fn test_offsets_commit_next_message() {
+        let mut offsets = Offsets::default();
+        assert!(offsets.is_empty());
+        offsets.processed("raw", 0, 41);
+        offsets.processed("raw", 1, 7);
+        offsets.processed("raw", 0, 42);
+        let committed: Vec<_> = offsets.0.into_iter().collect();
+        assert_eq!(
+            committed,
+            [(("raw".to_string(), 0), 43), (("raw".to_string(), 1), 8)]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! `rotate_cli merge`: concatenate result files, e.g. the shards of `--split-rows`.
+//!
+//! Every input must have the header of the first one, so files written with different
+//! options (`--emit-dimension`, `--format`, ...) are not mixed by accident. The rows are
+//! copied as they are, in the order of the arguments; `--dedupe-ids` keeps only the first
+//! row of each id, holding every id seen in memory. `--stats` prints the summary of the
+//! merged rows, recounted from their `is_valid` column.
+
+use crate::input;
+use crate::output::Output;
+use crate::stats::{self, Summary};
+use clap::Args;
+use csv::{ByteRecord, ReaderBuilder, WriterBuilder};
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::time::Instant;
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// Result files (paths or URIs) to combine, in this order
+    #[arg(required = true)]
+    inputs: Vec<String>,
+
+    /// Write the merged CSV to this path or URI instead of stdout
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Keep only the first row of each id
+    #[arg(long)]
+    dedupe_ids: bool,
+
+    /// Print the summary of the merged rows to stderr
+    #[arg(long)]
+    stats: bool,
+}
+
+pub fn run(args: MergeArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let started = Instant::now();
+    let mut output = Output::create(args.output.as_deref())?;
+    let inputs = args
+        .inputs
+        .iter()
+        .map(|name| Ok((name.as_str(), input::open(name)?)))
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+    let merged = merge(inputs, &mut output, args.dedupe_ids)?;
+    output.finish()?;
+
+    if merged.duplicates > 0 {
+        log::info!("Dropped {} row(s) with a repeated id", merged.duplicates);
+    }
+    if args.stats {
+        stats::print(Summary {
+            elapsed: started.elapsed(),
+            ..merged.summary
+        });
+    }
+    Ok(())
+}
+
+/// Totals of a merge.
+#[derive(Debug, Default, PartialEq)]
+struct Merged {
+    /// Rows written; `input_bytes` counts the bytes of all inputs
+    summary: Summary,
+    /// Rows dropped by `--dedupe-ids`
+    duplicates: u64,
+}
+
+fn merge<R: Read, W: Write>(
+    inputs: Vec<(&str, R)>,
+    output: W,
+    dedupe_ids: bool,
+) -> Result<Merged, Box<dyn Error + Send + Sync>> {
+    let mut writer = WriterBuilder::new().from_writer(output);
+    let mut merged = Merged::default();
+    let mut first: Option<(&str, ByteRecord)> = None;
+    let mut seen = HashSet::new();
+    let mut record = ByteRecord::new();
+
+    for (name, input) in inputs {
+        let mut reader = ReaderBuilder::new().from_reader(input);
+        let header = reader.byte_headers()?.clone();
+        let (id, is_valid) = match &first {
+            None => {
+                let (id, is_valid) = (column(&header, "id"), column(&header, "is_valid"));
+                let (Some(id), Some(is_valid)) = (id, is_valid) else {
+                    return Err(format!(
+                        "{name}: not a rotate_cli result file, the header has no 'id' and \
+                         'is_valid' columns"
+                    )
+                    .into());
+                };
+                writer.write_byte_record(&header)?;
+                first = Some((name, header));
+                (id, is_valid)
+            }
+            Some((first_name, first_header)) => {
+                if header != *first_header {
+                    return Err(format!(
+                        "{name}: header '{}' does not match '{}' of {first_name}",
+                        join(&header),
+                        join(first_header)
+                    )
+                    .into());
+                }
+                (
+                    column(&header, "id").expect("checked on the first file"),
+                    column(&header, "is_valid").expect("checked on the first file"),
+                )
+            }
+        };
+
+        while reader
+            .read_byte_record(&mut record)
+            .map_err(|e| format!("{name}: {e}"))?
+        {
+            let row_id = record.get(id).unwrap_or_default();
+            if dedupe_ids && !seen.insert(Box::<[u8]>::from(row_id)) {
+                merged.duplicates += 1;
+                continue;
+            }
+            writer.write_byte_record(&record)?;
+            merged.summary.rows += 1;
+            if record.get(is_valid) == Some(b"true") {
+                merged.summary.valid += 1;
+            }
+        }
+        merged.summary.input_bytes += reader.position().byte();
+    }
+    writer.flush()?;
+    Ok(merged)
+}
+
+fn column(header: &ByteRecord, name: &str) -> Option<usize> {
+    header.iter().position(|field| field == name.as_bytes())
+}
+
+fn join(header: &ByteRecord) -> String {
+    let fields: Vec<_> = header.iter().map(String::from_utf8_lossy).collect();
+    fields.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_merge(inputs: &[&str], dedupe_ids: bool) -> (String, Merged) {
+        let inputs = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, text)| (["a.csv", "b.csv", "c.csv"][i], text.as_bytes()))
+            .collect();
+        let mut output = Vec::new();
+        let merged = merge(inputs, &mut output, dedupe_ids).unwrap();
+        (String::from_utf8(output).unwrap(), merged)
+    }
+
+    #[test]
+    fn test_merge() {
+        let (output, merged) = run_merge(
+            &[
+                "id,json,is_valid\n1,[1],true\n2,[],false\n",
+                "id,json,is_valid\n3,\"[3,1,4,2]\",true\n",
+                "id,json,is_valid\n",
+            ],
+            false,
+        );
+        assert_eq!(
+            output,
+            "id,json,is_valid\n1,[1],true\n2,[],false\n3,\"[3,1,4,2]\",true\n"
+        );
+        assert_eq!((merged.summary.rows, merged.summary.valid), (3, 2));
+        assert_eq!(merged.summary.input_bytes, 92);
+    }
+
+    #[test]
+    fn test_merge_dedupe_ids() {
+        let inputs = [
+            "id,json,is_valid\n1,[1],true\n2,[],false\n",
+            "id,json,is_valid\n2,[2],true\n1,[1],true\n4,[4],true\n",
+        ];
+        let (output, merged) = run_merge(&inputs, true);
+        assert_eq!(
+            output,
+            "id,json,is_valid\n1,[1],true\n2,[],false\n4,[4],true\n"
+        );
+        assert_eq!(merged.duplicates, 2);
+        assert_eq!((merged.summary.rows, merged.summary.valid), (3, 2));
+
+        assert_eq!(run_merge(&inputs, false).1.summary.rows, 5);
+    }
+
+    #[test]
+    fn test_merge_rejects_mismatched_headers() {
+        let inputs: Vec<(&str, &[u8])> = vec![
+            ("a.csv", b"id,json,is_valid\n1,[1],true\n"),
+            ("b.csv", b"id,json,is_valid,n\n2,[2],true,1\n"),
+        ];
+        let error = merge(inputs, Vec::new(), false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "b.csv: header 'id,json,is_valid,n' does not match 'id,json,is_valid' of a.csv"
+        );
+
+        let inputs: Vec<(&str, &[u8])> = vec![("in.csv", b"id,json\n1,[1]\n")];
+        let error = merge(inputs, Vec::new(), false).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("in.csv: not a rotate_cli result file")
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Subcommands next to the default `rotate_cli INPUT.csv` mode.
+
+use clap::{Args, Subcommand};
+use rotate_cli::json::JsonStyle;
+use rotate_cli::{DEFAULT_MAX_N, InvalidReason, RowBuffers, process_json_bytes};
+use std::error::Error;
+use std::time::Duration;
+
+mod bench;
+mod compare;
+mod generate;
+mod image;
+mod inspect;
+mod kafka;
+mod merge;
+mod orchestrate;
+mod permutation;
+mod redis;
+mod serve;
+mod sort;
+mod view;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Check that two result files agree, comparing the json columns within a tolerance
+    Compare(compare::CompareArgs),
+    /// Measure time, CPU, peak memory and allocations of processing INPUT (output discarded)
+    Bench(bench::BenchArgs),
+    /// Write a reproducible input CSV with a chosen size distribution and invalid rows
+    Generate(generate::GenerateArgs),
+    /// Apply the ring shift (or another transform) to the pixels of a PNG or JPEG image
+    Image(image::ImageArgs),
+    /// Sample a CSV and report its encoding, delimiter, columns and table sizes, and the
+    /// flags that process it
+    Inspect(inspect::InspectArgs),
+    /// Consume tables from a Kafka topic and produce the rotated results to another
+    Kafka(kafka::KafkaArgs),
+    /// Concatenate result files with the same header, optionally dropping repeated ids
+    Merge(merge::MergeArgs),
+    /// Benchmark the implementations in this repo on one input and print a comparison table
+    Orchestrate(orchestrate::OrchestrateArgs),
+    /// Print the rotation of an N×N table as a JSON index mapping: dst[i] = src[perm[i]]
+    Permutation(permutation::PermutationArgs),
+    /// Consume tables from a Redis stream (consumer group) and append the results to another
+    Redis(redis::RedisArgs),
+    /// Serve rotations over HTTP (`POST /rotate`) and WebSockets (`GET /ws`)
+    Serve(serve::ServeArgs),
+    /// Sort a result file by id with bounded memory, keeping quoted fields intact
+    Sort(sort::SortArgs),
+    /// Page through the tables of a CSV in the terminal, transforming them step by step
+    View(view::ViewArgs),
+}
+
+/// Runs a subcommand to completion.
+pub fn run(command: Command) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match command {
+        Command::Bench(args) => bench::run(args),
+        Command::Compare(args) => compare::run(args),
+        Command::Generate(args) => generate::run(args),
+        Command::Image(args) => image::run(args),
+        Command::Inspect(args) => inspect::run(args),
+        Command::Kafka(args) => kafka::run(args),
+        Command::Merge(args) => merge::run(args),
+        Command::Orchestrate(args) => orchestrate::run(args),
+        Command::Permutation(args) => permutation::run(args),
+        Command::Redis(args) => redis::run(args),
+        Command::Serve(args) => serve::run(args),
+        Command::Sort(args) => sort::run(args),
+        Command::View(args) => view::run(args),
+    }
+}
+
+/// Per-row limits of the long-running modes, which take rows from untrusted clients.
+#[derive(Args, Default)]
+#[cfg_attr(
+    not(any(feature = "serve", feature = "kafka", feature = "redis")),
+    allow(dead_code)
+)]
+pub struct RowLimits {
+    /// Largest table side accepted; see the top-level --max-n (0 = no limit)
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_N)]
+    max_n: usize,
+
+    /// Give up on a table that takes longer than SECONDS to parse and report it as invalid
+    /// with error_reason `timeout`
+    #[arg(long, value_name = "SECONDS", value_parser = crate::parse_seconds)]
+    row_timeout: Option<Duration>,
+}
+
+#[cfg_attr(
+    not(any(feature = "serve", feature = "kafka", feature = "redis")),
+    allow(dead_code)
+)]
+impl RowLimits {
+    /// Buffers that enforce these limits.
+    fn buffers(&self) -> RowBuffers {
+        crate::row_buffers(self.max_n, self.row_timeout)
+    }
+}
+
+/// Rotates one message payload of a streaming mode into `buffers.json`; payloads that are
+/// missing or not UTF-8 are invalid.
+#[cfg_attr(not(any(feature = "kafka", feature = "redis")), allow(dead_code))]
+fn rotate_payload(payload: Option<&[u8]>, style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+    match payload {
+        Some(json) => process_json_bytes(json, style, buffers),
+        None => buffers.reject(InvalidReason::Malformed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_payload() {
+        let mut buffers = RowBuffers::default();
+        assert!(rotate_payload(
+            Some(b"[1, 2, 3, 4]"),
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        assert_eq!(buffers.json, b"[3,1,4,2]");
+        assert!(!rotate_payload(None, JsonStyle::Compact, &mut buffers));
+        assert!(!rotate_payload(
+            Some(b"[\xff]"),
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        assert_eq!(buffers.json, b"[]");
+    }
+}
@@ -0,0 +1,387 @@
+//! `rotate_cli orchestrate --config bench.toml`: run every implementation in this repo
+//! against the same input and print one comparison table.
+//!
+//! The configuration lists the input and the command of each implementation; `{input}` in
+//! a command is replaced by the absolute path of the input. The input and `cwd` are
+//! relative to the directory of the configuration file, and each command runs in its `cwd`
+//! (by default that directory) with relative programs resolved against it:
+//!
+//! ```toml
+//! input = "../input-samples/large.csv"
+//! runs = 3
+//!
+//! [[implementation]]
+//! name = "rust"
+//! command = ["../rust/target/release/rotate_cli", "{input}"]
+//!
+//! [[implementation]]
+//! name = "python"
+//! command = ["venv/bin/python", "-m", "rotate_cli", "{input}"]
+//! cwd = "../python"
+//! ```
+//!
+//! Commands run directly, without a shell, so a configuration behaves the same on every
+//! platform. Their output is discarded; wall time, CPU time and peak RSS are measured per
+//! process (CPU and RSS on Unix only). `--report FILE` also writes the comparison as JSON,
+//! CSV or a self-contained HTML page with a chart.
+
+use crate::report::{Report, Section};
+use crate::rusage::{self, Usage};
+use clap::Args;
+use serde::Deserialize;
+use serde_json::json;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+#[derive(Args)]
+pub struct OrchestrateArgs {
+    /// Benchmark configuration (see `benchmarks/bench.toml`)
+    #[arg(long, value_name = "FILE")]
+    config: PathBuf,
+
+    /// Runs per implementation, overriding `runs` from the configuration
+    #[arg(long)]
+    runs: Option<u32>,
+
+    /// Only run the implementations with these names (comma-separated)
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    only: Vec<String>,
+
+    /// Write a report to FILE (.json, .csv or .html); may be repeated
+    #[arg(long, value_name = "FILE")]
+    report: Vec<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    input: PathBuf,
+    #[serde(default = "default_runs")]
+    runs: u32,
+    #[serde(rename = "implementation")]
+    implementations: Vec<Implementation>,
+}
+
+fn default_runs() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Implementation {
+    name: String,
+    command: Vec<String>,
+    cwd: Option<PathBuf>,
+}
+
+/// Measurements of all runs of one implementation, or why it failed.
+struct Outcome {
+    name: String,
+    result: Result<Vec<Run>, String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    wall: Duration,
+    usage: Usage,
+}
+
+pub fn run(args: OrchestrateArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let text = std::fs::read_to_string(&args.config)
+        .map_err(|e| format!("cannot read {}: {e}", args.config.display()))?;
+    let config: Config =
+        toml::from_str(&text).map_err(|e| format!("invalid {}: {e}", args.config.display()))?;
+    // Absolute, so that relative programs still resolve after changing directories
+    let config_path = std::fs::canonicalize(&args.config)?;
+    let base = config_path.parent().unwrap_or(Path::new("/"));
+    let input = std::fs::canonicalize(base.join(&config.input))
+        .map_err(|e| format!("cannot open input {}: {e}", config.input.display()))?;
+    let runs = args.runs.unwrap_or(config.runs).max(1);
+
+    let mut outcomes = Vec::new();
+    for implementation in &config.implementations {
+        if !args.only.is_empty() && !args.only.contains(&implementation.name) {
+            continue;
+        }
+        log::info!("Running {} ({runs} runs)", implementation.name);
+        let result = (0..runs)
+            .map(|_| run_once(implementation, base, &input))
+            .collect();
+        outcomes.push(Outcome {
+            name: implementation.name.clone(),
+            result,
+        });
+    }
+    if outcomes.is_empty() {
+        return Err("no implementation selected".into());
+    }
+
+    println!("Input: {}", input.display());
+    print!("{}", render_table(&outcomes));
+    for path in &args.report {
+        structured_report(&input, &outcomes).write(path)?;
+    }
+    Ok(())
+}
+
+fn run_once(implementation: &Implementation, base: &Path, input: &Path) -> Result<Run, String> {
+    let input = input.to_string_lossy();
+    let [program, args @ ..] = implementation.command.as_slice() else {
+        return Err("empty command".to_string());
+    };
+    let cwd = match &implementation.cwd {
+        Some(cwd) => base.join(cwd),
+        None => base.to_path_buf(),
+    };
+    // Resolve relative programs like `../rust/target/release/rotate_cli` against `cwd`;
+    // bare names are looked up on PATH
+    let program = if program.contains('/') || program.contains('\\') {
+        cwd.join(program).into_os_string()
+    } else {
+        program.into()
+    };
+
+    let started = Instant::now();
+    let child = Command::new(&program)
+        .args(args.iter().map(|arg| arg.replace("{input}", &input)))
+        .current_dir(&cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("cannot start {}: {e}", program.to_string_lossy()))?;
+    let (status, usage) = rusage::wait(child).map_err(|e| e.to_string())?;
+    let wall = started.elapsed();
+    if !status.success() {
+        return Err(format!("failed ({status})"));
+    }
+    Ok(Run { wall, usage })
+}
+
+/// Aggregates of all runs of one implementation.
+struct Totals {
+    runs: usize,
+    mean: Duration,
+    min: Duration,
+    /// Mean CPU time, if measured
+    cpu: Option<Duration>,
+    max_rss: Option<u64>,
+}
+
+impl Totals {
+    fn of(runs: &[Run]) -> Self {
+        let cpu: Option<Duration> = runs
+            .iter()
+            .map(|run| run.usage.cpu.map(|cpu| cpu.total()))
+            .sum();
+        Totals {
+            runs: runs.len(),
+            mean: runs.iter().map(|run| run.wall).sum::<Duration>() / runs.len() as u32,
+            min: runs.iter().map(|run| run.wall).min().unwrap_or_default(),
+            cpu: cpu.map(|cpu| cpu / runs.len() as u32),
+            max_rss: runs.iter().map(|run| run.usage.max_rss).max().flatten(),
+        }
+    }
+}
+
+/// Totals of the successful outcomes and the fastest mean among them.
+fn totals(outcomes: &[Outcome]) -> (Vec<Result<Totals, &str>>, Option<Duration>) {
+    let totals: Vec<_> = outcomes
+        .iter()
+        .map(|outcome| match &outcome.result {
+            Ok(runs) => Ok(Totals::of(runs)),
+            Err(message) => Err(message.as_str()),
+        })
+        .collect();
+    let fastest = totals.iter().flatten().map(|totals| totals.mean).min();
+    (totals, fastest)
+}
+
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn render_table(outcomes: &[Outcome]) -> String {
+    let (totals, fastest) = totals(outcomes);
+    let mut rows = vec![
+        [
+            "implementation",
+            "runs",
+            "wall mean (s)",
+            "wall min (s)",
+            "cpu mean (s)",
+            "peak RSS (MiB)",
+            "relative",
+        ]
+        .map(String::from),
+    ];
+    for (outcome, totals) in outcomes.iter().zip(totals) {
+        let totals = match totals {
+            Ok(totals) => totals,
+            Err(message) => {
+                let mut row = [const { String::new() }; 7];
+                row[0] = outcome.name.clone();
+                row[1] = message.to_string();
+                rows.push(row);
+                continue;
+            }
+        };
+        let relative = fastest.map_or(1.0, |fastest| {
+            totals.mean.as_secs_f64() / fastest.as_secs_f64()
+        });
+        rows.push([
+            outcome.name.clone(),
+            totals.runs.to_string(),
+            format!("{:.3}", totals.mean.as_secs_f64()),
+            format!("{:.3}", totals.min.as_secs_f64()),
+            totals
+                .cpu
+                .map_or("-".to_string(), |cpu| format!("{:.3}", cpu.as_secs_f64())),
+            totals
+                .max_rss
+                .map_or("-".to_string(), |rss| format!("{:.1}", mib(rss))),
+            format!("{relative:.2}x"),
+        ]);
+    }
+
+    // Markdown table with columns padded to their widest cell
+    let widths: Vec<usize> = (0..7)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect();
+    let mut out = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        for (cell, width) in row.iter().zip(&widths) {
+            let _ = write!(out, "| {cell:width$} ");
+        }
+        out.push_str("|\n");
+        if i == 0 {
+            for width in &widths {
+                let _ = write!(out, "|-{}-", "-".repeat(*width));
+            }
+            out.push_str("|\n");
+        }
+    }
+    out
+}
+
+/// The comparison for `--report`.
+fn structured_report(input: &Path, outcomes: &[Outcome]) -> Report {
+    let (totals, fastest) = totals(outcomes);
+    let mut section = Section::new(
+        "implementations",
+        &[
+            "implementation",
+            "runs",
+            "wall mean (s)",
+            "wall min (s)",
+            "cpu mean (s)",
+            "peak RSS (MiB)",
+            "relative",
+            "error",
+        ],
+    )
+    .with_chart("Mean wall time (s)", "implementation", "wall mean (s)");
+    for (outcome, totals) in outcomes.iter().zip(totals) {
+        section.rows.push(match totals {
+            Ok(totals) => vec![
+                json!(outcome.name),
+                json!(totals.runs),
+                json!(totals.mean.as_secs_f64()),
+                json!(totals.min.as_secs_f64()),
+                json!(totals.cpu.map(|cpu| cpu.as_secs_f64())),
+                json!(totals.max_rss.map(mib)),
+                json!(fastest.map(|fastest| totals.mean.as_secs_f64() / fastest.as_secs_f64())),
+                json!(null),
+            ],
+            Err(message) => {
+                let mut row = vec![json!(null); 8];
+                row[0] = json!(outcome.name);
+                row[7] = json!(message);
+                row
+            }
+        });
+    }
+    Report {
+        title: format!("rotate_cli orchestrate: {}", input.display()),
+        sections: vec![section],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusage::CpuTime;
+
+    #[test]
+    fn test_parses_config() {
+        let config: Config = toml::from_str(
+            r#"
+            input = "data.csv"
+
+            [[implementation]]
+            name = "go"
+            command = ["./bin/rotate", "{input}"]
+            cwd = "../go"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.runs, 1);
+        assert_eq!(
+            config.implementations[0].command,
+            ["./bin/rotate", "{input}"]
+        );
+
+        let unknown = toml::from_str::<Config>("input = \"a\"\nimplementation = []\nrun = 2");
+        assert!(
+            unknown
+                .unwrap_err()
+                .to_string()
+                .contains("unknown field `run`")
+        );
+    }
+
+    #[test]
+    fn test_render_table() {
+        let run = |millis, rss: u64| Run {
+            wall: Duration::from_millis(millis),
+            usage: Usage {
+                cpu: Some(CpuTime {
+                    user: Duration::from_millis(millis / 2),
+                    system: Duration::ZERO,
+                }),
+                max_rss: Some(rss * 1024 * 1024),
+            },
+        };
+        let outcomes = [
+            Outcome {
+                name: "rust".to_string(),
+                result: Ok(vec![run(100, 4), run(300, 6)]),
+            },
+            Outcome {
+                name: "python".to_string(),
+                result: Ok(vec![run(800, 30)]),
+            },
+            Outcome {
+                name: "go".to_string(),
+                result: Err("failed (exit status: 1)".to_string()),
+            },
+        ];
+        let report = structured_report(Path::new("/data/in.csv"), &outcomes);
+        let rows = &report.sections[0].rows;
+        assert_eq!(rows[1][6], json!(4.0));
+        assert_eq!(rows[2][1], json!(null));
+        assert_eq!(rows[2][7], json!("failed (exit status: 1)"));
+        assert_eq!(
+            render_table(&outcomes),
+            "\
+| implementation | runs                    | wall mean (s) | wall min (s) | cpu mean (s) | peak RSS (MiB) | relative |
+|----------------|-------------------------|---------------|--------------|--------------|----------------|----------|
+| rust           | 2                       | 0.200         | 0.100        | 0.100        | 6.0            | 1.00x    |
+| python         | 1                       | 0.800         | 0.800        | 0.400        | 30.0           | 4.00x    |
+| go             | failed (exit status: 1) |               |              |              |                |          |
+"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! `rotate_cli permutation`: print the rotation of an N×N table as an index mapping.
+//!
+//! The output is one JSON array `perm` with `dst[i] = src[perm[i]]` over row-major cells,
+//! as returned by `rotate_cli::rotation_permutation`, so another system (a SQL UDF, a GPU
+//! kernel, a spreadsheet formula) can gather by index and get exactly the CLI's result
+//! without porting the ring walk. `--one-based` numbers the cells from 1, for SQL arrays
+//! and spreadsheet `INDEX`.
+
+use clap::Args;
+use rotate_cli::rotation_permutation;
+use std::error::Error;
+use std::io::{self, Write};
+
+#[derive(Args)]
+pub struct PermutationArgs {
+    /// Side length of the table
+    n: usize,
+
+    /// Clockwise steps; negative steps go counter-clockwise
+    #[arg(long, default_value_t = 1, allow_negative_numbers = true)]
+    steps: isize,
+
+    /// Number the cells from 1 instead of 0
+    #[arg(long)]
+    one_based: bool,
+}
+
+pub fn run(args: PermutationArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut output = io::stdout().lock();
+    write_permutation(&mut output, &args)?;
+    output.flush()?;
+    Ok(())
+}
+
+fn write_permutation(output: &mut dyn Write, args: &PermutationArgs) -> io::Result<()> {
+    let offset = usize::from(args.one_based);
+    let mut buffer = itoa::Buffer::new();
+    output.write_all(b"[")?;
+    for (i, from) in rotation_permutation(args.n, args.steps)
+        .into_iter()
+        .enumerate()
+    {
+        if i > 0 {
+            output.write_all(b",")?;
+        }
+        output.write_all(buffer.format(from + offset).as_bytes())?;
+    }
+    output.write_all(b"]\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permutation(n: usize, steps: isize, one_based: bool) -> String {
+        let mut output = Vec::new();
+        let args = PermutationArgs {
+            n,
+            steps,
+            one_based,
+        };
+        write_permutation(&mut output, &args).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_permutation() {
+        assert_eq!(permutation(2, 1, false), "[2,0,3,1]\n");
+        assert_eq!(permutation(2, -1, true), "[2,4,1,3]\n");
+        assert_eq!(permutation(3, 8, false), "[0,1,2,3,4,5,6,7,8]\n");
+        assert_eq!(permutation(0, 1, false), "[]\n");
+    }
+}
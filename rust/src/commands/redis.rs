@@ -0,0 +1,193 @@
+//! `rotate_cli redis`: rotate tables flowing through Redis Streams.
+//!
+//! Entries are read from `--in-stream` as a member of a consumer group (created on first
+//! use), so several processes can share one stream. Each entry's `json` field is rotated and
+//! appended to `--out-stream` with `id` (the entry's `id` field, or its stream id),
+//! `json` and `is_valid` fields, plus `error_reason` (such as `too_large`, see `--max-n`) for
+//! invalid tables. Results are appended and the inputs acknowledged in one
+//! MULTI transaction per batch; entries a crashed consumer left pending are processed again
+//! when it restarts under the same `--consumer` name. On SIGTERM or SIGINT the batch in
+//! progress is finished and committed before the process exits.
+
+use clap::Args;
+use rotate_cli::json::JsonStyle;
+use std::error::Error;
+
+#[derive(Args)]
+#[cfg_attr(not(feature = "redis"), allow(dead_code))]
+pub struct RedisArgs {
+    /// Server URL, e.g. `redis://127.0.0.1:6379`
+    #[arg(long, default_value = "redis://127.0.0.1:6379")]
+    url: String,
+
+    /// Stream to read tables from
+    #[arg(long)]
+    in_stream: String,
+
+    /// Stream to append rotated tables to
+    #[arg(long)]
+    out_stream: String,
+
+    /// Consumer group reading the input stream
+    #[arg(long, default_value = "rotate_cli")]
+    group: String,
+
+    /// Consumer name within the group; reuse it to resume this consumer's pending entries
+    #[arg(long, default_value = "rotate_cli-1")]
+    consumer: String,
+
+    /// Entries read, written and acknowledged per transaction
+    #[arg(long, default_value_t = 500)]
+    batch_size: usize,
+
+    /// Exit after no entry arrived for SECONDS (runs until interrupted otherwise)
+    #[arg(long, value_name = "SECONDS", value_parser = crate::parse_seconds)]
+    idle_timeout: Option<std::time::Duration>,
+
+    #[command(flatten)]
+    limits: super::RowLimits,
+
+    /// Output array style (see the top-level --json-style)
+    #[arg(long, value_name = "STYLE", default_value_t = JsonStyle::Compact)]
+    json_style: JsonStyle,
+
+    /// Print rows/sec throughput to stderr when done
+    #[arg(long)]
+    stats: bool,
+
+    /// Serve Prometheus metrics on HOST:PORT at /metrics
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<std::net::SocketAddr>,
+}
+
+#[cfg(feature = "redis")]
+pub fn run(args: RedisArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    use super::rotate_payload;
+    use crate::metrics::Metrics;
+    use crate::shutdown::Shutdown;
+    use crate::stats::{self, Counters};
+    use ::redis::streams::{StreamReadOptions, StreamReadReply};
+    use ::redis::{Commands, RedisResult};
+    use std::time::Instant;
+
+    /// Longest a single XREADGROUP waits for new entries.
+    const BLOCK_MS: usize = 1000;
+
+    let mut con = ::redis::Client::open(args.url.as_str())?.get_connection()?;
+    let created: RedisResult<()> = con.xgroup_create_mkstream(&args.in_stream, &args.group, "0");
+    // BUSYGROUP: the group already exists
+    if let Err(e) = created
+        && e.code() != Some("BUSYGROUP")
+    {
+        return Err(e.into());
+    }
+
+    let options = StreamReadOptions::default()
+        .group(&args.group, &args.consumer)
+        .count(args.batch_size)
+        .block(BLOCK_MS);
+    let idle_timeout = args.idle_timeout;
+    let counters = Counters::default();
+    let started = Instant::now();
+    let mut last_entry = Instant::now();
+    let mut buffers = args.limits.buffers();
+    let metrics = Metrics::serve(args.metrics_addr)?;
+    // Entries delivered to this consumer but never acknowledged come first
+    let mut start = "0";
+    let shutdown = Shutdown::install()?;
+
+    while !shutdown.requested() {
+        let reply: Option<StreamReadReply> =
+            con.xread_options(&[&args.in_stream], &[start], &options)?;
+        let entries: Vec<_> = reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .collect();
+
+        if entries.is_empty() {
+            if start == "0" {
+                start = ">";
+                continue;
+            }
+            if idle_timeout.is_some_and(|timeout| last_entry.elapsed() >= timeout) {
+                break;
+            }
+            continue;
+        }
+        last_entry = Instant::now();
+        let _in_flight = metrics
+            .as_deref()
+            .map(|metrics| metrics.in_flight(entries.len()));
+
+        let mut transaction = ::redis::pipe();
+        transaction.atomic();
+        let mut valid = 0;
+        for entry in &entries {
+            let json: Option<Vec<u8>> = entry.get("json");
+            let row_started = Instant::now();
+            let is_valid = rotate_payload(json.as_deref(), args.json_style, &mut buffers);
+            if let Some(metrics) = &metrics {
+                metrics.record_row(is_valid, row_started.elapsed());
+            }
+            let id: Vec<u8> = entry
+                .get("id")
+                .unwrap_or_else(|| entry.id.clone().into_bytes());
+            let is_valid_field: &[u8] = if is_valid { b"true" } else { b"false" };
+            let mut fields = vec![
+                ("id", id.as_slice()),
+                ("json", buffers.json.as_slice()),
+                ("is_valid", is_valid_field),
+            ];
+            if let Some(reason) = buffers.invalid_reason(is_valid) {
+                fields.push(("error_reason", reason.as_str().as_bytes()));
+            }
+            transaction.xadd(&args.out_stream, "*", &fields).ignore();
+            valid += u64::from(is_valid);
+        }
+        let ids: Vec<&str> = entries.iter().map(|entry| entry.id.as_str()).collect();
+        transaction
+            .xack(&args.in_stream, &args.group, &ids)
+            .ignore();
+        transaction.query::<()>(&mut con)?;
+        counters.add_rows(entries.len() as u64, valid);
+    }
+
+    if args.stats {
+        stats::print(counters.snapshot(started.elapsed()));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "redis"))]
+pub fn run(args: RedisArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "cannot connect to {}: the redis subcommand requires building with `--features redis`",
+            args.url
+        ),
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    #[test]
+    fn test_idle_timeout_must_be_positive() {
+        let args = ["rotate_cli", "redis", "--in-stream=in", "--out-stream=out"];
+        for value in [
+            "--idle-timeout=-1",
+            "--idle-timeout=NaN",
+            "--idle-timeout=1e300",
+        ] {
+            let error = crate::Cli::try_parse_from(args.into_iter().chain([value]))
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        }
+        assert!(crate::Cli::try_parse_from(args.into_iter().chain(["--idle-timeout=5"])).is_ok());
+    }
+}
@@ -0,0 +1,685 @@
+//! Router, shared state and the plain HTTP handlers.
+
+#[cfg(feature = "tls")]
+use super::tls;
+use super::{ServeArgs, auth, batch, jobs, limits, openapi, ws};
+use crate::commands::RowLimits;
+use crate::metrics::Metrics;
+use crate::rpc::{add_validity, raw_object, table_param, to_value};
+use crate::shutdown::Shutdown;
+use crate::stats;
+use axum::body::Bytes;
+use axum::extract::connect_info::IntoMakeServiceWithConnectInfo;
+use axum::extract::{DefaultBodyLimit, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router, middleware};
+use rotate_cli::errors::ErrorCode;
+use rotate_cli::json::JsonStyle;
+use rotate_cli::{RowBuffers, process_json_array};
+use serde_json::value::RawValue;
+use serde_json::{Value, json};
+use std::error::Error;
+use std::io::Write as _;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::Instrument;
+use utoipa::OpenApi;
+
+/// State shared by all connections.
+#[derive(Default)]
+pub struct AppState {
+    pub metrics: Metrics,
+    pub limits: RowLimits,
+    pub jobs: jobs::Jobs,
+    pub api_keys: auth::ApiKeys,
+    pub rate_limiter: limits::RateLimiter,
+    /// Largest request body accepted, in bytes; without it, axum's own 2 MB limit applies
+    /// to the bodies that handlers read whole
+    pub max_body: Option<usize>,
+    pub concurrency: limits::ConcurrencyLimit,
+}
+
+pub fn run(args: ServeArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if args.print_openapi {
+        let document = openapi::ApiDoc::openapi().to_pretty_json()?;
+        writeln!(std::io::stdout().lock(), "{document}")?;
+        return Ok(());
+    }
+    // clap makes --tls-cert and --tls-key require each other
+    #[cfg(feature = "tls")]
+    let tls = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(tls::config(cert, key)?),
+        _ => None,
+    };
+    #[cfg(not(feature = "tls"))]
+    if args.tls_cert.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "--tls-cert requires building with `--features tls`",
+        )
+        .into());
+    }
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    if let Some(workers) = args.http_workers {
+        runtime.worker_threads(workers.into());
+    }
+    let runtime = runtime.enable_all().build()?;
+    let shutdown = Shutdown::install()?;
+    let started = Instant::now();
+    let state = runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(args.listen).await?;
+        let state = AppState {
+            limits: args.limits,
+            api_keys: auth::ApiKeys::load(&args.api_key, args.api_keys_file.as_deref())?,
+            rate_limiter: limits::RateLimiter::new(args.rate_limit),
+            max_body: Some(args.max_body),
+            jobs: jobs::Jobs::new(args.job_ttl, args.max_jobs.into()),
+            concurrency: limits::ConcurrencyLimit::new(
+                args.max_concurrent_requests as usize,
+                args.queue_depth,
+            ),
+            ..AppState::default()
+        };
+        let state = Arc::new(state);
+        #[cfg(feature = "tls")]
+        if let Some(config) = tls {
+            log::info!("Listening on https://{}", listener.local_addr()?);
+            axum::serve(
+                tls::TlsListener::new(listener, config)?,
+                service(Arc::clone(&state)),
+            )
+            .with_graceful_shutdown(shutdown.wait())
+            .await?;
+            return Ok(state);
+        }
+        log::info!("Listening on http://{}", listener.local_addr()?);
+        axum::serve(listener, service(Arc::clone(&state)))
+            .with_graceful_shutdown(shutdown.wait())
+            .await?;
+        Ok::<_, Box<dyn Error + Send + Sync>>(state)
+    })?;
+    // Waits for the upload jobs still running
+    drop(runtime);
+    if args.stats {
+        stats::print(state.metrics.summary(started.elapsed()));
+    }
+    Ok(())
+}
+
+/// The router, with the client address of every connection for the rate limits.
+fn service(state: Arc<AppState>) -> IntoMakeServiceWithConnectInfo<Router, limits::ClientAddr> {
+    router(state).into_make_service_with_connect_info::<limits::ClientAddr>()
+}
+
+fn router(state: Arc<AppState>) -> Router {
+    let mut router = Router::new()
+        .route("/rotate", post(rotate))
+        .route("/rotate/csv", post(batch::rotate_csv))
+        .route("/ws", get(ws::upgrade))
+        .route("/metrics", get(metrics))
+        .merge(jobs::routes())
+        .merge(openapi::routes());
+    if state.max_body.is_some() {
+        // Bodies are capped by `--max-body` alone, in `enforce_limits`
+        router = router.layer(DefaultBodyLimit::disable());
+    }
+    router
+        .layer(middleware::from_fn_with_state(
+            Arc::clone(&state),
+            auth::require_api_key,
+        ))
+        // Outermost, so that refused clients cost no more than a map lookup
+        .layer(middleware::from_fn_with_state(
+            Arc::clone(&state),
+            limits::enforce_limits,
+        ))
+        .layer(middleware::from_fn(trace_request))
+        .with_state(state)
+}
+
+/// Runs every request in a `request` span, part of the caller's trace when it sends a
+/// `traceparent` header.
+async fn trace_request(request: Request, next: Next) -> Response {
+    let traceparent = request
+        .headers()
+        .get("traceparent")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        otel.kind = "server",
+        http.request.method = %request.method(),
+        url.path = request.uri().path(),
+        http.response.status_code = tracing::field::Empty,
+        traceparent,
+    );
+    let response = next.run(request).instrument(span.clone()).await;
+    span.record("http.response.status_code", response.status().as_u16());
+    response
+}
+
+/// Rotates one table.
+#[utoipa::path(
+    post,
+    path = "/rotate",
+    request_body = openapi::RotateRequest,
+    responses(
+        (status = 200, description = "The result, valid or not", body = openapi::RotateResult),
+        (status = 400, description = "Not JSON, or neither `json` nor `table` given", body = openapi::ErrorBody)
+    )
+)]
+async fn rotate(State(state): State<Arc<AppState>>, body: Bytes) -> Response {
+    // Parsing a large table would hold up every connection served by this worker thread
+    let span = tracing::Span::current();
+    let rotated = tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        let _in_flight = state.metrics.in_flight(1);
+        let mut buffers = state.limits.buffers();
+        rotate_row(&body, &mut buffers, &state.metrics)
+    })
+    .await;
+    match rotated {
+        Ok(Ok(result)) => Json(result).into_response(),
+        Ok(Err(error)) => (StatusCode::BAD_REQUEST, Json(error)).into_response(),
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+/// Prometheus metrics.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses((status = 200, description = "Text exposition format", body = String, content_type = "text/plain"))
+)]
+async fn metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [("content-type", "text/plain; version=0.0.4")],
+        format!(
+            "{}{}{}",
+            state.metrics.render(),
+            state.concurrency.render(),
+            state.api_keys.render()
+        ),
+    )
+}
+
+/// Rotates one `{"json": string}` or `{"table": array}` row, echoing its `id` if present.
+///
+/// Reading the row counts towards `--row-timeout` along with its table. A row that is not
+/// JSON or has no table gets `{"id": ..., "error": "...", "error_code": "..."}` instead.
+pub fn rotate_row(
+    text: &[u8],
+    buffers: &mut RowBuffers,
+    metrics: &Metrics,
+) -> Result<Value, Value> {
+    let started = Instant::now();
+    let row = match serde_json::from_slice::<&RawValue>(text) {
+        Ok(row) => raw_object(row),
+        Err(e) => {
+            let error = format!("invalid JSON: {e}");
+            return Err(json!({"error": error, "error_code": ErrorCode::Parse.as_str()}));
+        }
+    };
+    let id = row.get("id").map(|id| to_value(id));
+    let json_text = match table_param(&row) {
+        Ok(json_text) => json_text,
+        Err((_, message)) => {
+            let mut error = json!({"error": message, "error_code": ErrorCode::Usage.as_str()});
+            if let Some(id) = id {
+                error["id"] = id;
+            }
+            return Err(error);
+        }
+    };
+    // The table gets what is left of the row's timeout
+    let timeout = buffers.timeout;
+    buffers.timeout = timeout.map(|timeout| timeout.saturating_sub(started.elapsed()));
+    let is_valid = process_json_array(&json_text, JsonStyle::Compact, buffers);
+    buffers.timeout = timeout;
+    metrics.record_row(is_valid, started.elapsed());
+
+    let mut result = json!({"json": String::from_utf8_lossy(&buffers.json)});
+    add_validity(&mut result, buffers, is_valid);
+    if let Some(id) = id {
+        result["id"] = id;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_rotate_row() {
+        let metrics = Metrics::default();
+        let mut buffers = RowBuffers::default();
+        let result = rotate_row(
+            br#"{"id": 7, "table": [1, 2, 3, 4]}"#,
+            &mut buffers,
+            &metrics,
+        );
+        assert_eq!(
+            result.unwrap(),
+            json!({"id": 7, "json": "[3,1,4,2]", "is_valid": true, "n": 2})
+        );
+        let result = rotate_row(br#"{"json": "[]"}"#, &mut buffers, &metrics).unwrap();
+        assert_eq!(result["error_reason"], "empty");
+        assert_eq!(result["error_code"], "E003");
+        assert_eq!(result["explanation"], "empty: add 1 for 1×1");
+        assert_eq!(
+            result["shape"],
+            json!({"len": 0, "smaller": 0, "remove": null, "larger": 1, "add": 1})
+        );
+        let result = rotate_row(
+            br#"{"table": [1, 2, 3, 4]}"#,
+            &mut RowBuffers::with_max_n(Some(1)),
+            &metrics,
+        );
+        assert_eq!(result.unwrap()["error_reason"], "too_large");
+        assert_eq!(
+            rotate_row(br#"{"id": 3, "rows": []}"#, &mut buffers, &metrics).unwrap_err()["id"],
+            3
+        );
+        assert_eq!(
+            rotate_row(b"[1, 2", &mut buffers, &metrics).unwrap_err()["error_code"],
+            "E001"
+        );
+        assert!(
+            metrics
+                .render()
+                .contains("rotate_cli_invalid_rows_total 2\n")
+        );
+    }
+
+    #[test]
+    fn test_rotate_row_timeout() {
+        let mut buffers = RowBuffers::default();
+        buffers.timeout = Some(std::time::Duration::ZERO);
+        let row = json!({"table": vec![1; 64 * 64]}).to_string();
+        let result = rotate_row(row.as_bytes(), &mut buffers, &Metrics::default()).unwrap();
+        assert_eq!(result["error_reason"], "timeout");
+        // Only shortened for the one row
+        assert_eq!(buffers.timeout, Some(std::time::Duration::ZERO));
+    }
+
+    /// Serves the router on a local port for as long as the runtime lives.
+    fn spawn_server(state: AppState) -> (tokio::runtime::Runtime, std::net::SocketAddr) {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let listener = runtime
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        runtime.spawn(async move { axum::serve(listener, service(Arc::new(state))).await });
+        (runtime, addr)
+    }
+
+    #[test]
+    fn test_post_rotate() {
+        let (_runtime, addr) = spawn_server(AppState::default());
+        let body = r#"{"json": "[1, 2, 3, 4]"}"#;
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /rotate HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#"{"is_valid":true,"json":"[3,1,4,2]","n":2}"#));
+    }
+
+    #[test]
+    fn test_post_rotate_over_max_n() {
+        let limits = RowLimits {
+            max_n: 2,
+            ..RowLimits::default()
+        };
+        let (_runtime, addr) = spawn_server(AppState {
+            limits,
+            ..AppState::default()
+        });
+        let table = serde_json::to_string(&vec![1; 100_000]).unwrap();
+        let response = request(
+            addr,
+            "POST /rotate HTTP/1.1\r\nContent-Type: application/json",
+            &format!(r#"{{"id": 1, "table": {table}}}"#),
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(r#""error_reason":"too_large""#));
+        assert!(response.contains(r#""id":1"#));
+    }
+
+    /// Sends a request with a `Connection: close` header and returns the whole response.
+    fn request(addr: std::net::SocketAddr, head: &str, body: &str) -> String {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{head}\r\nHost: x\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_upload_job() {
+        let (_runtime, addr) = spawn_server(AppState::default());
+        let body = "--XyZ\r\n\
+                    Content-Disposition: form-data; name=\"file\"; filename=\"in.csv\"\r\n\
+                    Content-Type: text/csv\r\n\r\n\
+                    id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2, 3]\"\n\r\n\
+                    --XyZ--\r\n";
+        let response = request(
+            addr,
+            "POST /upload HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=XyZ",
+            body,
+        );
+        assert!(
+            response.starts_with("HTTP/1.1 202 Accepted\r\n"),
+            "{response}"
+        );
+        assert!(response.contains(r#""id":1"#));
+
+        let status = loop {
+            let response = request(addr, "GET /jobs/1 HTTP/1.1", "");
+            let (_, body) = response.split_once("\r\n\r\n").unwrap();
+            let status: Value = serde_json::from_str(body).unwrap();
+            if status["status"] != "running" {
+                break status;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(
+            status,
+            json!({
+                "id": 1,
+                "file_name": "in.csv",
+                "status": "done",
+                "rows": 2,
+                "valid": 1,
+                "invalid": 1,
+                "result": "/jobs/1/result",
+            })
+        );
+
+        let response = request(addr, "GET /jobs/1/result HTTP/1.1", "");
+        assert!(response.contains("content-type: text/csv\r\n"));
+        assert!(response.contains("id,json,is_valid\n1,\"[3,1,4,2]\",true\n2,[],false\n"));
+        let response = request(addr, "GET /jobs/2 HTTP/1.1", "");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        let response = request(
+            addr,
+            "POST /upload HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=XyZ",
+            "--XyZ--\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(response.contains(r#""error_code":"E012""#));
+    }
+
+    #[test]
+    fn test_expired_job() {
+        let (_runtime, addr) = spawn_server(AppState {
+            jobs: jobs::Jobs::new(
+                std::time::Duration::from_millis(200),
+                jobs::DEFAULT_MAX_RUNNING,
+            ),
+            ..AppState::default()
+        });
+        let body = "--XyZ\r\n\
+                    Content-Disposition: form-data; name=\"file\"\r\n\r\n\
+                    id,json\n1,\"[1, 2, 3, 4]\"\n\r\n\
+                    --XyZ--\r\n";
+        let response = request(
+            addr,
+            "POST /upload HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=XyZ",
+            body,
+        );
+        assert!(response.starts_with("HTTP/1.1 202 Accepted\r\n"));
+        while request(addr, "GET /jobs/1 HTTP/1.1", "").contains(r#""status":"running""#) {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(250));
+        for path in ["/jobs/1", "/jobs/1/result"] {
+            let response = request(addr, &format!("GET {path} HTTP/1.1"), "");
+            assert!(
+                response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+                "{response}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_upload_when_jobs_are_busy() {
+        let (_runtime, addr) = spawn_server(AppState {
+            jobs: jobs::Jobs::new(jobs::DEFAULT_TTL, 0),
+            ..AppState::default()
+        });
+        let body = "--XyZ\r\n\
+                    Content-Disposition: form-data; name=\"file\"\r\n\r\n\
+                    id,json\n1,\"[1]\"\n\r\n\
+                    --XyZ--\r\n";
+        let response = request(
+            addr,
+            "POST /upload HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=XyZ",
+            body,
+        );
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.contains(r#""error_code":"E017""#));
+    }
+
+    #[test]
+    fn test_api_keys() {
+        let api_keys = auth::ApiKeys::load(&["s3cret".to_string()], None).unwrap();
+        let (_runtime, addr) = spawn_server(AppState {
+            api_keys,
+            ..AppState::default()
+        });
+        let head = "POST /rotate HTTP/1.1\r\nContent-Type: application/json";
+        let body = r#"{"table": [1, 2, 3, 4]}"#;
+
+        let response = request(addr, head, body);
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert!(response.contains(r#""error_code":"E014""#));
+        let response = request(addr, &format!("{head}\r\nX-API-Key: s3cre"), body);
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        let response = request(addr, &format!("{head}\r\nX-API-Key: s3cret"), body);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let head = "GET /metrics HTTP/1.1\r\nAuthorization: Bearer s3cret";
+        let response = request(addr, head, "");
+        assert!(response.contains("rotate_cli_api_key_requests_total{key=\"key1\"} 2\n"));
+    }
+
+    #[test]
+    fn test_limits() {
+        let (_runtime, addr) = spawn_server(AppState {
+            rate_limiter: limits::RateLimiter::new(Some(2.0)),
+            max_body: Some(32),
+            ..AppState::default()
+        });
+        let head = "POST /rotate HTTP/1.1\r\nContent-Type: application/json";
+        let response = request(addr, head, r#"{"table": [1, 2, 3, 4, 5, 6, 7, 8, 9]}"#);
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert!(response.contains(r#""error_code":"E016""#));
+        let response = request(addr, head, r#"{"table": [1, 2, 3, 4]}"#);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let response = request(addr, head, r#"{"table": [1, 2, 3, 4]}"#);
+        assert!(response.starts_with("HTTP/1.1 429 Too Many Requests\r\n"));
+        assert!(response.contains("retry-after: 1\r\n"));
+        assert!(response.contains(r#""error_code":"E015""#));
+    }
+
+    #[test]
+    fn test_max_body_above_axum_default() {
+        // 3 MB, more than the 2 MB axum allows by default
+        let table = vec!["1"; 1_000_000].join(", ");
+        let head = "POST /rotate HTTP/1.1\r\nContent-Type: application/json";
+        let (_runtime, addr) = spawn_server(AppState::default());
+        let response = request(addr, head, &format!(r#"{{"json": "[{table}]"}}"#));
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+
+        let (_runtime, addr) = spawn_server(AppState {
+            max_body: Some(4 << 20),
+            ..AppState::default()
+        });
+        let response = request(addr, head, &format!(r#"{{"json": "[{table}]"}}"#));
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK\r\n"),
+            "{}",
+            &response[..100]
+        );
+        assert!(response.contains(r#""n":1000"#));
+
+        // Refused from its length alone, reporting the configured limit
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{head}\r\nHost: x\r\nContent-Length: 5000000\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert!(response.contains("larger than 4194304 bytes"));
+    }
+
+    #[test]
+    fn test_load_shedding() {
+        let (_runtime, addr) = spawn_server(AppState {
+            concurrency: limits::ConcurrencyLimit::new(1, 0),
+            ..AppState::default()
+        });
+        // A streamed response holds the only slot until its input is complete
+        let mut upload = std::net::TcpStream::connect(addr).unwrap();
+        write!(
+            upload,
+            "POST /rotate/csv HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\
+             Transfer-Encoding: chunked\r\n\r\n8\r\nid,json\n\r\n"
+        )
+        .unwrap();
+        let mut first_line = [0; 17];
+        upload.read_exact(&mut first_line).unwrap();
+        assert_eq!(&first_line, b"HTTP/1.1 200 OK\r\n");
+
+        let head = "POST /rotate HTTP/1.1\r\nContent-Type: application/json";
+        let response = request(addr, head, r#"{"table": [1]}"#);
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.contains("retry-after: 1\r\n"));
+        assert!(response.contains(r#""error_code":"E017""#));
+
+        write!(upload, "0\r\n\r\n").unwrap();
+        let mut rest = String::new();
+        upload.read_to_string(&mut rest).unwrap();
+        let response = request(addr, head, r#"{"table": [1]}"#);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        let response = request(addr, "GET /metrics HTTP/1.1", "");
+        assert!(response.contains("rotate_cli_requests_shed_total 1\n"));
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_https() {
+        use rustls::pki_types::pem::PemObject;
+        use rustls::pki_types::{CertificateDer, ServerName};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../fixtures/tls");
+        let cert = fixtures.join("cert.pem");
+        let config = tls::config(&cert, &fixtures.join("key.pem")).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let response = runtime.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let listener = tls::TlsListener::new(listener, config).unwrap();
+            tokio::spawn(async move {
+                axum::serve(listener, service(Arc::new(AppState::default()))).await
+            });
+
+            let mut roots = rustls::RootCertStore::empty();
+            roots
+                .add(CertificateDer::from_pem_file(&cert).unwrap())
+                .unwrap();
+            let provider = Arc::new(rustls::crypto::ring::default_provider());
+            let client = rustls::ClientConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(client));
+            let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let server_name = ServerName::try_from("localhost").unwrap();
+            let mut stream = connector.connect(server_name, stream).await.unwrap();
+            let body = r#"{"table": [1, 2, 3, 4]}"#;
+            let request = format!(
+                "POST /rotate HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                 Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            // Servers may close without a close_notify once the response is complete
+            let _ = stream.read_to_string(&mut response).await;
+            response
+        });
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.ends_with(r#"{"is_valid":true,"json":"[3,1,4,2]","n":2}"#));
+    }
+
+    #[test]
+    fn test_get_openapi() {
+        let (_runtime, addr) = spawn_server(AppState::default());
+        let response = request(addr, "GET /openapi.json HTTP/1.1", "");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let document: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            document,
+            serde_json::to_value(openapi::ApiDoc::openapi()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_post_rotate_csv_chunked() {
+        let (_runtime, addr) = spawn_server(AppState::default());
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /rotate/csv HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\
+             Content-Type: text/csv\r\nTransfer-Encoding: chunked\r\n\r\n"
+        )
+        .unwrap();
+        // Records split across chunks
+        for chunk in [
+            "id,json\n1,\"[1, 2,",
+            " 3, 4]\"\n2,\"[1, 2, 3]\"\n",
+            "3,[42]\n",
+        ] {
+            write!(stream, "{:x}\r\n{chunk}\r\n", chunk.len()).unwrap();
+        }
+        write!(stream, "0\r\n\r\n").unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("content-type: text/csv\r\n"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let rows: String = body.split("\r\n").skip(1).step_by(2).collect();
+        assert_eq!(
+            rows,
+            "id,json,is_valid\n1,\"[3,1,4,2]\",true\n2,[],false\n3,[42],true\n"
+        );
+    }
+}
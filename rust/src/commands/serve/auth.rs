@@ -0,0 +1,218 @@
+//! API keys for the HTTP endpoints (`--api-key`, `--api-keys-file`).
+//!
+//! Without keys every request is served. With keys, every route requires one, sent as
+//! `Authorization: Bearer KEY` or `X-API-Key: KEY`; other requests get `401` with
+//! `error_code` `E014`. A key is compared against all the configured keys in constant time,
+//! so response times do not reveal how much of a guess was right. Requests are counted per
+//! key in `/metrics` as `rotate_cli_api_key_requests_total{key="NAME"}`, with the name given
+//! in the keys file (`key1`, `key2`, … for `--api-key`), never the key itself.
+
+use super::app::AppState;
+use axum::Json;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use rotate_cli::errors::ErrorCode;
+use serde_json::json;
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The accepted keys and how often each was used.
+#[derive(Default)]
+pub struct ApiKeys {
+    keys: Vec<ApiKey>,
+}
+
+struct ApiKey {
+    name: String,
+    key: String,
+    requests: AtomicU64,
+}
+
+impl ApiKeys {
+    /// The `--api-key` values, named `key1`, `key2`, … in order, and the keys of `file`.
+    pub fn load(keys: &[String], file: Option<&Path>) -> io::Result<Self> {
+        let mut named: Vec<(String, String)> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (format!("key{}", i + 1), key.clone()))
+            .collect();
+        if let Some(file) = file {
+            let text = std::fs::read_to_string(file)?;
+            named.extend(parse_keys_file(&text).map_err(|message| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {message}", file.display()),
+                )
+            })?);
+        }
+        let keys = named
+            .into_iter()
+            .map(|(name, key)| ApiKey {
+                name,
+                key,
+                requests: AtomicU64::new(0),
+            })
+            .collect();
+        Ok(ApiKeys { keys })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// The key `presented` matches, checking all of them so that the time taken does not
+    /// depend on which one, if any, it is.
+    fn find(&self, presented: &[u8]) -> Option<&ApiKey> {
+        self.keys.iter().fold(None, |found, key| {
+            match constant_time_eq(key.key.as_bytes(), presented) {
+                true => Some(key),
+                false => found,
+            }
+        })
+    }
+
+    /// The usage counters in the Prometheus text format; empty without keys.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if self.is_enabled() {
+            out.push_str(
+                "# HELP rotate_cli_api_key_requests_total Requests authorized by each API key.\n\
+                 # TYPE rotate_cli_api_key_requests_total counter\n",
+            );
+        }
+        for key in &self.keys {
+            let _ = writeln!(
+                out,
+                "rotate_cli_api_key_requests_total{{key=\"{}\"}} {}",
+                key.name,
+                key.requests.load(Ordering::Relaxed)
+            );
+        }
+        out
+    }
+}
+
+/// `NAME:KEY` lines, or bare `KEY` lines named `file1`, `file2`, … in order; blank lines and
+/// lines starting with `#` are skipped.
+fn parse_keys_file(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut keys = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, key) = match line.split_once(':') {
+            Some((name, key)) => (name.trim().to_string(), key.trim()),
+            None => (format!("file{}", keys.len() + 1), line),
+        };
+        let valid_name = name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+        if key.is_empty() || name.is_empty() || !valid_name {
+            return Err(format!(
+                "line {}: expected KEY or NAME:KEY with a name of letters, digits, _ and -",
+                number + 1
+            ));
+        }
+        keys.push((name, key.to_string()));
+    }
+    Ok(keys)
+}
+
+/// Whether `a` and `b` are equal, in a time that depends only on the longer length.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let mut diff = a.len() ^ b.len();
+    for i in 0..a.len().max(b.len()) {
+        let (x, y) = (a.get(i).copied(), b.get(i).copied());
+        diff |= usize::from(x.unwrap_or(0) ^ y.unwrap_or(0));
+    }
+    std::hint::black_box(diff) == 0
+}
+
+/// The key a request presents, if any.
+fn presented_key(headers: &HeaderMap) -> Option<&[u8]> {
+    if let Some(key) = headers.get("x-api-key") {
+        return Some(key.as_bytes());
+    }
+    let authorization = headers.get(header::AUTHORIZATION)?.as_bytes();
+    authorization.strip_prefix(b"Bearer ")
+}
+
+/// Middleware that refuses requests without a valid key, when keys are configured.
+pub async fn require_api_key(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let keys = &state.api_keys;
+    if !keys.is_enabled() {
+        return next.run(request).await;
+    }
+    match presented_key(request.headers()).and_then(|key| keys.find(key)) {
+        Some(key) => {
+            key.requests.fetch_add(1, Ordering::Relaxed);
+            next.run(request).await
+        }
+        None => {
+            let body = json!({
+                "error": "missing or unknown API key",
+                "error_code": ErrorCode::Unauthorized.as_str(),
+            });
+            let challenge = [(header::WWW_AUTHENTICATE, "Bearer")];
+            (StatusCode::UNAUTHORIZED, challenge, Json(body)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret\0"));
+        assert!(!constant_time_eq(b"secret", b""));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn test_keys_file() {
+        let text = "# ci runners\nci: abc123\n\n  def456  \nbench-node:ghi:789\n";
+        assert_eq!(
+            parse_keys_file(text).unwrap(),
+            [
+                ("ci".to_string(), "abc123".to_string()),
+                ("file2".to_string(), "def456".to_string()),
+                ("bench-node".to_string(), "ghi:789".to_string()),
+            ]
+        );
+        assert!(parse_keys_file("ci:\n").is_err());
+        assert_eq!(
+            parse_keys_file("a b:key").unwrap_err(),
+            "line 1: expected KEY or NAME:KEY with a name of letters, digits, _ and -"
+        );
+    }
+
+    #[test]
+    fn test_find_and_render() {
+        let keys = ApiKeys::load(&["k1".to_string(), "k2".to_string()], None).unwrap();
+        assert_eq!(keys.find(b"k2").map(|key| key.name.as_str()), Some("key2"));
+        assert!(keys.find(b"k3").is_none());
+        keys.find(b"k2")
+            .unwrap()
+            .requests
+            .fetch_add(1, Ordering::Relaxed);
+        assert!(
+            keys.render()
+                .ends_with("{key=\"key1\"} 0\nrotate_cli_api_key_requests_total{key=\"key2\"} 1\n")
+        );
+        assert_eq!(ApiKeys::default().render(), "");
+    }
+}
@@ -0,0 +1,108 @@
+//! `POST /rotate/csv`: a whole CSV file in, the rotated CSV out.
+//!
+//! The request body is read as it arrives, chunked transfer encoding included, and the
+//! output rows are sent back as the pipeline writes them, so a large file is never held in
+//! memory on either side. Input and output are those of `rotate_cli INPUT.csv`, with the
+//! server's `--max-n` and `--row-timeout`. The response starts before the whole input has
+//! been read, so an error such as a malformed CSV record cannot change its status any more:
+//! the server aborts the response instead, and the client sees an incomplete chunked body.
+
+use super::app::AppState;
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::header;
+use axum::response::Response;
+use futures::TryStreamExt;
+use rotate_cli::json::JsonStyle;
+use rotate_cli::processor::{RotateRight, RowProcessor};
+use rotate_cli::{CsvRotator, RotatorOptions, RowBuffers};
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_util::io::{StreamReader, SyncIoBridge};
+
+/// Output chunks buffered between the pipeline and a slow client before the pipeline waits.
+const QUEUED_CHUNKS: usize = 16;
+
+/// Size of the output chunks.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Rotates every table of a CSV file.
+#[utoipa::path(
+    post,
+    path = "/rotate/csv",
+    request_body(content = String, description = "`id,json` records", content_type = "text/csv"),
+    responses(
+        (status = 200, description = "The output CSV, streamed", body = String, content_type = "text/csv")
+    )
+)]
+pub async fn rotate_csv(State(state): State<Arc<AppState>>, body: Body) -> Response {
+    let input = StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+    let input = SyncIoBridge::new(input);
+    let (tx, mut rx) = mpsc::channel(QUEUED_CHUNKS);
+
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        let _in_flight = state.metrics.in_flight(1);
+        let output = BufWriter::with_capacity(CHUNK_SIZE, Chunks(tx.clone()));
+        if let Err(e) = rotator(&state).process(input, output) {
+            log::warn!("POST /rotate/csv failed: {e}");
+            // Fails the response body, so the client does not take the output as complete
+            let _ = tx.blocking_send(Err(io::Error::other(e.to_string())));
+        }
+    });
+
+    let chunks = futures::stream::poll_fn(move |cx| rx.poll_recv(cx));
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/csv")
+        .body(Body::from_stream(chunks))
+        .expect("the response headers are valid")
+}
+
+/// The pipeline of `rotate_cli INPUT.csv` with the server's row limits and metrics.
+pub(super) fn rotator(state: &Arc<AppState>) -> CsvRotator {
+    let limits = state.limits.buffers();
+    CsvRotator::new(RotatorOptions {
+        max_n: limits.max_n,
+        ..RotatorOptions::default()
+    })
+    .with_processor(Metered {
+        state: Arc::clone(state),
+        timeout: limits.timeout,
+    })
+}
+
+/// Sends everything written to it to the response body, waiting while the client is behind.
+struct Chunks(mpsc::Sender<io::Result<Bytes>>);
+
+impl Write for Chunks {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the client went away"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// [`RotateRight`] with the server's row limits, counting every row in the metrics.
+struct Metered {
+    state: Arc<AppState>,
+    timeout: Option<Duration>,
+}
+
+impl RowProcessor for Metered {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        // The pipeline's buffers only know `--max-n`
+        buffers.timeout = self.timeout;
+        let started = Instant::now();
+        let is_valid = RotateRight.process(json, style, buffers);
+        self.state.metrics.record_row(is_valid, started.elapsed());
+        is_valid
+    }
+}
@@ -0,0 +1,370 @@
+//! `POST /upload` and `GET /jobs/{id}`: CSV files processed in the background.
+//!
+//! An upload is a `multipart/form-data` request with the CSV in a field named `file`, as an
+//! HTML form or `curl -F file=@input.csv` sends it. The file is saved to a temporary file,
+//! the request is answered right away with `202 Accepted` and a job id, and the file is
+//! processed on a blocking thread as `rotate_cli INPUT.csv` would. `GET /jobs/{id}` reports
+//! the job's status, `running`, `done` with its row counts or `failed` with the error and
+//! its `error_code`; `GET /jobs/{id}/result` downloads the output CSV of a finished job.
+//! Finished jobs and their results are kept for `--job-ttl`, then answer `404` like
+//! unknown ones. At most `--max-jobs` jobs run at once; further uploads are refused with
+//! `503` until one finishes.
+
+use super::app::AppState;
+use super::{batch, openapi};
+use axum::body::Body;
+use axum::extract::multipart::{Field, Multipart, MultipartError};
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use rotate_cli::counters::Summary;
+use rotate_cli::errors::{CliError, ErrorCode};
+use serde_json::{Value, json};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::io::{BufReader, BufWriter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::io::ReaderStream;
+
+/// How long a finished job is kept by default (`--job-ttl`).
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Jobs running at once by default (`--max-jobs`).
+pub const DEFAULT_MAX_RUNNING: usize = 4;
+
+/// The uploaded files, by job id.
+pub struct Jobs {
+    next_id: AtomicU64,
+    registry: Mutex<Registry>,
+    /// How long a job and its result are kept once it has finished
+    ttl: Duration,
+    /// One permit per job that may run at once
+    running: Arc<Semaphore>,
+}
+
+#[derive(Default)]
+struct Registry {
+    jobs: HashMap<u64, Job>,
+    /// Finished jobs, oldest first
+    finished: VecDeque<(Instant, u64)>,
+}
+
+struct Job {
+    /// The file name the client gave, if any
+    file_name: Option<String>,
+    status: Status,
+}
+
+enum Status {
+    Running,
+    Done {
+        summary: Summary,
+        output: NamedTempFile,
+    },
+    Failed(CliError),
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        Jobs::new(DEFAULT_TTL, DEFAULT_MAX_RUNNING)
+    }
+}
+
+impl Jobs {
+    /// Jobs that are forgotten, result included, `ttl` after they finish, with at most
+    /// `max_running` of them running at once.
+    pub fn new(ttl: Duration, max_running: usize) -> Self {
+        Jobs {
+            next_id: AtomicU64::new(0),
+            registry: Mutex::default(),
+            ttl,
+            running: Arc::new(Semaphore::new(max_running.min(Semaphore::MAX_PERMITS))),
+        }
+    }
+
+    /// A turn to run a job, unless `max_running` jobs are running already.
+    fn try_run(&self) -> Option<OwnedSemaphorePermit> {
+        Arc::clone(&self.running).try_acquire_owned().ok()
+    }
+
+    fn start(&self, file_name: Option<String>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let job = Job {
+            file_name,
+            status: Status::Running,
+        };
+        self.lock().jobs.insert(id, job);
+        id
+    }
+
+    fn finish(&self, id: u64, status: Status) {
+        let mut registry = self.lock();
+        if let Some(job) = registry.jobs.get_mut(&id) {
+            job.status = status;
+            registry.finished.push_back((Instant::now(), id));
+        }
+    }
+
+    /// The registry, without the jobs that have expired.
+    fn lock(&self) -> MutexGuard<'_, Registry> {
+        // A job is only ever replaced whole, so a panic cannot leave one half-updated
+        let mut registry = self
+            .registry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Dropping a job deletes its temporary output file
+        while let Some(&(finished, id)) = registry.finished.front()
+            && finished.elapsed() >= self.ttl
+        {
+            registry.finished.pop_front();
+            registry.jobs.remove(&id);
+        }
+        registry
+    }
+}
+
+impl Job {
+    fn to_json(&self, id: u64) -> Value {
+        let mut status = json!({"id": id, "file_name": self.file_name});
+        match &self.status {
+            Status::Running => status["status"] = "running".into(),
+            Status::Done { summary, .. } => {
+                status["status"] = "done".into();
+                status["rows"] = summary.rows.into();
+                status["valid"] = summary.valid.into();
+                status["invalid"] = summary.invalid().into();
+                status["result"] = format!("/jobs/{id}/result").into();
+            }
+            Status::Failed(error) => {
+                status["status"] = "failed".into();
+                status["error"] = error.to_string().into();
+                status["error_code"] = error.code.as_str().into();
+            }
+        }
+        status
+    }
+}
+
+/// The job routes.
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/upload", post(upload))
+        .route("/jobs/{id}", get(status))
+        .route("/jobs/{id}/result", get(result))
+}
+
+/// Starts processing a CSV file in the background.
+#[utoipa::path(
+    post,
+    path = "/upload",
+    request_body(content = openapi::Upload, content_type = "multipart/form-data"),
+    responses(
+        (status = 202, description = "The job, running", body = openapi::Job),
+        (status = 400, description = "No `file` field", body = openapi::ErrorBody),
+        (status = 503, description = "`--max-jobs` jobs are running already", body = openapi::ErrorBody)
+    )
+)]
+pub async fn upload(State(state): State<Arc<AppState>>, mut multipart: Multipart) -> Response {
+    // Taken before the file is saved, so that a refused upload costs no disk space
+    let Some(permit) = state.jobs.try_run() else {
+        let message = "too many upload jobs are running; retry later";
+        return error(StatusCode::SERVICE_UNAVAILABLE, message);
+    };
+    let field = loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) if field.name() == Some("file") => break field,
+            Ok(Some(_)) => continue,
+            Ok(None) => return error(StatusCode::BAD_REQUEST, "expected a field named 'file'"),
+            Err(e) => return error(e.status(), &e.to_string()),
+        }
+    };
+    let file_name = field.file_name().map(str::to_string);
+    let input = match save(field).await {
+        Ok(input) => input,
+        Err(e) => {
+            // `413` for a file past `--max-body`
+            let status = e
+                .downcast_ref::<MultipartError>()
+                .map_or(StatusCode::BAD_REQUEST, MultipartError::status);
+            return error(status, &format!("upload failed: {e}"));
+        }
+    };
+
+    let id = state.jobs.start(file_name);
+    // Read before the job starts: a finished one may already have expired by the reply
+    let job = state.jobs.lock().jobs[&id].to_json(id);
+    let shared = Arc::clone(&state);
+    // Outlives the request, so it is a span of its own
+    let span = tracing::info_span!("job", id);
+    tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        let _permit = permit;
+        let _in_flight = shared.metrics.in_flight(1);
+        let status = match process(&shared, input) {
+            Ok((summary, output)) => Status::Done { summary, output },
+            Err(e) => Status::Failed(CliError::classify(e)),
+        };
+        shared.jobs.finish(id, status);
+    });
+
+    (StatusCode::ACCEPTED, Json(job)).into_response()
+}
+
+/// Writes an uploaded file to a temporary file as it arrives.
+async fn save(mut field: Field<'_>) -> Result<NamedTempFile, Box<dyn Error + Send + Sync>> {
+    let input = NamedTempFile::new()?;
+    let mut file = tokio::fs::File::from_std(input.reopen()?);
+    while let Some(chunk) = field.chunk().await? {
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(input)
+}
+
+fn process(
+    state: &Arc<AppState>,
+    input: NamedTempFile,
+) -> Result<(Summary, NamedTempFile), Box<dyn Error + Send + Sync>> {
+    let output = NamedTempFile::new()?;
+    let reader = BufReader::new(input.reopen()?);
+    let summary = batch::rotator(state).process(reader, BufWriter::new(output.reopen()?))?;
+    Ok((summary, output))
+}
+
+/// A job's status.
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    params(("id" = u64, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "The job", body = openapi::Job),
+        (status = 404, description = "No such job", body = openapi::ErrorBody)
+    )
+)]
+pub async fn status(State(state): State<Arc<AppState>>, Path(id): Path<u64>) -> Response {
+    match state.jobs.lock().jobs.get(&id) {
+        Some(job) => Json(job.to_json(id)).into_response(),
+        None => error(StatusCode::NOT_FOUND, &format!("no job {id}")),
+    }
+}
+
+/// The output CSV of a finished job.
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}/result",
+    params(("id" = u64, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "The output CSV", body = String, content_type = "text/csv"),
+        (status = 404, description = "No such job", body = openapi::ErrorBody),
+        (status = 409, description = "The job is running or failed", body = openapi::ErrorBody)
+    )
+)]
+pub async fn result(State(state): State<Arc<AppState>>, Path(id): Path<u64>) -> Response {
+    let output = match state.jobs.lock().jobs.get(&id).map(|job| &job.status) {
+        Some(Status::Done { output, .. }) => output.reopen(),
+        Some(_) => return error(StatusCode::CONFLICT, &format!("job {id} has no result")),
+        None => return error(StatusCode::NOT_FOUND, &format!("no job {id}")),
+    };
+    match output {
+        Ok(file) => {
+            let stream = ReaderStream::new(tokio::fs::File::from_std(file));
+            let headers = [(header::CONTENT_TYPE, "text/csv")];
+            (headers, Body::from_stream(stream)).into_response()
+        }
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// A failed request, with the [`ErrorCode`] of its status.
+fn error(status: StatusCode, message: &str) -> Response {
+    let code = match status {
+        StatusCode::INTERNAL_SERVER_ERROR => ErrorCode::Io,
+        StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::BodyTooLarge,
+        StatusCode::SERVICE_UNAVAILABLE => ErrorCode::Overloaded,
+        _ => ErrorCode::Usage,
+    };
+    let body = json!({"error": message, "error_code": code.as_str()});
+    (status, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_job_status() {
+        let jobs = Jobs::default();
+        let id = jobs.start(Some("input.csv".to_string()));
+        assert_eq!(id, 1);
+        assert_eq!(
+            jobs.lock().jobs[&id].to_json(id),
+            json!({"id": 1, "file_name": "input.csv", "status": "running"})
+        );
+
+        let summary = Summary {
+            rows: 3,
+            valid: 2,
+            ..Summary::default()
+        };
+        let output = NamedTempFile::new().unwrap();
+        jobs.finish(id, Status::Done { summary, output });
+        assert_eq!(
+            jobs.lock().jobs[&id].to_json(id),
+            json!({
+                "id": 1,
+                "file_name": "input.csv",
+                "status": "done",
+                "rows": 3,
+                "valid": 2,
+                "invalid": 1,
+                "result": "/jobs/1/result",
+            })
+        );
+
+        let id = jobs.start(None);
+        let failure = io::Error::new(io::ErrorKind::InvalidData, "bad input");
+        jobs.finish(id, Status::Failed(CliError::classify(failure.into())));
+        let status = jobs.lock().jobs[&id].to_json(id);
+        assert_eq!(status["status"], "failed");
+        assert_eq!(status["error"], "bad input");
+        assert_eq!(status["error_code"], "E011");
+    }
+
+    #[test]
+    fn test_finished_jobs_expire() {
+        let jobs = Jobs::new(Duration::from_millis(50), DEFAULT_MAX_RUNNING);
+        let running = jobs.start(None);
+        let done = jobs.start(None);
+        let output = NamedTempFile::new().unwrap();
+        let path = output.path().to_owned();
+        let summary = Summary::default();
+        jobs.finish(done, Status::Done { summary, output });
+        assert!(jobs.lock().jobs.contains_key(&done));
+
+        std::thread::sleep(Duration::from_millis(60));
+        let registry = jobs.lock();
+        assert!(!registry.jobs.contains_key(&done));
+        assert!(registry.finished.is_empty());
+        assert!(!path.exists());
+        // Only finished jobs expire
+        assert!(registry.jobs.contains_key(&running));
+    }
+
+    #[test]
+    fn test_max_running() {
+        let jobs = Jobs::new(DEFAULT_TTL, 2);
+        let first = jobs.try_run().unwrap();
+        let _second = jobs.try_run().unwrap();
+        assert!(jobs.try_run().is_none());
+        drop(first);
+        assert!(jobs.try_run().is_some());
+    }
+}
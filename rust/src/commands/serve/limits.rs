@@ -0,0 +1,349 @@
+//! Per-client rate limits (`--rate-limit`) and request size limits (`--max-body`).
+//!
+//! Each client address gets a token bucket that holds one second's worth of requests and
+//! refills continuously, so a client may burst up to the rate and is then held to it;
+//! requests beyond it get `429 Too Many Requests` with a `Retry-After` and `error_code`
+//! `E015`. A body longer than `--max-body` gets `413 Payload Too Large` with `error_code`
+//! `E016`: right away when its `Content-Length` says so, otherwise when reading passes the
+//! limit, which for the streaming `/rotate/csv` aborts the response instead. Tables are
+//! capped separately by `--max-n`, as everywhere else.
+//!
+//! At most `--max-concurrent-requests` requests are processed at once, a streamed response
+//! counting until it has been sent; up to `--queue-depth` more wait for their turn, and the
+//! rest are shed right away with `503 Service Unavailable`, a `Retry-After` and `error_code`
+//! `E017`, so that overload shows up as fast refusals instead of ever-growing latencies.
+//! `/metrics` reports the queue as `rotate_cli_requests_queued` and the refusals as
+//! `rotate_cli_requests_shed_total`.
+
+use super::app::AppState;
+use axum::Json;
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::connect_info::Connected;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::serve::IncomingStream;
+use http_body::{Frame, SizeHint};
+use http_body_util::Limited;
+use rotate_cli::errors::ErrorCode;
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::net::TcpListener;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Clients tracked before the least recently seen one is forgotten.
+const MAX_CLIENTS: usize = 10_000;
+
+/// The address a connection came from, over plain TCP or TLS.
+#[derive(Clone, Copy)]
+pub struct ClientAddr(pub SocketAddr);
+
+impl Connected<IncomingStream<'_, TcpListener>> for ClientAddr {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        ClientAddr(*stream.remote_addr())
+    }
+}
+
+/// Token buckets per client address.
+#[derive(Default)]
+pub struct RateLimiter {
+    /// Requests per second per client; `None` does not limit
+    rate: Option<f64>,
+    buckets: Mutex<Buckets>,
+}
+
+/// The buckets, and the same clients ordered by when they were last seen.
+#[derive(Default)]
+struct Buckets {
+    by_client: HashMap<IpAddr, Bucket>,
+    by_age: BTreeSet<(Instant, IpAddr)>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: Option<f64>) -> Self {
+        RateLimiter {
+            rate,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Takes a token for a request from `client` at `now`, or returns the seconds until one
+    /// is available.
+    fn acquire(&self, client: IpAddr, now: Instant) -> Result<(), f64> {
+        let Some(rate) = self.rate else {
+            return Ok(());
+        };
+        let capacity = rate.max(1.0);
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let Buckets { by_client, by_age } = &mut *buckets;
+        if by_client.len() >= MAX_CLIENTS
+            && !by_client.contains_key(&client)
+            && let Some((_, oldest)) = by_age.pop_first()
+        {
+            by_client.remove(&oldest);
+        }
+        let bucket = by_client.entry(client).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        by_age.remove(&(bucket.updated, client));
+        by_age.insert((now, client));
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err((1.0 - bucket.tokens) / rate)
+        }
+    }
+}
+
+/// Middleware that refuses requests beyond the client's rate, bodies beyond the size limit
+/// and requests beyond the queue.
+pub async fn enforce_limits(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<ClientAddr>>()
+        .map(|ConnectInfo(ClientAddr(addr))| addr.ip());
+    if let Some(client) = client
+        && let Err(wait) = state.rate_limiter.acquire(client, Instant::now())
+    {
+        let retry_after = [(header::RETRY_AFTER, (wait.ceil() as u64).to_string())];
+        let message = "too many requests; retry later";
+        let body = error_body(message, ErrorCode::RateLimited);
+        return (StatusCode::TOO_MANY_REQUESTS, retry_after, body).into_response();
+    }
+
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse::<u64>().ok());
+    if let Some(max_body) = state.max_body
+        && declared.is_some_and(|length| length > max_body as u64)
+    {
+        return payload_too_large(max_body);
+    }
+
+    let Some(permit) = state.concurrency.acquire().await else {
+        let retry_after = [(header::RETRY_AFTER, "1")];
+        let body = error_body(
+            "the server is overloaded; retry later",
+            ErrorCode::Overloaded,
+        );
+        return (StatusCode::SERVICE_UNAVAILABLE, retry_after, body).into_response();
+    };
+    let response = match state.max_body {
+        Some(max_body) => {
+            let (parts, body) = request.into_parts();
+            let request = Request::from_parts(parts, Body::new(Limited::new(body, max_body)));
+            let response = next.run(request).await;
+            // Extractors that buffer the body answer a limited one with a plain-text 413
+            match response.status() {
+                StatusCode::PAYLOAD_TOO_LARGE => payload_too_large(max_body),
+                _ => response,
+            }
+        }
+        None => next.run(request).await,
+    };
+    // A streamed response, such as that of `/rotate/csv`, is still being worked on
+    response.map(|body| {
+        Body::new(Held {
+            body,
+            _permit: permit,
+        })
+    })
+}
+
+/// Requests processed at once (`--max-concurrent-requests`) and waiting for a turn
+/// (`--queue-depth`).
+pub struct ConcurrencyLimit {
+    permits: Arc<Semaphore>,
+    queue_depth: usize,
+    queued: AtomicUsize,
+    shed: AtomicU64,
+}
+
+impl Default for ConcurrencyLimit {
+    /// No limit.
+    fn default() -> Self {
+        ConcurrencyLimit::new(Semaphore::MAX_PERMITS, usize::MAX)
+    }
+}
+
+impl ConcurrencyLimit {
+    pub fn new(max_concurrent: usize, queue_depth: usize) -> Self {
+        ConcurrencyLimit {
+            permits: Arc::new(Semaphore::new(max_concurrent.min(Semaphore::MAX_PERMITS))),
+            queue_depth,
+            queued: AtomicUsize::new(0),
+            shed: AtomicU64::new(0),
+        }
+    }
+
+    /// A turn to process a request, after waiting in the queue if need be; `None` if the
+    /// queue is full.
+    async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = Arc::clone(&self.permits).try_acquire_owned() {
+            return Some(permit);
+        }
+        if self.queued.fetch_add(1, Ordering::Relaxed) >= self.queue_depth {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            self.shed.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        // Leaves the queue even when the client goes away while waiting
+        let _queued = Queued(&self.queued);
+        let permit = Arc::clone(&self.permits).acquire_owned().await;
+        Some(permit.expect("the semaphore is never closed"))
+    }
+
+    /// The queue metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        format!(
+            "# HELP rotate_cli_requests_queued Requests waiting for a --max-concurrent-requests slot.\n\
+             # TYPE rotate_cli_requests_queued gauge\n\
+             rotate_cli_requests_queued {}\n\
+             # HELP rotate_cli_requests_shed_total Requests refused because the queue was full.\n\
+             # TYPE rotate_cli_requests_shed_total counter\n\
+             rotate_cli_requests_shed_total {}\n",
+            self.queued.load(Ordering::Relaxed),
+            self.shed.load(Ordering::Relaxed)
+        )
+    }
+}
+
+struct Queued<'a>(&'a AtomicUsize);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A response body that keeps its request's turn until it has been sent.
+struct Held {
+    body: Body,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl HttpBody for Held {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        Pin::new(&mut self.body).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+fn payload_too_large(max_body: usize) -> Response {
+    let message = format!("the request body is larger than {max_body} bytes");
+    let body = error_body(&message, ErrorCode::BodyTooLarge);
+    (StatusCode::PAYLOAD_TOO_LARGE, body).into_response()
+}
+
+fn error_body(message: &str, code: ErrorCode) -> Json<serde_json::Value> {
+    Json(json!({"error": message, "error_code": code.as_str()}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(Some(2.0));
+        let (a, b) = (IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2]));
+        let start = Instant::now();
+        assert_eq!(limiter.acquire(a, start), Ok(()));
+        assert_eq!(limiter.acquire(a, start), Ok(()));
+        assert_eq!(limiter.acquire(a, start), Err(0.5));
+        // Other clients have their own bucket
+        assert_eq!(limiter.acquire(b, start), Ok(()));
+        let later = start + Duration::from_millis(500);
+        assert_eq!(limiter.acquire(a, later), Ok(()));
+        assert!(limiter.acquire(a, later).is_err());
+        // A bucket refills only up to one second's worth
+        let much_later = start + Duration::from_secs(60);
+        assert_eq!(limiter.acquire(a, much_later), Ok(()));
+        assert_eq!(limiter.acquire(a, much_later), Ok(()));
+        assert!(limiter.acquire(a, much_later).is_err());
+
+        // Past the cap, the least recently seen clients are forgotten: b, then `first`
+        let client = |i: usize| IpAddr::from([192, 168, (i >> 8) as u8, i as u8]);
+        let first = client(0);
+        for i in 0..MAX_CLIENTS - 2 {
+            assert_eq!(limiter.acquire(client(i), much_later), Ok(()));
+        }
+        let last = much_later + Duration::from_secs(1);
+        assert!(limiter.acquire(a, last).is_ok());
+        for i in MAX_CLIENTS - 2..MAX_CLIENTS {
+            assert_eq!(limiter.acquire(client(i), last), Ok(()));
+        }
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.by_client.len(), MAX_CLIENTS);
+        assert_eq!(buckets.by_age.len(), MAX_CLIENTS);
+        assert!(!buckets.by_client.contains_key(&b));
+        assert!(!buckets.by_client.contains_key(&first));
+        assert!(buckets.by_client.contains_key(&a));
+        drop(buckets);
+
+        let unlimited = RateLimiter::default();
+        for _ in 0..100 {
+            assert_eq!(unlimited.acquire(a, start), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let limit = ConcurrencyLimit::new(1, 1);
+            let first = limit.acquire().await.unwrap();
+            // The second request waits in the queue, the third is shed
+            let mut second = std::pin::pin!(limit.acquire());
+            assert!(futures::poll!(&mut second).is_pending());
+            assert!(limit.acquire().await.is_none());
+            assert!(limit.render().contains("rotate_cli_requests_queued 1\n"));
+            drop(first);
+            assert!(second.await.is_some());
+            assert!(limit.render().contains("rotate_cli_requests_queued 0\n"));
+            assert!(
+                limit
+                    .render()
+                    .contains("rotate_cli_requests_shed_total 1\n")
+            );
+        });
+    }
+}
@@ -0,0 +1,144 @@
+//! `rotate_cli serve`: rotate tables over HTTP and WebSockets.
+//!
+//! Routes:
+//! - `POST /rotate` `{"json": "[1,2,3,4]"}` or `{"table": [1,2,3,4]}` (plus an optional
+//!   `id`) → `{"json": "[3,1,4,2]", "is_valid": true, "n": 2}`; invalid tables also get an
+//!   `error_reason` (`too_large` for tables with more than `--max-n` rows, `timeout` for
+//!   tables that took longer than `--row-timeout` to parse); empty and non-square tables
+//!   also get an `explanation` and a `shape`, as in [`rpc`](crate::rpc). Invalid tables and
+//!   `400` responses carry an `error_code` (see [`ErrorCode`](rotate_cli::errors::ErrorCode))
+//! - `POST /rotate/csv` with a whole CSV file as the body (chunked transfer encoding
+//!   welcome) → the output CSV of `rotate_cli INPUT.csv`, streamed back as it is written
+//!   (see [`batch`])
+//! - `POST /upload` with a multipart CSV file → `202 Accepted` and a job id; the file is
+//!   processed in the background, `GET /jobs/{id}` reports its status and
+//!   `GET /jobs/{id}/result` downloads the output (see [`jobs`])
+//! - `GET /ws` upgrades to a WebSocket; every text frame is one row in the `/rotate` format
+//!   and is answered by one frame with its result, in order
+//! - `GET /metrics` Prometheus metrics, as with `--metrics-addr` in the other modes
+//! - `GET /openapi.json` the OpenAPI document of these routes, and with the `swagger-ui`
+//!   feature `GET /docs` to browse it (see [`openapi`])
+//!
+//! With `--api-key` or `--api-keys-file`, every route requires one of the keys (see
+//! [`auth`]). `--rate-limit` and `--max-body` answer clients over their request rate with
+//! `429` and bodies over the size limit with `413`; requests beyond
+//! `--max-concurrent-requests` wait in a queue of `--queue-depth`, and the ones that do not
+//! fit are refused with `503` (see [`limits`]). `--http-workers` sets the number of threads
+//! answering requests.
+//!
+//! With `--tls-cert` and `--tls-key` (and the `tls` feature) the same routes are served over
+//! HTTPS (see [`tls`]).
+//!
+//! On SIGTERM or SIGINT the server stops accepting connections, answers the requests it
+//! already has (streaming `/rotate/csv` responses and running upload jobs included) and
+//! prints the `--stats` summary before exiting; open WebSocket connections are closed.
+
+use clap::Args;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[cfg(feature = "serve")]
+mod app;
+#[cfg(feature = "serve")]
+mod auth;
+#[cfg(feature = "serve")]
+mod batch;
+#[cfg(feature = "serve")]
+mod jobs;
+#[cfg(feature = "serve")]
+mod limits;
+#[cfg(feature = "serve")]
+mod openapi;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(feature = "serve")]
+mod ws;
+
+#[derive(Args)]
+#[cfg_attr(not(feature = "serve"), allow(dead_code))]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    #[command(flatten)]
+    limits: super::RowLimits,
+
+    /// Require this API key on every request (repeatable); prefer --api-keys-file, as
+    /// command lines are visible to other users
+    #[arg(long, value_name = "KEY")]
+    api_key: Vec<String>,
+
+    /// Require one of the keys in FILE, one `NAME:KEY` or `KEY` per line, on every request
+    #[arg(long, value_name = "FILE")]
+    api_keys_file: Option<PathBuf>,
+
+    /// Serve HTTPS with the PEM certificate chain in FILE (requires the `tls` feature)
+    #[arg(long, value_name = "FILE", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// The PEM private key of --tls-cert
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Requests per second to allow each client address, with bursts of up to one second's
+    /// worth; unlimited by default
+    #[arg(long, value_name = "RPS", value_parser = parse_rate)]
+    rate_limit: Option<f64>,
+
+    /// Refuse request bodies larger than SIZE bytes (k, M and G suffixes accepted)
+    #[arg(long, value_name = "SIZE", default_value = "16M", value_parser = crate::parse_size)]
+    max_body: usize,
+
+    /// Threads answering requests [default: one per CPU]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    http_workers: Option<u16>,
+
+    /// Requests processed at once; a streamed response counts until it has been sent
+    #[arg(long, value_name = "N", default_value_t = 256, value_parser = clap::value_parser!(u32).range(1..))]
+    max_concurrent_requests: u32,
+
+    /// Requests waiting for their turn before new ones are refused with 503
+    #[arg(long, value_name = "N", default_value_t = 1024)]
+    queue_depth: usize,
+
+    /// Upload jobs processed at once; further uploads are refused with 503
+    #[arg(long, value_name = "N", default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    max_jobs: u16,
+
+    /// Seconds an upload job and its result are kept after it finishes
+    #[arg(long, value_name = "SECONDS", default_value = "3600", value_parser = crate::parse_seconds)]
+    job_ttl: std::time::Duration,
+
+    /// Print rows/sec throughput to stderr when shut down
+    #[arg(long)]
+    stats: bool,
+
+    /// Print the OpenAPI document of the API and exit, without listening
+    #[arg(long)]
+    print_openapi: bool,
+}
+
+fn parse_rate(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(format!(
+            "expected a positive number of requests per second, got '{text}'"
+        )),
+    }
+}
+
+#[cfg(feature = "serve")]
+pub use app::run;
+
+#[cfg(not(feature = "serve"))]
+pub fn run(args: ServeArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "cannot listen on {}: the serve subcommand requires building with `--features serve`",
+            args.listen
+        ),
+    )
+    .into())
+}
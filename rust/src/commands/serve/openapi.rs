@@ -0,0 +1,165 @@
+//! The OpenAPI 3 document of the server, served at `GET /openapi.json` and printed by
+//! `serve --print-openapi`, so clients in the other benchmark languages can be generated
+//! from it. With the `swagger-ui` feature, `GET /docs` browses it.
+//!
+//! The handlers build their JSON with `serde_json`; the types here only describe it.
+#![allow(dead_code)]
+
+use super::app::AppState;
+use super::{app, batch, jobs, ws};
+use axum::Router;
+use std::sync::Arc;
+use utoipa::{OpenApi, ToSchema};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "rotate_cli serve",
+        description = "Rotates square tables of numbers one step clockwise, ring by ring."
+    ),
+    paths(
+        app::rotate,
+        batch::rotate_csv,
+        jobs::upload,
+        jobs::status,
+        jobs::result,
+        ws::upgrade,
+        app::metrics
+    )
+)]
+pub struct ApiDoc;
+
+/// `GET /openapi.json`, and `GET /docs` with the `swagger-ui` feature.
+pub fn routes() -> Router<Arc<AppState>> {
+    #[cfg(feature = "swagger-ui")]
+    let routes = utoipa_swagger_ui::SwaggerUi::new("/docs")
+        .url("/openapi.json", ApiDoc::openapi())
+        .into();
+    #[cfg(not(feature = "swagger-ui"))]
+    let routes = Router::new().route(
+        "/openapi.json",
+        axum::routing::get(|| async { axum::Json(ApiDoc::openapi()) }),
+    );
+    routes
+}
+
+/// One table, as a JSON string or as an array; exactly one of `json` and `table` is given.
+#[derive(ToSchema)]
+pub struct RotateRequest {
+    /// Echoed in the result
+    #[schema(value_type = Option<Value>)]
+    id: Option<serde_json::Value>,
+    /// The table as JSON text, such as `[1,2,3,4]`
+    json: Option<String>,
+    table: Option<Vec<f64>>,
+}
+
+/// The rotated table, or why it could not be rotated.
+#[derive(ToSchema)]
+pub struct RotateResult {
+    #[schema(value_type = Option<Value>)]
+    id: Option<serde_json::Value>,
+    /// The rotated table as compact JSON; `[]` if invalid
+    json: String,
+    is_valid: bool,
+    /// Side of the table; null if invalid
+    n: Option<usize>,
+    /// Why the table is invalid: malformed, empty, not_square, too_large, timeout or rejected
+    error_reason: Option<String>,
+    /// The stable code of the failure, such as `E002`
+    error_code: Option<String>,
+    /// For empty and non-square tables, such as `3 elements: remove 2 for 1×1 or add 1 for 2×2`
+    explanation: Option<String>,
+    shape: Option<Shape>,
+}
+
+/// How far an empty or non-square table is from the nearest squares.
+#[derive(ToSchema)]
+pub struct Shape {
+    len: usize,
+    smaller: usize,
+    /// Elements to remove for a `smaller`×`smaller` table; null for an empty table
+    remove: Option<usize>,
+    larger: usize,
+    /// Elements to add for a `larger`×`larger` table
+    add: usize,
+}
+
+/// A request that could not be carried out.
+#[derive(ToSchema)]
+pub struct ErrorBody {
+    error: String,
+    /// The stable code of the failure, such as `E012`
+    error_code: String,
+}
+
+/// A CSV file to process in the background.
+#[derive(ToSchema)]
+pub struct Upload {
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+/// An uploaded file's job.
+#[derive(ToSchema)]
+pub struct Job {
+    id: u64,
+    file_name: Option<String>,
+    status: JobStatus,
+    /// Once `done`
+    rows: Option<u64>,
+    valid: Option<u64>,
+    invalid: Option<u64>,
+    /// Where to download the output, once `done`
+    result: Option<String>,
+    /// Once `failed`
+    error: Option<String>,
+    error_code: Option<String>,
+}
+
+#[derive(ToSchema)]
+#[schema(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document() {
+        let document = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+        let paths: Vec<&str> = document["paths"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "/jobs/{id}",
+                "/jobs/{id}/result",
+                "/metrics",
+                "/rotate",
+                "/rotate/csv",
+                "/upload",
+                "/ws"
+            ]
+        );
+        let schemas = &document["components"]["schemas"];
+        assert_eq!(
+            schemas["JobStatus"]["enum"],
+            serde_json::json!(["running", "done", "failed"])
+        );
+        assert_eq!(
+            document["paths"]["/upload"]["post"]["requestBody"]["content"]["multipart/form-data"]["schema"]
+                ["$ref"],
+            "#/components/schemas/Upload"
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! `GET /ws`: stream rows over a WebSocket and receive each result as soon as it is ready.
+//!
+//! Rows of one connection are handled in order and the next frame is only read once the
+//! previous result has been handed to the socket, so a client that stops reading its
+//! results eventually stops being read from (backpressure through the TCP window) instead
+//! of making the server buffer results without bound.
+
+use super::app::{AppState, rotate_row};
+use crate::metrics::Metrics;
+use axum::extract::State;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::Response;
+use rotate_cli::RowBuffers;
+use serde_json::json;
+use std::sync::Arc;
+
+/// Rotates rows sent as text frames, answering each with a frame in the `/rotate` format.
+#[utoipa::path(
+    get,
+    path = "/ws",
+    responses((status = 101, description = "Switching to the WebSocket protocol"))
+)]
+pub async fn upgrade(State(state): State<Arc<AppState>>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| handle(socket, state))
+}
+
+async fn handle(mut socket: WebSocket, state: Arc<AppState>) {
+    let mut buffers = state.limits.buffers();
+    while let Some(Ok(message)) = socket.recv().await {
+        let reply = match message {
+            Message::Text(text) => {
+                // Off the worker threads, like the `/rotate` handler
+                let state = Arc::clone(&state);
+                let replied = tokio::task::spawn_blocking(move || {
+                    let _in_flight = state.metrics.in_flight(1);
+                    let reply = frame_reply(&text, &mut buffers, &state.metrics);
+                    (reply, buffers)
+                })
+                .await;
+                match replied {
+                    Ok((reply, returned)) => {
+                        buffers = returned;
+                        reply
+                    }
+                    Err(e) => std::panic::resume_unwind(e.into_panic()),
+                }
+            }
+            Message::Binary(_) => json!({"error": "expected a text frame"}).to_string(),
+            Message::Close(_) => break,
+            // Pings are answered by axum itself
+            Message::Ping(_) | Message::Pong(_) => continue,
+        };
+        if socket.send(Message::Text(reply.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Answers one text frame with its result or `{"id": ..., "error": "...", "error_code": "..."}`.
+fn frame_reply(text: &str, buffers: &mut RowBuffers, metrics: &Metrics) -> String {
+    match rotate_row(text.as_bytes(), buffers, metrics) {
+        Ok(result) | Err(result) => result.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_reply() {
+        let metrics = Metrics::default();
+        let reply = |text| frame_reply(text, &mut RowBuffers::default(), &metrics);
+
+        assert_eq!(
+            reply(r#"{"id": "a", "json": "[1, 2, 3, 4]"}"#),
+            r#"{"id":"a","is_valid":true,"json":"[3,1,4,2]","n":2}"#
+        );
+        assert_eq!(
+            reply(r#"{"id": 3, "rows": []}"#),
+            r#"{"error":"expected params {\"json\": string} or {\"table\": array}","error_code":"E012","id":3}"#
+        );
+        assert!(reply("[1, 2").starts_with(r#"{"error":"invalid JSON: "#));
+    }
+}
@@ -0,0 +1,47 @@
+//! `rotate_cli view`: page through the tables of a CSV in the terminal.
+//!
+//! The rows are listed by id on the left and the selected row's table is drawn as a grid
+//! on the right. Transforms are applied to the shown table one key at a time, so the effect
+//! of a rotation or flip can be followed step by step:
+//!
+//! - `↑`/`↓` (or `k`/`j`), `PgUp`/`PgDn`, `Home`/`End` move through the rows
+//! - `r`/`R` rotate right/left, `t` transposes, `f`/`v` flip horizontally/vertically, `u`
+//!   undoes the last of them and `0` goes back to the table as read
+//! - `g` (or `/`) asks for an id and jumps to its first row
+//! - `q` (or `Esc`) quits
+//!
+//! The whole file is read into memory first, as the files worth browsing are small.
+
+use clap::Args;
+use rotate_cli::DEFAULT_MAX_N;
+
+#[cfg(feature = "tui")]
+mod state;
+#[cfg(feature = "tui")]
+mod ui;
+
+#[derive(Args)]
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub struct ViewArgs {
+    /// Path or URI of a CSV file with columns 'id' and 'json'
+    input: String,
+
+    /// Largest table side shown; see the top-level --max-n (0 = no limit)
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_N)]
+    max_n: usize,
+}
+
+#[cfg(feature = "tui")]
+pub use ui::run;
+
+#[cfg(not(feature = "tui"))]
+pub fn run(args: ViewArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "cannot view {}: the view subcommand requires building with `--features tui`",
+            args.input
+        ),
+    )
+    .into())
+}
@@ -0,0 +1,174 @@
+//! What the viewer shows, apart from how it is drawn: the rows of the file, the selected
+//! one, and its table with the transforms applied so far.
+
+use rotate_cli::{InvalidReason, RowBuffers, Transform, parse_table};
+use std::io::Read;
+
+/// One `id,json` record as read.
+pub struct Row {
+    pub id: String,
+    pub json: String,
+}
+
+/// Reads every record of an `id,json` CSV; records without a `json` field get an empty
+/// one, which shows as malformed.
+pub fn load<R: Read>(input: R) -> Result<Vec<Row>, csv::Error> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(input);
+    let mut rows = Vec::new();
+    for record in rdr.byte_records() {
+        let record = record?;
+        let field = |i| String::from_utf8_lossy(record.get(i).unwrap_or_default()).into_owned();
+        rows.push(Row {
+            id: field(0),
+            json: field(1),
+        });
+    }
+    Ok(rows)
+}
+
+pub struct Viewer {
+    rows: Vec<Row>,
+    selected: usize,
+    buffers: RowBuffers,
+    /// Side length of the selected row's table, whose cells are in `buffers.numbers`
+    table: Result<usize, InvalidReason>,
+    transforms: Vec<Transform>,
+}
+
+impl Viewer {
+    /// A viewer on the first of `rows`, which must not be empty.
+    pub fn new(rows: Vec<Row>, max_n: Option<usize>) -> Self {
+        assert!(!rows.is_empty(), "nothing to view");
+        let mut viewer = Viewer {
+            rows,
+            selected: 0,
+            buffers: RowBuffers::with_max_n(max_n),
+            table: Err(InvalidReason::Malformed),
+            transforms: Vec::new(),
+        };
+        viewer.load();
+        viewer
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects row `index`, or the last row if there are fewer, as read.
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.rows.len() - 1);
+        self.load();
+    }
+
+    /// Selects the first row with this id; `false` if there is none.
+    pub fn jump(&mut self, id: &str) -> bool {
+        match self.rows.iter().position(|row| row.id == id) {
+            Some(index) => {
+                self.select(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The selected table as it is now, row by row, or why the row is not a table.
+    pub fn table(&self) -> Result<(usize, &[i64]), InvalidReason> {
+        self.table.map(|n| (n, self.buffers.numbers.as_slice()))
+    }
+
+    /// The transforms applied to the selected table since it was read.
+    pub fn transforms(&self) -> &[Transform] {
+        &self.transforms
+    }
+
+    /// Applies `transform` to the selected table; `false` if the row is not a table.
+    pub fn apply(&mut self, transform: Transform) -> bool {
+        if self.table.is_err() {
+            return false;
+        }
+        transform
+            .apply(self.buffers.numbers.as_mut_slice())
+            .expect("parse_table checked the table");
+        self.transforms.push(transform);
+        true
+    }
+
+    /// Undoes the last transform; `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        let Some(transform) = self.transforms.pop() else {
+            return false;
+        };
+        transform
+            .undo(self.buffers.numbers.as_mut_slice())
+            .expect("parse_table checked the table");
+        true
+    }
+
+    /// Goes back to the selected table as read.
+    pub fn reset(&mut self) {
+        self.load();
+    }
+
+    fn load(&mut self) {
+        let buffers = &mut self.buffers;
+        self.table = parse_table(&self.rows[self.selected].json, buffers).ok_or_else(|| {
+            buffers
+                .invalid_reason(false)
+                .unwrap_or(InvalidReason::Malformed)
+        });
+        self.transforms.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewer() -> Viewer {
+        let input = "id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2, 3]\"\n3\n1,[5]\n";
+        Viewer::new(load(input.as_bytes()).unwrap(), Some(10))
+    }
+
+    #[test]
+    fn test_transforms() {
+        let mut viewer = viewer();
+        assert_eq!(viewer.table(), Ok((2, &[1, 2, 3, 4][..])));
+        assert!(viewer.apply(Transform::RotateRight));
+        assert!(viewer.apply(Transform::FlipHorizontal));
+        assert_eq!(viewer.table(), Ok((2, &[1, 3, 2, 4][..])));
+        assert_eq!(
+            viewer.transforms(),
+            [Transform::RotateRight, Transform::FlipHorizontal]
+        );
+        assert!(viewer.undo());
+        assert_eq!(viewer.table(), Ok((2, &[3, 1, 4, 2][..])));
+        assert_eq!(viewer.transforms(), [Transform::RotateRight]);
+        viewer.reset();
+        assert_eq!(viewer.table(), Ok((2, &[1, 2, 3, 4][..])));
+        assert!(!viewer.undo());
+        assert!(viewer.transforms().is_empty());
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut viewer = viewer();
+        assert_eq!(viewer.rows().len(), 4);
+        viewer.select(1);
+        assert_eq!(viewer.table(), Err(InvalidReason::NotSquare));
+        assert!(!viewer.apply(Transform::Transpose));
+        viewer.select(2);
+        assert_eq!(viewer.table(), Err(InvalidReason::Malformed));
+        viewer.select(usize::MAX);
+        assert_eq!((viewer.selected(), viewer.table()), (3, Ok((1, &[5][..]))));
+
+        // The first row with the id
+        assert!(viewer.jump("1"));
+        assert_eq!(viewer.selected(), 0);
+        assert!(!viewer.jump("4"));
+        assert_eq!(viewer.selected(), 0);
+    }
+}
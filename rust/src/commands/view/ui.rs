@@ -0,0 +1,196 @@
+//! The terminal side of `rotate_cli view`: drawing the rows and table, and the keys.
+
+use super::ViewArgs;
+use super::state::{self, Viewer};
+use crate::render::grid;
+use crate::{input, max_n_limit};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use rotate_cli::Transform;
+use std::error::Error;
+use std::io;
+
+const HELP: &str = "↑↓ PgUp PgDn: move · r/R: rotate · t: transpose · f/v: flip · u: undo · 0: reset · \
+                    g: jump to id · q: quit";
+
+pub fn run(args: ViewArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let rows = state::load(input::open(&args.input)?)?;
+    if rows.is_empty() {
+        return Err(format!("{}: no rows to view", args.input).into());
+    }
+    let mut viewer = Viewer::new(rows, max_n_limit(args.max_n));
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut viewer, &args.input);
+    ratatui::restore();
+    Ok(result?)
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, viewer: &mut Viewer, title: &str) -> io::Result<()> {
+    // The id typed after `g`, while asking for one
+    let mut prompt: Option<String> = None;
+    let mut message = String::new();
+    let mut page = 1;
+    loop {
+        terminal.draw(|frame| {
+            page = draw(frame, viewer, title, prompt.as_deref(), &message);
+        })?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        message.clear();
+
+        if let Some(id) = &mut prompt {
+            match key.code {
+                KeyCode::Enter => {
+                    if !viewer.jump(id) {
+                        message = format!("no row with id '{id}'");
+                    }
+                    prompt = None;
+                }
+                KeyCode::Esc => prompt = None,
+                KeyCode::Backspace => {
+                    id.pop();
+                }
+                KeyCode::Char(c) => id.push(c),
+                _ => {}
+            }
+            continue;
+        }
+
+        let selected = viewer.selected();
+        let transform = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Down | KeyCode::Char('j') => {
+                viewer.select(selected + 1);
+                None
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                viewer.select(selected.saturating_sub(1));
+                None
+            }
+            KeyCode::PageDown => {
+                viewer.select(selected + page);
+                None
+            }
+            KeyCode::PageUp => {
+                viewer.select(selected.saturating_sub(page));
+                None
+            }
+            KeyCode::Home => {
+                viewer.select(0);
+                None
+            }
+            KeyCode::End => {
+                viewer.select(usize::MAX);
+                None
+            }
+            KeyCode::Char('g' | '/') => {
+                prompt = Some(String::new());
+                None
+            }
+            KeyCode::Char('u') => {
+                if !viewer.undo() {
+                    message = "nothing to undo".to_string();
+                }
+                None
+            }
+            KeyCode::Char('0') => {
+                viewer.reset();
+                None
+            }
+            KeyCode::Char('r') => Some(Transform::RotateRight),
+            KeyCode::Char('R') => Some(Transform::RotateLeft),
+            KeyCode::Char('t') => Some(Transform::Transpose),
+            KeyCode::Char('f') => Some(Transform::FlipHorizontal),
+            KeyCode::Char('v') => Some(Transform::FlipVertical),
+            _ => None,
+        };
+        if let Some(transform) = transform
+            && !viewer.apply(transform)
+        {
+            message = "not a table; nothing to transform".to_string();
+        }
+    }
+}
+
+/// Draws one frame and returns the number of rows in the list.
+fn draw(
+    frame: &mut Frame,
+    viewer: &Viewer,
+    title: &str,
+    prompt: Option<&str>,
+    message: &str,
+) -> usize {
+    let [main, status] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let [list_area, table_area] =
+        Layout::horizontal([Constraint::Length(24), Constraint::Min(0)]).areas(main);
+
+    // Only the page of ids around the selected row, as files may have millions
+    let page = usize::from(list_area.height.saturating_sub(2)).max(1);
+    let rows = viewer.rows();
+    let first = viewer.selected() - viewer.selected() % page;
+    let ids = rows[first..rows.len().min(first + page)]
+        .iter()
+        .map(|row| row.id.replace(['\r', '\n'], " "));
+    let list = List::new(ids)
+        .block(Block::bordered().title(title))
+        .highlight_style(Style::new().reversed());
+    let mut state = ListState::default().with_selected(Some(viewer.selected() - first));
+    frame.render_stateful_widget(list, list_area, &mut state);
+
+    let row = &rows[viewer.selected()];
+    let heading = format!(
+        " {} (row {} of {}) ",
+        row.id.replace(['\r', '\n'], " "),
+        viewer.selected() + 1,
+        rows.len()
+    );
+    let lines: Vec<Line> = match viewer.table() {
+        Ok((n, cells)) => {
+            let width = cells
+                .iter()
+                .map(|&x| itoa::Buffer::new().format(x).len())
+                .max()
+                .unwrap_or(1);
+            let transforms: Vec<_> = viewer.transforms().iter().map(|t| t.name()).collect();
+            let mut lines: Vec<Line> = grid(cells, n, width, |_| None)
+                .into_iter()
+                .map(Line::from)
+                .collect();
+            lines.push(Line::default());
+            lines.push(Line::from(match transforms.is_empty() {
+                true => format!("{n}×{n}, as read"),
+                false => format!("{n}×{n}, {}", transforms.join(", ")),
+            }));
+            lines
+        }
+        Err(reason) => vec![
+            Line::from(format!("invalid: {reason}")).red(),
+            Line::default(),
+            Line::from(row.json.as_str()),
+        ],
+    };
+    let mut table = Paragraph::new(lines).block(Block::bordered().title(heading));
+    // Grids wider than the pane are cut rather than wrapped out of shape
+    if viewer.table().is_err() {
+        table = table.wrap(Wrap { trim: false });
+    }
+    frame.render_widget(table, table_area);
+
+    let status_line = match prompt {
+        Some(id) => format!("jump to id: {id}"),
+        None if !message.is_empty() => message.to_string(),
+        None => HELP.to_string(),
+    };
+    frame.render_widget(Paragraph::new(status_line), status);
+    page
+}
@@ -0,0 +1,136 @@
+//! Live run counters, shared between the pipeline stages and whoever reports progress.
+//!
+//! Stages bump atomic counters once per batch; a [`Summary`] is a snapshot of them, which
+//! [`CsvRotator`](crate::CsvRotator) returns and `--stats` / `--progress-interval` print.
+
+use crate::latency::LatencyHistogram;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Live counters shared between the pipeline stages.
+#[derive(Default)]
+pub struct Counters {
+    rows: AtomicU64,
+    valid: AtomicU64,
+    input_bytes: AtomicU64,
+    /// Filled only when the pipeline measures row latencies
+    latencies: Mutex<LatencyHistogram>,
+}
+
+impl Counters {
+    /// Records a processed batch of `rows` rows, `valid` of which were rotated.
+    pub fn add_rows(&self, rows: u64, valid: u64) {
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+        self.valid.fetch_add(valid, Ordering::Relaxed);
+    }
+
+    /// Records the reader's position in the input.
+    pub fn set_input_bytes(&self, bytes: u64) {
+        self.input_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Adds the row latencies a worker measured.
+    pub fn add_latencies(&self, latencies: &LatencyHistogram) {
+        self.latencies
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .merge(latencies);
+    }
+
+    /// The row latencies measured so far.
+    pub fn latencies(&self) -> LatencyHistogram {
+        self.latencies
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Captures the current counter values.
+    pub fn snapshot(&self, elapsed: Duration) -> Summary {
+        Summary {
+            rows: self.rows.load(Ordering::Relaxed),
+            valid: self.valid.load(Ordering::Relaxed),
+            input_bytes: self.input_bytes.load(Ordering::Relaxed),
+            elapsed,
+        }
+    }
+}
+
+/// Point-in-time view of a run's progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    pub rows: u64,
+    pub valid: u64,
+    pub input_bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl Summary {
+    /// Rows whose table could not be rotated.
+    pub fn invalid(&self) -> u64 {
+        self.rows - self.valid
+    }
+
+    /// Processed rows per second of wall time.
+    pub fn rows_per_sec(&self) -> f64 {
+        per_sec(self.rows as f64, self.elapsed)
+    }
+
+    /// Input throughput in megabytes (10^6 bytes) per second of wall time.
+    pub fn mb_per_sec(&self) -> f64 {
+        per_sec(self.input_bytes as f64 / 1e6, self.elapsed)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} rows ({} valid, {} invalid), {:.2} MB in {:.3}s: {:.0} rows/s, {:.2} MB/s",
+            self.rows,
+            self.valid,
+            self.invalid(),
+            self.input_bytes as f64 / 1e6,
+            self.elapsed.as_secs_f64(),
+            self.rows_per_sec(),
+            self.mb_per_sec()
+        )
+    }
+}
+
+fn per_sec(amount: f64, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 { amount / secs } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_rates() {
+        let counters = Counters::default();
+        counters.add_rows(300, 200);
+        counters.add_rows(100, 100);
+        counters.set_input_bytes(2_000_000);
+
+        let summary = counters.snapshot(Duration::from_secs(2));
+        assert_eq!(summary.rows, 400);
+        assert_eq!(summary.invalid(), 100);
+        assert_eq!(summary.rows_per_sec(), 200.0);
+        assert_eq!(summary.mb_per_sec(), 1.0);
+        assert_eq!(
+            summary.to_string(),
+            "400 rows (300 valid, 100 invalid), 2.00 MB in 2.000s: 200 rows/s, 1.00 MB/s"
+        );
+    }
+
+    #[test]
+    fn test_summary_zero_elapsed() {
+        let summary = Counters::default().snapshot(Duration::ZERO);
+        assert_eq!(summary.rows_per_sec(), 0.0);
+        assert_eq!(summary.mb_per_sec(), 0.0);
+    }
+}
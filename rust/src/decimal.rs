@@ -0,0 +1,112 @@
+//! `--decimal`: tables of fixed-point decimals, such as prices, which must come out exactly as
+//! they went in rather than rounded through `f64`.
+//!
+//! Every element is parsed into a [`Decimal`] and written back with its own scale, so `19.99`
+//! stays `19.99` and `1.50` stays `1.50`. A value a `Decimal` cannot hold exactly (more than
+//! 28 fractional digits, or beyond about 7.9 × 10^28) makes the table invalid instead of being
+//! rounded, and so does an exponent such as `1e3`, so that every value is written in the
+//! notation it was read in.
+
+use crate::RowBuffers;
+use crate::json::JsonStyle;
+use crate::processor::{Processor, RowProcessor};
+use crate::tokens::{parse_values, write_processed};
+use rust_decimal::Decimal;
+
+/// A built-in [`Processor`] applied to tables of decimals.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecimalProcessor {
+    processor: Processor,
+}
+
+impl DecimalProcessor {
+    pub fn new(processor: Processor) -> Self {
+        DecimalProcessor { processor }
+    }
+}
+
+impl RowProcessor for DecimalProcessor {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        match parse_values(json, buffers, parse_decimal) {
+            Some(values) => write_processed(self.processor, &values, style, buffers),
+            None => false,
+        }
+    }
+}
+
+/// The decimal a JSON value stands for, if it is a number without an exponent that a
+/// [`Decimal`] holds exactly.
+fn parse_decimal(token: &str) -> Option<Decimal> {
+    // serde_json has checked the grammar, so only numbers parse
+    if token.contains(['e', 'E']) {
+        return None;
+    }
+    Decimal::from_str_exact(token).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::row::InvalidReason;
+
+    fn process(processor: Processor, json: &str, style: JsonStyle) -> (String, bool) {
+        let mut buffers = RowBuffers::new();
+        let valid = DecimalProcessor::new(processor).process(json.as_bytes(), style, &mut buffers);
+        (String::from_utf8(buffers.json).unwrap(), valid)
+    }
+
+    #[test]
+    fn test_decimal_processors() {
+        let table = "[19.99, 0.1, -3.50, 1000]";
+        assert_eq!(
+            process(Processor::Rotate, table, JsonStyle::Compact),
+            ("[-3.50,19.99,1000,0.1]".to_string(), true)
+        );
+        assert_eq!(
+            process(Processor::Transpose, table, JsonStyle::Spaced),
+            ("[19.99, -3.50, 0.1, 1000]".to_string(), true)
+        );
+        assert_eq!(
+            process(Processor::Validate, table, JsonStyle::Node),
+            ("[19.99,0.1,-3.50,1000]".to_string(), true)
+        );
+        // 28 fractional digits and 28 integer digits are still exact
+        let table = "[0.0000000000000000000000000001, 2, 3, 7922816251426433759354395033]";
+        assert_eq!(
+            process(Processor::Rotate, table, JsonStyle::Compact),
+            (
+                "[3,0.0000000000000000000000000001,7922816251426433759354395033,2]".to_string(),
+                true
+            )
+        );
+    }
+
+    #[test]
+    fn test_decimal_invalid_tables() {
+        let cases = [
+            ("[1.5, 2, 3]", InvalidReason::NotSquare),
+            (
+                "[0.00000000000000000000000000001, 2, 3, 4]",
+                InvalidReason::Malformed,
+            ),
+            (
+                "[79228162514264337593543950336, 2, 3, 4]",
+                InvalidReason::Malformed,
+            ),
+            ("[1, 2, 3, 1e3]", InvalidReason::Malformed),
+            ("[1, 2, 3, \"4.5\"]", InvalidReason::Malformed),
+            ("[1, 2, 3, true]", InvalidReason::Malformed),
+        ];
+        for (json, reason) in cases {
+            let mut buffers = RowBuffers::new();
+            let valid = DecimalProcessor::default().process(
+                json.as_bytes(),
+                JsonStyle::Compact,
+                &mut buffers,
+            );
+            assert!(!valid, "{json}");
+            assert_eq!(buffers.invalid_reason(valid), Some(reason), "{json}");
+        }
+    }
+}
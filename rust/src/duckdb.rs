@@ -0,0 +1,287 @@
+//! DuckDB input and output through the `duckdb` command-line shell.
+//!
+//! With the `duckdb` feature, `--from-duckdb FILE --query SQL` runs the query in a read-only
+//! `duckdb -csv` shell and streams its CSV output into the pipeline, and `--to-table TABLE`
+//! loads the output rows back with `COPY ... FROM`. The shell is looked up on `PATH`.
+//!
+//! A database file accepts a single writer, and the reading shell keeps the file open until
+//! the input ends, so the output rows are staged in a temporary CSV file and loaded in one
+//! statement by [`TableWriter::finish`].
+
+#[cfg(feature = "duckdb")]
+pub use imp::{TableWriter, create_writer, open_reader};
+
+#[cfg(feature = "duckdb")]
+mod imp {
+    use std::ffi::{OsStr, OsString};
+    use std::fs::File;
+    use std::io::{self, BufWriter, Read, Write};
+    use std::path::{Path, PathBuf};
+    use std::process::{Child, ChildStdout, Command, Output, Stdio};
+    use std::thread::{self, JoinHandle};
+    use tempfile::NamedTempFile;
+
+    /// The DuckDB shell, found on `PATH`.
+    const PROGRAM: &str = "duckdb";
+
+    /// Streams the result of `query` on the database `file` as CSV with a header row.
+    pub fn open_reader(file: &Path, query: &str) -> io::Result<Box<dyn Read + Send>> {
+        open_with(OsStr::new(PROGRAM), file, query).map(|reader| Box::new(reader) as _)
+    }
+
+    fn open_with(program: &OsStr, file: &Path, query: &str) -> io::Result<ShellReader> {
+        let mut child = Command::new(program)
+            .arg("-readonly")
+            .arg("-csv")
+            .arg(file)
+            .arg("-c")
+            .arg(query)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| spawn_error(program, e))?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        // Drained on its own thread so a chatty shell cannot block on a full pipe
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let errors = thread::spawn(move || {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text);
+            text
+        });
+
+        Ok(ShellReader {
+            child,
+            stdout,
+            errors: Some(errors),
+        })
+    }
+
+    /// Reading end of [`open_reader`]; reports a failed query once its output ends.
+    struct ShellReader {
+        child: Child,
+        stdout: ChildStdout,
+        errors: Option<JoinHandle<String>>,
+    }
+
+    impl Read for ShellReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let read = self.stdout.read(buf)?;
+            if read == 0
+                && !buf.is_empty()
+                && let Some(errors) = self.errors.take()
+            {
+                let status = self.child.wait()?;
+                let errors = errors.join().unwrap_or_default();
+                if !status.success() {
+                    return Err(query_error(status, &errors));
+                }
+            }
+            Ok(read)
+        }
+    }
+
+    impl Drop for ShellReader {
+        fn drop(&mut self) {
+            // The pipeline may stop reading early; don't leave the shell running
+            if self.errors.is_some() {
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+            }
+        }
+    }
+
+    /// Stages the CSV rows for `COPY table (columns) FROM` on the database `file`.
+    ///
+    /// The first row written is the header and is skipped by DuckDB. Nothing becomes
+    /// visible in the table until [`TableWriter::finish`]; dropping the writer discards the
+    /// rows.
+    pub fn create_writer(file: &Path, table: &str, columns: &[&str]) -> io::Result<TableWriter> {
+        let staged = NamedTempFile::new()?;
+        Ok(TableWriter {
+            program: PROGRAM.into(),
+            file: file.into(),
+            table: table.into(),
+            columns: columns
+                .iter()
+                .map(|column| quote_identifier(column))
+                .collect::<Vec<_>>()
+                .join(", "),
+            out: BufWriter::new(staged.reopen()?),
+            staged,
+        })
+    }
+
+    /// Rows staged for `--to-table`, loaded into DuckDB by [`TableWriter::finish`].
+    pub struct TableWriter {
+        program: OsString,
+        file: PathBuf,
+        table: String,
+        columns: String,
+        out: BufWriter<File>,
+        staged: NamedTempFile,
+    }
+
+    impl TableWriter {
+        /// Loads the staged rows into the table in a single `COPY` statement.
+        pub fn finish(mut self) -> io::Result<()> {
+            self.out.flush()?;
+            let Output { status, stderr, .. } = Command::new(&self.program)
+                .arg(&self.file)
+                .arg("-c")
+                .arg(self.copy_sql())
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|e| spawn_error(&self.program, e))?;
+            if !status.success() {
+                return Err(query_error(status, &String::from_utf8_lossy(&stderr)));
+            }
+            log::info!("Copied the output rows into DuckDB table {}", self.table);
+            Ok(())
+        }
+
+        fn copy_sql(&self) -> String {
+            let path = self.staged.path().display().to_string();
+            format!(
+                "COPY {} ({}) FROM '{}' (FORMAT csv, HEADER true)",
+                quote_identifier(&self.table),
+                self.columns,
+                path.replace('\'', "''")
+            )
+        }
+    }
+
+    impl Write for TableWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.out.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.out.flush()
+        }
+    }
+
+    /// Quotes a table or column name, so names that are keywords or contain any other
+    /// character are taken literally rather than as SQL.
+    fn quote_identifier(name: &str) -> String {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+
+    fn spawn_error(program: &OsStr, e: io::Error) -> io::Error {
+        io::Error::new(
+            e.kind(),
+            format!("cannot run the DuckDB shell `{}`: {e}", program.display()),
+        )
+    }
+
+    /// Includes the shell's message, which it prints to stderr.
+    fn query_error(status: std::process::ExitStatus, errors: &str) -> io::Error {
+        match errors.trim() {
+            "" => io::Error::other(format!("duckdb exited with {status}")),
+            message => io::Error::other(message.to_string()),
+        }
+    }
+
+    #[cfg(all(test, unix))]
+    mod tests {
+        use super::*;
+        use std::os::unix::fs::PermissionsExt;
+        use tempfile::TempDir;
+
+        /// Writes an executable stand-in for the DuckDB shell.
+        fn stub_shell(dir: &TempDir, script: &str) -> OsString {
+            let path = dir.path().join("duckdb");
+            std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path.into_os_string()
+        }
+
+        #[test]
+        fn test_reader_streams_query_output() {
+            let dir = TempDir::new().unwrap();
+            let shell = stub_shell(
+                &dir,
+                r#"[ "$1 $2 $4 $5" = "-readonly -csv -c SELECT id, json FROM t" ] || exit 2
+printf 'id,json\n1,"[1, 2, 3, 4]"\n'"#,
+            );
+
+            let mut reader =
+                open_with(&shell, Path::new("bench.duckdb"), "SELECT id, json FROM t").unwrap();
+            let mut text = String::new();
+            reader.read_to_string(&mut text).unwrap();
+            assert_eq!(text, "id,json\n1,\"[1, 2, 3, 4]\"\n");
+        }
+
+        #[test]
+        fn test_reader_surfaces_errors() {
+            let dir = TempDir::new().unwrap();
+            let shell = stub_shell(
+                &dir,
+                "echo 'Catalog Error: Table with name t does not exist!' >&2; exit 1",
+            );
+
+            let mut reader =
+                open_with(&shell, Path::new("bench.duckdb"), "SELECT * FROM t").unwrap();
+            let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+            assert!(err.to_string().contains("does not exist"));
+        }
+
+        #[test]
+        fn test_writer_copies_staged_rows() {
+            let dir = TempDir::new().unwrap();
+            let loaded = dir.path().join("loaded.csv");
+            // Copies the file named in the COPY statement, as DuckDB would read it
+            let shell = stub_shell(
+                &dir,
+                &format!(
+                    r#"[ "$1" = bench.duckdb ] || exit 2
+cp "$(echo "$3" | sed "s/.* FROM '\(.*\)' (.*/\1/")" '{}'"#,
+                    loaded.display()
+                ),
+            );
+
+            let mut writer = create_writer(
+                Path::new("bench.duckdb"),
+                "rotated",
+                &["id", "json", "is_valid"],
+            )
+            .unwrap();
+            assert!(
+                writer
+                    .copy_sql()
+                    .starts_with(r#"COPY "rotated" ("id", "json", "is_valid") FROM '"#)
+            );
+            writer.program = shell;
+            writer
+                .write_all(b"id,json,is_valid\n1,\"[3, 1, 4, 2]\",true\n")
+                .unwrap();
+            writer.finish().unwrap();
+            assert_eq!(
+                std::fs::read_to_string(loaded).unwrap(),
+                "id,json,is_valid\n1,\"[3, 1, 4, 2]\",true\n"
+            );
+        }
+
+        #[test]
+        fn test_quote_identifier() {
+            assert_eq!(quote_identifier("rotated"), r#""rotated""#);
+            assert_eq!(
+                quote_identifier(r#"x" (id) FROM 'a'; DROP TABLE t; --"#),
+                r#""x"" (id) FROM 'a'; DROP TABLE t; --""#
+            );
+        }
+
+        #[test]
+        fn test_missing_shell() {
+            let err = open_with(
+                OsStr::new("/nonexistent/duckdb"),
+                Path::new("bench.duckdb"),
+                "SELECT 1",
+            )
+            .err()
+            .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert!(err.to_string().contains("DuckDB shell"));
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Duplicate id detection for `--check-duplicate-ids`.
+//!
+//! Seen ids are kept in a hash map together with the line they first appeared on, so
+//! duplicates are reported as soon as they are read. Once the map outgrows its memory
+//! budget, all ids are spilled to hash-partitioned bucket files instead and duplicates are
+//! found bucket by bucket when the input is exhausted, keeping memory bounded for inputs
+//! with billions of rows.
+
+use clap::ValueEnum;
+use rotate_cli::pipeline::ObserveIds;
+use std::collections::HashMap;
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+
+/// Number of bucket files ids are partitioned into after spilling.
+const BUCKETS: usize = 256;
+
+/// Approximate per-id bookkeeping overhead on top of the id bytes.
+const ENTRY_OVERHEAD: usize = 48;
+
+/// What to do when a duplicate id is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DuplicatePolicy {
+    /// Print a warning for every duplicate and a count at the end
+    Report,
+    /// Like `report`, but fail the run with a non-zero exit code
+    Reject,
+}
+
+/// A repeated id with the lines of its first and repeated occurrence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub id: Vec<u8>,
+    pub first_line: u64,
+    pub line: u64,
+}
+
+/// Error returned when duplicates are found under [`DuplicatePolicy::Reject`].
+#[derive(Debug)]
+pub struct DuplicateIdsError {
+    pub count: u64,
+}
+
+impl std::fmt::Display for DuplicateIdsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} duplicate id(s) found", self.count)
+    }
+}
+
+impl std::error::Error for DuplicateIdsError {}
+
+/// Tracks ids across the whole input.
+pub struct DuplicateIds {
+    policy: DuplicatePolicy,
+    memory_limit: usize,
+    seen: HashMap<Box<[u8]>, u64>,
+    seen_bytes: usize,
+    buckets: Option<Vec<BufWriter<File>>>,
+    found: u64,
+}
+
+impl DuplicateIds {
+    /// Creates a tracker that spills to disk once roughly `memory_limit` bytes of ids are held.
+    pub fn new(policy: DuplicatePolicy, memory_limit: usize) -> Self {
+        Self {
+            policy,
+            memory_limit,
+            seen: HashMap::new(),
+            seen_bytes: 0,
+            buckets: None,
+            found: 0,
+        }
+    }
+
+    /// Records an id seen on `line`, reporting it immediately if it is a known duplicate.
+    pub fn observe(&mut self, id: &[u8], line: u64) {
+        if let Some(buckets) = &mut self.buckets {
+            // Spilled: duplicates are resolved in `finish`
+            if let Err(e) = write_entry(&mut buckets[bucket_of(id)], id, line) {
+                log::error!("Failed to spill id to disk: {e}");
+            }
+            return;
+        }
+
+        match self.seen.entry(id.into()) {
+            Entry::Occupied(entry) => {
+                let first_line = *entry.get();
+                self.report(&Duplicate {
+                    id: id.to_vec(),
+                    first_line,
+                    line,
+                });
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(line);
+                self.seen_bytes += id.len() + ENTRY_OVERHEAD;
+                if self.seen_bytes >= self.memory_limit {
+                    self.spill();
+                }
+            }
+        }
+    }
+
+    /// Resolves spilled ids and prints the summary; errors under `reject` if any were found.
+    ///
+    /// Rejection is decided only here, so every duplicate is reported before the run fails.
+    pub fn finish(mut self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(buckets) = self.buckets.take() {
+            for bucket in buckets {
+                let mut file = bucket.into_inner().map_err(|e| e.into_error())?;
+                file.rewind()?;
+                self.resolve_bucket(BufReader::new(file))?;
+            }
+        }
+
+        if self.found > 0 {
+            if self.policy == DuplicatePolicy::Reject {
+                return Err(DuplicateIdsError { count: self.found }.into());
+            }
+            eprintln!("Warning: {} duplicate id(s) found", self.found);
+        }
+        Ok(self.found)
+    }
+
+    fn report(&mut self, duplicate: &Duplicate) {
+        self.found += 1;
+        eprintln!(
+            "Warning: duplicate id '{}' on line {} (first seen on line {})",
+            String::from_utf8_lossy(&duplicate.id),
+            duplicate.line,
+            duplicate.first_line
+        );
+    }
+
+    /// Moves every id held in memory into the bucket files.
+    fn spill(&mut self) {
+        log::info!(
+            "Duplicate id tracking exceeded its memory budget, spilling {} ids to disk",
+            self.seen.len()
+        );
+        let buckets = (0..BUCKETS)
+            .map(|_| tempfile::tempfile().map(BufWriter::new))
+            .collect::<io::Result<Vec<_>>>();
+        let mut buckets = match buckets {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                log::error!("Cannot create spill files, keeping ids in memory: {e}");
+                self.memory_limit = usize::MAX;
+                return;
+            }
+        };
+
+        for (id, line) in self.seen.drain() {
+            if let Err(e) = write_entry(&mut buckets[bucket_of(&id)], &id, line) {
+                log::error!("Failed to spill id to disk: {e}");
+            }
+        }
+        self.seen_bytes = 0;
+        self.buckets = Some(buckets);
+    }
+
+    /// Finds duplicates within one bucket; entries are in input order, so the first wins.
+    fn resolve_bucket<R: Read>(&mut self, mut bucket: R) -> io::Result<()> {
+        let mut first: HashMap<Vec<u8>, u64> = HashMap::new();
+        let mut duplicates = Vec::new();
+        while let Some((id, line)) = read_entry(&mut bucket)? {
+            match first.entry(id) {
+                Entry::Occupied(entry) => duplicates.push(Duplicate {
+                    id: entry.key().clone(),
+                    first_line: *entry.get(),
+                    line,
+                }),
+                Entry::Vacant(entry) => {
+                    entry.insert(line);
+                }
+            }
+        }
+
+        duplicates.sort_by_key(|d| d.line);
+        for duplicate in &duplicates {
+            self.report(duplicate);
+        }
+        Ok(())
+    }
+}
+
+impl ObserveIds for DuplicateIds {
+    fn observe(&mut self, id: &[u8], line: u64) {
+        DuplicateIds::observe(self, id, line);
+    }
+}
+
+fn bucket_of(id: &[u8]) -> usize {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    (hasher.finish() % BUCKETS as u64) as usize
+}
+
+/// Appends a length-prefixed id and its line number to a bucket file.
+fn write_entry<W: Write>(out: &mut W, id: &[u8], line: u64) -> io::Result<()> {
+    out.write_all(&(id.len() as u64).to_le_bytes())?;
+    out.write_all(id)?;
+    out.write_all(&line.to_le_bytes())
+}
+
+fn read_entry<R: Read>(input: &mut R) -> io::Result<Option<(Vec<u8>, u64)>> {
+    let mut word = [0u8; 8];
+    match input.read_exact(&mut word) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut id = vec![0u8; u64::from_le_bytes(word) as usize];
+    input.read_exact(&mut id)?;
+    input.read_exact(&mut word)?;
+    Ok(Some((id, u64::from_le_bytes(word))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observe_all(tracker: &mut DuplicateIds, ids: &[&str]) {
+        for (i, id) in ids.iter().enumerate() {
+            tracker.observe(id.as_bytes(), i as u64 + 2);
+        }
+    }
+
+    #[test]
+    fn test_in_memory_duplicates() {
+        let mut tracker = DuplicateIds::new(DuplicatePolicy::Report, usize::MAX);
+        observe_all(&mut tracker, &["1", "2", "1", "3", "2", "1"]);
+        assert_eq!(tracker.finish().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_spilled_duplicates() {
+        // A budget of one byte spills on the first id
+        let mut tracker = DuplicateIds::new(DuplicatePolicy::Report, 1);
+        let ids: Vec<String> = (0..1000).map(|i| (i % 300).to_string()).collect();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        observe_all(&mut tracker, &ids);
+        assert!(tracker.buckets.is_some());
+        assert_eq!(tracker.finish().unwrap(), 700);
+    }
+
+    #[test]
+    fn test_reject_fails_run() {
+        let mut tracker = DuplicateIds::new(DuplicatePolicy::Reject, usize::MAX);
+        observe_all(&mut tracker, &["a", "b", "a"]);
+        let err = tracker.finish().unwrap_err();
+        assert_eq!(err.to_string(), "1 duplicate id(s) found");
+    }
+
+    #[test]
+    fn test_no_duplicates() {
+        let mut tracker = DuplicateIds::new(DuplicatePolicy::Reject, usize::MAX);
+        observe_all(&mut tracker, &["a", "b", "c"]);
+        assert_eq!(tracker.finish().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_bucket_entries_round_trip() {
+        let mut buf = Vec::new();
+        write_entry(&mut buf, b"id,with,commas", 42).unwrap();
+        write_entry(&mut buf, b"", 7).unwrap();
+
+        let mut input = buf.as_slice();
+        assert_eq!(
+            read_entry(&mut input).unwrap(),
+            Some((b"id,with,commas".to_vec(), 42))
+        );
+        assert_eq!(read_entry(&mut input).unwrap(), Some((Vec::new(), 7)));
+        assert_eq!(read_entry(&mut input).unwrap(), None);
+    }
+}
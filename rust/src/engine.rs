@@ -0,0 +1,135 @@
+//! Alternative row processing engines (`--engine`).
+//!
+//! `csv` is the streaming reader → workers → writer pipeline. `polars` loads the whole file
+//! into a `DataFrame` with Polars' multithreaded CSV reader, rotates the `json` column with
+//! [`rotate_cli::polars::rotate_column`] and writes it back with Polars' CSV writer; it
+//! exists to benchmark that path against the pipeline and produces the same output, except
+//! that records with fewer than two fields become invalid rows instead of being skipped.
+
+use crate::{Cli, DEFAULT_MAX_N};
+use clap::ValueEnum;
+use rotate_cli::errors::CliError;
+use std::error::Error;
+
+/// Which implementation processes the rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Engine {
+    /// Streaming csv-crate pipeline with a pool of worker threads
+    #[default]
+    Csv,
+    /// Polars DataFrame (local files only; requires the `polars` feature)
+    Polars,
+}
+
+/// Rejects options the Polars engine does not implement instead of silently ignoring them.
+fn check_options(cli: &Cli) -> Result<(), String> {
+    let unsupported = [
+        ("--sort-by-id", cli.sort_by_id.is_some()),
+        ("--check-duplicate-ids", cli.check_duplicate_ids.is_some()),
+        ("--emit-dimension", cli.emit_dimension),
+        ("--json-style", cli.json_style != Default::default()),
+        ("--parser", cli.parser != Default::default()),
+        ("--keep-original-on-invalid", cli.keep_original_on_invalid),
+        ("--explain", cli.explain),
+        ("--output-columns", cli.output_columns.is_some()),
+        ("--progress-interval", cli.progress_interval.is_some()),
+        ("--self-check", cli.self_check.is_some()),
+        ("--on-error", cli.on_error != Default::default()),
+        ("--processor", cli.processor != Default::default()),
+        ("--script", cli.script.is_some()),
+        ("--preserve-numbers", cli.preserve_numbers),
+        ("--profile", cli.profile.is_some()),
+        ("--bigint", cli.bigint),
+        ("--decimal", cli.decimal),
+        ("--schema", cli.schema.is_some()),
+        ("--id-type", cli.id_type.is_some()),
+        ("--delimiter", cli.delimiter != b','),
+        ("--columns", cli.columns.is_some()),
+        ("--max-n", cli.max_n != DEFAULT_MAX_N),
+        ("--max-memory", cli.max_memory.is_some()),
+        ("--sample", cli.sample.is_some()),
+        ("--split-*", cli.split().is_enabled()),
+        ("--timing-column", cli.timing_column),
+        ("--latency-histogram", cli.latency_histogram),
+    ];
+    match unsupported.iter().find(|(_, used)| *used) {
+        Some((option, _)) => Err(format!("{option} is not supported with --engine polars")),
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "polars")]
+pub fn run_polars(cli: &Cli) -> Result<(), Box<dyn Error + Send + Sync>> {
+    use crate::output::Output;
+    use crate::stats::{self, Counters};
+    use polars::prelude::*;
+    use rotate_cli::polars::{IS_VALID, rotate_column};
+    use std::time::Instant;
+
+    check_options(cli).map_err(CliError::usage)?;
+    let path = cli.input.as_deref().ok_or("missing input argument")?;
+    if path.contains("://") {
+        return Err(CliError::usage("--engine polars reads local files only").into());
+    }
+
+    let started = Instant::now();
+    // Read every column as text: ids are copied verbatim and tables are parsed by us
+    let df = CsvReadOptions::default()
+        .with_has_header(true)
+        .with_infer_schema_length(Some(0))
+        .with_n_threads(cli.threads)
+        .try_into_reader_with_file_path(Some(path.into()))?
+        .finish()?;
+    let [id, json, ..] = df.get_columns() else {
+        return Err("expected columns 'id' and 'json'".into());
+    };
+    let mut df = DataFrame::new(vec![
+        id.clone().with_name("id".into()),
+        json.clone().with_name("json".into()),
+    ])?;
+    rotate_column(&mut df, "json")?;
+
+    let mut output = Output::create(cli.output.as_deref())?;
+    CsvWriter::new(&mut output)
+        .include_header(true)
+        .finish(&mut df)?;
+    output.finish()?;
+
+    if cli.stats {
+        let counters = Counters::default();
+        let valid = df.column(IS_VALID)?.bool()?.sum().unwrap_or(0);
+        counters.add_rows(df.height() as u64, u64::from(valid));
+        counters.set_input_bytes(std::fs::metadata(path)?.len());
+        stats::print(counters.snapshot(started.elapsed()));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "polars"))]
+pub fn run_polars(cli: &Cli) -> Result<(), Box<dyn Error + Send + Sync>> {
+    check_options(cli).map_err(CliError::usage)?;
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "--engine polars requires building with `--features polars`",
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_rejects_unsupported_options() {
+        let cli = Cli::parse_from(["rotate_cli", "in.csv", "--engine", "polars"]);
+        assert_eq!(cli.engine, Engine::Polars);
+        assert!(check_options(&cli).is_ok());
+
+        let cli = Cli::parse_from(["rotate_cli", "in.csv", "--engine=polars", "--sort-by-id"]);
+        assert_eq!(
+            check_options(&cli).unwrap_err(),
+            "--sort-by-id is not supported with --engine polars"
+        );
+    }
+}
@@ -0,0 +1,512 @@
+//! Input errors with their position in the CSV stream, and what to do about them
+//! (`--on-error`).
+//!
+//! The csv crate reports I/O failures without saying where in the input they happened, and
+//! records that are too short to hold an `id` and a `json` field are not an error to it at
+//! all. [`RecordError`] attaches the record number, line and byte offset to both, so a bad
+//! record in a multi-gigabyte file can be found with `sed -n` or `tail -c`.
+//!
+//! Every failure also has a stable [`ErrorCode`], so scripts can branch on it instead of on
+//! the wording of a message: the CLI prints it with the error (`Error [E009 csv]: CSV error
+//! at record 2 …`) through a [`CliError`], and the HTTP and JSON-RPC modes return it as
+//! `error_code`, for invalid rows as well as for failed requests.
+
+use crate::budget::BudgetError;
+use crate::row::InvalidReason;
+use clap::ValueEnum;
+use csv::{ByteRecord, Position};
+use std::error::Error;
+use std::{fmt, io};
+
+/// A stable, machine-readable failure code. Codes are never renumbered or reused; new
+/// failures get new codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// E001: not valid JSON, or not an array of numbers
+    Parse,
+    /// E002: the element count is not a perfect square
+    NotSquare,
+    /// E003: an empty table
+    Empty,
+    /// E004: a table beyond `--max-n`
+    TooLarge,
+    /// E005: a table that took longer than `--row-timeout` to parse
+    Timeout,
+    /// E006: a valid table the row processor refused
+    Rejected,
+    /// E007: a record that violates the `--schema`
+    Schema,
+    /// E008: an id that is not of the `--id-type`
+    Id,
+    /// E009: the CSV input could not be read
+    Csv,
+    /// E010: a record with too few fields for an id and a table
+    TooFewFields,
+    /// E011: reading or writing a file, socket or pipe failed
+    Io,
+    /// E012: options or a request that cannot be carried out as given
+    Usage,
+    /// E013: `--self-check` found a wrong rotation
+    SelfCheck,
+    /// E014: a request without a valid API key (`serve --api-key`)
+    Unauthorized,
+    /// E015: a client over `serve --rate-limit`
+    RateLimited,
+    /// E016: a request body over `serve --max-body`
+    BodyTooLarge,
+    /// E017: a request shed because `serve --queue-depth` requests were already waiting
+    Overloaded,
+    /// E018: a batch or table that does not fit in `--max-memory`
+    OverBudget,
+    /// E019: CBOR input (`--format cbor`) that could not be decoded
+    Cbor,
+    /// E099: anything else
+    Other,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 20] = [
+        ErrorCode::Parse,
+        ErrorCode::NotSquare,
+        ErrorCode::Empty,
+        ErrorCode::TooLarge,
+        ErrorCode::Timeout,
+        ErrorCode::Rejected,
+        ErrorCode::Schema,
+        ErrorCode::Id,
+        ErrorCode::Csv,
+        ErrorCode::TooFewFields,
+        ErrorCode::Io,
+        ErrorCode::Usage,
+        ErrorCode::SelfCheck,
+        ErrorCode::Unauthorized,
+        ErrorCode::RateLimited,
+        ErrorCode::BodyTooLarge,
+        ErrorCode::Overloaded,
+        ErrorCode::OverBudget,
+        ErrorCode::Cbor,
+        ErrorCode::Other,
+    ];
+
+    /// The code itself, such as `E002`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Parse => "E001",
+            ErrorCode::NotSquare => "E002",
+            ErrorCode::Empty => "E003",
+            ErrorCode::TooLarge => "E004",
+            ErrorCode::Timeout => "E005",
+            ErrorCode::Rejected => "E006",
+            ErrorCode::Schema => "E007",
+            ErrorCode::Id => "E008",
+            ErrorCode::Csv => "E009",
+            ErrorCode::TooFewFields => "E010",
+            ErrorCode::Io => "E011",
+            ErrorCode::Usage => "E012",
+            ErrorCode::SelfCheck => "E013",
+            ErrorCode::Unauthorized => "E014",
+            ErrorCode::RateLimited => "E015",
+            ErrorCode::BodyTooLarge => "E016",
+            ErrorCode::Overloaded => "E017",
+            ErrorCode::OverBudget => "E018",
+            ErrorCode::Cbor => "E019",
+            ErrorCode::Other => "E099",
+        }
+    }
+
+    /// A short name for people, such as `not_square`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCode::Parse => "parse",
+            ErrorCode::NotSquare => "not_square",
+            ErrorCode::Empty => "empty",
+            ErrorCode::TooLarge => "too_large",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Rejected => "rejected",
+            ErrorCode::Schema => "schema",
+            ErrorCode::Id => "id",
+            ErrorCode::Csv => "csv",
+            ErrorCode::TooFewFields => "too_few_fields",
+            ErrorCode::Io => "io",
+            ErrorCode::Usage => "usage",
+            ErrorCode::SelfCheck => "self_check",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::BodyTooLarge => "body_too_large",
+            ErrorCode::Overloaded => "overloaded",
+            ErrorCode::OverBudget => "over_budget",
+            ErrorCode::Cbor => "cbor",
+            ErrorCode::Other => "other",
+        }
+    }
+}
+
+impl From<InvalidReason> for ErrorCode {
+    fn from(reason: InvalidReason) -> Self {
+        match reason {
+            InvalidReason::Malformed => ErrorCode::Parse,
+            InvalidReason::Empty => ErrorCode::Empty,
+            InvalidReason::NotSquare => ErrorCode::NotSquare,
+            InvalidReason::TooLarge => ErrorCode::TooLarge,
+            InvalidReason::Timeout => ErrorCode::Timeout,
+            InvalidReason::Rejected => ErrorCode::Rejected,
+            InvalidReason::Schema => ErrorCode::Schema,
+            InvalidReason::Id => ErrorCode::Id,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error that ended a run, with its [`ErrorCode`].
+///
+/// Errors are passed up as `Box<dyn Error>`; those that know their code wrap themselves in a
+/// `CliError` on the way, and [`CliError::classify`] finds the code of the others from their
+/// type.
+#[derive(Debug)]
+pub struct CliError {
+    pub code: ErrorCode,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl CliError {
+    pub fn new(code: ErrorCode, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        CliError {
+            code,
+            source: source.into(),
+        }
+    }
+
+    /// Options that cannot be carried out as given.
+    pub fn usage(message: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        CliError::new(ErrorCode::Usage, message)
+    }
+
+    /// The error with its code: a `CliError` as it is, other errors with the code their type
+    /// implies, or [`ErrorCode::Other`].
+    pub fn classify(error: Box<dyn Error + Send + Sync>) -> Self {
+        let error = match error.downcast::<CliError>() {
+            Ok(error) => return *error,
+            Err(error) => error,
+        };
+        let code = if let Some(error) = error.downcast_ref::<RecordError>() {
+            match error {
+                RecordError::Csv { source, .. } if source.is_io_error() => ErrorCode::Io,
+                RecordError::Csv { .. } => ErrorCode::Csv,
+                RecordError::TooFewFields { .. } => ErrorCode::TooFewFields,
+                RecordError::OverBudget { .. } => ErrorCode::OverBudget,
+                RecordError::Cbor { source, .. } => cbor_code(source),
+            }
+        } else if let Some(error) = error.downcast_ref::<csv::Error>() {
+            match error.is_io_error() {
+                true => ErrorCode::Io,
+                false => ErrorCode::Csv,
+            }
+        } else if let Some(error) = error.downcast_ref::<io::Error>() {
+            // The stubs of features left out of the build
+            match error.kind() {
+                io::ErrorKind::Unsupported => ErrorCode::Usage,
+                _ => ErrorCode::Io,
+            }
+        } else if let Some(error) = error.downcast_ref::<CborError>() {
+            cbor_code(error)
+        } else if error.is::<BudgetError>() {
+            ErrorCode::OverBudget
+        } else if error.is::<serde_json::Error>() {
+            ErrorCode::Parse
+        } else {
+            ErrorCode::Other
+        };
+        CliError::new(code, error)
+    }
+}
+
+/// A CBOR decoding error, as `ciborium::from_reader` returns it.
+pub type CborError = ciborium::de::Error<io::Error>;
+
+fn cbor_code(error: &CborError) -> ErrorCode {
+    match error {
+        ciborium::de::Error::Io(_) => ErrorCode::Io,
+        _ => ErrorCode::Cbor,
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl Error for CliError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.source()
+    }
+}
+
+/// How to handle a record that cannot be processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OnError {
+    /// Warn on stderr and leave the record out of the output
+    #[default]
+    Skip,
+    /// Write the record as an invalid row (`[]`, `false`), keeping its id if it has one
+    Invalid,
+    /// Stop the run with an error
+    Fail,
+}
+
+/// Where a record starts in the input; the header is record 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Location {
+    pub record: u64,
+    pub line: u64,
+    pub byte: u64,
+}
+
+impl From<&Position> for Location {
+    fn from(position: &Position) -> Self {
+        Location {
+            record: position.record(),
+            line: position.line(),
+            byte: position.byte(),
+        }
+    }
+}
+
+impl Location {
+    /// Location of a record read by a `csv::Reader`, which always sets one.
+    pub fn of(record: &ByteRecord) -> Self {
+        record.position().map(Location::from).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "record {} (line {}, byte {})",
+            self.record, self.line, self.byte
+        )
+    }
+}
+
+/// A record that could not be read or processed.
+#[derive(Debug)]
+pub enum RecordError {
+    /// The CSV reader failed at `location`
+    Csv {
+        location: Location,
+        source: csv::Error,
+    },
+    /// Too few fields to hold both the `id` and the `json` column
+    TooFewFields {
+        location: Location,
+        fields: usize,
+        expected: usize,
+    },
+    /// The batch starting at `location`, or the table there, does not fit in `--max-memory`
+    OverBudget {
+        location: Location,
+        source: BudgetError,
+    },
+    /// CBOR record `record` (counting from 1) could not be decoded
+    Cbor { record: u64, source: CborError },
+}
+
+impl RecordError {
+    /// Wraps a reader error; `position` is where the reader stood, for errors without one.
+    pub fn csv(source: csv::Error, position: &Position) -> Self {
+        let location = source.position().unwrap_or(position).into();
+        RecordError::Csv { location, source }
+    }
+
+    /// A record with fewer than `expected` fields.
+    pub fn too_few_fields(record: &ByteRecord, expected: usize) -> Self {
+        RecordError::TooFewFields {
+            location: Location::of(record),
+            fields: record.len(),
+            expected,
+        }
+    }
+
+    /// A batch or table at `record` that does not fit in the memory budget.
+    pub fn over_budget(record: &ByteRecord, source: BudgetError) -> Self {
+        RecordError::OverBudget {
+            location: Location::of(record),
+            source,
+        }
+    }
+
+    /// Whether reading can go on after this error; I/O errors end the input.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            RecordError::Csv { source, .. } => !source.is_io_error(),
+            RecordError::TooFewFields { .. } => true,
+            RecordError::OverBudget { .. } | RecordError::Cbor { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Csv { location, source } => {
+                // The csv message repeats its own position; ours is complete
+                let message = match source.kind() {
+                    csv::ErrorKind::Io(e) => e.to_string(),
+                    csv::ErrorKind::Utf8 { err, .. } => err.to_string(),
+                    _ => source.to_string(),
+                };
+                write!(f, "CSV error at {location}: {message}")
+            }
+            RecordError::TooFewFields {
+                location,
+                fields,
+                expected,
+            } => write!(
+                f,
+                "{location} has {fields} field{}, expected at least {expected} (id, json)",
+                if *fields == 1 { "" } else { "s" }
+            ),
+            RecordError::OverBudget { location, source } => write!(f, "{location}: {source}"),
+            RecordError::Cbor { record, source } => {
+                write!(f, "invalid CBOR in record {record}: {source}")
+            }
+        }
+    }
+}
+
+impl Error for RecordError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RecordError::Csv { source, .. } => Some(source),
+            RecordError::TooFewFields { .. } => None,
+            RecordError::OverBudget { source, .. } => Some(source),
+            RecordError::Cbor { source, .. } => Some(source),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use csv::ReaderBuilder;
+    use std::io::{self, Read};
+
+    #[test]
+    fn test_error_codes_are_unique() {
+        for (i, code) in ErrorCode::ALL.iter().enumerate() {
+            for other in &ErrorCode::ALL[i + 1..] {
+                assert_ne!(code.as_str(), other.as_str());
+                assert_ne!(code.name(), other.name());
+            }
+        }
+        assert_eq!(ErrorCode::from(InvalidReason::NotSquare).as_str(), "E002");
+        assert_eq!(ErrorCode::from(InvalidReason::Malformed).name(), "parse");
+    }
+
+    #[test]
+    fn test_classify() {
+        let classify = |error: Box<dyn Error + Send + Sync>| CliError::classify(error).code;
+        let record: ByteRecord = vec!["1"].into();
+        assert_eq!(
+            classify(RecordError::too_few_fields(&record, 2).into()),
+            ErrorCode::TooFewFields
+        );
+        assert_eq!(
+            classify(io::Error::from(io::ErrorKind::NotFound).into()),
+            ErrorCode::Io
+        );
+        assert_eq!(
+            classify(io::Error::new(io::ErrorKind::Unsupported, "needs a feature").into()),
+            ErrorCode::Usage
+        );
+        assert_eq!(
+            classify(CliError::usage("--x is not supported").into()),
+            ErrorCode::Usage
+        );
+        assert_eq!(classify("something else".into()), ErrorCode::Other);
+
+        let over_budget = BudgetError::Buffers {
+            pinned: 120,
+            limit: 100,
+        };
+        assert_eq!(
+            classify(RecordError::over_budget(&record, over_budget).into()),
+            ErrorCode::OverBudget
+        );
+        assert_eq!(classify(over_budget.into()), ErrorCode::OverBudget);
+        let truncated = ciborium::from_reader::<ciborium::Value, _>(&[0x82, 0x01][..]);
+        let truncated = truncated.unwrap_err();
+        assert_eq!(classify(truncated.into()), ErrorCode::Io);
+        let garbage = ciborium::from_reader::<ciborium::Value, _>(&[0xff][..]).unwrap_err();
+        let garbage = RecordError::Cbor {
+            record: 3,
+            source: garbage,
+        };
+        assert!(
+            garbage
+                .to_string()
+                .starts_with("invalid CBOR in record 3: ")
+        );
+        assert_eq!(classify(garbage.into()), ErrorCode::Cbor);
+
+        let error = CliError::classify(CliError::new(ErrorCode::SelfCheck, "wrong").into());
+        assert_eq!(
+            (error.code, error.to_string()),
+            (ErrorCode::SelfCheck, "wrong".to_string())
+        );
+    }
+
+    #[test]
+    fn test_too_few_fields() {
+        let input = "id,json\n1,[1]\n2\n";
+        let mut rdr = ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(input.as_bytes());
+        let records: Vec<ByteRecord> = rdr.byte_records().map(Result::unwrap).collect();
+        let error = RecordError::too_few_fields(&records[1], 2);
+        assert_eq!(
+            error.to_string(),
+            "record 2 (line 3, byte 14) has 1 field, expected at least 2 (id, json)"
+        );
+        assert!(error.is_recoverable());
+    }
+
+    /// Yields `data` a few bytes at a time, then fails.
+    struct Truncated<'a> {
+        data: &'a [u8],
+    }
+
+    impl Read for Truncated<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.data.is_empty() {
+                return Err(io::Error::other("connection reset"));
+            }
+            let n = buf.len().min(self.data.len()).min(4);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_io_error_location() {
+        let mut rdr = ReaderBuilder::new().from_reader(Truncated {
+            data: b"id,json\n1,[1]\n2,[2]",
+        });
+        let mut record = ByteRecord::new();
+        assert!(rdr.read_byte_record(&mut record).unwrap());
+        let source = rdr.read_byte_record(&mut record).unwrap_err();
+        let error = RecordError::csv(source, rdr.position());
+        assert_eq!(
+            error.to_string(),
+            "CSV error at record 2 (line 3, byte 19): connection reset"
+        );
+        assert!(!error.is_recoverable());
+    }
+}
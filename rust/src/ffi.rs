@@ -0,0 +1,145 @@
+//! C ABI bindings for the rotation core.
+//!
+//! The crate is also built as a `cdylib`, so the C and C++ entries of the benchmark can link
+//! against `librotate_cli` and call these functions directly. The matching declarations are in
+//! `include/rotate_cli.h`, which is the stable contract: change both together.
+//!
+//! Every function reports its outcome as one of the `ROTATE_*` status codes instead of
+//! panicking or unwinding across the FFI boundary.
+
+use crate::{RotationError, rotate_right, square_len};
+use std::ffi::{c_char, c_int};
+
+/// The table was rotated.
+pub const ROTATE_OK: c_int = 0;
+/// The length is not a perfect square.
+pub const ROTATE_ERR_NOT_SQUARE: c_int = -1;
+/// The table has no elements.
+pub const ROTATE_ERR_EMPTY: c_int = -2;
+/// A null pointer was passed with a non-zero length.
+pub const ROTATE_ERR_NULL: c_int = -3;
+
+/// Rotates the `len` elements at `data` in place; shared body of the typed entry points.
+///
+/// # Safety
+///
+/// `data` must be null or valid for reads and writes of `len` elements.
+unsafe fn rotate_raw<T: Copy>(data: *mut T, len: usize) -> c_int {
+    if len == 0 {
+        return ROTATE_ERR_EMPTY;
+    }
+    if data.is_null() {
+        return ROTATE_ERR_NULL;
+    }
+
+    // SAFETY: non-null and valid for `len` elements per the caller's contract
+    let table = unsafe { std::slice::from_raw_parts_mut(data, len) };
+    match rotate_right(table) {
+        Ok(()) => ROTATE_OK,
+        Err(RotationError::NotSquare) => ROTATE_ERR_NOT_SQUARE,
+        Err(RotationError::Empty) => ROTATE_ERR_EMPTY,
+    }
+}
+
+/// Rotates a row-major square table of `int64_t` one step clockwise in place.
+///
+/// Returns [`ROTATE_OK`] or one of the `ROTATE_ERR_*` codes; the table is untouched on error.
+///
+/// # Safety
+///
+/// `data` must be null or point to `len` initialized, writable `int64_t` values that are not
+/// accessed by anything else during the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rotate_right_i64(data: *mut i64, len: usize) -> c_int {
+    // SAFETY: forwarded caller contract
+    unsafe { rotate_raw(data, len) }
+}
+
+/// Same as [`rotate_right_i64`] for `int32_t` tables.
+///
+/// # Safety
+///
+/// See [`rotate_right_i64`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rotate_right_i32(data: *mut i32, len: usize) -> c_int {
+    // SAFETY: forwarded caller contract
+    unsafe { rotate_raw(data, len) }
+}
+
+/// Same as [`rotate_right_i64`] for `double` tables.
+///
+/// # Safety
+///
+/// See [`rotate_right_i64`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rotate_right_f64(data: *mut f64, len: usize) -> c_int {
+    // SAFETY: forwarded caller contract
+    unsafe { rotate_raw(data, len) }
+}
+
+/// Returns the side length `n` of an `n × n` table with `len` elements, or 0 if `len` is not
+/// a perfect square (or is 0).
+#[unsafe(no_mangle)]
+pub extern "C" fn rotate_square_len(len: usize) -> usize {
+    square_len(len).unwrap_or(0)
+}
+
+/// Returns the library version as a static NUL-terminated string.
+#[unsafe(no_mangle)]
+pub extern "C" fn rotate_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_rotate_i64() {
+        let mut data = [1i64, 2, 3, 4, 5, 6, 7, 8, 9];
+        let status = unsafe { rotate_right_i64(data.as_mut_ptr(), data.len()) };
+        assert_eq!(status, ROTATE_OK);
+        assert_eq!(data, [4, 1, 2, 7, 5, 3, 8, 9, 6]);
+    }
+
+    #[test]
+    fn test_rotate_other_types() {
+        let mut ints = [1i32, 2, 3, 4];
+        assert_eq!(unsafe { rotate_right_i32(ints.as_mut_ptr(), 4) }, ROTATE_OK);
+        assert_eq!(ints, [3, 1, 4, 2]);
+
+        let mut floats = [1.5f64, 2.5, 3.5, 4.5];
+        assert_eq!(
+            unsafe { rotate_right_f64(floats.as_mut_ptr(), 4) },
+            ROTATE_OK
+        );
+        assert_eq!(floats, [3.5, 1.5, 4.5, 2.5]);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let mut data = [1i64, 2, 3];
+        assert_eq!(
+            unsafe { rotate_right_i64(data.as_mut_ptr(), 3) },
+            ROTATE_ERR_NOT_SQUARE
+        );
+        assert_eq!(data, [1, 2, 3]);
+        assert_eq!(
+            unsafe { rotate_right_i64(std::ptr::null_mut(), 0) },
+            ROTATE_ERR_EMPTY
+        );
+        assert_eq!(
+            unsafe { rotate_right_i64(std::ptr::null_mut(), 4) },
+            ROTATE_ERR_NULL
+        );
+    }
+
+    #[test]
+    fn test_square_len_and_version() {
+        assert_eq!(rotate_square_len(16), 4);
+        assert_eq!(rotate_square_len(15), 0);
+        let version = unsafe { CStr::from_ptr(rotate_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,214 @@
+//! Golden-file cases: checked-in inputs with the exact output, warnings and exit code the
+//! CLI must produce for them.
+//!
+//! Every case is a directory under `fixtures/golden/` at the repository root:
+//!
+//! - `input.csv`: the input file, passed as the last argument
+//! - `args`: optional extra arguments, one per line, placed before the input
+//! - `stdout.csv`: the expected standard output, byte for byte
+//! - `stderr.txt`: the expected standard error (empty when the file is missing)
+//! - `exit_code`: the expected exit code (0 when the file is missing)
+//!
+//! The command runs inside the case directory with `input.csv` as a relative path, so
+//! messages never contain machine-specific paths. The files are plain text on purpose: the
+//! other implementations' test suites can run the same cases with their own CLIs, skipping
+//! the ones whose `args` they do not support.
+//!
+//! `tests/golden.rs` runs all cases against the compiled binary; with `UPDATE_GOLDEN=1` it
+//! rewrites the expected files from the actual results instead, for review in the diff.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// One golden case, loaded from its directory.
+#[derive(Debug, Clone)]
+pub struct GoldenCase {
+    pub name: String,
+    pub dir: PathBuf,
+    pub args: Vec<String>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_code: i32,
+}
+
+/// Loads every case under `root`, sorted by name.
+pub fn cases(root: &Path) -> io::Result<Vec<GoldenCase>> {
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            cases.push(GoldenCase::load(&path)?);
+        }
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+/// Contents of `path`, or `None` if it does not exist.
+fn read_optional(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn invalid_data(path: &Path, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {message}", path.display()),
+    )
+}
+
+impl GoldenCase {
+    pub fn load(dir: &Path) -> io::Result<Self> {
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let args = match read_optional(&dir.join("args"))? {
+            Some(bytes) => String::from_utf8(bytes)
+                .map_err(|_| invalid_data(&dir.join("args"), "not UTF-8"))?
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            None => Vec::new(),
+        };
+        let exit_code = match read_optional(&dir.join("exit_code"))? {
+            Some(bytes) => String::from_utf8_lossy(&bytes)
+                .trim()
+                .parse()
+                .map_err(|_| invalid_data(&dir.join("exit_code"), "not an integer"))?,
+            None => 0,
+        };
+        Ok(GoldenCase {
+            name,
+            dir: dir.to_path_buf(),
+            args,
+            stdout: fs::read(dir.join("stdout.csv"))?,
+            stderr: read_optional(&dir.join("stderr.txt"))?.unwrap_or_default(),
+            exit_code,
+        })
+    }
+
+    /// The command that runs this case with `program`.
+    pub fn command(&self, program: &Path) -> Command {
+        let mut command = Command::new(program);
+        command
+            .args(&self.args)
+            .arg("input.csv")
+            .current_dir(&self.dir)
+            // Log lines would depend on the caller's environment
+            .env_remove("RUST_LOG");
+        command
+    }
+
+    /// Compares a run of [`command`](Self::command) with the expected results.
+    ///
+    /// Returns a description of every mismatch, naming the first differing line.
+    pub fn check(&self, output: &Output) -> Result<(), String> {
+        let mut mismatches = Vec::new();
+        if output.status.code() != Some(self.exit_code) {
+            mismatches.push(format!(
+                "exit code: expected {}, got {}",
+                self.exit_code, output.status
+            ));
+        }
+        if let Some(difference) = first_difference(&self.stdout, &output.stdout) {
+            mismatches.push(format!("stdout: {difference}"));
+        }
+        if let Some(difference) = first_difference(&self.stderr, &output.stderr) {
+            mismatches.push(format!("stderr: {difference}"));
+        }
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{}: {}", self.name, mismatches.join("\n  ")))
+        }
+    }
+
+    /// Replaces the expected files with the results of `output`.
+    pub fn update(&self, output: &Output) -> io::Result<()> {
+        fs::write(self.dir.join("stdout.csv"), &output.stdout)?;
+        let stderr = self.dir.join("stderr.txt");
+        if output.stderr.is_empty() {
+            if stderr.exists() {
+                fs::remove_file(stderr)?;
+            }
+        } else {
+            fs::write(stderr, &output.stderr)?;
+        }
+        let exit_code = self.dir.join("exit_code");
+        match output.status.code() {
+            Some(0) if exit_code.exists() => fs::remove_file(exit_code),
+            Some(0) => Ok(()),
+            Some(code) => fs::write(exit_code, format!("{code}\n")),
+            None => Err(io::Error::other(format!(
+                "{}: killed by a signal ({})",
+                self.name, output.status
+            ))),
+        }
+    }
+}
+
+/// The first line where `actual` differs from `expected`, or `None` if they are equal.
+fn first_difference(expected: &[u8], actual: &[u8]) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let expected_lines: Vec<&[u8]> = expected.split_inclusive(|&b| b == b'\n').collect();
+    let actual_lines: Vec<&[u8]> = actual.split_inclusive(|&b| b == b'\n').collect();
+    let line = (0..)
+        .find(|&i| expected_lines.get(i) != actual_lines.get(i))
+        .expect("unequal inputs differ in some line");
+    let show = |lines: &[&[u8]]| match lines.get(line) {
+        Some(text) => format!("{:?}", String::from_utf8_lossy(text)),
+        None => "end of output".to_string(),
+    };
+    Some(format!(
+        "line {} differs\n    expected: {}\n    actual:   {}",
+        line + 1,
+        show(&expected_lines),
+        show(&actual_lines)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_and_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let case_dir = dir.path().join("short");
+        fs::create_dir(&case_dir).unwrap();
+        fs::write(case_dir.join("input.csv"), "id,json\n").unwrap();
+        fs::write(case_dir.join("args"), "-j\n1\n").unwrap();
+        fs::write(case_dir.join("stdout.csv"), "a\nb\nc\n").unwrap();
+        fs::write(case_dir.join("exit_code"), "1\n").unwrap();
+
+        let cases = cases(dir.path()).unwrap();
+        assert_eq!(cases.len(), 1);
+        let case = &cases[0];
+        assert_eq!((case.name.as_str(), case.exit_code), ("short", 1));
+        assert_eq!(case.args, ["-j", "1"]);
+        assert!(case.stderr.is_empty());
+
+        // `sh -c 'printf ...; exit N'` stands in for the CLI
+        let run = |script: &str| Command::new("sh").args(["-c", script]).output().unwrap();
+        assert_eq!(case.check(&run("printf 'a\\nb\\nc\\n'; exit 1")), Ok(()));
+        let error = case
+            .check(&run("printf 'a\\nx\\n' >&2; printf 'a\\nB\\n'"))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "short: exit code: expected 1, got exit status: 0\n  \
+             stdout: line 2 differs\n    expected: \"b\\n\"\n    actual:   \"B\\n\"\n  \
+             stderr: line 1 differs\n    expected: end of output\n    actual:   \"a\\n\""
+        );
+    }
+}
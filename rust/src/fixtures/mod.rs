@@ -0,0 +1,451 @@
+//! Deterministic tables and input files for tests, benchmarks and `rotate_cli generate`.
+//!
+//! [`matrix`] builds a single table filled with a [`Pattern`]; [`Dataset`] builds whole
+//! `id,json` input files with table sizes drawn from a [`SizeDistribution`] and a share of
+//! invalid rows. All randomness comes from [`Rng`], a SplitMix64 generator whose output
+//! depends on the seed alone, so a seed identifies the same file on every platform and
+//! release. The other implementations' test suites use files generated this way as shared
+//! fixtures, next to the hand-written cases of [`golden`].
+//!
+//! ```
+//! use rotate_cli::fixtures::{Dataset, SizeDistribution};
+//!
+//! let dataset = Dataset {
+//!     rows: 3,
+//!     seed: 7,
+//!     sizes: SizeDistribution::Fixed(2),
+//!     ..Dataset::default()
+//! };
+//! let mut csv = Vec::new();
+//! dataset.write_csv(&mut csv).unwrap();
+//! assert_eq!(csv.iter().filter(|&&b| b == b'\n').count(), 4);
+//! ```
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+pub mod golden;
+
+/// SplitMix64 pseudo-random number generator.
+///
+/// Not suitable for anything security related; it is small, fast and, unlike the generators
+/// of the `rand` crate, guaranteed to produce the same sequence in every version.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`; `bound` must not be zero.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(bound)) >> 64) as u64
+    }
+
+    /// Uniform value in `min..=max`.
+    pub fn between(&mut self, min: i64, max: i64) -> i64 {
+        let span = max.abs_diff(min).wrapping_add(1);
+        if span == 0 {
+            // The full i64 range
+            return self.next_u64() as i64;
+        }
+        min.wrapping_add(self.below(span) as i64)
+    }
+
+    /// `true` with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+/// How the cells of a [`matrix`] are filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    /// `1, 2, 3, ...`
+    Sequential,
+    /// Values in `0..10000`, the same for a given size on every call
+    Random,
+    /// Every cell is 42
+    Repeated,
+    /// `-1, -2, -3, ...`
+    Negative,
+    /// `-1, 2, -3, 4, ...`
+    Mixed,
+}
+
+impl Pattern {
+    /// Every pattern, in declaration order.
+    pub const ALL: [Pattern; 5] = [
+        Pattern::Sequential,
+        Pattern::Random,
+        Pattern::Repeated,
+        Pattern::Negative,
+        Pattern::Mixed,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::Sequential => "sequential",
+            Pattern::Random => "random",
+            Pattern::Repeated => "repeated",
+            Pattern::Negative => "negative",
+            Pattern::Mixed => "mixed",
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pattern::ALL
+            .into_iter()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Pattern::ALL.iter().map(|p| p.name()).collect();
+                format!("unknown pattern '{s}' (expected {})", names.join(", "))
+            })
+    }
+}
+
+/// Flat n×n table filled with `pattern`.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_cli::fixtures::{Pattern, matrix};
+///
+/// assert_eq!(matrix(2, Pattern::Mixed), vec![-1, 2, -3, 4]);
+/// ```
+pub fn matrix(n: usize, pattern: Pattern) -> Vec<i32> {
+    let size = n * n;
+    match pattern {
+        Pattern::Sequential => (1..=size as i32).collect(),
+        Pattern::Random => {
+            let mut rng = Rng::new(n as u64);
+            (0..size).map(|_| rng.below(10_000) as i32).collect()
+        }
+        Pattern::Repeated => vec![42; size],
+        Pattern::Negative => (1..=size as i32).map(|x| -x).collect(),
+        Pattern::Mixed => (1..=size as i32)
+            .map(|x| if x % 2 == 0 { x } else { -x })
+            .collect(),
+    }
+}
+
+/// Distribution of the side length `n` of generated tables.
+///
+/// Parsed from `5` ([`Fixed`](Self::Fixed)), `1-50` ([`Uniform`](Self::Uniform), inclusive)
+/// or `2:10,50:1` ([`Weighted`](Self::Weighted), `size:weight` pairs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SizeDistribution {
+    Fixed(usize),
+    Uniform {
+        min: usize,
+        max: usize,
+    },
+    /// Sizes with relative weights
+    Weighted(Vec<(usize, u32)>),
+}
+
+impl Default for SizeDistribution {
+    fn default() -> Self {
+        SizeDistribution::Uniform { min: 1, max: 50 }
+    }
+}
+
+impl SizeDistribution {
+    pub fn sample(&self, rng: &mut Rng) -> usize {
+        match self {
+            SizeDistribution::Fixed(n) => *n,
+            SizeDistribution::Uniform { min, max } => {
+                rng.between(*min as i64, *max as i64) as usize
+            }
+            SizeDistribution::Weighted(sizes) => {
+                let total: u64 = sizes.iter().map(|&(_, weight)| u64::from(weight)).sum();
+                let mut pick = rng.below(total);
+                for &(n, weight) in sizes {
+                    if pick < u64::from(weight) {
+                        return n;
+                    }
+                    pick -= u64::from(weight);
+                }
+                unreachable!("pick is below the total weight")
+            }
+        }
+    }
+}
+
+impl fmt::Display for SizeDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizeDistribution::Fixed(n) => write!(f, "{n}"),
+            SizeDistribution::Uniform { min, max } => write!(f, "{min}-{max}"),
+            SizeDistribution::Weighted(sizes) => {
+                for (i, (n, weight)) in sizes.iter().enumerate() {
+                    let separator = if i > 0 { "," } else { "" };
+                    write!(f, "{separator}{n}:{weight}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for SizeDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let size = |text: &str| {
+            text.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid table size '{text}'"))
+        };
+        if s.contains(':') {
+            let sizes = s
+                .split(',')
+                .map(|pair| {
+                    let (n, weight) = pair
+                        .split_once(':')
+                        .ok_or_else(|| format!("expected SIZE:WEIGHT, got '{pair}'"))?;
+                    let weight = weight
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|_| format!("invalid weight '{weight}'"))?;
+                    Ok((size(n)?, weight))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            if sizes.iter().all(|&(_, weight)| weight == 0) {
+                return Err("at least one weight must be positive".to_string());
+            }
+            Ok(SizeDistribution::Weighted(sizes))
+        } else if let Some((min, max)) = s.split_once('-') {
+            let (min, max) = (size(min)?, size(max)?);
+            if min > max {
+                return Err(format!("empty size range '{s}'"));
+            }
+            Ok(SizeDistribution::Uniform { min, max })
+        } else {
+            Ok(SizeDistribution::Fixed(size(s)?))
+        }
+    }
+}
+
+/// Ways a generated row is made invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InvalidKind {
+    /// An element count that is not a perfect square
+    NotSquare,
+    /// `[]`
+    Empty,
+    /// A string element
+    NonNumeric,
+    /// A number with a fractional part
+    Fractional,
+    /// Truncated JSON
+    Malformed,
+}
+
+impl InvalidKind {
+    /// Every kind, in declaration order.
+    pub const ALL: [InvalidKind; 5] = [
+        InvalidKind::NotSquare,
+        InvalidKind::Empty,
+        InvalidKind::NonNumeric,
+        InvalidKind::Fractional,
+        InvalidKind::Malformed,
+    ];
+}
+
+/// One generated row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub id: u64,
+    /// The `json` column, e.g. `[1, 2, 3, 4]`
+    pub json: String,
+    /// `None` for a valid table
+    pub invalid: Option<InvalidKind>,
+}
+
+/// Specification of a generated input file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset {
+    pub rows: usize,
+    pub seed: u64,
+    pub sizes: SizeDistribution,
+    /// Share of invalid rows, from 0.0 to 1.0; their kind is chosen uniformly
+    pub invalid_ratio: f64,
+    /// Cells are drawn uniformly from `-max_value..=max_value`
+    pub max_value: i64,
+}
+
+impl Default for Dataset {
+    fn default() -> Self {
+        Dataset {
+            rows: 1000,
+            seed: 0,
+            sizes: SizeDistribution::default(),
+            invalid_ratio: 0.1,
+            max_value: 1000,
+        }
+    }
+}
+
+impl Dataset {
+    /// The rows, with ids counting from 1.
+    pub fn rows(&self) -> impl Iterator<Item = Row> + '_ {
+        let mut rng = Rng::new(self.seed);
+        (1..=self.rows as u64).map(move |id| {
+            let invalid = rng
+                .chance(self.invalid_ratio)
+                .then(|| InvalidKind::ALL[rng.below(InvalidKind::ALL.len() as u64) as usize]);
+            let json = self.table(&mut rng, invalid);
+            Row { id, json, invalid }
+        })
+    }
+
+    fn table(&self, rng: &mut Rng, invalid: Option<InvalidKind>) -> String {
+        let n = self.sizes.sample(rng);
+        let mut len = n * n;
+        match invalid {
+            Some(InvalidKind::Empty) => return "[]".to_string(),
+            // n² + 1 is never a square for n ≥ 1, and 2 is not one either
+            Some(InvalidKind::NotSquare) => len = (len + 1).max(2),
+            _ => len = len.max(1),
+        }
+
+        let mut cells: Vec<String> = (0..len)
+            .map(|_| rng.between(-self.max_value, self.max_value).to_string())
+            .collect();
+        let target = rng.below(len as u64) as usize;
+        match invalid {
+            Some(InvalidKind::NonNumeric) => cells[target] = format!("\"{}\"", cells[target]),
+            Some(InvalidKind::Fractional) => cells[target].push_str(".5"),
+            _ => {}
+        }
+        let mut json = format!("[{}]", cells.join(", "));
+        if invalid == Some(InvalidKind::Malformed) {
+            json.pop();
+        }
+        json
+    }
+
+    /// Writes the rows as an `id,json` CSV file, quoted like the files in `input-samples`.
+    pub fn write_csv<W: io::Write>(&self, mut out: W) -> csv::Result<()> {
+        // The header unquoted, the `json` column always quoted
+        out.write_all(b"id,json\n")?;
+        let mut wtr = csv::WriterBuilder::new()
+            .quote_style(csv::QuoteStyle::NonNumeric)
+            .from_writer(out);
+        let mut id = itoa::Buffer::new();
+        for row in self.rows() {
+            wtr.write_record([id.format(row.id), row.json.as_str()])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_numbers;
+
+    #[test]
+    fn test_rng_is_stable() {
+        // Reference values of SplitMix64, which other implementations can check against
+        let mut rng = Rng::new(1234567);
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+        for _ in 0..1000 {
+            assert!((-3..=3).contains(&rng.between(-3, 3)));
+        }
+    }
+
+    #[test]
+    fn test_matrix_patterns() {
+        assert_eq!(matrix(2, Pattern::Sequential), vec![1, 2, 3, 4]);
+        assert_eq!(matrix(2, Pattern::Negative), vec![-1, -2, -3, -4]);
+        assert_eq!(matrix(3, Pattern::Random), matrix(3, Pattern::Random));
+        assert!(
+            matrix(10, Pattern::Random)
+                .iter()
+                .all(|v| (0..10_000).contains(v))
+        );
+        for pattern in Pattern::ALL {
+            assert_eq!(pattern.to_string().parse::<Pattern>(), Ok(pattern));
+        }
+    }
+
+    #[test]
+    fn test_size_distributions() {
+        for text in ["5", "1-50", "2:10,50:1"] {
+            let sizes: SizeDistribution = text.parse().unwrap();
+            assert_eq!(sizes.to_string(), text);
+        }
+        assert!("5-1".parse::<SizeDistribution>().is_err());
+        assert!("2:0".parse::<SizeDistribution>().is_err());
+        assert!("x".parse::<SizeDistribution>().is_err());
+
+        let mut rng = Rng::new(0);
+        let weighted = SizeDistribution::Weighted(vec![(2, 3), (7, 0), (9, 1)]);
+        let samples: Vec<usize> = (0..1000).map(|_| weighted.sample(&mut rng)).collect();
+        assert!(samples.iter().all(|&n| n == 2 || n == 9));
+        let twos = samples.iter().filter(|&&n| n == 2).count();
+        assert!((650..850).contains(&twos), "{twos}");
+    }
+
+    #[test]
+    fn test_dataset_rows() {
+        let dataset = Dataset {
+            rows: 2000,
+            seed: 42,
+            sizes: SizeDistribution::Uniform { min: 1, max: 10 },
+            ..Dataset::default()
+        };
+        let rows: Vec<Row> = dataset.rows().collect();
+        assert_eq!(rows, dataset.rows().collect::<Vec<_>>());
+        assert_eq!(rows.last().unwrap().id, 2000);
+
+        let mut numbers = Vec::new();
+        let mut invalid = 0;
+        for row in &rows {
+            let valid = parse_numbers(&row.json, &mut numbers)
+                && !numbers.is_empty()
+                && crate::square_len(numbers.len()).is_some();
+            assert_eq!(valid, row.invalid.is_none(), "{row:?}");
+            invalid += usize::from(!valid);
+        }
+        assert!((150..250).contains(&invalid), "{invalid}");
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let dataset = Dataset {
+            rows: 2,
+            seed: 1,
+            sizes: SizeDistribution::Fixed(1),
+            invalid_ratio: 0.0,
+            max_value: 0,
+        };
+        let mut out = Vec::new();
+        dataset.write_csv(&mut out).unwrap();
+        assert_eq!(out, b"id,json\n1,\"[0]\"\n2,\"[0]\"\n");
+    }
+}
@@ -0,0 +1,147 @@
+//! Tables laid out as blocks of CSV rows (`--format block`), as in a spreadsheet.
+//!
+//! Each table spans N consecutive rows of N cells. A row whose first cell starts with `#`
+//! (`# ID`) names the table after it; blank rows separate tables, which are otherwise
+//! numbered from 1. Empty cells at the end of a row are ignored, so sheets padded to their
+//! widest table read as written. The output has the same shape: the `# ID` row, then the
+//! rotated block, or a single `invalid` cell, then a blank row.
+//!
+//! ```text
+//! # small
+//! 1,2
+//! 3,4
+//!
+//! # large
+//! 1,2,3
+//! 4,5,6
+//! 7,8,9
+//! ```
+
+use super::grid::Grid;
+use crate::RowFormat;
+use crate::stats::Counters;
+use csv::{ByteRecord, ReaderBuilder, WriterBuilder};
+use std::error::Error;
+use std::io::{Read, Write};
+use std::slice::Chunks;
+
+/// Rotates every block of `input` and writes the results to `output`.
+pub fn process<R: Read, W: Write>(
+    input: R,
+    mut output: W,
+    format: RowFormat,
+    counters: &Counters,
+) -> Result<W, Box<dyn Error + Send + Sync>> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(input);
+    let mut grid = Grid::default();
+    let mut record = ByteRecord::new();
+
+    loop {
+        let line = rdr.position().line();
+        let more = rdr.read_byte_record(&mut record)?;
+        // The reader skips blank lines; they show as a jump in the line count
+        let newlines = record.as_slice().iter().filter(|&&b| b == b'\n').count() as u64;
+        let after_blank = rdr.position().line() > line + newlines + 1;
+        let cells = cells(&record)?;
+        let id = cells.first().and_then(|cell| cell.trim().strip_prefix('#'));
+        if (!more || after_blank || cells.is_empty() || id.is_some()) && !grid.is_empty() {
+            let is_valid = grid.finish(format, |id, rows| write(&mut output, id, rows))?;
+            counters.add_rows(1, u64::from(is_valid));
+        }
+        if !more {
+            break;
+        }
+        match id {
+            Some(id) => grid.set_id(id),
+            None if !cells.is_empty() => grid.add_row(cells),
+            None => {}
+        }
+    }
+
+    output.flush()?;
+    Ok(output)
+}
+
+/// The cells of a row up to its last non-empty one.
+fn cells(record: &ByteRecord) -> Result<Vec<&str>, std::str::Utf8Error> {
+    let mut cells = record
+        .iter()
+        .map(std::str::from_utf8)
+        .collect::<Result<Vec<_>, _>>()?;
+    while cells.last().is_some_and(|cell| cell.trim().is_empty()) {
+        cells.pop();
+    }
+    Ok(cells)
+}
+
+/// Writes the `# ID` row and the rotated block, or `invalid`, and a blank row.
+fn write<W: Write>(
+    output: &mut W,
+    id: &str,
+    rows: Option<Chunks<i64>>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut wtr = WriterBuilder::new().flexible(true).from_writer(Vec::new());
+    wtr.write_record([format!("# {id}")])?;
+    match rows {
+        Some(rows) => {
+            let mut buffer = itoa::Buffer::new();
+            for row in rows {
+                for &x in row {
+                    wtr.write_field(buffer.format(x))?;
+                }
+                wtr.write_record(None::<&[u8]>)?;
+            }
+        }
+        None => wtr.write_record(["invalid"])?,
+    }
+    output.write_all(&wtr.into_inner().map_err(|e| e.into_error())?)?;
+    // Written directly: the csv writer would turn an empty record into `""`
+    output.write_all(b"\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &str) -> (String, u64) {
+        let counters = Counters::default();
+        let output = process(
+            input.as_bytes(),
+            Vec::new(),
+            RowFormat::default(),
+            &counters,
+        )
+        .unwrap();
+        let summary = counters.snapshot(Default::default());
+        (String::from_utf8(output).unwrap(), summary.valid)
+    }
+
+    #[test]
+    fn test_blocks() {
+        let input = "# small,,\n1,2,\n3,4,\n,,\n\"# b, quoted\",,\n1,2,3\n4,5,6\n7,8,9\n\n-1\n";
+        assert_eq!(
+            run(input),
+            (
+                "# small\n3,1\n4,2\n\n\"# b, quoted\"\n4,1,2\n7,5,3\n8,9,6\n\n# 3\n-1\n\n"
+                    .to_string(),
+                3
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        let input = "# ragged\n1,2\n3\n# words\na,b\nc,d\n\n1,,3\n4,5,6\n7,8,9\n";
+        assert_eq!(
+            run(input),
+            (
+                "# ragged\ninvalid\n\n# words\ninvalid\n\n# 3\ninvalid\n\n".to_string(),
+                0
+            )
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! CBOR record streams (`--format cbor`).
+//!
+//! Input is a CBOR sequence (RFC 8742) of maps with an `id` of any type and a `json` table,
+//! given either as a native array of integers or as JSON text like the CSV column. Every
+//! record produces a map with the same `id`, the rotated table as a native integer array
+//! (empty for invalid rows), `is_valid` and, with `--emit-dimension`, `n`.
+
+use crate::RowFormat;
+use crate::stats::Counters;
+use ciborium::Value;
+use rotate_cli::errors::RecordError;
+use rotate_cli::{parse, rotate_right, square_len};
+use std::error::Error;
+use std::io::{BufRead, Write};
+
+/// Rotates every record of `input` and writes the results to `output`.
+pub fn process<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    format: RowFormat,
+    counters: &Counters,
+) -> Result<W, Box<dyn Error + Send + Sync>> {
+    let mut numbers = Vec::new();
+    let mut index = 0u64;
+
+    while !input.fill_buf()?.is_empty() {
+        index += 1;
+        let record: Value =
+            ciborium::from_reader(&mut input).map_err(|source| RecordError::Cbor {
+                record: index,
+                source,
+            })?;
+        let Some((id, table)) = split_record(record) else {
+            eprintln!("Warning: Skipping record {index} without 'id' and 'json' fields");
+            continue;
+        };
+
+        let is_valid = read_table(&table, &mut numbers) && rotate_right(&mut numbers).is_ok();
+        let json = if is_valid {
+            numbers.iter().map(|&value| Value::from(value)).collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut fields = vec![
+            (Value::from("id"), id),
+            (Value::from("json"), Value::Array(json)),
+            (Value::from("is_valid"), Value::Bool(is_valid)),
+        ];
+        if format.emit_dimension {
+            let n = is_valid.then(|| square_len(numbers.len())).flatten();
+            fields.push((
+                Value::from("n"),
+                n.map_or(Value::Null, |n| Value::from(n as u64)),
+            ));
+        }
+        ciborium::into_writer(&Value::Map(fields), &mut output)?;
+        counters.add_rows(1, u64::from(is_valid));
+    }
+
+    output.flush()?;
+    Ok(output)
+}
+
+/// Takes the `id` and `json` values out of a record map.
+fn split_record(record: Value) -> Option<(Value, Value)> {
+    let Value::Map(fields) = record else {
+        return None;
+    };
+    let (mut id, mut table) = (None, None);
+    for (key, value) in fields {
+        match key.as_text() {
+            Some("id") => id = Some(value),
+            Some("json") => table = Some(value),
+            _ => {}
+        }
+    }
+    id.zip(table)
+}
+
+/// Reads a native integer array or JSON text into `numbers`; returns `false` if invalid.
+fn read_table(table: &Value, numbers: &mut Vec<i64>) -> bool {
+    match table {
+        Value::Text(text) => parse::parse_numbers(text, numbers),
+        Value::Array(items) => {
+            numbers.clear();
+            items.iter().all(|item| {
+                item.as_integer()
+                    .and_then(|value| i64::try_from(value).ok())
+                    .map(|value| numbers.push(value))
+                    .is_some()
+            })
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(records: &[Value]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for record in records {
+            ciborium::into_writer(record, &mut bytes).unwrap();
+        }
+        bytes
+    }
+
+    fn decode(mut bytes: &[u8]) -> Vec<Value> {
+        let mut records = Vec::new();
+        while !bytes.is_empty() {
+            records.push(ciborium::from_reader(&mut bytes).unwrap());
+        }
+        records
+    }
+
+    fn record(id: Value, json: Value) -> Value {
+        Value::Map(vec![(Value::from("id"), id), (Value::from("json"), json)])
+    }
+
+    fn ints(values: &[i64]) -> Value {
+        Value::Array(values.iter().map(|&v| Value::from(v)).collect())
+    }
+
+    fn run(input: &[Value], format: RowFormat) -> Vec<Value> {
+        let counters = Counters::default();
+        let output = process(encode(input).as_slice(), Vec::new(), format, &counters).unwrap();
+        decode(&output)
+    }
+
+    #[test]
+    fn test_native_and_text_tables() {
+        let output = run(
+            &[
+                record(Value::from(1), ints(&[1, 2, 3, 4])),
+                record(Value::from("b"), Value::from("[1, 2, 3, 4, 5, 6, 7, 8, 9]")),
+                record(Value::from(3), ints(&[1, 2, 3])),
+                record(Value::from(4), Value::Array(vec![Value::from("x")])),
+            ],
+            RowFormat::default(),
+        );
+
+        let expected = |id: Value, json: &[i64], valid: bool| {
+            Value::Map(vec![
+                (Value::from("id"), id),
+                (Value::from("json"), ints(json)),
+                (Value::from("is_valid"), Value::Bool(valid)),
+            ])
+        };
+        assert_eq!(
+            output,
+            [
+                expected(Value::from(1), &[3, 1, 4, 2], true),
+                expected(Value::from("b"), &[4, 1, 2, 7, 5, 3, 8, 9, 6], true),
+                expected(Value::from(3), &[], false),
+                expected(Value::from(4), &[], false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emit_dimension_and_skipped_records() {
+        let format = RowFormat {
+            emit_dimension: true,
+            ..RowFormat::default()
+        };
+        let output = run(
+            &[
+                Value::from("not a map"),
+                record(Value::from(1), ints(&[1, 2, 3, 4])),
+                record(Value::from(2), ints(&[])),
+            ],
+            format,
+        );
+        assert_eq!(output.len(), 2);
+        let n = |record: &Value| record.as_map().unwrap()[3].1.clone();
+        assert_eq!(n(&output[0]), Value::from(2u64));
+        assert_eq!(n(&output[1]), Value::Null);
+    }
+
+    #[test]
+    fn test_truncated_input_fails() {
+        let mut bytes = encode(&[record(Value::from(1), ints(&[1]))]);
+        bytes.pop();
+        let counters = Counters::default();
+        let err = process(
+            bytes.as_slice(),
+            Vec::new(),
+            RowFormat::default(),
+            &counters,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("record 1"));
+    }
+}
@@ -0,0 +1,195 @@
+//! Whitespace-separated grids (`--format grid`).
+//!
+//! Each table is written as N lines of N numbers separated by spaces or tabs, and tables
+//! are separated by blank lines. A `# ID` comment line before a table names it; tables
+//! without one are numbered from 1 in input order. The output has the same shape: a
+//! `# ID` line, then the rotated rows, or the single line `invalid` for a table that is
+//! ragged, not square, empty or holds something other than integers.
+//!
+//! ```text
+//! # small
+//! 1 2
+//! 3 4
+//!
+//! 1 2 3
+//! 4 5 6
+//! 7 8 9
+//! ```
+
+use crate::RowFormat;
+use crate::stats::Counters;
+use rotate_cli::rotate_right;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::slice::Chunks;
+
+/// Line written instead of the rows of an invalid table.
+const INVALID: &str = "invalid";
+
+/// Rotates every table of `input` and writes the results to `output`.
+pub fn process<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    format: RowFormat,
+    counters: &Counters,
+) -> Result<W, Box<dyn Error + Send + Sync>> {
+    let mut grid = Grid::default();
+    let mut line = String::new();
+    let mut line_number = 0u64;
+
+    loop {
+        line.clear();
+        let read = input
+            .read_line(&mut line)
+            .map_err(|e| format!("line {}: {e}", line_number + 1))?;
+        line_number += 1;
+        let text = line.trim();
+        if read == 0 || text.is_empty() || text.starts_with('#') {
+            if !grid.is_empty() {
+                let is_valid = grid.finish(format, |id, rows| write(&mut output, id, rows))?;
+                counters.add_rows(1, u64::from(is_valid));
+            }
+            if read == 0 {
+                break;
+            }
+            if let Some(id) = text.strip_prefix('#') {
+                grid.set_id(id);
+            }
+            continue;
+        }
+        grid.add_row(text.split_ascii_whitespace());
+    }
+
+    output.flush()?;
+    Ok(output)
+}
+
+/// Writes `# ID` and the space-separated rows, or `invalid`, and a blank line.
+fn write<W: Write>(output: &mut W, id: &str, rows: Option<Chunks<i64>>) -> io::Result<()> {
+    writeln!(output, "# {id}")?;
+    match rows {
+        Some(rows) => {
+            let mut buffer = itoa::Buffer::new();
+            let mut line = String::new();
+            for row in rows {
+                line.clear();
+                for (i, &x) in row.iter().enumerate() {
+                    if i > 0 {
+                        line.push(' ');
+                    }
+                    line.push_str(buffer.format(x));
+                }
+                writeln!(output, "{line}")?;
+            }
+        }
+        None => writeln!(output, "{INVALID}")?,
+    }
+    writeln!(output)
+}
+
+/// A table read row by row, shared with `--format block`.
+#[derive(Default)]
+pub(super) struct Grid {
+    /// From the `# ID` line before the table
+    id: Option<String>,
+    /// Tables written so far, for numbering unnamed ones
+    count: u64,
+    numbers: Vec<i64>,
+    rows: usize,
+    /// Set by a ragged row or a token that is not an integer
+    malformed: bool,
+}
+
+impl Grid {
+    /// Whether neither an id nor a row was read since the last table.
+    pub(super) fn is_empty(&self) -> bool {
+        self.rows == 0 && self.id.is_none()
+    }
+
+    pub(super) fn set_id(&mut self, id: &str) {
+        self.id = Some(id.trim().to_string());
+    }
+
+    pub(super) fn add_row<'a>(&mut self, cells: impl IntoIterator<Item = &'a str>) {
+        let start = self.numbers.len();
+        for token in cells {
+            match token.trim().parse() {
+                Ok(value) => self.numbers.push(value),
+                Err(_) => self.malformed = true,
+            }
+        }
+        if self.rows > 0 && self.numbers.len() - start != start / self.rows {
+            self.malformed = true;
+        }
+        self.rows += 1;
+    }
+
+    /// Rotates the table, hands its id and rows (`None` if invalid) to `write` and starts
+    /// the next one.
+    ///
+    /// Returns the table's validity.
+    pub(super) fn finish<E>(
+        &mut self,
+        format: RowFormat,
+        write: impl FnOnce(&str, Option<Chunks<i64>>) -> Result<(), E>,
+    ) -> Result<bool, E> {
+        self.count += 1;
+        let id = self.id.take().unwrap_or_else(|| self.count.to_string());
+        let n = self.rows;
+        let is_valid = !self.malformed
+            && self.numbers.len() == n * n
+            && format.max_n.is_none_or(|max_n| n <= max_n)
+            && rotate_right(&mut self.numbers).is_ok();
+        let result = write(&id, is_valid.then(|| self.numbers.chunks(n)));
+
+        self.numbers.clear();
+        self.rows = 0;
+        self.malformed = false;
+        result.map(|()| is_valid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &str) -> (String, u64) {
+        let counters = Counters::default();
+        let output = process(
+            input.as_bytes(),
+            Vec::new(),
+            RowFormat::default(),
+            &counters,
+        )
+        .unwrap();
+        let summary = counters.snapshot(Default::default());
+        (String::from_utf8(output).unwrap(), summary.valid)
+    }
+
+    #[test]
+    fn test_grids() {
+        let input = "# small\n1 2\n3 4\n\n\n1 2 3\n4\t5  6\n7 8 9\n# last\n-5\n";
+        assert_eq!(
+            run(input),
+            (
+                "# small\n3 1\n4 2\n\n# 2\n4 1 2\n7 5 3\n8 9 6\n\n# last\n-5\n\n".to_string(),
+                3
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_grids() {
+        let input = "1 2 3\n4 5 6\n\n1 2\n3\n\n# words\na b\nc d\n\n1 2\n3 4 5\n";
+        assert_eq!(
+            run(input),
+            (
+                "# 1\ninvalid\n\n# 2\ninvalid\n\n# words\ninvalid\n\n# 4\ninvalid\n\n".to_string(),
+                0
+            )
+        );
+        // An id without a table is an empty, invalid table
+        assert_eq!(run("# alone\n"), ("# alone\ninvalid\n\n".to_string(), 0));
+        assert_eq!(run(""), (String::new(), 0));
+    }
+}
@@ -0,0 +1,139 @@
+//! Record formats other than CSV (`--format`).
+//!
+//! CSV goes through the parallel [`pipeline`](crate::pipeline); the formats here are read,
+//! rotated and written record by record on the main thread.
+
+use crate::engine::Engine;
+use crate::output::Output;
+use crate::stats::{self, Counters};
+use crate::{Cli, DEFAULT_MAX_N, RowFormat, input};
+use clap::ValueEnum;
+use rotate_cli::errors::CliError;
+use std::error::Error;
+use std::io::{self, BufReader, BufWriter, Read};
+use std::time::Instant;
+
+mod block;
+mod cbor;
+mod grid;
+mod wide;
+
+/// Encoding of the input and output record streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum RecordFormat {
+    /// `id,json` CSV in, `id,json,is_valid` CSV out
+    #[default]
+    Csv,
+    /// CBOR sequence of `{"id", "json"}` maps in and out, tables as native integer arrays
+    Cbor,
+    /// Tables as lines of space-separated integers, separated by blank lines, `# ID` comments
+    Grid,
+    /// CSV with an id and N² value columns (`id,c0,c1,...`) in and out, plus `is_valid`
+    Wide,
+    /// Each table as N CSV rows of N cells, `# ID` rows or blank rows between tables
+    Block,
+}
+
+/// Processes the whole input in a non-CSV record format.
+pub fn run(cli: &Cli) -> Result<(), Box<dyn Error + Send + Sync>> {
+    check_options(cli).map_err(CliError::usage)?;
+    // The blocks already show the dimension
+    let shaped = match cli.format {
+        RecordFormat::Grid => Some("grid"),
+        RecordFormat::Block => Some("block"),
+        _ => None,
+    };
+    if let Some(name) = shaped.filter(|_| cli.emit_dimension) {
+        let message = format!("--emit-dimension is not supported with --format {name}");
+        return Err(CliError::usage(message).into());
+    }
+    let path = cli.input.as_deref().ok_or("missing input argument")?;
+    let format = RowFormat {
+        emit_dimension: cli.emit_dimension,
+        json_style: cli.json_style,
+        ..RowFormat::default()
+    };
+
+    let counters = Counters::default();
+    let started = Instant::now();
+    let source = BufReader::new(Counted {
+        inner: input::open(path)?,
+        counters: &counters,
+        bytes: 0,
+    });
+    let output = BufWriter::new(Output::create(cli.output.as_deref())?);
+
+    let output = match cli.format {
+        RecordFormat::Csv => unreachable!("CSV is handled by the pipeline"),
+        RecordFormat::Cbor => cbor::process(source, output, format, &counters)?,
+        RecordFormat::Grid => grid::process(source, output, format, &counters)?,
+        RecordFormat::Wide => wide::process(source, output, format, &counters)?,
+        RecordFormat::Block => block::process(source, output, format, &counters)?,
+    };
+    output.into_inner().map_err(|e| e.into_error())?.finish()?;
+
+    if cli.stats {
+        stats::print(counters.snapshot(started.elapsed()));
+    }
+    Ok(())
+}
+
+/// Rejects options that only apply to the CSV pipeline instead of silently ignoring them.
+fn check_options(cli: &Cli) -> Result<(), String> {
+    let engine = ("--engine polars", cli.engine == Engine::Polars);
+    match pipeline_option(cli).or(engine.1.then_some(engine.0)) {
+        Some(option) => Err(format!("{option} is only supported with --format csv")),
+        None => Ok(()),
+    }
+}
+
+/// The first option given that only the parallel CSV pipeline implements.
+pub(crate) fn pipeline_option(cli: &Cli) -> Option<&'static str> {
+    let options = [
+        ("--sort-by-id", cli.sort_by_id.is_some()),
+        ("--check-duplicate-ids", cli.check_duplicate_ids.is_some()),
+        ("--json-style", cli.json_style != Default::default()),
+        ("--parser", cli.parser != Default::default()),
+        ("--keep-original-on-invalid", cli.keep_original_on_invalid),
+        ("--explain", cli.explain),
+        ("--output-columns", cli.output_columns.is_some()),
+        ("--progress-interval", cli.progress_interval.is_some()),
+        ("--self-check", cli.self_check.is_some()),
+        ("--on-error", cli.on_error != Default::default()),
+        ("--processor", cli.processor != Default::default()),
+        ("--script", cli.script.is_some()),
+        ("--preserve-numbers", cli.preserve_numbers),
+        ("--profile", cli.profile.is_some()),
+        ("--bigint", cli.bigint),
+        ("--decimal", cli.decimal),
+        ("--schema", cli.schema.is_some()),
+        ("--id-type", cli.id_type.is_some()),
+        ("--delimiter", cli.delimiter != b','),
+        ("--columns", cli.columns.is_some()),
+        ("--max-n", cli.max_n != DEFAULT_MAX_N),
+        ("--max-memory", cli.max_memory.is_some()),
+        ("--sample", cli.sample.is_some()),
+        ("--split-*", cli.split().is_enabled()),
+        ("--timing-column", cli.timing_column),
+        ("--latency-histogram", cli.latency_histogram),
+    ];
+    options
+        .into_iter()
+        .find_map(|(option, used)| used.then_some(option))
+}
+
+/// Reader that publishes how many bytes have been consumed, for `--stats`.
+struct Counted<'a, R> {
+    inner: R,
+    counters: &'a Counters,
+    bytes: u64,
+}
+
+impl<R: Read> Read for Counted<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes += read as u64;
+        self.counters.set_input_bytes(self.bytes);
+        Ok(read)
+    }
+}
@@ -0,0 +1,129 @@
+//! Wide CSV with one number per cell (`--format wide`).
+//!
+//! The header is `id` followed by N² value columns (`id,c0,c1,...`), so every table in the
+//! file has the side N that the column count gives. The output keeps the header and the
+//! columns, with the rotated values, and appends `is_valid` (and, with
+//! `--emit-dimension`, `n`). A row with a missing, extra or non-integer cell is invalid and
+//! written with empty value cells.
+
+use crate::RowFormat;
+use crate::stats::Counters;
+use csv::{ByteRecord, ReaderBuilder, WriterBuilder};
+use rotate_cli::{rotate_right, square_len};
+use std::error::Error;
+use std::io::{Read, Write};
+
+/// Rotates every row of `input` and writes the results to `output`.
+pub fn process<R: Read, W: Write>(
+    input: R,
+    output: W,
+    format: RowFormat,
+    counters: &Counters,
+) -> Result<W, Box<dyn Error + Send + Sync>> {
+    let mut rdr = ReaderBuilder::new().flexible(true).from_reader(input);
+    let mut wtr = WriterBuilder::new().flexible(true).from_writer(output);
+
+    let mut header = rdr.byte_headers()?.clone();
+    let cells = header.len().saturating_sub(1);
+    let Some(n) = square_len(cells).filter(|&n| n > 0) else {
+        return Err(format!(
+            "--format wide needs an id column and N² value columns, found {cells} value columns"
+        )
+        .into());
+    };
+    header.push_field(b"is_valid");
+    if format.emit_dimension {
+        header.push_field(b"n");
+    }
+    wtr.write_byte_record(&header)?;
+
+    let mut record = ByteRecord::new();
+    let mut numbers = Vec::with_capacity(cells);
+    let mut buffer = itoa::Buffer::new();
+    while rdr.read_byte_record(&mut record)? {
+        let is_valid =
+            read_cells(&record, cells, &mut numbers) && rotate_right(&mut numbers).is_ok();
+
+        wtr.write_field(record.get(0).unwrap_or_default())?;
+        if is_valid {
+            for &value in &numbers {
+                wtr.write_field(buffer.format(value))?;
+            }
+        } else {
+            for _ in 0..cells {
+                wtr.write_field("")?;
+            }
+        }
+        wtr.write_field(if is_valid { "true" } else { "false" })?;
+        if format.emit_dimension {
+            wtr.write_field(if is_valid { buffer.format(n) } else { "" })?;
+        }
+        wtr.write_record(None::<&[u8]>)?;
+        counters.add_rows(1, u64::from(is_valid));
+    }
+
+    wtr.flush()?;
+    Ok(wtr.into_inner().map_err(|e| e.into_error())?)
+}
+
+/// Parses the value cells of `record` into `numbers`; returns `false` if any is missing,
+/// extra or not an integer.
+fn read_cells(record: &ByteRecord, cells: usize, numbers: &mut Vec<i64>) -> bool {
+    numbers.clear();
+    record.len() == cells + 1
+        && record.iter().skip(1).all(|cell| {
+            std::str::from_utf8(cell)
+                .ok()
+                .and_then(|cell| cell.trim().parse().ok())
+                .map(|value| numbers.push(value))
+                .is_some()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &str, format: RowFormat) -> String {
+        let counters = Counters::default();
+        let output = process(input.as_bytes(), Vec::new(), format, &counters).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_wide_rows() {
+        let input = "id,c0,c1,c2,c3\n1,1,2,3,4\n2, -1 ,2,3,4\n3,1,2,3\n4,1,2,x,4\n5,1,2,3,4,5\n";
+        assert_eq!(
+            run(input, RowFormat::default()),
+            "id,c0,c1,c2,c3,is_valid\n1,3,1,4,2,true\n2,3,-1,4,2,true\n3,,,,,false\n\
+             4,,,,,false\n5,,,,,false\n"
+        );
+
+        let format = RowFormat {
+            emit_dimension: true,
+            ..RowFormat::default()
+        };
+        assert_eq!(
+            run("id,a\n7,5\n8,\n", format),
+            "id,a,is_valid,n\n7,5,true,1\n8,,false,\n"
+        );
+    }
+
+    #[test]
+    fn test_wide_header_must_be_square() {
+        let counters = Counters::default();
+        for input in ["id,c0,c1\n", "id\n"] {
+            let error = process(
+                input.as_bytes(),
+                Vec::new(),
+                RowFormat::default(),
+                &counters,
+            )
+            .unwrap_err();
+            assert!(
+                error.to_string().starts_with("--format wide needs"),
+                "{error}"
+            );
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Checking record ids against an expected type (`--id-type int|uuid|string`).
+//!
+//! Ids are otherwise opaque: the pipeline copies the id field's bytes to the output as they
+//! are, so UUIDs, composite keys with commas or quotes and even bytes that are not UTF-8
+//! round-trip unchanged, quoted as CSV needs. An [`IdCheck`] makes a row with a malformed id
+//! invalid, with the reason [`InvalidReason::Id`] and a detail such as
+//! `'12a' is not an integer`, instead of passing the garbage through.
+
+use crate::json::JsonStyle;
+use crate::processor::RowProcessor;
+use crate::row::{InvalidReason, RowBuffers};
+use clap::ValueEnum;
+
+/// What a valid id looks like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IdType {
+    /// A 64-bit signed integer in decimal, such as `42` or `-7`
+    Int,
+    /// A hyphenated UUID, such as `67e55044-10b1-426f-9247-bb680e5fe0c8`, in either case
+    Uuid,
+    /// Any non-empty UTF-8 text
+    String,
+}
+
+impl IdType {
+    /// Why `id` is not of this type, if it is not.
+    pub fn check(self, id: &[u8]) -> Result<(), String> {
+        let Ok(text) = std::str::from_utf8(id) else {
+            return Err("the id is not UTF-8".to_string());
+        };
+        let valid = match self {
+            IdType::Int => text.parse::<i64>().is_ok() && !text.starts_with('+'),
+            IdType::Uuid => is_uuid(text),
+            IdType::String => !text.is_empty(),
+        };
+        match valid {
+            true => Ok(()),
+            false if text.is_empty() => Err("the id is empty".to_string()),
+            false => Err(format!("'{text}' is not {}", self.description())),
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            IdType::Int => "an integer",
+            IdType::Uuid => "a UUID",
+            IdType::String => "a string",
+        }
+    }
+}
+
+/// `8-4-4-4-12` hex digits.
+fn is_uuid(text: &str) -> bool {
+    let groups: Vec<&str> = text.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// A processor that checks the id of every record before `inner` processes its table.
+pub struct IdCheck<'a> {
+    id_type: IdType,
+    inner: &'a dyn RowProcessor,
+}
+
+impl<'a> IdCheck<'a> {
+    pub fn new(id_type: IdType, inner: &'a dyn RowProcessor) -> Self {
+        IdCheck { id_type, inner }
+    }
+}
+
+impl RowProcessor for IdCheck<'_> {
+    /// Without an id there is nothing to check.
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        self.inner.process(json, style, buffers)
+    }
+
+    fn process_record(
+        &self,
+        id: &[u8],
+        json: &[u8],
+        style: JsonStyle,
+        buffers: &mut RowBuffers,
+    ) -> bool {
+        match self.id_type.check(id) {
+            Ok(()) => self.inner.process_record(id, json, style, buffers),
+            Err(problem) => buffers.reject_with(InvalidReason::Id, problem),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::processor::RotateRight;
+
+    #[test]
+    fn test_id_types() {
+        let cases: [(&[u8], [bool; 3]); 9] = [
+            (b"42", [true, false, true]),
+            (b"-9223372036854775808", [true, false, true]),
+            (b"9223372036854775808", [false, false, true]),
+            (b"+1", [false, false, true]),
+            (b"67e55044-10b1-426f-9247-bb680e5fe0c8", [false, true, true]),
+            (b"67E55044-10B1-426F-9247-BB680E5FE0C8", [false, true, true]),
+            (b"67e5504410b1426f9247bb680e5fe0c8", [false, false, true]),
+            (b"", [false, false, false]),
+            (b"\xff", [false, false, false]),
+        ];
+        for (id, expected) in cases {
+            let valid = [IdType::Int, IdType::Uuid, IdType::String].map(|t| t.check(id).is_ok());
+            assert_eq!(valid, expected, "{}", String::from_utf8_lossy(id));
+        }
+        assert_eq!(
+            IdType::Int.check(b"12a"),
+            Err("'12a' is not an integer".to_string())
+        );
+        assert_eq!(IdType::Uuid.check(b""), Err("the id is empty".to_string()));
+    }
+
+    #[test]
+    fn test_id_check() {
+        let check = IdCheck::new(IdType::Int, &RotateRight);
+        let mut buffers = RowBuffers::new();
+        assert!(check.process_record(b"7", b"[1, 2, 3, 4]", JsonStyle::Compact, &mut buffers));
+        assert_eq!(buffers.json, b"[3,1,4,2]");
+        assert!(!check.process_record(b"x", b"[1, 2, 3, 4]", JsonStyle::Compact, &mut buffers));
+        assert_eq!(buffers.json, b"[]");
+        assert_eq!(
+            buffers.error_reason(false).as_deref(),
+            Some("id: 'x' is not an integer")
+        );
+        // A valid id does not make the table valid
+        assert!(!check.process_record(b"7", b"[1, 2, 3]", JsonStyle::Compact, &mut buffers));
+        assert_eq!(buffers.error_reason(false).as_deref(), Some("not_square"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Input sources for the CLI.
+//!
+//! The positional input argument is either a local file path or, when built with the
+//! `http` feature, an `http://` / `https://` URL whose body is streamed into the CSV reader.
+//! With the `object-store` feature, `s3://` and `gs://` URIs are streamed the same way.
+
+use crate::cloud;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Opens the input argument as a byte stream.
+///
+/// URLs are recognised by their scheme; everything else is treated as a local path.
+pub fn open(input: &str) -> io::Result<Box<dyn Read + Send>> {
+    if is_url(input) {
+        return open_url(input);
+    }
+
+    if cloud::is_object_uri(input) {
+        return open_object(input);
+    }
+
+    Ok(Box::new(File::open(input)?))
+}
+
+/// Streams the result of `query` from PostgreSQL as CSV (`--from-postgres`).
+#[cfg(feature = "postgres")]
+pub fn open_postgres(dsn: &str, query: &str) -> io::Result<Box<dyn Read + Send>> {
+    crate::postgres::open_reader(dsn, query)
+}
+
+#[cfg(not(feature = "postgres"))]
+pub fn open_postgres(_dsn: &str, _query: &str) -> io::Result<Box<dyn Read + Send>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--from-postgres requires building with `--features postgres`",
+    ))
+}
+
+/// Streams the result of `query` on a DuckDB database file as CSV (`--from-duckdb`).
+#[cfg(feature = "duckdb")]
+pub fn open_duckdb(file: &Path, query: &str) -> io::Result<Box<dyn Read + Send>> {
+    crate::duckdb::open_reader(file, query)
+}
+
+#[cfg(not(feature = "duckdb"))]
+pub fn open_duckdb(_file: &Path, _query: &str) -> io::Result<Box<dyn Read + Send>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--from-duckdb requires building with `--features duckdb`",
+    ))
+}
+
+/// Returns `true` if the input argument should be fetched over HTTP(S).
+fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+#[cfg(feature = "http")]
+fn open_url(url: &str) -> io::Result<Box<dyn Read + Send>> {
+    Ok(Box::new(http::HttpReader::open(url)?))
+}
+
+#[cfg(not(feature = "http"))]
+fn open_url(url: &str) -> io::Result<Box<dyn Read + Send>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("cannot read {url}: URL input requires building with `--features http`"),
+    ))
+}
+
+#[cfg(feature = "object-store")]
+fn open_object(uri: &str) -> io::Result<Box<dyn Read + Send>> {
+    cloud::open_reader(uri)
+}
+
+#[cfg(not(feature = "object-store"))]
+fn open_object(uri: &str) -> io::Result<Box<dyn Read + Send>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "cannot read {uri}: object-store input requires building with `--features object-store`"
+        ),
+    ))
+}
+
+#[cfg(feature = "http")]
+mod http {
+    use log::warn;
+    use std::io::{self, Read};
+    use std::thread;
+    use std::time::Duration;
+    use ureq::{Agent, BodyReader};
+
+    /// Maximum number of consecutive retries before a transient failure becomes fatal.
+    const MAX_RETRIES: u32 = 5;
+
+    /// Delay before the first retry; doubled after every further failed attempt.
+    const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+    /// Streaming HTTP(S) body reader that resumes interrupted downloads.
+    ///
+    /// The response body is never buffered as a whole: bytes flow straight from the socket
+    /// into the CSV reader. When the connection drops mid-body, the request is re-issued with
+    /// a `Range: bytes=<offset>-` header so processing continues exactly where it stopped.
+    /// Servers that ignore the range (answering `200` instead of `206`) are handled by
+    /// discarding the already-consumed prefix.
+    pub struct HttpReader {
+        agent: Agent,
+        url: String,
+        body: Option<BodyReader<'static>>,
+        offset: u64,
+    }
+
+    impl HttpReader {
+        /// Issues the initial request, retrying transient failures.
+        pub fn open(url: &str) -> io::Result<Self> {
+            let config = Agent::config_builder()
+                .timeout_connect(Some(Duration::from_secs(30)))
+                .timeout_recv_response(Some(Duration::from_secs(60)))
+                .build();
+            let agent = Agent::new_with_config(config);
+
+            let mut reader = Self {
+                agent,
+                url: url.to_string(),
+                body: None,
+                offset: 0,
+            };
+            reader.connect()?;
+            Ok(reader)
+        }
+
+        /// (Re)connects at the current offset, retrying transient failures with backoff.
+        fn connect(&mut self) -> io::Result<()> {
+            let mut attempt = 0;
+            loop {
+                match self.request() {
+                    Ok(body) => {
+                        self.body = Some(body);
+                        return Ok(());
+                    }
+                    Err(e) if is_transient(&e) && attempt < MAX_RETRIES => {
+                        warn!("Request to {} failed ({}), retrying", self.url, e);
+                        thread::sleep(backoff(attempt));
+                        attempt += 1;
+                    }
+                    Err(e) => return Err(e.into_io()),
+                }
+            }
+        }
+
+        /// Sends a single GET request starting at the current offset.
+        fn request(&self) -> Result<BodyReader<'static>, ureq::Error> {
+            let mut request = self.agent.get(&self.url);
+            if self.offset > 0 {
+                request = request.header("Range", format!("bytes={}-", self.offset));
+            }
+
+            let response = request.call()?;
+            let partial = response.status() == 206;
+            let mut body = response.into_body().into_reader();
+
+            if self.offset > 0 && !partial {
+                // Range not honoured: skip the bytes the CSV reader has already seen
+                let skipped = io::copy(&mut (&mut body).take(self.offset), &mut io::sink())?;
+                if skipped < self.offset {
+                    return Err(ureq::Error::Io(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "response body shorter than previously received data",
+                    )));
+                }
+            }
+
+            Ok(body)
+        }
+    }
+
+    impl Read for HttpReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let mut failures = 0;
+            loop {
+                if self.body.is_none() {
+                    self.connect()?;
+                }
+
+                let body = self.body.as_mut().expect("connected above");
+                match body.read(buf) {
+                    Ok(n) => {
+                        self.offset += n as u64;
+                        return Ok(n);
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) if failures < MAX_RETRIES => {
+                        warn!(
+                            "Download of {} interrupted at byte {} ({}), resuming",
+                            self.url, self.offset, e
+                        );
+                        self.body = None;
+                        thread::sleep(backoff(failures));
+                        failures += 1;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    /// Returns `true` for failures worth retrying: network errors, timeouts, 429 and 5xx.
+    fn is_transient(error: &ureq::Error) -> bool {
+        match error {
+            ureq::Error::StatusCode(status) => *status == 429 || *status >= 500,
+            ureq::Error::Io(_) | ureq::Error::Timeout(_) | ureq::Error::ConnectionFailed => true,
+            _ => false,
+        }
+    }
+
+    /// Exponential backoff delay for the given (zero-based) attempt.
+    fn backoff(attempt: u32) -> Duration {
+        INITIAL_BACKOFF * 2u32.pow(attempt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("http://example.com/data.csv"));
+        assert!(is_url("https://example.com/data.csv"));
+        assert!(!is_url("input-samples/small.csv"));
+        assert!(!is_url("/tmp/https.csv"));
+    }
+
+    #[test]
+    fn test_open_missing_file() {
+        let err = open("does/not/exist.csv").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
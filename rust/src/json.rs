@@ -0,0 +1,364 @@
+//! Fast JSON serialization for rotated tables.
+//!
+//! Integer arrays are the only JSON the CLI ever emits, so instead of going through
+//! `serde_json::to_string` (which builds a fresh `String` per row) the digits are formatted
+//! with `itoa` straight into a caller-owned byte buffer that is reused between rows.
+//!
+//! [`JsonStyle`] selects between the compact form and the byte-exact output of the other
+//! reference implementations, so results can be `diff`ed across languages.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest magnitude a JavaScript number holds exactly (2^53).
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+/// Elements formatted on the stack before they are copied to the output in one go.
+const BLOCK_LEN: usize = 64;
+
+/// Most bytes one element takes in a block: `-9223372036854775808` and a `, ` separator.
+const MAX_ELEMENT_LEN: usize = 22;
+
+/// Magnitudes below this are copied from [`SMALL_INTS`] instead of formatted; it covers
+/// every element of the 100×100 benchmark tables.
+const SMALL_INT_LIMIT: u64 = 10_000;
+
+/// The digits of every magnitude below [`SMALL_INT_LIMIT`], left-aligned (40 KB).
+static SMALL_INTS: [[u8; 4]; SMALL_INT_LIMIT as usize] = small_ints();
+
+const fn small_ints() -> [[u8; 4]; SMALL_INT_LIMIT as usize] {
+    let mut table = [[0; 4]; SMALL_INT_LIMIT as usize];
+    let mut value = 0;
+    while value < SMALL_INT_LIMIT {
+        let len = digit_count(value);
+        let (mut rest, mut i) = (value, len);
+        while i > 0 {
+            i -= 1;
+            table[value as usize][i] = b'0' + (rest % 10) as u8;
+            rest /= 10;
+        }
+        value += 1;
+    }
+    table
+}
+
+/// Decimal digits of a magnitude below [`SMALL_INT_LIMIT`].
+const fn digit_count(magnitude: u64) -> usize {
+    1 + (magnitude >= 10) as usize + (magnitude >= 100) as usize + (magnitude >= 1000) as usize
+}
+
+/// How output arrays are serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonStyle {
+    /// `[3,1,4,2]`, as produced by the Python (`separators=(',', ':')`) and TypeScript CLIs
+    #[default]
+    Compact,
+    /// `[3, 1, 4, 2]`, Python's default `json.dumps` separators
+    Spaced,
+    /// Compact, with integers beyond 2^53 rounded like `JSON.stringify` on a JS number
+    Node,
+}
+
+impl FromStr for JsonStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compact" => Ok(JsonStyle::Compact),
+            "spaced" => Ok(JsonStyle::Spaced),
+            "node" => Ok(JsonStyle::Node),
+            _ => Err(format!(
+                "unknown JSON style '{s}' (expected compact, spaced or node)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for JsonStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JsonStyle::Compact => "compact",
+            JsonStyle::Spaced => "spaced",
+            JsonStyle::Node => "node",
+        })
+    }
+}
+
+/// Appends `values` to `out` as a compact JSON array, e.g. `[3,1,4,2]`.
+///
+/// The buffer is not cleared first, so callers can build a larger record around it.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_cli::json::write_array;
+///
+/// let mut out = Vec::new();
+/// write_array(&mut out, &[3i64, 1, -4, 2]);
+/// assert_eq!(out, b"[3,1,-4,2]");
+/// ```
+pub fn write_array<T: itoa::Integer + Copy>(out: &mut Vec<u8>, values: &[T]) {
+    let mut digits = itoa::Buffer::new();
+
+    out.push(b'[');
+    for (i, &value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        out.extend_from_slice(digits.format(value).as_bytes());
+    }
+    out.push(b']');
+}
+
+/// [`write_array`] for `i64`s, formatted in blocks: up to 64 elements are written to a stack
+/// buffer, magnitudes below 10,000 copied from a precomputed table and the others formatted
+/// with `itoa`, and each block is appended to `out` at once.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_cli::json::write_array_batched;
+///
+/// let mut out = Vec::new();
+/// write_array_batched(&mut out, &[3, 1000, -4, 2], b",");
+/// assert_eq!(out, b"[3,1000,-4,2]");
+/// ```
+///
+/// # Panics
+///
+/// If `separator` is longer than two bytes.
+pub fn write_array_batched(out: &mut Vec<u8>, values: &[i64], separator: &[u8]) {
+    let separator_len = separator.len();
+    let mut padded_separator = [0; 2];
+    padded_separator[..separator_len].copy_from_slice(separator);
+    let mut digits = itoa::Buffer::new();
+    // Room for a whole table entry after the last element
+    let mut block = [0; BLOCK_LEN * MAX_ELEMENT_LEN + 4];
+
+    out.push(b'[');
+    for (i, chunk) in values.chunks(BLOCK_LEN).enumerate() {
+        let mut len = 0;
+        for (j, &value) in chunk.iter().enumerate() {
+            // Fixed-size copies, moving on by the bytes that count
+            if i > 0 || j > 0 {
+                block[len..len + 2].copy_from_slice(&padded_separator);
+                len += separator_len;
+            }
+            let magnitude = value.unsigned_abs();
+            if magnitude < SMALL_INT_LIMIT {
+                block[len] = b'-';
+                len += usize::from(value < 0);
+                block[len..len + 4].copy_from_slice(&SMALL_INTS[magnitude as usize]);
+                len += digit_count(magnitude);
+            } else {
+                let text = digits.format(value).as_bytes();
+                block[len..len + text.len()].copy_from_slice(text);
+                len += text.len();
+            }
+        }
+        out.extend_from_slice(&block[..len]);
+    }
+    out.push(b']');
+}
+
+/// Appends `values` to `out` as a JSON array in the given style.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_cli::json::{JsonStyle, write_array_styled};
+///
+/// let mut out = Vec::new();
+/// write_array_styled(&mut out, &[3, 1, 9007199254740993], JsonStyle::Spaced);
+/// assert_eq!(out, b"[3, 1, 9007199254740993]");
+///
+/// out.clear();
+/// write_array_styled(&mut out, &[3, 1, 9007199254740993], JsonStyle::Node);
+/// assert_eq!(out, b"[3,1,9007199254740992]");
+/// ```
+pub fn write_array_styled(out: &mut Vec<u8>, values: &[i64], style: JsonStyle) {
+    match style {
+        JsonStyle::Compact => return write_array_batched(out, values, b","),
+        JsonStyle::Spaced => return write_array_batched(out, values, b", "),
+        JsonStyle::Node => {}
+    }
+    let mut digits = itoa::Buffer::new();
+
+    out.push(b'[');
+    for (i, &value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        if value.unsigned_abs() > MAX_SAFE_INTEGER {
+            // Shortest round-trip digits padded with zeros, exactly like JS number printing
+            // (no exponent below 1e21, which every i64 is)
+            use std::io::Write;
+            let _ = write!(out, "{}", value as f64);
+        } else {
+            out.extend_from_slice(digits.format(value).as_bytes());
+        }
+    }
+    out.push(b']');
+}
+
+/// A number printed as JavaScript prints it (`Number.prototype.toString`, and so
+/// `JSON.stringify`): the shortest digits that round-trip, in positional notation from 1e-6
+/// up to 1e21 and as `1.5e-7` or `1e+21` beyond, with `-0` printed as `0`. NaN and the
+/// infinities print as `null`, as in `JSON.stringify`.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_cli::json::JsNumber;
+///
+/// assert_eq!(JsNumber(1.50).to_string(), "1.5");
+/// assert_eq!(JsNumber(1e21).to_string(), "1e+21");
+/// assert_eq!(JsNumber(0.1 + 0.2).to_string(), "0.30000000000000004");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JsNumber(pub f64);
+
+impl fmt::Display for JsNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let x = self.0;
+        if !x.is_finite() {
+            return f.write_str("null");
+        }
+        if x == 0.0 {
+            return f.write_str("0");
+        }
+        if x < 0.0 {
+            f.write_str("-")?;
+        }
+        // `{:e}` gives the shortest round-trip digits as `d.ddde±x`; lay them out like JS,
+        // where the value is 0.digits × 10^point
+        let scientific = format!("{:e}", x.abs());
+        let (mantissa, exponent) = scientific
+            .split_once('e')
+            .expect("`{:e}` always has an exponent");
+        let digits = mantissa.replace('.', "");
+        let exponent: i32 = exponent.parse().expect("`{:e}` exponents are integers");
+        let (len, point) = (digits.len() as i32, exponent + 1);
+        if len <= point && point <= 21 {
+            write!(f, "{digits}{:0>1$}", "", (point - len) as usize)
+        } else if 0 < point && point <= 21 {
+            let (whole, fraction) = digits.split_at(point as usize);
+            write!(f, "{whole}.{fraction}")
+        } else if -6 < point && point <= 0 {
+            write!(f, "0.{:0>1$}{digits}", "", -point as usize)
+        } else {
+            let (first, rest) = digits.split_at(1);
+            let dot = if rest.is_empty() { "" } else { "." };
+            let sign = if exponent < 0 { '-' } else { '+' };
+            write!(f, "{first}{dot}{rest}e{sign}{}", exponent.abs())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_string<T: itoa::Integer + Copy>(values: &[T]) -> String {
+        let mut out = Vec::new();
+        write_array(&mut out, values);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_array_matches_serde_json() {
+        let cases: [&[i64]; 4] = [&[], &[42], &[3, 1, 4, 2], &[i64::MIN, -1, 0, i64::MAX]];
+        for values in cases {
+            assert_eq!(to_string(values), serde_json::to_string(values).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_write_array_batched_matches_itoa() {
+        // Crosses the small-int table edges and a block boundary
+        let mut values: Vec<i64> = (-10_100..10_100).step_by(7).collect();
+        values.extend([i64::MIN, -10_000, -9999, -10, 0, 9, 9999, 10_000, i64::MAX]);
+        for separator in [&b","[..], b", "] {
+            let mut out = Vec::new();
+            write_array_batched(&mut out, &values, separator);
+            let expected = values
+                .iter()
+                .map(i64::to_string)
+                .collect::<Vec<_>>()
+                .join(std::str::from_utf8(separator).unwrap());
+            assert_eq!(String::from_utf8(out).unwrap(), format!("[{expected}]"));
+        }
+        let mut out = Vec::new();
+        write_array_batched(&mut out, &[], b",");
+        assert_eq!(out, b"[]");
+    }
+
+    #[test]
+    fn test_styles() {
+        let values = [3, -1, 4, 2];
+        let styled = |style| {
+            let mut out = Vec::new();
+            write_array_styled(&mut out, &values, style);
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(styled(JsonStyle::Compact), "[3,-1,4,2]");
+        assert_eq!(styled(JsonStyle::Spaced), "[3, -1, 4, 2]");
+        assert_eq!(styled(JsonStyle::Node), "[3,-1,4,2]");
+    }
+
+    #[test]
+    fn test_node_style_rounds_unsafe_integers() {
+        // Expected strings are what `JSON.stringify` prints for these literals
+        let mut out = Vec::new();
+        write_array_styled(
+            &mut out,
+            &[9007199254740992, 12345678901234567, i64::MIN, i64::MAX],
+            JsonStyle::Node,
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[9007199254740992,12345678901234568,-9223372036854776000,9223372036854776000]"
+        );
+    }
+
+    #[test]
+    fn test_js_number() {
+        let cases = [
+            (2.0, "2"),
+            (-2.5, "-2.5"),
+            (-0.0, "0"),
+            (123.456, "123.456"),
+            (9007199254740993.0, "9007199254740992"),
+            (1e20, "100000000000000000000"),
+            (1.2345678901234568e20, "123456789012345680000"),
+            (1e21, "1e+21"),
+            (1.5e300, "1.5e+300"),
+            (f64::MAX, "1.7976931348623157e+308"),
+            (0.000123, "0.000123"),
+            (1e-6, "0.000001"),
+            (1e-7, "1e-7"),
+            (-1.5e-7, "-1.5e-7"),
+            (5e-324, "5e-324"),
+            (f64::NAN, "null"),
+            (f64::NEG_INFINITY, "null"),
+        ];
+        for (x, js) in cases {
+            assert_eq!(JsNumber(x).to_string(), js, "{x:e}");
+        }
+    }
+
+    #[test]
+    fn test_style_from_str() {
+        for style in [JsonStyle::Compact, JsonStyle::Spaced, JsonStyle::Node] {
+            assert_eq!(style.to_string().parse::<JsonStyle>(), Ok(style));
+        }
+        assert!("pretty".parse::<JsonStyle>().is_err());
+    }
+
+    #[test]
+    fn test_write_array_appends() {
+        let mut out = b"x=".to_vec();
+        write_array(&mut out, &[1u8, 2]);
+        assert_eq!(out, b"x=[1,2]");
+    }
+}
@@ -1,211 +1,807 @@
-use clap::Parser;
+use clap::{ArgGroup, Parser, ValueEnum};
 use csv::{ReaderBuilder, WriterBuilder};
+use duplicates::{DuplicateIds, DuplicatePolicy};
+use engine::Engine;
 use env_logger::Env;
-use rotate_cli::{rotate_right, square_len};
-use serde_json::Value;
-use std::{fs::File, io, process};
+use formats::RecordFormat;
+use output::Output;
+use render::OutputFormat;
+use rotate_cli::errors::{CliError, OnError};
+use rotate_cli::ids::{IdCheck, IdType};
+use rotate_cli::json::JsonStyle;
+use rotate_cli::parse::JsonParser;
+use rotate_cli::pipeline::{self, ObserveIds, OutputColumns, RowFormat, Sample};
+use rotate_cli::processor::{Processor, RowProcessor};
+use rotate_cli::schema::{Schema, SchemaCheck};
+use rotate_cli::tokens::{JsNumbers, RawNumbers};
+use rotate_cli::{Columns, DEFAULT_MAX_N, RowBuffers};
+use sort::{ExternalSorter, IdOrder};
+use split::SplitOptions;
+use stats::Counters;
+use std::io::{self, BufReader, BufWriter, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{process, thread};
+
+mod cloud;
+mod commands;
+mod duckdb;
+mod duplicates;
+mod engine;
+mod formats;
+mod input;
+mod metrics;
+mod output;
+mod postgres;
+mod render;
+mod report;
+mod rpc;
+mod rusage;
+#[cfg(any(feature = "kafka", feature = "redis", feature = "serve"))]
+mod shutdown;
+mod sort;
+mod split;
+mod stats;
+#[cfg(feature = "otel")]
+mod telemetry;
+
+#[cfg(feature = "count-allocs")]
+#[global_allocator]
+static ALLOCATOR: rotate_cli::counting_alloc::CountingAllocator =
+    rotate_cli::counting_alloc::CountingAllocator;
 
 /// Rotate square tables inside a CSV file shifting each element one position clockwise around its ring.
 #[derive(Parser)]
 #[command(name = "rotate_cli")]
 #[command(about = "A CLI tool to rotate square numerical tables in CSV files")]
 #[command(version = "0.1.0")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+#[command(group = ArgGroup::new("database").args(["from_postgres", "from_duckdb"]))]
 struct Cli {
-    /// Path to input CSV file with columns 'id' and 'json'
-    input: String,
+    #[command(subcommand)]
+    command: Option<commands::Command>,
+
+    /// Path or http(s) URL of the input CSV file with columns 'id' and 'json'
+    #[arg(required_unless_present_any = ["rpc", "from_postgres", "from_duckdb"])]
+    input: Option<String>,
+
+    /// Read rows from PostgreSQL (connection string) instead of a file, via COPY
+    #[arg(long, value_name = "DSN", conflicts_with = "input", requires = "query")]
+    from_postgres: Option<String>,
+
+    /// Read rows from a DuckDB database file instead of a CSV file, via the `duckdb` shell
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["input", "from_postgres"],
+        requires = "query"
+    )]
+    from_duckdb: Option<PathBuf>,
+
+    /// Query returning the `id` and `json` columns for --from-postgres or --from-duckdb
+    #[arg(long, value_name = "SQL", requires = "database")]
+    query: Option<String>,
+
+    /// COPY the results into this table of the source database instead of writing CSV
+    #[arg(
+        long,
+        value_name = "TABLE",
+        requires = "database",
+        conflicts_with = "output"
+    )]
+    to_table: Option<String>,
+
+    /// Write results to this file (or s3:// / gs:// URI) instead of stdout
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Start a new numbered output file (out-00001.csv, ...) after N rows
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "output",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    split_rows: Option<u64>,
+
+    /// Start a new numbered output file before one would grow past SIZE (e.g. 1G)
+    #[arg(long, value_name = "SIZE", requires = "output", value_parser = parse_size)]
+    split_size: Option<usize>,
+
+    /// Write valid and invalid rows to separate numbered files (out-valid-00001.csv, ...)
+    #[arg(long, requires = "output")]
+    split_by_validity: bool,
+
+    /// Number of worker threads rotating rows (defaults to the number of CPU cores)
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+
+    /// Print rows/sec and MB/s throughput to stderr when done
+    #[arg(long)]
+    stats: bool,
+
+    /// Also print progress to stderr every SECONDS while running
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    progress_interval: Option<Duration>,
+
+    /// Add an `elapsed_us` column with the microseconds spent processing each row
+    #[arg(long)]
+    timing_column: bool,
+
+    /// Print p50/p90/p99/p99.9 per-row processing latencies to stderr when done
+    #[arg(long)]
+    latency_histogram: bool,
+
+    /// Sort output rows by id (numeric by default); spills to temp files when large
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ORDER",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "numeric"
+    )]
+    sort_by_id: Option<IdOrder>,
+
+    /// Memory budget for --sort-by-id before sorted runs are spilled to disk (e.g. 512M, 2G)
+    #[arg(long, value_name = "SIZE", default_value = "256M", value_parser = parse_size)]
+    sort_memory: usize,
+
+    /// Warn about repeated ids (`report`, the default) or fail the run on them (`reject`)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "report"
+    )]
+    check_duplicate_ids: Option<DuplicatePolicy>,
+
+    /// Memory budget for --check-duplicate-ids before seen ids are spilled to disk
+    #[arg(long, value_name = "SIZE", default_value = "256M", value_parser = parse_size)]
+    duplicate_ids_memory: usize,
+
+    /// Add an `n` column with the detected table dimension (empty for invalid rows)
+    #[arg(long)]
+    emit_dimension: bool,
+
+    /// Add an `explanation` column saying what is wrong with each invalid row, such as
+    /// `15 elements: remove 6 for 3×3 or add 1 for 4×4`
+    #[arg(long)]
+    explain: bool,
+
+    /// Output columns in order, from id, json, is_valid, n, error_reason, explanation and
+    /// elapsed_us (e.g. `id,is_valid,json`), instead of `id,json,is_valid` and the optional ones
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        conflicts_with_all = [
+            "emit_dimension",
+            "explain",
+            "timing_column",
+            "sort_by_id",
+            "split_by_validity"
+        ]
+    )]
+    output_columns: Option<OutputColumns>,
+
+    /// Output array style: compact (`[1,2]`), spaced (`[1, 2]`) or node (JS number rounding)
+    #[arg(long, value_name = "STYLE", default_value_t = JsonStyle::Compact)]
+    json_style: JsonStyle,
+
+    /// Parser for the json column: serde (streaming serde_json), simd (simd-json's tape;
+    /// requires the `simd-json` feature) or fast (a scanner for arrays of plain integers that
+    /// leaves anything else to serde)
+    #[arg(long, value_name = "PARSER", default_value_t = JsonParser::Serde)]
+    parser: JsonParser,
+
+    /// Write the original json field of invalid rows instead of `[]`, so it can be inspected
+    /// or repaired downstream
+    #[arg(long)]
+    keep_original_on_invalid: bool,
+
+    /// Verify one in EVERY valid rows (default 1000) by rotating each ring back to its start,
+    /// and fail the run on a mismatch; a correctness canary for new rotation backends
+    #[arg(
+        long,
+        value_name = "EVERY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1000",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    self_check: Option<u64>,
+
+    /// What to do with a record that cannot be processed: warn and skip it, write it as an
+    /// invalid row, or fail the run
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = OnError::Skip)]
+    on_error: OnError,
+
+    /// Largest table side accepted; bigger tables become invalid rows as soon as parsing
+    /// passes N * N elements, bounding memory per row (0 = no limit)
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_N)]
+    max_n: usize,
+
+    /// Approximate memory budget for queued rows and row buffers (e.g. 512M, 2G): the reader
+    /// waits while it is used up, and a table too large for it fails the run
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_memory: Option<usize>,
+
+    /// Process only a reproducible random FRACTION of the rows (e.g. 0.01), to estimate the
+    /// validity rate and speed of a large input before a full run
+    #[arg(long, value_name = "FRACTION", value_parser = parse_ratio)]
+    sample: Option<f64>,
+
+    /// Seed choosing the --sample rows; the same input and seed always give the same rows
+    #[arg(long, default_value_t = 0, requires = "sample")]
+    seed: u64,
+
+    /// What to do with each table: rotate it, transpose it, or only validate it
+    #[arg(long, value_enum, value_name = "PROCESSOR", default_value_t = Processor::Rotate)]
+    processor: Processor,
+
+    /// Rhai script whose `fn transform(table, n)` is applied to every table after rotation
+    #[arg(long, value_name = "FILE", conflicts_with = "processor")]
+    script: Option<PathBuf>,
+
+    /// Write every number exactly as it was read (`1.50`, `1e3`, `-0`), only reordered
+    #[arg(long, conflicts_with = "script")]
+    preserve_numbers: bool,
+
+    /// Match the output of another implementation byte for byte
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with_all = ["json_style", "script", "preserve_numbers"]
+    )]
+    profile: Option<Profile>,
+
+    /// Parse the tables as integers of any size, such as 256-bit test vectors, instead of i64
+    #[arg(long, conflicts_with_all = ["script", "preserve_numbers", "profile"])]
+    bigint: bool,
+
+    /// Parse the tables as exact fixed-point decimals, such as prices, instead of i64
+    #[arg(long, conflicts_with_all = ["script", "preserve_numbers", "profile", "bigint"])]
+    decimal: bool,
+
+    /// JSON Schema for `{"id", "json"}` records; violating rows become invalid, and an
+    /// `error_reason` column tells why each invalid row is
+    #[arg(long, value_name = "FILE")]
+    schema: Option<PathBuf>,
+
+    /// Make rows whose id is not an int, a UUID or non-empty UTF-8 text invalid, and add an
+    /// `error_reason` column telling why each invalid row is
+    #[arg(long, value_enum, value_name = "TYPE")]
+    id_type: Option<IdType>,
+
+    /// Field separator of the input CSV: one ASCII character, or `tab`
+    #[arg(long, value_name = "CHAR", default_value = ",", value_parser = parse_delimiter)]
+    delimiter: u8,
+
+    /// Header names of the input columns holding the id and the table, such as `key,table`
+    /// (the first two columns otherwise)
+    #[arg(long, value_name = "ID,JSON", value_parser = parse_columns)]
+    columns: Option<Columns>,
+
+    /// Record format of the input and output streams
+    #[arg(long, value_enum, default_value_t = RecordFormat::Csv)]
+    format: RecordFormat,
+
+    /// Write CSV rows or render every table for people
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    output_format: OutputFormat,
+
+    /// Draw the first N tables before and after the rotation as grids, and write nothing else
+    #[arg(long, value_name = "N", conflicts_with_all = ["output", "output_format"])]
+    preview: Option<u64>,
+
+    /// Color the elements the rotation moved by ring: in the --preview grids and the HTML
+    /// report, or in before/after grids drawn to stderr with the other output formats
+    #[arg(long)]
+    diff: bool,
+
+    /// Row processing engine: the streaming csv pipeline or a Polars DataFrame
+    #[arg(long, value_enum, default_value_t = Engine::Csv)]
+    engine: Engine,
+
+    /// Serve JSON-RPC 2.0 requests (rotate, validate, stats) on stdin/stdout instead
+    #[arg(long, conflicts_with = "input")]
+    rpc: bool,
+
+    /// Give up on a table that takes longer than SECONDS to parse in --rpc mode and report it
+    /// as invalid with error_reason `timeout`
+    #[arg(long, value_name = "SECONDS", requires = "rpc", value_parser = parse_seconds)]
+    row_timeout: Option<Duration>,
+
+    /// Serve Prometheus metrics on HOST:PORT at /metrics while in --rpc mode
+    #[arg(long, value_name = "ADDR", requires = "rpc")]
+    metrics_addr: Option<std::net::SocketAddr>,
+}
+
+/// Output conventions of the other implementations (`--profile`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Profile {
+    /// The TypeScript CLI: any finite number, fractions included, written as `JSON.stringify`
+    /// writes it
+    Node,
+}
+
+impl Cli {
+    fn split(&self) -> SplitOptions {
+        SplitOptions {
+            rows: self.split_rows,
+            bytes: self.split_size.map(|size| size as u64),
+            by_validity: self.split_by_validity,
+        }
+    }
 }
 
 fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+    #[cfg(feature = "otel")]
+    let exporter = telemetry::init();
 
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
+    let result = run();
+    // Sends the last spans before exiting
+    #[cfg(feature = "otel")]
+    drop(exporter);
+    if let Err(e) = result {
+        let e = CliError::classify(e);
+        eprintln!("Error [{} {}]: {}", e.code, e.code.name(), e);
         process::exit(1);
     }
 }
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
+fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let cli = Cli::parse();
+    if let Some(command) = cli.command {
+        return commands::run(command);
+    }
+    if cli.parser == JsonParser::Simd && !cfg!(feature = "simd-json") {
+        let message = "--parser simd requires building with `--features simd-json`";
+        return Err(io::Error::new(io::ErrorKind::Unsupported, message).into());
+    }
+    if cli.rpc {
+        let metrics = metrics::Metrics::serve(cli.metrics_addr)?;
+        let mut buffers = row_buffers(cli.max_n, cli.row_timeout);
+        buffers.parser = cli.parser;
+        return Ok(rpc::serve(
+            io::stdin().lock(),
+            io::stdout().lock(),
+            buffers,
+            metrics,
+        )?);
+    }
+    if cli.output_format != OutputFormat::Csv || cli.preview.is_some() || cli.diff {
+        return render::run(&cli);
+    }
+    if cli.format != RecordFormat::Csv {
+        return formats::run(&cli);
+    }
+    if cli.engine == Engine::Polars {
+        return engine::run_polars(&cli);
+    }
 
-    // Open input CSV file
-    let file = File::open(&cli.input)?;
+    // Compile the script before any output is created
+    let script = cli.script.as_deref().map(load_script).transpose()?;
+    let bigint = cli
+        .bigint
+        .then(|| bigint_processor(cli.processor))
+        .transpose()?;
+    let decimal = cli
+        .decimal
+        .then(|| decimal_processor(cli.processor))
+        .transpose()?;
+    let preserved = cli.preserve_numbers.then(|| RawNumbers::new(cli.processor));
+    let node = (cli.profile == Some(Profile::Node)).then(|| JsNumbers::new(cli.processor));
+    let processor = script
+        .as_deref()
+        .or(preserved.as_ref().map(|raw| raw as &dyn RowProcessor))
+        .or(node.as_ref().map(|node| node as &dyn RowProcessor))
+        .or(bigint.as_deref())
+        .or(decimal.as_deref())
+        .unwrap_or(cli.processor.get());
+    let schema = cli.schema.as_deref().map(Schema::load).transpose()?;
+    let schema = schema.map(|schema| SchemaCheck::new(schema, processor));
+    let processor: &dyn RowProcessor = match &schema {
+        Some(check) => check,
+        None => processor,
+    };
+    let id_check = cli.id_type.map(|id_type| IdCheck::new(id_type, processor));
+    let processor: &dyn RowProcessor = match &id_check {
+        Some(check) => check,
+        None => processor,
+    };
+
+    // Open input CSV file (or stream it from a URL or a database query)
+    let source = match (&cli.from_postgres, &cli.from_duckdb, &cli.query) {
+        (Some(dsn), _, Some(query)) => input::open_postgres(dsn, query)?,
+        (_, Some(file), Some(query)) => input::open_duckdb(file, query)?,
+        _ => input::open(cli.input.as_deref().ok_or("missing input argument")?)?,
+    };
     let mut rdr = ReaderBuilder::new()
         .has_headers(true)
+        .delimiter(cli.delimiter)
         .flexible(true)
-        .from_reader(file);
+        .from_reader(source);
+    let (id_column, json_column) = match &cli.columns {
+        Some(columns) => columns.resolve(Some(rdr.byte_headers()?))?,
+        None => (0, 1),
+    };
 
-    // Create CSV writer to stdout
-    let mut wtr = WriterBuilder::new()
-        .has_headers(true)
-        .from_writer(io::stdout());
+    // Create CSV writer to stdout, the --output destination or the --to-table table
+    let format = RowFormat {
+        emit_dimension: cli.emit_dimension,
+        json_style: cli.json_style,
+        keep_original: cli.keep_original_on_invalid,
+        self_check: cli.self_check,
+        on_error: cli.on_error,
+        id_column,
+        json_column,
+        max_n: max_n_limit(cli.max_n),
+        parser: cli.parser,
+        max_memory: cli.max_memory,
+        sample: cli.sample.map(|fraction| Sample {
+            fraction,
+            seed: cli.seed,
+        }),
+        emit_reason: cli.schema.is_some() || cli.id_type.is_some(),
+        emit_explanation: cli.explain,
+        emit_timing: cli.timing_column,
+        output_columns: cli.output_columns,
+        record_latencies: cli.latency_histogram,
+    };
+    let output = match (&cli.from_postgres, &cli.from_duckdb, &cli.to_table) {
+        (Some(dsn), _, Some(table)) => Output::postgres_table(dsn, table, &format.header())?,
+        (_, Some(file), Some(table)) => Output::duckdb_table(file, table, &format.header())?,
+        _ if cli.split().is_enabled() => {
+            let location = cli
+                .output
+                .as_deref()
+                .ok_or("--split-* options need --output")?;
+            Output::split(location, cli.split())
+        }
+        _ => Output::create(cli.output.as_deref())?,
+    };
+    let mut wtr = WriterBuilder::new().has_headers(true).from_writer(output);
 
     // Write output headers
-    wtr.write_record(["id", "json", "is_valid"])?;
+    wtr.write_record(format.header())?;
+    let output = wtr.into_inner().map_err(|e| e.into_error())?;
 
-    // Process each record
-    for result in rdr.records() {
-        let record = result?;
-
-        // Ensure we have at least 2 fields (id and json)
-        if record.len() < 2 {
-            eprintln!("Warning: Skipping record with insufficient fields");
-            continue;
+    // Process records on the reader → workers → writer pipeline
+    let workers = cli.threads.unwrap_or_else(default_threads);
+    let counters = &Counters::default();
+    let started = Instant::now();
+    let file = tracing::info_span!(
+        "file",
+        path = cli.input.as_deref().unwrap_or("-"),
+        workers,
+        traceparent = std::env::var("TRACEPARENT").unwrap_or_default(),
+    );
+    let _file = file.enter();
+    let result = thread::scope(|s| {
+        let (stop_tx, stop_rx) = crossbeam_channel::bounded::<()>(0);
+        if let Some(interval) = cli.progress_interval {
+            s.spawn(move || stats::report_progress(counters, started, interval, stop_rx));
         }
+        let result = process(rdr, output, workers, format, processor, counters, &cli);
+        drop(stop_tx);
+        result
+    })
+    .and_then(|output| Ok(output.finish()?));
 
-        let id = &record[0];
-        let json_text = &record[1];
+    // A failed run still reports how far it got
+    if cli.stats {
+        stats::print(counters.snapshot(started.elapsed()));
+    }
+    if cli.latency_histogram {
+        eprintln!("Latency: {}", counters.latencies());
+    }
+    result
+}
 
-        // Process the JSON and determine validity
-        let (rotated_json, is_valid) = process_json_array(json_text);
+/// Runs the pipeline, routing rows through the external sorter when `--sort-by-id` is set.
+///
+/// Duplicate ids are resolved before the output is finalized, so a rejected run never
+/// completes an object upload.
+fn process<R: Read + Send>(
+    rdr: csv::Reader<R>,
+    output: Output,
+    workers: usize,
+    format: RowFormat,
+    processor: &dyn RowProcessor,
+    counters: &Counters,
+    cli: &Cli,
+) -> Result<Output, Box<dyn std::error::Error + Send + Sync>> {
+    let mut ids = cli
+        .check_duplicate_ids
+        .map(|policy| DuplicateIds::new(policy, cli.duplicate_ids_memory));
 
-        // Write output record
-        wtr.write_record([id, &rotated_json, if is_valid { "true" } else { "false" }])?;
+    let Some(order) = cli.sort_by_id else {
+        let output = pipeline::run(
+            rdr,
+            output,
+            workers,
+            format,
+            processor,
+            counters,
+            ids.as_mut().map(|ids| ids as &mut dyn ObserveIds),
+        )?;
+        if let Some(ids) = ids {
+            ids.finish()?;
+        }
+        return Ok(output);
+    };
+
+    // Rows are spooled unsorted to a temp file first, then sorted with bounded memory
+    let spool = pipeline::run(
+        rdr,
+        BufWriter::new(tempfile::tempfile()?),
+        workers,
+        format,
+        processor,
+        counters,
+        ids.as_mut().map(|ids| ids as &mut dyn ObserveIds),
+    )?;
+    if let Some(ids) = ids {
+        ids.finish()?;
     }
+    let mut spool = spool.into_inner().map_err(|e| e.into_error())?;
+    spool.rewind()?;
 
-    wtr.flush()?;
-    Ok(())
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(BufReader::new(spool));
+    let mut wtr = WriterBuilder::new().from_writer(output);
+    ExternalSorter::new(order, cli.sort_memory).sort(&mut rdr, &mut wtr)?;
+    Ok(wtr.into_inner().map_err(|e| e.into_error())?)
 }
 
-/// Process a JSON string containing an array of numbers.
-/// Returns (json_string, is_valid) where json_string is either the rotated array or empty array.
-fn process_json_array(json_text: &str) -> (String, bool) {
-    // Try to parse JSON
-    let parsed_value = match serde_json::from_str::<Value>(json_text) {
-        Ok(value) => value,
-        Err(_) => return ("[]".to_string(), false),
-    };
+/// The `--script` processor.
+#[cfg(feature = "script")]
+fn load_script(
+    path: &Path,
+) -> Result<Box<dyn RowProcessor>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(Box::new(rotate_cli::script::ScriptProcessor::load(path)?))
+}
 
-    // Ensure it's an array
-    let array = match parsed_value {
-        Value::Array(arr) => arr,
-        _ => return ("[]".to_string(), false),
+#[cfg(not(feature = "script"))]
+fn load_script(
+    _path: &Path,
+) -> Result<Box<dyn RowProcessor>, Box<dyn std::error::Error + Send + Sync>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--script requires building with `--features script`",
+    )
+    .into())
+}
+
+/// The `--bigint` version of `processor`.
+#[cfg(feature = "bigint")]
+fn bigint_processor(
+    processor: Processor,
+) -> Result<Box<dyn RowProcessor>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(Box::new(rotate_cli::bigint::BigIntProcessor::new(
+        processor,
+    )))
+}
+
+#[cfg(not(feature = "bigint"))]
+fn bigint_processor(
+    _processor: Processor,
+) -> Result<Box<dyn RowProcessor>, Box<dyn std::error::Error + Send + Sync>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--bigint requires building with `--features bigint`",
+    )
+    .into())
+}
+
+/// The `--decimal` version of `processor`.
+#[cfg(feature = "decimal")]
+fn decimal_processor(
+    processor: Processor,
+) -> Result<Box<dyn RowProcessor>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(Box::new(rotate_cli::decimal::DecimalProcessor::new(
+        processor,
+    )))
+}
+
+#[cfg(not(feature = "decimal"))]
+fn decimal_processor(
+    _processor: Processor,
+) -> Result<Box<dyn RowProcessor>, Box<dyn std::error::Error + Send + Sync>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--decimal requires building with `--features decimal`",
+    )
+    .into())
+}
+
+/// The table side cap of a `--max-n` value, where 0 means no cap.
+fn max_n_limit(max_n: usize) -> Option<usize> {
+    (max_n > 0).then_some(max_n)
+}
+
+/// Row buffers enforcing `--max-n` and `--row-timeout`.
+fn row_buffers(max_n: usize, row_timeout: Option<Duration>) -> RowBuffers {
+    let mut buffers = RowBuffers::with_max_n(max_n_limit(max_n));
+    buffers.timeout = row_timeout;
+    buffers
+}
+
+/// Parses a byte size such as `512M`, `2G`, `64k` or a plain number of bytes.
+fn parse_size(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (digits, unit) = text.split_at(split);
+    let value: usize = digits
+        .parse()
+        .map_err(|_| format!("invalid size '{text}'"))?;
+    let multiplier: usize = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => return Err(format!("invalid size unit in '{text}' (use K, M or G)")),
     };
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size '{text}' is too large"))
+}
 
-    // Convert to numbers
-    let mut numbers: Vec<i64> = Vec::with_capacity(array.len());
-    for value in array {
-        match value {
-            Value::Number(num) => {
-                if let Some(int_val) = num.as_i64() {
-                    numbers.push(int_val);
-                } else if let Some(float_val) = num.as_f64() {
-                    // Handle float numbers by converting to int if they're whole numbers
-                    if float_val.fract() == 0.0 {
-                        numbers.push(float_val as i64);
-                    } else {
-                        return ("[]".to_string(), false);
-                    }
-                } else {
-                    return ("[]".to_string(), false);
-                }
-            }
-            _ => return ("[]".to_string(), false),
-        }
+/// Parses a `--delimiter`: a single ASCII character other than a quote or line break, or
+/// `tab` (also `\t`).
+fn parse_delimiter(text: &str) -> Result<u8, String> {
+    match text {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ => match text.as_bytes() {
+            [byte] if byte.is_ascii() && !matches!(byte, b'"' | b'\n' | b'\r') => Ok(*byte),
+            _ => Err(format!("'{text}' is not a single-character delimiter")),
+        },
     }
+}
 
-    // Check if it can form a square table
-    if square_len(numbers.len()).is_none() {
-        return ("[]".to_string(), false);
+/// Parses `--columns ID,JSON` into the two header names.
+fn parse_columns(text: &str) -> Result<Columns, String> {
+    match text.split_once(',') {
+        Some((id, json)) if !id.is_empty() && !json.is_empty() => Ok(Columns::named(id, json)),
+        _ => Err(format!(
+            "'{text}' is not two column names separated by a comma"
+        )),
     }
+}
 
-    // If empty array, it's technically a 0x0 square but we treat as invalid per spec
-    if numbers.is_empty() {
-        return ("[]".to_string(), false);
+/// Parses a ratio between 0 and 1, such as `--sample` or `generate --invalid-ratio`.
+fn parse_ratio(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(format!("'{text}' is not a ratio between 0 and 1")),
     }
+}
 
-    // Rotate the table
-    match rotate_right(&mut numbers) {
-        Ok(()) => {
-            // Convert back to JSON
-            let json_result = serde_json::to_string(&numbers).unwrap_or_else(|_| "[]".to_string());
-            (json_result, true)
-        }
-        Err(_) => ("[]".to_string(), false),
+/// Parses a positive number of seconds, such as `--row-timeout 0.5`.
+fn parse_seconds(text: &str) -> Result<Duration, String> {
+    match text.parse::<f64>().map(Duration::try_from_secs_f64) {
+        Ok(Ok(duration)) if !duration.is_zero() => Ok(duration),
+        _ => Err(format!("'{text}' is not a positive number of seconds")),
     }
 }
 
+/// Number of worker threads when `--threads` is not given: one per available core.
+fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_process_valid_2x2() {
-        // Original:        After 1-step clockwise:
-        // [1, 2]       →   [3, 1]
-        // [3, 4]           [4, 2]
-        // Ring: 1→2→4→3 becomes 3→1→2→4
-        // Expected JSON: "[3,1,4,2]"
-        let (result, valid) = process_json_array("[1, 2, 3, 4]");
-        assert!(valid);
-        assert_eq!(result, "[3,1,4,2]");
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("64k"), Ok(64 << 10));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size("2GB"), Ok(2 << 30));
+        assert!(parse_size("12X").is_err());
+        assert!(parse_size("M").is_err());
     }
 
     #[test]
-    fn test_process_valid_3x3() {
-        // Original:           After 1-step clockwise:
-        // [1, 2, 3]       →   [4, 1, 2]
-        // [4, 5, 6]           [7, 5, 3]
-        // [7, 8, 9]           [8, 9, 6]
-        // Ring: 1→2→3→6→9→8→7→4 becomes 4→1→2→3→6→9→8→7, center 5 unchanged
-        // Expected JSON: "[4,1,2,7,5,3,8,9,6]"
-        let (result, valid) = process_json_array("[1, 2, 3, 4, 5, 6, 7, 8, 9]");
-        assert!(valid);
-        assert_eq!(result, "[4,1,2,7,5,3,8,9,6]");
+    fn test_parse_ratio() {
+        assert_eq!(parse_ratio("0.01"), Ok(0.01));
+        assert_eq!(parse_ratio("1"), Ok(1.0));
+        assert!(parse_ratio("1.5").is_err());
+        assert!(parse_ratio("-0.1").is_err());
+        assert!(parse_ratio("NaN").is_err());
+        assert!(parse_ratio("x").is_err());
     }
 
     #[test]
-    fn test_process_valid_1x1() {
-        // Original: [42]  →  After: [42] (single element unchanged)
-        // Expected JSON: "[42]"
-        let (result, valid) = process_json_array("[42]");
-        assert!(valid);
-        assert_eq!(result, "[42]");
+    fn test_parse_seconds() {
+        assert_eq!(parse_seconds("0.5"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_seconds("30"), Ok(Duration::from_secs(30)));
+        for text in ["0", "-1", "NaN", "inf", "1e300", "1e-10", "x"] {
+            assert!(parse_seconds(text).is_err(), "{text}");
+        }
     }
 
     #[test]
-    fn test_process_invalid_non_square() {
-        let (result, valid) = process_json_array("[1, 2, 3]");
-        assert!(!valid);
-        assert_eq!(result, "[]");
+    fn test_row_timeout_must_be_positive() {
+        for value in ["--row-timeout=-1", "--row-timeout=NaN"] {
+            let error = Cli::try_parse_from(["rotate_cli", "--rpc", value])
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        }
+        let cli = Cli::try_parse_from(["rotate_cli", "--rpc", "--row-timeout=0.25"]).unwrap();
+        assert_eq!(cli.row_timeout, Some(Duration::from_millis(250)));
     }
 
     #[test]
-    fn test_process_invalid_empty() {
-        let (result, valid) = process_json_array("[]");
-        assert!(!valid);
-        assert_eq!(result, "[]");
+    fn test_progress_interval_must_be_positive() {
+        for value in [
+            "--progress-interval=-1",
+            "--progress-interval=0",
+            "--progress-interval=NaN",
+        ] {
+            let error = Cli::try_parse_from(["rotate_cli", value, "in.csv"])
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+        }
+        let cli = Cli::try_parse_from(["rotate_cli", "--progress-interval=2", "in.csv"]).unwrap();
+        assert_eq!(cli.progress_interval, Some(Duration::from_secs(2)));
     }
 
     #[test]
-    fn test_process_invalid_non_array() {
-        let (result, valid) = process_json_array("42");
-        assert!(!valid);
-        assert_eq!(result, "[]");
-    }
+    fn test_database_sources() {
+        let args = [
+            "rotate_cli",
+            "--from-duckdb",
+            "bench.duckdb",
+            "--query",
+            "SELECT 1",
+        ];
+        let cli = Cli::try_parse_from(args.into_iter().chain(["--to-table", "rotated"])).unwrap();
+        assert_eq!(cli.from_duckdb, Some(PathBuf::from("bench.duckdb")));
+        assert_eq!(cli.to_table.as_deref(), Some("rotated"));
 
-    #[test]
-    fn test_process_invalid_non_numeric() {
-        let (result, valid) = process_json_array("[1, \"hello\", 3]");
-        assert!(!valid);
-        assert_eq!(result, "[]");
+        let error = Cli::try_parse_from(args.into_iter().chain(["--from-postgres", "host=db"]))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+        let error = Cli::try_parse_from(["rotate_cli", "in.csv", "--to-table", "rotated"])
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
     }
 
     #[test]
-    fn test_process_malformed_json() {
-        let (result, valid) = process_json_array("[1, 2,");
-        assert!(!valid);
-        assert_eq!(result, "[]");
+    fn test_parse_delimiter() {
+        assert_eq!(parse_delimiter(";"), Ok(b';'));
+        assert_eq!(parse_delimiter("tab"), Ok(b'\t'));
+        assert_eq!(parse_delimiter("\\t"), Ok(b'\t'));
+        assert!(parse_delimiter("\"").is_err());
+        assert!(parse_delimiter(";;").is_err());
+        assert!(parse_delimiter("é").is_err());
     }
 
     #[test]
-    fn test_process_with_negative_numbers() {
-        // Original:         After 1-step clockwise:
-        // [-1, -2]      →   [-3, -1]
-        // [-3, -4]          [-4, -2]
-        // Ring: -1→-2→-4→-3 becomes -3→-1→-2→-4
-        // Expected JSON: "[-3,-1,-4,-2]"
-        let (result, valid) = process_json_array("[-1, -2, -3, -4]");
-        assert!(valid);
-        assert_eq!(result, "[-3,-1,-4,-2]");
+    fn test_parse_columns() {
+        assert_eq!(
+            parse_columns("key,table"),
+            Ok(Columns::named("key", "table"))
+        );
+        assert!(parse_columns("key").is_err());
+        assert!(parse_columns(",table").is_err());
     }
 }
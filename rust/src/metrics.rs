@@ -0,0 +1,230 @@
+//! Prometheus metrics for the long-running modes (`--rpc`, `kafka`, `redis`, `serve`).
+//!
+//! With `--metrics-addr HOST:PORT` a background thread answers `GET /metrics` with the
+//! Prometheus text exposition format, so every implementation can be scraped and graphed
+//! side by side. The endpoint is served by a minimal HTTP/1.0 responder on a plain
+//! `TcpListener`; scrapes are rare and tiny, so nothing more is needed.
+
+use rotate_cli::counters::Summary;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Upper bounds of the row latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 8] = [1e-6, 1e-5, 1e-4, 1e-3, 1e-2, 0.1, 1.0, 10.0];
+
+/// Counters shared between the processing loop and the metrics endpoint.
+#[derive(Default)]
+pub struct Metrics {
+    rows: AtomicU64,
+    invalid: AtomicU64,
+    in_flight: AtomicI64,
+    /// Cumulative counts per bucket of `LATENCY_BUCKETS`
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    latency_nanos: AtomicU64,
+}
+
+impl Metrics {
+    /// Starts serving the metrics on `addr` when given.
+    pub fn serve(addr: Option<SocketAddr>) -> io::Result<Option<Arc<Self>>> {
+        let Some(addr) = addr else {
+            return Ok(None);
+        };
+        let listener = TcpListener::bind(addr)?;
+        log::info!(
+            "Serving metrics on http://{}/metrics",
+            listener.local_addr()?
+        );
+
+        let metrics = Arc::new(Self::default());
+        let shared = Arc::clone(&metrics);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(|stream| respond(stream, &shared));
+                if let Err(e) = result {
+                    log::warn!("Metrics request failed: {e}");
+                }
+            }
+        });
+        Ok(Some(metrics))
+    }
+
+    /// Records one processed row and how long it took.
+    pub fn record_row(&self, is_valid: bool, latency: Duration) {
+        self.rows.fetch_add(1, Ordering::Relaxed);
+        if !is_valid {
+            self.invalid.fetch_add(1, Ordering::Relaxed);
+        }
+        let seconds = latency.as_secs_f64();
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&self.latency_buckets) {
+            if seconds <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.latency_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Counts `count` requests or messages as in flight until the guard is dropped.
+    pub fn in_flight(&self, count: usize) -> InFlight<'_> {
+        let count = count as i64;
+        self.in_flight.fetch_add(count, Ordering::Relaxed);
+        InFlight {
+            metrics: self,
+            count,
+        }
+    }
+
+    /// The rows processed so far, as the `--stats` summary of `serve` reports them.
+    #[cfg_attr(not(feature = "serve"), allow(dead_code))]
+    pub fn summary(&self, elapsed: Duration) -> Summary {
+        let rows = self.rows.load(Ordering::Relaxed);
+        Summary {
+            rows,
+            valid: rows - self.invalid.load(Ordering::Relaxed),
+            elapsed,
+            ..Summary::default()
+        }
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let rows = self.rows.load(Ordering::Relaxed);
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# HELP rotate_cli_rows_total Rows processed.\n\
+             # TYPE rotate_cli_rows_total counter\n\
+             rotate_cli_rows_total {rows}\n\
+             # HELP rotate_cli_invalid_rows_total Rows whose table could not be rotated.\n\
+             # TYPE rotate_cli_invalid_rows_total counter\n\
+             rotate_cli_invalid_rows_total {}\n\
+             # HELP rotate_cli_in_flight Requests or messages currently being processed.\n\
+             # TYPE rotate_cli_in_flight gauge\n\
+             rotate_cli_in_flight {}\n\
+             # HELP rotate_cli_row_duration_seconds Time to parse, rotate and serialize a row.\n\
+             # TYPE rotate_cli_row_duration_seconds histogram",
+            self.invalid.load(Ordering::Relaxed),
+            self.in_flight.load(Ordering::Relaxed),
+        );
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&self.latency_buckets) {
+            let _ = writeln!(
+                out,
+                "rotate_cli_row_duration_seconds_bucket{{le=\"{bound}\"}} {}",
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+        let seconds = self.latency_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        let _ = writeln!(
+            out,
+            "rotate_cli_row_duration_seconds_bucket{{le=\"+Inf\"}} {rows}\n\
+             rotate_cli_row_duration_seconds_sum {seconds}\n\
+             rotate_cli_row_duration_seconds_count {rows}"
+        );
+        out
+    }
+}
+
+/// Guard returned by [`Metrics::in_flight`].
+pub struct InFlight<'a> {
+    metrics: &'a Metrics,
+    count: i64,
+}
+
+impl InFlight<'_> {
+    /// Counts `count` more items as in flight.
+    #[cfg_attr(not(feature = "kafka"), allow(dead_code))]
+    pub fn add(&mut self, count: usize) {
+        self.metrics
+            .in_flight
+            .fetch_add(count as i64, Ordering::Relaxed);
+        self.count += count as i64;
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.metrics
+            .in_flight
+            .fetch_sub(self.count, Ordering::Relaxed);
+    }
+}
+
+/// Answers a single HTTP request and closes the connection.
+fn respond(stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers so the client sees an orderly close
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let (status, content_type, body) = match request_line.split_whitespace().nth(1) {
+        Some("/metrics") => ("200 OK", "text/plain; version=0.0.4", metrics.render()),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.0 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.record_row(true, Duration::from_micros(5));
+        metrics.record_row(false, Duration::from_millis(5));
+        let mut guard = metrics.in_flight(1);
+        guard.add(2);
+
+        let text = metrics.render();
+        assert!(text.contains("rotate_cli_rows_total 2\n"));
+        assert!(text.contains("rotate_cli_invalid_rows_total 1\n"));
+        assert!(text.contains("rotate_cli_in_flight 3\n"));
+        assert!(text.contains("rotate_cli_row_duration_seconds_bucket{le=\"0.00001\"} 1\n"));
+        assert!(text.contains("rotate_cli_row_duration_seconds_bucket{le=\"0.01\"} 2\n"));
+        assert!(text.contains("rotate_cli_row_duration_seconds_count 2\n"));
+
+        drop(guard);
+        assert!(metrics.render().contains("rotate_cli_in_flight 0\n"));
+    }
+
+    #[test]
+    fn test_respond() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(b"GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n")
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        let metrics = Metrics::default();
+        metrics.record_row(true, Duration::ZERO);
+        let (stream, _) = listener.accept().unwrap();
+        respond(stream, &metrics).unwrap();
+
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(response.ends_with(&metrics.render()));
+    }
+}
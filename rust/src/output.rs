@@ -0,0 +1,148 @@
+//! Output destinations for the CLI.
+//!
+//! Results go to stdout by default, or to the `--output` location: a local file path or,
+//! when built with the `object-store` feature, an `s3://` / `gs://` URI. The `--split-*`
+//! options spread it over several numbered files.
+
+use crate::cloud;
+use crate::split::{SplitOptions, SplitOutput};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Destination the CSV writer streams results into.
+pub enum Output {
+    Stdout(io::Stdout),
+    File(File),
+    #[cfg(feature = "object-store")]
+    Object(Box<cloud::ObjectWriter>),
+    #[cfg(feature = "postgres")]
+    Postgres(Box<crate::postgres::CopyWriter>),
+    #[cfg(feature = "duckdb")]
+    DuckDb(Box<crate::duckdb::TableWriter>),
+    Split(Box<SplitOutput>),
+}
+
+impl Output {
+    /// Opens the destination named by `--output`, or stdout when absent.
+    pub fn create(location: Option<&str>) -> io::Result<Self> {
+        let Some(location) = location else {
+            return Ok(Output::Stdout(io::stdout()));
+        };
+
+        if cloud::is_object_uri(location) {
+            return create_object(location);
+        }
+
+        Ok(Output::File(File::create(location)?))
+    }
+
+    /// Numbered files next to `location`, as `options` say.
+    pub fn split(location: &str, options: SplitOptions) -> Self {
+        Output::Split(Box::new(SplitOutput::new(location, options)))
+    }
+
+    /// Streams rows into a PostgreSQL table with `COPY ... FROM STDIN` (`--to-table`).
+    pub fn postgres_table(dsn: &str, table: &str, columns: &[&str]) -> io::Result<Self> {
+        create_postgres(dsn, table, columns)
+    }
+
+    /// Loads rows into a DuckDB table with `COPY ... FROM` once the run ends (`--to-table`).
+    pub fn duckdb_table(file: &Path, table: &str, columns: &[&str]) -> io::Result<Self> {
+        create_duckdb(file, table, columns)
+    }
+
+    /// Flushes remaining data and, for object stores and tables, completes the upload.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Stdout(mut out) => out.flush(),
+            Output::File(mut file) => file.flush(),
+            #[cfg(feature = "object-store")]
+            Output::Object(writer) => writer.finish(),
+            #[cfg(feature = "postgres")]
+            Output::Postgres(writer) => writer.finish(),
+            #[cfg(feature = "duckdb")]
+            Output::DuckDb(writer) => writer.finish(),
+            Output::Split(split) => split.finish(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::File(file) => file.write(buf),
+            #[cfg(feature = "object-store")]
+            Output::Object(writer) => writer.write(buf),
+            #[cfg(feature = "postgres")]
+            Output::Postgres(writer) => writer.write(buf),
+            #[cfg(feature = "duckdb")]
+            Output::DuckDb(writer) => writer.write(buf),
+            Output::Split(split) => split.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::File(file) => file.flush(),
+            #[cfg(feature = "object-store")]
+            Output::Object(writer) => writer.flush(),
+            #[cfg(feature = "postgres")]
+            Output::Postgres(writer) => writer.flush(),
+            #[cfg(feature = "duckdb")]
+            Output::DuckDb(writer) => writer.flush(),
+            Output::Split(split) => split.flush(),
+        }
+    }
+}
+
+#[cfg(feature = "object-store")]
+fn create_object(uri: &str) -> io::Result<Output> {
+    Ok(Output::Object(Box::new(cloud::create_writer(uri)?)))
+}
+
+#[cfg(not(feature = "object-store"))]
+fn create_object(uri: &str) -> io::Result<Output> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "cannot write {uri}: object-store output requires building with `--features object-store`"
+        ),
+    ))
+}
+
+#[cfg(feature = "postgres")]
+fn create_postgres(dsn: &str, table: &str, columns: &[&str]) -> io::Result<Output> {
+    Ok(Output::Postgres(Box::new(crate::postgres::create_writer(
+        dsn, table, columns,
+    )?)))
+}
+
+#[cfg(not(feature = "postgres"))]
+fn create_postgres(_dsn: &str, table: &str, _columns: &[&str]) -> io::Result<Output> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "cannot write to table {table}: --to-table requires building with `--features postgres`"
+        ),
+    ))
+}
+
+#[cfg(feature = "duckdb")]
+fn create_duckdb(file: &Path, table: &str, columns: &[&str]) -> io::Result<Output> {
+    Ok(Output::DuckDb(Box::new(crate::duckdb::create_writer(
+        file, table, columns,
+    )?)))
+}
+
+#[cfg(not(feature = "duckdb"))]
+fn create_duckdb(_file: &Path, table: &str, _columns: &[&str]) -> io::Result<Output> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "cannot write to table {table}: --to-table requires building with `--features duckdb`"
+        ),
+    ))
+}
@@ -0,0 +1,602 @@
+//! Streaming parser for the `json` column.
+//!
+//! Numbers are deserialized straight into a `Vec<i64>` through a custom serde visitor
+//! instead of building a `serde_json::Value` tree first. The first non-numeric token
+//! aborts parsing, so invalid rows cost no more than the prefix that had to be read.
+//!
+//! [`parse_numbers_fast`] scans the common case, a flat array of plain integers, byte by
+//! byte and hands anything else to the serde visitor. With the `simd-json` feature,
+//! [`SimdParser`] offers the same semantics on top of simd-json's tape.
+//! `cargo bench --bench rotation_bench --features simd-json -- row_parsing` compares them on
+//! a realistic mix of rows; [`JsonParser`] picks one for the CLI.
+
+use serde::de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+/// Elements parsed between two checks of [`Limits::deadline`].
+const DEADLINE_CHECK_INTERVAL: usize = 1024;
+
+/// Which parser reads the `json` column (`--parser`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonParser {
+    /// The streaming serde_json visitor
+    #[default]
+    Serde,
+    /// [`SimdParser`]; without the `simd-json` feature, rows are parsed with serde_json
+    Simd,
+    /// [`parse_numbers_fast`]
+    Fast,
+}
+
+impl FromStr for JsonParser {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "serde" => Ok(JsonParser::Serde),
+            "simd" => Ok(JsonParser::Simd),
+            "fast" => Ok(JsonParser::Fast),
+            _ => Err(format!(
+                "unknown parser '{s}' (expected serde, simd or fast)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for JsonParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JsonParser::Serde => "serde",
+            JsonParser::Simd => "simd",
+            JsonParser::Fast => "fast",
+        })
+    }
+}
+
+/// Parses a JSON array of integers into `out`, replacing its previous contents.
+///
+/// Whole-number floats such as `2.0` are accepted and converted, matching the CSV
+/// semantics of the other implementations. Returns `false` for malformed JSON, a
+/// non-array value, or any element that is not an integral number.
+pub fn parse_numbers(json_text: &str, out: &mut Vec<i64>) -> bool {
+    parse_numbers_limited(json_text, out, Limits::default()).is_ok()
+}
+
+/// Bounds on the work [`parse_numbers_limited`] may do for one array.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    /// Most elements the array may have
+    pub max_len: Option<usize>,
+    /// When to give up, checked every 1024 elements
+    pub deadline: Option<Instant>,
+}
+
+impl Limits {
+    /// Whether parsing must stop now that `len` elements have been read.
+    fn check(&self, len: usize) -> Option<ParseError> {
+        if self.max_len.is_some_and(|max_len| len > max_len) {
+            return Some(ParseError::TooLong);
+        }
+        let timed_out = len.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+        timed_out.then_some(ParseError::TimedOut)
+    }
+}
+
+/// Why [`parse_numbers_limited`] rejected its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// Not a JSON array of integral numbers
+    Invalid,
+    /// The array has more than `max_len` elements
+    TooLong,
+    /// The deadline passed before the array was parsed
+    TimedOut,
+}
+
+/// [`parse_numbers`] within `limits`: an oversized array never grows `out` past
+/// `max_len + 1` elements, and a slow one is abandoned soon after the deadline.
+pub fn parse_numbers_limited(
+    json_text: &str,
+    out: &mut Vec<i64>,
+    limits: Limits,
+) -> Result<(), ParseError> {
+    out.clear();
+    let mut de = serde_json::Deserializer::from_str(json_text);
+    let mut stopped = None;
+    let parsed = NumberArray {
+        out,
+        limits,
+        stopped: &mut stopped,
+    }
+    .deserialize(&mut de);
+    if let Some(error) = stopped {
+        return Err(error);
+    }
+    if parsed.is_ok() && de.end().is_ok() {
+        Ok(())
+    } else {
+        Err(ParseError::Invalid)
+    }
+}
+
+/// [`parse_numbers_limited`] for the restricted grammar real rows use: `[`, integers that fit
+/// an `i64` separated by commas, `]`, with JSON whitespace anywhere between tokens.
+///
+/// The first byte outside that grammar (a fraction, an exponent, a leading zero, an integer
+/// out of range, a syntax error) sends the whole text to [`parse_numbers_limited`], so the
+/// result is always the one the serde visitor gives.
+pub fn parse_numbers_fast(
+    json_text: &str,
+    out: &mut Vec<i64>,
+    limits: Limits,
+) -> Result<(), ParseError> {
+    out.clear();
+    match scan_integers(json_text.as_bytes(), out, limits) {
+        Some(parsed) => parsed,
+        None => parse_numbers_limited(json_text, out, limits),
+    }
+}
+
+/// The array of plain integers in `bytes`, or `None` if it is anything else.
+fn scan_integers(
+    bytes: &[u8],
+    out: &mut Vec<i64>,
+    limits: Limits,
+) -> Option<Result<(), ParseError>> {
+    let mut pos = skip_whitespace(bytes, 0);
+    if bytes.get(pos) != Some(&b'[') {
+        return None;
+    }
+    pos = skip_whitespace(bytes, pos + 1);
+    if bytes.get(pos) == Some(&b']') {
+        pos += 1;
+    } else {
+        loop {
+            let (value, end) = scan_integer(bytes, pos)?;
+            out.push(value);
+            if let Some(error) = limits.check(out.len()) {
+                return Some(Err(error));
+            }
+            pos = skip_whitespace(bytes, end);
+            match bytes.get(pos) {
+                Some(b',') => pos = skip_whitespace(bytes, pos + 1),
+                Some(b']') => {
+                    pos += 1;
+                    break;
+                }
+                _ => return None,
+            }
+        }
+    }
+    (skip_whitespace(bytes, pos) == bytes.len()).then_some(Ok(()))
+}
+
+/// An integer starting at `start` without a fraction or exponent, and the position after it.
+fn scan_integer(bytes: &[u8], start: usize) -> Option<(i64, usize)> {
+    let negative = bytes.get(start) == Some(&b'-');
+    let digits = start + usize::from(negative);
+    let mut pos = digits;
+    let mut magnitude: u64 = 0;
+    while let Some(digit) = bytes.get(pos).filter(|b| b.is_ascii_digit()) {
+        magnitude = magnitude
+            .checked_mul(10)?
+            .checked_add(u64::from(digit - b'0'))?;
+        pos += 1;
+    }
+    let len = pos - digits;
+    // JSON has no leading zeros; fractions and exponents are left to serde
+    if len == 0 || (len > 1 && bytes[digits] == b'0') {
+        return None;
+    }
+    if matches!(bytes.get(pos), Some(b'.' | b'e' | b'E')) {
+        return None;
+    }
+    let value = match negative {
+        // -2^63 is the one magnitude that only fits negated
+        true if magnitude <= i64::MIN.unsigned_abs() => 0i64.wrapping_sub_unsigned(magnitude),
+        false => i64::try_from(magnitude).ok()?,
+        true => return None,
+    };
+    Some((value, pos))
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while matches!(bytes.get(pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Seed that appends the elements of a JSON array to an existing vector.
+struct NumberArray<'a> {
+    out: &'a mut Vec<i64>,
+    limits: Limits,
+    /// Set when `limits` stopped the parse
+    stopped: &'a mut Option<ParseError>,
+}
+
+impl<'de> DeserializeSeed<'de> for NumberArray<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NumberArray<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an array of integers")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        if let Some(hint) = seq.size_hint() {
+            self.out
+                .reserve(hint.min(self.limits.max_len.unwrap_or(usize::MAX)));
+        }
+        while let Some(value) = seq.next_element_seed(Integer)? {
+            self.out.push(value);
+            if let Some(error) = self.limits.check(self.out.len()) {
+                *self.stopped = Some(error);
+                return Err(de::Error::custom("limit exceeded"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Seed accepting a single integral JSON number.
+struct Integer;
+
+impl<'de> DeserializeSeed<'de> for Integer {
+    type Value = i64;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<i64, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl Visitor<'_> for Integer {
+    type Value = i64;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an integer")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<i64, E> {
+        Ok(v)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<i64, E> {
+        // Out-of-range values go through the float path, like `Value::as_f64` did
+        Ok(i64::try_from(v).unwrap_or(v as f64 as i64))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<i64, E> {
+        if v.fract() == 0.0 {
+            Ok(v as i64)
+        } else {
+            Err(E::custom("non-integral number"))
+        }
+    }
+}
+
+/// Reusable simd-json based alternative to [`parse_numbers`].
+///
+/// simd-json parses in place, so every row is first copied into an owned buffer; that
+/// buffer, simd-json's scratch buffers and the tape are kept between calls. The numbers are
+/// read straight off the tape, without building a DOM.
+#[cfg(feature = "simd-json")]
+pub struct SimdParser {
+    input: Vec<u8>,
+    buffers: simd_json::Buffers,
+    tape: simd_json::tape::Tape<'static>,
+}
+
+#[cfg(feature = "simd-json")]
+impl Default for SimdParser {
+    fn default() -> Self {
+        SimdParser {
+            input: Vec::new(),
+            buffers: simd_json::Buffers::default(),
+            tape: simd_json::tape::Tape(Vec::new()),
+        }
+    }
+}
+
+#[cfg(feature = "simd-json")]
+impl SimdParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Same contract as [`parse_numbers`].
+    pub fn parse_numbers(&mut self, json_text: &str, out: &mut Vec<i64>) -> bool {
+        self.parse_numbers_limited(json_text, out, Limits::default())
+            .is_ok()
+    }
+
+    /// Same contract as [`parse_numbers_limited`], except that the whole row is tokenized
+    /// before the limits are checked: text that is not JSON at all is [`ParseError::Invalid`]
+    /// however long it is, and the deadline cannot interrupt the tokenizer itself.
+    pub fn parse_numbers_limited(
+        &mut self,
+        json_text: &str,
+        out: &mut Vec<i64>,
+        limits: Limits,
+    ) -> Result<(), ParseError> {
+        out.clear();
+        self.input.clear();
+        self.input.extend_from_slice(json_text.as_bytes());
+        let mut tape = std::mem::replace(&mut self.tape, simd_json::tape::Tape(Vec::new())).reset();
+        let parsed = match simd_json::fill_tape(&mut self.input, &mut self.buffers, &mut tape) {
+            Ok(()) => read_numbers(&tape.0, out, limits),
+            Err(_) => Err(ParseError::Invalid),
+        };
+        self.tape = tape.reset();
+        parsed
+    }
+}
+
+#[cfg(feature = "simd-json")]
+impl fmt::Debug for SimdParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimdParser").finish_non_exhaustive()
+    }
+}
+
+/// Appends the elements of the array at the root of `tape` to `out`.
+#[cfg(feature = "simd-json")]
+fn read_numbers(
+    tape: &[simd_json::tape::Node<'_>],
+    out: &mut Vec<i64>,
+    limits: Limits,
+) -> Result<(), ParseError> {
+    use simd_json::StaticNode;
+    use simd_json::tape::Node;
+
+    let Some((Node::Array { len, .. }, elements)) = tape.split_first() else {
+        return Err(ParseError::Invalid);
+    };
+    out.reserve((*len).min(limits.max_len.unwrap_or(usize::MAX)));
+    // Nodes come in document order, so a nested array or object is met before any of the
+    // elements after it; stopping there keeps the reads to top-level elements
+    for node in elements {
+        let value = match node {
+            Node::Static(StaticNode::I64(v)) => *v,
+            Node::Static(StaticNode::U64(v)) => i64::try_from(*v).unwrap_or(*v as f64 as i64),
+            Node::Static(StaticNode::F64(v)) if v.fract() == 0.0 => *v as i64,
+            _ => return Err(ParseError::Invalid),
+        };
+        out.push(value);
+        if let Some(error) = limits.check(out.len()) {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json_text: &str) -> Option<Vec<i64>> {
+        let mut out = Vec::new();
+        parse_numbers(json_text, &mut out).then_some(out)
+    }
+
+    #[test]
+    fn test_parse_integers() {
+        assert_eq!(parse("[1, -2, 3]"), Some(vec![1, -2, 3]));
+        assert_eq!(parse(" [ ] "), Some(vec![]));
+    }
+
+    #[test]
+    fn test_parse_whole_floats() {
+        assert_eq!(parse("[1.0, 2e2, -3.0]"), Some(vec![1, 200, -3]));
+        assert_eq!(parse("[1.5]"), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_non_numbers() {
+        assert_eq!(parse("[1, \"2\"]"), None);
+        assert_eq!(parse("[1, null]"), None);
+        assert_eq!(parse("[[1]]"), None);
+        assert_eq!(parse("{\"a\": 1}"), None);
+        assert_eq!(parse("42"), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed() {
+        assert_eq!(parse("[1, 2,"), None);
+        assert_eq!(parse("[1, 2] x"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_parse_limited() {
+        let mut out = Vec::new();
+        let limits = Limits {
+            max_len: Some(3),
+            ..Limits::default()
+        };
+        assert_eq!(parse_numbers_limited("[1, 2, 3]", &mut out, limits), Ok(()));
+        assert_eq!(out, [1, 2, 3]);
+        assert_eq!(
+            parse_numbers_limited("[1, 2, 3, 4, 5, 6]", &mut out, limits),
+            Err(ParseError::TooLong)
+        );
+        assert_eq!(out.len(), 4);
+        // The limit is hit before the syntax error is reached
+        assert_eq!(
+            parse_numbers_limited("[1, 2, 3, 4, x", &mut out, limits),
+            Err(ParseError::TooLong)
+        );
+        assert_eq!(
+            parse_numbers_limited("[1, x]", &mut out, limits),
+            Err(ParseError::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_parse_deadline() {
+        let mut out = Vec::new();
+        let expired = Limits {
+            deadline: Some(Instant::now()),
+            ..Limits::default()
+        };
+        let long = format!("[{}]", vec!["7"; 5000].join(","));
+        assert_eq!(
+            parse_numbers_limited(&long, &mut out, expired),
+            Err(ParseError::TimedOut)
+        );
+        assert_eq!(out.len(), DEADLINE_CHECK_INTERVAL);
+        // Short arrays finish before the first check
+        assert_eq!(parse_numbers_limited("[1, 2]", &mut out, expired), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_hostile_input() {
+        // Shapes the fuzz targets in fuzz/ generate: none may panic or overflow the stack
+        assert_eq!(parse(&"[".repeat(100_000)), None);
+        assert_eq!(
+            parse(&format!("{}1{}", "[".repeat(200), "]".repeat(200))),
+            None
+        );
+        assert_eq!(parse("[1e400]"), None);
+        assert_eq!(parse("[-1e300]"), Some(vec![i64::MIN]));
+        assert_eq!(
+            parse("[123456789012345678901234567890]"),
+            Some(vec![i64::MAX])
+        );
+        assert_eq!(parse("[\"\\ud800\"]"), None);
+    }
+
+    #[test]
+    fn test_parse_reuses_buffer() {
+        let mut out = vec![9, 9, 9];
+        assert!(parse_numbers("[1]", &mut out));
+        assert_eq!(out, vec![1]);
+    }
+
+    /// Both parsers on `json_text`, with and without a length limit.
+    fn check_fast_agrees(json_text: &str) {
+        for max_len in [None, Some(2)] {
+            let limits = Limits {
+                max_len,
+                ..Limits::default()
+            };
+            let (mut fast, mut serde) = (vec![7], vec![7]);
+            assert_eq!(
+                parse_numbers_fast(json_text, &mut fast, limits),
+                parse_numbers_limited(json_text, &mut serde, limits),
+                "{json_text:?} {max_len:?}"
+            );
+            assert_eq!(fast, serde, "{json_text:?} {max_len:?}");
+        }
+    }
+
+    #[test]
+    fn test_fast_parser_agrees() {
+        for json_text in [
+            "[1,-2,3]",
+            "\t[ 1 ,\n2\r]\n",
+            "[]",
+            "[ ]",
+            "[0, -0, 10]",
+            "[9223372036854775807, -9223372036854775808]",
+            "[9223372036854775808, -9223372036854775809, 99999999999999999999]",
+            "[1.0, 2e2, 3E-0, -4.5]",
+            "[01]",
+            "[-]",
+            "[- 1]",
+            "[1 2]",
+            "[1,]",
+            "[,1]",
+            "[1, \"2\"]",
+            "[1, [2]]",
+            "[1, 2, 3] x",
+            "[1, 2, 3, x",
+            "[1, 2",
+            "{\"a\": 1}",
+            "42",
+            "",
+        ] {
+            check_fast_agrees(json_text);
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_fast_parser_agrees(json_text in r#"[ \t\n]?\[[ \n]?(-?[0-9]{1,20}(\.[0-9])?([eE][+-]?[0-9])?[ ]?,[ \r]?){0,4}[-0-9.eEx\]\[ ,"]{0,6}"#) {
+            check_fast_agrees(&json_text);
+        }
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn test_simd_parser_agrees() {
+        let mut parser = SimdParser::new();
+        for json_text in [
+            "[1, -2, 3]",
+            " [ ] ",
+            "[1.0, 2e2, -3.0]",
+            "[1.5]",
+            "[9223372036854775807, 18446744073709551615]",
+            "[1, \"2\"]",
+            "[1, null]",
+            "[[1]]",
+            "[[1], 2]",
+            "[1, [2, 3]]",
+            "{\"a\": 1}",
+            "42",
+            "[1, 2,",
+            "[1, 2] x",
+            "",
+        ] {
+            let mut out = vec![7];
+            let ok = parser.parse_numbers(json_text, &mut out);
+            assert_eq!(ok.then_some(out), parse(json_text), "{json_text:?}");
+        }
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn test_simd_parser_limited() {
+        let mut parser = SimdParser::new();
+        let mut out = Vec::new();
+        let limits = Limits {
+            max_len: Some(3),
+            ..Limits::default()
+        };
+        let mut parse = |json_text: &str, limits| {
+            let parsed = parser.parse_numbers_limited(json_text, &mut out, limits);
+            (parsed, out.len())
+        };
+        assert_eq!(parse("[1, 2, 3]", limits), (Ok(()), 3));
+        assert_eq!(
+            parse("[1, 2, 3, 4, 5]", limits),
+            (Err(ParseError::TooLong), 4)
+        );
+        // Unlike the streaming parser, the whole row is tokenized first
+        assert_eq!(
+            parse("[1, 2, 3, 4, x", limits),
+            (Err(ParseError::Invalid), 0)
+        );
+        let expired = Limits {
+            deadline: Some(Instant::now()),
+            ..Limits::default()
+        };
+        let long = format!("[{}]", vec!["7"; 5000].join(","));
+        assert_eq!(
+            parse(&long, expired),
+            (Err(ParseError::TimedOut), DEADLINE_CHECK_INTERVAL)
+        );
+    }
+}
@@ -0,0 +1,1215 @@
+//! Three-stage processing pipeline: reader → worker pool → writer.
+//!
+//! The reader thread parses CSV records into fixed-size batches, a pool of workers rotates
+//! each batch and serializes it to CSV bytes, and the writer thread emits the chunks. The
+//! stages are connected by bounded channels, so a slow consumer applies backpressure all
+//! the way back to the reader instead of letting queues grow without limit. Every batch
+//! carries a sequence number and the writer reorders chunks, so output order always
+//! matches input order no matter how many workers run. With `max_memory` set, a
+//! [`MemoryBudget`] also bounds the bytes all stages hold together. A run is recorded as a
+//! `pipeline` [`tracing`] span with a child span per batch.
+
+use crate::budget::{BATCH_FACTOR, MemoryBudget};
+use crate::counters::Counters;
+use crate::errors::{CliError, ErrorCode, OnError, RecordError};
+use crate::fixtures::Rng;
+use crate::json::JsonStyle;
+use crate::latency::LatencyHistogram;
+use crate::parse::JsonParser;
+use crate::processor::RowProcessor;
+use crate::{InvalidReason, RowBuffers, verify_rotation_identity};
+use crossbeam_channel::{Receiver, Sender, bounded, unbounded};
+use csv::{ByteRecord, Reader, Writer, WriterBuilder};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::thread;
+use std::time::Instant;
+
+/// Maximum number of records handed to a worker at once.
+const BATCH_SIZE: usize = 512;
+
+/// Input bytes after which a batch is sent even if it holds fewer than `BATCH_SIZE` records,
+/// so files with a few huge tables still spread across all workers.
+const BATCH_BYTES: u64 = 64 * 1024;
+
+/// Bytes of capacity a worker's buffers beyond the first keep from one batch to the next.
+const MAX_SPARE_BUFFER: usize = 16 * 1024;
+
+/// Batches in flight per worker before the reader blocks.
+const QUEUE_DEPTH_PER_WORKER: usize = 2;
+
+type PipelineError = Box<dyn Error + Send + Sync>;
+
+/// Layout of the output rows and per-row checks, shared by the header and every worker.
+#[derive(Debug, Clone, Copy)]
+pub struct RowFormat {
+    /// Append the `n` column
+    pub emit_dimension: bool,
+    /// Serialization of the `json` column
+    pub json_style: JsonStyle,
+    /// Write the input's `json` field for invalid rows instead of `[]`
+    pub keep_original: bool,
+    /// Run `verify_rotation_identity` on one in this many valid rows
+    pub self_check: Option<u64>,
+    /// Handling of records that cannot be processed
+    pub on_error: OnError,
+    /// Input field holding the id
+    pub id_column: usize,
+    /// Input field holding the table
+    pub json_column: usize,
+    /// Largest accepted table side (see [`RowBuffers::max_n`])
+    pub max_n: Option<usize>,
+    /// Parser for the `json` field (see [`RowBuffers::parser`])
+    pub parser: JsonParser,
+    /// Approximate bytes the pipeline may hold at once (see [`MemoryBudget`])
+    pub max_memory: Option<usize>,
+    /// Process only a random subset of the records
+    pub sample: Option<Sample>,
+    /// Append the `error_reason` column (see [`RowBuffers::error_reason`])
+    pub emit_reason: bool,
+    /// Append the `explanation` column (see [`RowBuffers::explanation`])
+    pub emit_explanation: bool,
+    /// Append the `elapsed_us` column with the microseconds the processor took
+    pub emit_timing: bool,
+    /// Collect the processor's time per row in [`Counters::latencies`]
+    pub record_latencies: bool,
+    /// The output columns in this order, instead of the ones the `emit_*` flags add
+    pub output_columns: Option<OutputColumns>,
+}
+
+/// A column of the output rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputColumn {
+    Id,
+    Json,
+    IsValid,
+    /// Side length of valid tables
+    N,
+    /// See [`RowBuffers::error_reason`]
+    ErrorReason,
+    /// See [`RowBuffers::explanation`]
+    Explanation,
+    /// Microseconds the processor took
+    ElapsedUs,
+}
+
+impl OutputColumn {
+    pub const ALL: [OutputColumn; 7] = [
+        OutputColumn::Id,
+        OutputColumn::Json,
+        OutputColumn::IsValid,
+        OutputColumn::N,
+        OutputColumn::ErrorReason,
+        OutputColumn::Explanation,
+        OutputColumn::ElapsedUs,
+    ];
+
+    /// The column's name in the header row.
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputColumn::Id => "id",
+            OutputColumn::Json => "json",
+            OutputColumn::IsValid => "is_valid",
+            OutputColumn::N => "n",
+            OutputColumn::ErrorReason => "error_reason",
+            OutputColumn::Explanation => "explanation",
+            OutputColumn::ElapsedUs => "elapsed_us",
+        }
+    }
+}
+
+/// A selection of output columns in order, each at most once (`--output-columns`).
+///
+/// # Examples
+///
+/// ```
+/// use rotate_cli::pipeline::{OutputColumn, OutputColumns};
+///
+/// let columns: OutputColumns = "id,is_valid,json,n".parse().unwrap();
+/// assert_eq!(columns.as_slice()[1], OutputColumn::IsValid);
+/// assert!("id,id".parse::<OutputColumns>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputColumns {
+    columns: [OutputColumn; OutputColumn::ALL.len()],
+    len: usize,
+}
+
+impl OutputColumns {
+    /// The columns in `columns` order; `None` if one is listed twice or none is.
+    pub fn new(columns: &[OutputColumn]) -> Option<Self> {
+        let mut selection = OutputColumns {
+            columns: OutputColumn::ALL,
+            len: 0,
+        };
+        for &column in columns {
+            if selection.contains(column) {
+                return None;
+            }
+            selection.columns[selection.len] = column;
+            selection.len += 1;
+        }
+        (selection.len > 0).then_some(selection)
+    }
+
+    pub fn as_slice(&self) -> &[OutputColumn] {
+        &self.columns[..self.len]
+    }
+
+    pub fn contains(&self, column: OutputColumn) -> bool {
+        self.as_slice().contains(&column)
+    }
+}
+
+impl std::str::FromStr for OutputColumns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let columns = s
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                OutputColumn::ALL
+                    .into_iter()
+                    .find(|column| column.name() == name)
+                    .ok_or_else(|| {
+                        format!(
+                            "unknown output column '{name}' (expected id, json, is_valid, n, \
+                             error_reason, explanation or elapsed_us)"
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        OutputColumns::new(&columns).ok_or_else(|| format!("output column listed twice in '{s}'"))
+    }
+}
+
+impl fmt::Display for OutputColumns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, column) in self.as_slice().iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str(column.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// A reproducible random subset of the input records (`--sample`).
+///
+/// Each record is kept with probability `fraction`, decided in input order by an [`Rng`]
+/// seeded with `seed`, so the same input, fraction and seed select the same records
+/// whatever the number of workers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub fraction: f64,
+    pub seed: u64,
+}
+
+impl Default for RowFormat {
+    fn default() -> Self {
+        RowFormat {
+            emit_dimension: false,
+            json_style: JsonStyle::default(),
+            keep_original: false,
+            self_check: None,
+            on_error: OnError::default(),
+            id_column: 0,
+            json_column: 1,
+            max_n: None,
+            parser: JsonParser::default(),
+            max_memory: None,
+            sample: None,
+            emit_reason: false,
+            emit_explanation: false,
+            emit_timing: false,
+            record_latencies: false,
+            output_columns: None,
+        }
+    }
+}
+
+impl RowFormat {
+    /// Output column names.
+    pub fn header(&self) -> Vec<&'static str> {
+        self.columns()
+            .as_slice()
+            .iter()
+            .map(|column| column.name())
+            .collect()
+    }
+
+    /// The output columns in order: [`output_columns`](Self::output_columns) if set, otherwise `id`,
+    /// `json` and `is_valid` followed by the ones the `emit_*` flags add.
+    pub fn columns(&self) -> OutputColumns {
+        if let Some(columns) = self.output_columns {
+            return columns;
+        }
+        let optional = [
+            (OutputColumn::N, self.emit_dimension),
+            (OutputColumn::ErrorReason, self.emit_reason),
+            (OutputColumn::Explanation, self.emit_explanation),
+            (OutputColumn::ElapsedUs, self.emit_timing),
+        ];
+        let mut columns = OutputColumns {
+            columns: OutputColumn::ALL,
+            len: 3,
+        };
+        for (column, emitted) in optional {
+            if emitted {
+                columns.columns[columns.len] = column;
+                columns.len += 1;
+            }
+        }
+        columns
+    }
+
+    /// Number of fields a record needs to hold both the id and the table.
+    fn min_fields(&self) -> usize {
+        self.id_column.max(self.json_column) + 1
+    }
+
+    /// Whether the processor is timed for every row.
+    fn is_timed(&self) -> bool {
+        self.record_latencies || self.columns().contains(OutputColumn::ElapsedUs)
+    }
+}
+
+/// Receives every id in input order, e.g. to detect duplicates.
+pub trait ObserveIds: Send {
+    /// Called once per record read from the input, with the line it starts on.
+    fn observe(&mut self, id: &[u8], line: u64);
+}
+
+/// Records read from the input, tagged with their position in the stream.
+struct Batch {
+    seq: u64,
+    records: Vec<ByteRecord>,
+    len: usize,
+    /// Bytes reserved for it in the memory budget until its rows are written
+    charge: usize,
+}
+
+/// Serialized output rows for one batch.
+struct Chunk {
+    seq: u64,
+    bytes: Vec<u8>,
+    charge: usize,
+    /// The batch stopped at a record that failed the run; nothing after it is written
+    last: bool,
+}
+
+/// Runs all records from `rdr` through `workers` threads, each applying `processor`, and
+/// writes the rows to `output`.
+///
+/// Progress is published to `counters` after every batch, and ids are fed to `ids` in input
+/// order when given. Returns the output so the caller
+/// can finalize it. Rows read before a CSV error are still written before the error is
+/// reported, exactly as in a sequential loop. Records that cannot be processed are skipped,
+/// written as invalid rows or fail the run as `format.on_error` says.
+pub fn run<R, W>(
+    rdr: Reader<R>,
+    output: W,
+    workers: usize,
+    format: RowFormat,
+    processor: &dyn RowProcessor,
+    counters: &Counters,
+    ids: Option<&mut dyn ObserveIds>,
+) -> Result<W, PipelineError>
+where
+    R: Read + Send,
+    W: Write + Send,
+{
+    let workers = workers.max(1);
+    let (batch_tx, batch_rx) = bounded::<Batch>(workers * QUEUE_DEPTH_PER_WORKER);
+    let (chunk_tx, chunk_rx) = bounded::<Chunk>(workers * QUEUE_DEPTH_PER_WORKER);
+    // Processed record batches flow back to the reader for reuse
+    let (free_tx, free_rx) = unbounded::<Vec<ByteRecord>>();
+    let budget = &MemoryBudget::new(format.max_memory);
+    // The parent of the `batch` spans, which are recorded on the worker threads
+    let span = &tracing::info_span!("pipeline", workers);
+
+    thread::scope(|s| {
+        let reader =
+            s.spawn(move || read_batches(rdr, batch_tx, free_rx, counters, ids, format, budget));
+
+        let pool: Vec<_> = (0..workers)
+            .map(|_| {
+                let batch_rx = batch_rx.clone();
+                let chunk_tx = chunk_tx.clone();
+                let free_tx = free_tx.clone();
+                s.spawn(move || {
+                    let stage = Stage {
+                        format,
+                        processor,
+                        counters,
+                        budget,
+                        span,
+                    };
+                    process_batches(batch_rx, chunk_tx, free_tx, stage)
+                })
+            })
+            .collect();
+        // Only the stage threads may hold channel ends, so closing propagates
+        drop((batch_rx, chunk_tx, free_tx));
+
+        let writer = s.spawn(move || {
+            let result = write_chunks(chunk_rx, output, budget);
+            // Nothing is released after this; a reader waiting for memory must not hang
+            budget.close();
+            result
+        });
+
+        let read_result = reader.join().expect("reader thread panicked");
+        let pool_result = pool
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("worker thread panicked"));
+        let write_result = writer.join().expect("writer thread panicked");
+
+        // A writer failure (e.g. closed stdout) is the root cause of any upstream send errors
+        let output = write_result?;
+        pool_result?;
+        read_result?;
+        Ok(output)
+    })
+}
+
+/// Reader stage: fills batches of records until EOF, an error, or the workers hang up.
+fn read_batches<R: Read>(
+    mut rdr: Reader<R>,
+    batch_tx: Sender<Batch>,
+    free_rx: Receiver<Vec<ByteRecord>>,
+    counters: &Counters,
+    mut ids: Option<&mut dyn ObserveIds>,
+    format: RowFormat,
+    budget: &MemoryBudget,
+) -> Result<(), PipelineError> {
+    let mut sampler = format
+        .sample
+        .map(|sample| (Rng::new(sample.seed), sample.fraction));
+    // Only batches that are sent get a number, so the writer never waits for a gap
+    let mut seq = 0;
+    loop {
+        let mut records = free_rx.try_recv().unwrap_or_default();
+        records.resize_with(BATCH_SIZE, ByteRecord::new);
+
+        let mut len = 0;
+        let mut result = Ok(true);
+        let batch_start = rdr.position().byte();
+        while len < BATCH_SIZE && rdr.position().byte() - batch_start < BATCH_BYTES {
+            result = rdr.read_byte_record(&mut records[len]);
+            match result {
+                // Records left out of the sample are overwritten by the next one
+                Ok(true) if sampler.as_mut().is_some_and(|(rng, p)| !rng.chance(*p)) => {}
+                Ok(true) => len += 1,
+                _ => break,
+            }
+        }
+        counters.set_input_bytes(rdr.position().byte());
+        if let Some(ids) = ids.as_deref_mut() {
+            for record in &records[..len] {
+                let line = record.position().map_or(0, |p| p.line());
+                ids.observe(record.get(format.id_column).unwrap_or_default(), line);
+            }
+        }
+
+        if len > 0 {
+            let charge = (rdr.position().byte() - batch_start) as usize * BATCH_FACTOR;
+            budget
+                .reserve(charge)
+                .map_err(|e| RecordError::over_budget(&records[0], e))?;
+            let batch = Batch {
+                seq,
+                records,
+                len,
+                charge,
+            };
+            if batch_tx.send(batch).is_err() {
+                // Downstream stopped early; its error is reported by the writer
+                return Ok(());
+            }
+            seq += 1;
+        }
+        match result {
+            Ok(true) => {}
+            Ok(false) => return Ok(()),
+            Err(e) => {
+                let error = RecordError::csv(e, rdr.position());
+                if !error.is_recoverable() || format.on_error == OnError::Fail {
+                    return Err(error.into());
+                }
+                // The record's fields are lost with the error, so `invalid` skips it too
+                eprintln!("Warning: {error}; skipped");
+            }
+        }
+    }
+}
+
+/// What every worker shares.
+#[derive(Clone, Copy)]
+struct Stage<'a> {
+    format: RowFormat,
+    processor: &'a dyn RowProcessor,
+    counters: &'a Counters,
+    budget: &'a MemoryBudget,
+    span: &'a tracing::Span,
+}
+
+/// Worker stage: rotates every record of a batch and serializes the rows to CSV bytes.
+fn process_batches(
+    batch_rx: Receiver<Batch>,
+    chunk_tx: Sender<Chunk>,
+    free_tx: Sender<Vec<ByteRecord>>,
+    stage: Stage,
+) -> Result<(), PipelineError> {
+    let Stage {
+        format,
+        processor,
+        counters,
+        budget,
+        span,
+    } = stage;
+    let new_buffers = || {
+        let mut row_buffers = RowBuffers::with_max_n(format.max_n);
+        row_buffers.parser = format.parser;
+        row_buffers
+    };
+    // One set of buffers per row of a batch, and their capacity already pinned in the budget
+    let mut buffers: Vec<RowBuffers> = Vec::new();
+    let mut pinned: Vec<usize> = Vec::new();
+    let mut valid_rows = Vec::new();
+    let mut latencies = LatencyHistogram::default();
+    // Timed rows are processed one by one, so each gets its own elapsed time, and so are the
+    // rows of a --max-memory run, which then keeps a single row's buffers per worker
+    let batched = !format.is_timed() && format.max_memory.is_none();
+
+    for batch in batch_rx {
+        let batch_span = tracing::info_span!(
+            parent: span,
+            "batch",
+            seq = batch.seq,
+            rows = batch.len,
+            valid = tracing::field::Empty,
+        );
+        let _batch_span = batch_span.enter();
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(Vec::new());
+        let records = &batch.records[..batch.len];
+        let complete = |record: &&ByteRecord| record.len() >= format.min_fields();
+        let used = if batched {
+            records.iter().filter(complete).count().max(1)
+        } else {
+            1
+        };
+        while buffers.len() < used {
+            buffers.push(new_buffers());
+            pinned.push(0);
+        }
+        if batched {
+            let mut fields = records
+                .iter()
+                .filter(complete)
+                .map(|record| (&record[format.id_column], &record[format.json_column]));
+            processor.process_batch(
+                &mut fields,
+                format.json_style,
+                &mut buffers,
+                &mut valid_rows,
+            );
+        }
+
+        let (mut rows, mut valid) = (0, 0);
+        let mut failure = None;
+        let mut next = 0;
+        for record in records {
+            let (index, processed) = if batched && complete(&record) {
+                (next, Some(valid_rows[next]))
+            } else {
+                (0, None)
+            };
+            next += usize::from(processed.is_some());
+            let row = write_row(
+                &mut wtr,
+                record,
+                format,
+                processor,
+                &mut buffers[index],
+                &mut latencies,
+                processed,
+            )
+            .and_then(|row| {
+                let buffers = &buffers[index];
+                let capacity =
+                    buffers.numbers.capacity() * size_of::<i64>() + buffers.json.capacity();
+                if capacity > pinned[index] {
+                    budget
+                        .pin(capacity - pinned[index])
+                        .map_err(|e| RecordError::over_budget(record, e))?;
+                    pinned[index] = capacity;
+                }
+                Ok(row)
+            });
+            match row {
+                Ok(Some(is_valid)) => {
+                    rows += 1;
+                    valid += u64::from(is_valid);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    // The rows before it are still written, as in a sequential loop
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+        // Only the first buffers keep the largest table seen for the whole run; the others
+        // would otherwise each hold on to the largest table of their slot
+        for (row_buffers, pinned) in buffers.iter_mut().zip(&mut pinned).skip(1) {
+            if *pinned > MAX_SPARE_BUFFER {
+                *row_buffers = new_buffers();
+                budget.unpin(*pinned);
+                *pinned = 0;
+            }
+        }
+        counters.add_rows(rows, valid);
+        batch_span.record("valid", valid);
+        if format.record_latencies {
+            counters.add_latencies(&latencies);
+            latencies.clear();
+        }
+        let bytes = wtr.into_inner().map_err(|e| e.into_error())?;
+
+        // The reader may already be done; then the records are simply dropped
+        let _ = free_tx.send(batch.records);
+        let sent = chunk_tx.send(Chunk {
+            seq: batch.seq,
+            bytes,
+            charge: batch.charge,
+            last: failure.is_some(),
+        });
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        if sent.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Processes a single input record and appends the output row to `wtr`.
+///
+/// `processed` is the row's validity if `buffers` already hold its output, from
+/// [`RowProcessor::process_batch`]. Returns the row's validity, or `None` if the record was
+/// skipped.
+fn write_row(
+    wtr: &mut Writer<Vec<u8>>,
+    record: &ByteRecord,
+    format: RowFormat,
+    processor: &dyn RowProcessor,
+    buffers: &mut RowBuffers,
+    latencies: &mut LatencyHistogram,
+    processed: Option<bool>,
+) -> Result<Option<bool>, PipelineError> {
+    // Ensure we have both the id and the json field
+    if record.len() < format.min_fields() {
+        let error = RecordError::too_few_fields(record, format.min_fields());
+        return match format.on_error {
+            OnError::Skip => {
+                eprintln!("Warning: {error}; skipped");
+                Ok(None)
+            }
+            OnError::Invalid => {
+                for column in format.columns().as_slice() {
+                    wtr.write_field(match column {
+                        OutputColumn::Id => record.get(format.id_column).unwrap_or_default(),
+                        OutputColumn::Json => b"[]",
+                        OutputColumn::IsValid => b"false",
+                        OutputColumn::ErrorReason | OutputColumn::Explanation => {
+                            InvalidReason::Malformed.as_str().as_bytes()
+                        }
+                        OutputColumn::N | OutputColumn::ElapsedUs => b"",
+                    })?;
+                }
+                wtr.write_record(None::<&[u8]>)?;
+                Ok(Some(false))
+            }
+            OnError::Fail => Err(error.into()),
+        };
+    }
+
+    // Process the JSON and determine validity
+    let id = &record[format.id_column];
+    let started = format.is_timed().then(Instant::now);
+    let json = &record[format.json_column];
+    let is_valid =
+        processed.unwrap_or_else(|| processor.process_record(id, json, format.json_style, buffers));
+    let elapsed = started.map(|started| started.elapsed());
+    if let Some(elapsed) = elapsed.filter(|_| format.record_latencies) {
+        latencies.record(elapsed);
+    }
+    if is_valid
+        && format
+            .self_check
+            .is_some_and(|every| is_sampled(record, every))
+    {
+        verify_rotation_identity(&buffers.numbers).map_err(|violation| {
+            let message = format!(
+                "self-check failed for id {}: {violation}",
+                String::from_utf8_lossy(id)
+            );
+            CliError::new(ErrorCode::SelfCheck, message)
+        })?;
+    }
+
+    let mut digits = itoa::Buffer::new();
+    for column in format.columns().as_slice() {
+        match column {
+            OutputColumn::Id => wtr.write_field(id)?,
+            OutputColumn::Json if is_valid || !format.keep_original => {
+                wtr.write_field(&buffers.json)?
+            }
+            OutputColumn::Json => wtr.write_field(json)?,
+            OutputColumn::IsValid => wtr.write_field(if is_valid { "true" } else { "false" })?,
+            OutputColumn::N => match buffers.dimension(is_valid) {
+                Some(n) => wtr.write_field(digits.format(n))?,
+                None => wtr.write_field("")?,
+            },
+            OutputColumn::ErrorReason => {
+                wtr.write_field(buffers.error_reason(is_valid).unwrap_or_default())?
+            }
+            OutputColumn::Explanation => {
+                wtr.write_field(buffers.explanation(is_valid).unwrap_or_default())?
+            }
+            OutputColumn::ElapsedUs => match elapsed {
+                Some(elapsed) => wtr.write_field(digits.format(elapsed.as_micros()))?,
+                None => wtr.write_field("")?,
+            },
+        }
+    }
+    wtr.write_record(None::<&[u8]>)?;
+    Ok(Some(is_valid))
+}
+
+/// Whether `--self-check` verifies this record: one in `every`, by position in the input, so
+/// the same rows are checked whatever the number of workers.
+fn is_sampled(record: &ByteRecord, every: u64) -> bool {
+    record
+        .position()
+        .is_none_or(|p| p.record().is_multiple_of(every))
+}
+
+/// Writer stage: emits chunks strictly in sequence order.
+fn write_chunks<W: Write>(
+    chunk_rx: Receiver<Chunk>,
+    mut output: W,
+    budget: &MemoryBudget,
+) -> Result<W, PipelineError> {
+    let mut pending = BTreeMap::new();
+    let mut next_seq = 0;
+
+    'chunks: for chunk in chunk_rx {
+        pending.insert(chunk.seq, chunk);
+        while let Some(chunk) = pending.remove(&next_seq) {
+            output.write_all(&chunk.bytes)?;
+            budget.release(chunk.charge);
+            next_seq += 1;
+            if chunk.last {
+                // Hanging up stops the other workers
+                break 'chunks;
+            }
+        }
+    }
+
+    output.flush()?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::RotateRight;
+    use csv::ReaderBuilder;
+
+    fn run_to_string(input: &str, workers: usize) -> String {
+        run_with_format(input, workers, RowFormat::default())
+    }
+
+    fn run_with_format(input: &str, workers: usize, format: RowFormat) -> String {
+        let rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(input.as_bytes());
+        let output = run(
+            rdr,
+            Vec::new(),
+            workers,
+            format,
+            &RotateRight,
+            &Counters::default(),
+            None,
+        )
+        .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Most heap allocations a row may cost once the per-worker buffers have grown: none for
+    /// valid rows; rows rejected by the JSON parser pay for serde_json's boxed error and its
+    /// message.
+    #[cfg(feature = "count-allocs")]
+    const MAX_ALLOCATIONS_VALID_ROW: u64 = 0;
+    #[cfg(feature = "count-allocs")]
+    const MAX_ALLOCATIONS_INVALID_ROW: u64 = 5;
+
+    #[cfg(feature = "count-allocs")]
+    #[test]
+    fn test_steady_state_allocations_per_row() {
+        use crate::counting_alloc;
+        use crate::fixtures::{Dataset, SizeDistribution};
+
+        let dataset = Dataset {
+            rows: 2000,
+            seed: 619,
+            sizes: SizeDistribution::Uniform { min: 1, max: 20 },
+            invalid_ratio: 0.2,
+            ..Dataset::default()
+        };
+        let mut input = Vec::new();
+        dataset.write_csv(&mut input).unwrap();
+        let records: Vec<ByteRecord> = ReaderBuilder::new()
+            .from_reader(input.as_slice())
+            .byte_records()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        for format in [
+            RowFormat::default(),
+            RowFormat {
+                emit_dimension: true,
+                json_style: JsonStyle::Spaced,
+                ..RowFormat::default()
+            },
+        ] {
+            // The first pass grows the buffers to the largest row
+            let mut buffers = RowBuffers::default();
+            let mut latencies = LatencyHistogram::default();
+            let mut warmup = WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(Vec::new());
+            for record in &records {
+                write_row(
+                    &mut warmup,
+                    record,
+                    format,
+                    &RotateRight,
+                    &mut buffers,
+                    &mut latencies,
+                    None,
+                )
+                .unwrap();
+            }
+            let capacity = warmup.into_inner().unwrap().len();
+
+            let mut wtr = WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(Vec::with_capacity(capacity));
+            for (record, row) in records.iter().zip(dataset.rows()) {
+                let before = counting_alloc::thread_stats();
+                write_row(
+                    &mut wtr,
+                    record,
+                    format,
+                    &RotateRight,
+                    &mut buffers,
+                    &mut latencies,
+                    None,
+                )
+                .unwrap();
+                let used = counting_alloc::thread_stats() - before;
+
+                let max = match row.invalid {
+                    None => MAX_ALLOCATIONS_VALID_ROW,
+                    Some(_) => MAX_ALLOCATIONS_INVALID_ROW,
+                };
+                assert!(
+                    used.allocations <= max,
+                    "{} allocations for row {row:?} with {format:?}",
+                    used.allocations
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pipeline_rows() {
+        let input = "id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2, 3]\"\n3,[42]\n";
+        let output = run_to_string(input, 2);
+        assert_eq!(output, "1,\"[3,1,4,2]\",true\n2,[],false\n3,[42],true\n");
+    }
+
+    #[test]
+    fn test_pipeline_emit_dimension() {
+        let input = "id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2, 3]\"\n3,[42]\n";
+        let format = RowFormat {
+            emit_dimension: true,
+            ..RowFormat::default()
+        };
+        let output = run_with_format(input, 2, format);
+        assert_eq!(
+            output,
+            "1,\"[3,1,4,2]\",true,2\n2,[],false,\n3,[42],true,1\n"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_keep_original() {
+        let input = "id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2, 3]\"\n3,not json\n4,\n";
+        let format = RowFormat {
+            keep_original: true,
+            ..RowFormat::default()
+        };
+        assert_eq!(
+            run_with_format(input, 2, format),
+            "1,\"[3,1,4,2]\",true\n2,\"[1, 2, 3]\",false\n3,not json,false\n4,,false\n"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_output_columns() {
+        let input = "id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2, 3]\"\nshort\n";
+        let format = RowFormat {
+            output_columns: Some("is_valid,n, id,error_reason".parse().unwrap()),
+            on_error: OnError::Invalid,
+            ..RowFormat::default()
+        };
+        assert_eq!(format.header(), ["is_valid", "n", "id", "error_reason"]);
+        assert_eq!(
+            run_with_format(input, 2, format),
+            "true,2,1,\nfalse,,2,not_square\nfalse,,short,malformed\n"
+        );
+
+        let format = RowFormat {
+            emit_explanation: true,
+            ..RowFormat::default()
+        };
+        assert_eq!(format.header(), ["id", "json", "is_valid", "explanation"]);
+        assert_eq!(
+            run_with_format(input, 2, format),
+            "1,\"[3,1,4,2]\",true,\n2,[],false,3 elements: remove 2 for 1×1 or add 1 for 2×2\n"
+        );
+
+        let format = RowFormat {
+            emit_dimension: true,
+            emit_timing: true,
+            ..RowFormat::default()
+        };
+        assert_eq!(
+            format.columns().to_string(),
+            "id,json,is_valid,n,elapsed_us"
+        );
+        assert!(format.is_timed());
+        assert!("json,id,json".parse::<OutputColumns>().is_err());
+        assert!("id,size".parse::<OutputColumns>().is_err());
+        assert!("".parse::<OutputColumns>().is_err());
+    }
+
+    #[test]
+    fn test_pipeline_opaque_ids() {
+        let ids: [&[u8]; 6] = [
+            b"67e55044-10b1-426f-9247-bb680e5fe0c8",
+            b"tenant-7,table-3",
+            b"say \"hi\"",
+            b"two\nlines",
+            b"  padded ",
+            b"\xff\xfe",
+        ];
+        let mut input = Vec::new();
+        let mut wtr = WriterBuilder::new().from_writer(&mut input);
+        wtr.write_record(["id", "json"]).unwrap();
+        for id in ids {
+            wtr.write_record([id, b"[1]"]).unwrap();
+        }
+        drop(wtr);
+
+        let rdr = ReaderBuilder::new().from_reader(input.as_slice());
+        let counters = Counters::default();
+        let format = RowFormat::default();
+        let output = run(rdr, Vec::new(), 2, format, &RotateRight, &counters, None).unwrap();
+        let written: Vec<Vec<u8>> = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(output.as_slice())
+            .byte_records()
+            .map(|record| record.unwrap()[0].to_vec())
+            .collect();
+        assert_eq!(written, ids);
+    }
+
+    #[test]
+    fn test_pipeline_self_check() {
+        let input =
+            "id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2, 3]\"\n3,\"[1, 2, 3, 4, 5, 6, 7, 8, 9]\"\n";
+        let format = RowFormat {
+            self_check: Some(1),
+            ..RowFormat::default()
+        };
+        assert_eq!(run_with_format(input, 2, format), run_to_string(input, 2),);
+
+        let mut rdr = ReaderBuilder::new().from_reader(input.as_bytes());
+        let sampled: Vec<bool> = rdr
+            .byte_records()
+            .map(|record| is_sampled(&record.unwrap(), 2))
+            .collect();
+        assert_eq!(sampled, [false, true, false]);
+    }
+
+    #[test]
+    fn test_pipeline_on_error() {
+        let input = "id,json\n1,[42]\n2\n3,[7]\n";
+        let run_with = |on_error| {
+            let rdr = ReaderBuilder::new()
+                .flexible(true)
+                .from_reader(input.as_bytes());
+            let format = RowFormat {
+                emit_dimension: true,
+                on_error,
+                ..RowFormat::default()
+            };
+            run(
+                rdr,
+                Vec::new(),
+                2,
+                format,
+                &RotateRight,
+                &Counters::default(),
+                None,
+            )
+        };
+        assert_eq!(
+            run_with(OnError::Skip).unwrap(),
+            b"1,[42],true,1\n3,[7],true,1\n"
+        );
+        assert_eq!(
+            run_with(OnError::Invalid).unwrap(),
+            b"1,[42],true,1\n2,[],false,\n3,[7],true,1\n"
+        );
+        let mut output = Vec::new();
+        let rdr = ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(input.as_bytes());
+        let format = RowFormat {
+            on_error: OnError::Fail,
+            ..RowFormat::default()
+        };
+        let error = run(
+            rdr,
+            &mut output,
+            2,
+            format,
+            &RotateRight,
+            &Counters::default(),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(output, b"1,[42],true\n");
+        assert_eq!(
+            error.to_string(),
+            "record 2 (line 3, byte 15) has 1 field, expected at least 2 (id, json)"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_preserves_order_across_batches() {
+        let rows = BATCH_SIZE * 7 + 13;
+        let mut input = String::from("id,json\n");
+        for id in 0..rows {
+            input.push_str(&format!("{id},\"[{id}]\"\n"));
+        }
+
+        let expected: String = (0..rows).map(|id| format!("{id},[{id}],true\n")).collect();
+        assert_eq!(run_to_string(&input, 1), expected);
+        assert_eq!(run_to_string(&input, 4), expected);
+    }
+
+    #[test]
+    fn test_pipeline_splits_large_rows() {
+        // Rows bigger than BATCH_BYTES end up one per batch
+        let big = format!("[{}]", vec!["1"; 40_000].join(","));
+        let mut input = String::from("id,json\n");
+        for id in 0..5 {
+            input.push_str(&format!("{id},\"{big}\"\n"));
+        }
+
+        let output = run_to_string(&input, 3);
+        assert_eq!(output.lines().count(), 5);
+        assert!(output.lines().all(|line| line.ends_with(",true")));
+    }
+
+    #[test]
+    fn test_pipeline_max_memory() {
+        let rows = BATCH_SIZE * 7 + 13;
+        let mut input = String::from("id,json\n");
+        for id in 0..rows {
+            input.push_str(&format!("{id},\"[{id}]\"\n"));
+        }
+        let format = RowFormat {
+            max_memory: Some(32 << 10),
+            ..RowFormat::default()
+        };
+        // Batches wait for each other but all get through
+        assert_eq!(run_with_format(&input, 4, format), run_to_string(&input, 4));
+
+        let big = format!("id,json\n1,[1]\n2,\"[{}]\"\n", vec!["1"; 40_000].join(","));
+        let run_big = |max_memory| {
+            let rdr = ReaderBuilder::new()
+                .has_headers(true)
+                .from_reader(big.as_bytes());
+            let format = RowFormat {
+                max_memory: Some(max_memory),
+                ..RowFormat::default()
+            };
+            run(
+                rdr,
+                Vec::new(),
+                2,
+                format,
+                &RotateRight,
+                &Counters::default(),
+                None,
+            )
+            .err()
+            .unwrap()
+            .to_string()
+        };
+        // Too big to even queue, then too big for the worker's buffers
+        let error = run_big(100 << 10);
+        assert!(
+            error.starts_with("record 1 (line 2, byte 8): a batch of 156.3 KiB"),
+            "{error}"
+        );
+        let error = run_big(200 << 10);
+        assert!(
+            error.starts_with("record 2 (line 3, byte 14): row buffers need"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_sample() {
+        let rows = BATCH_SIZE * 4;
+        let mut input = String::from("id,json\n");
+        for id in 0..rows {
+            input.push_str(&format!("{id},\"[{id}]\"\n"));
+        }
+        let sampled = |fraction, seed, workers| {
+            let format = RowFormat {
+                sample: Some(Sample { fraction, seed }),
+                ..RowFormat::default()
+            };
+            run_with_format(&input, workers, format)
+        };
+
+        let output = sampled(0.1, 7, 1);
+        let ids: Vec<usize> = output
+            .lines()
+            .map(|line| line.split(',').next().unwrap().parse().unwrap())
+            .collect();
+        // About a tenth of the rows, in input order, the same with any number of workers
+        assert!((150..260).contains(&ids.len()), "{}", ids.len());
+        assert!(ids.is_sorted());
+        assert_eq!(sampled(0.1, 7, 4), output);
+        assert_ne!(sampled(0.1, 8, 1), output);
+        assert_eq!(sampled(1.0, 7, 2), run_to_string(&input, 2));
+        assert_eq!(sampled(0.0, 7, 2), "");
+    }
+
+    #[test]
+    fn test_pipeline_timing() {
+        let input = "id,json\n1,[1]\nshort\n2,\"[1, 2]\"\n";
+        let format = RowFormat {
+            emit_timing: true,
+            record_latencies: true,
+            on_error: OnError::Invalid,
+            ..RowFormat::default()
+        };
+        assert_eq!(format.header(), ["id", "json", "is_valid", "elapsed_us"]);
+        let rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(input.as_bytes());
+        let counters = Counters::default();
+        let output = run(rdr, Vec::new(), 2, format, &RotateRight, &counters, None).unwrap();
+
+        let rows: Vec<Vec<String>> = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(output.as_slice())
+            .records()
+            .map(|record| record.unwrap().iter().map(str::to_string).collect())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0][3].parse::<u64>().is_ok());
+        // The record with too few fields never reached the processor
+        assert_eq!(rows[1][3], "");
+        assert!(rows[2][3].parse::<u64>().is_ok());
+        assert_eq!(counters.latencies().len(), 2);
+    }
+
+    #[test]
+    fn test_pipeline_counts_rows() {
+        let input = "id,json\n1,[1]\n2,\"[1, 2]\"\nshort\n3,[3]\n";
+        let rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(input.as_bytes());
+        let counters = Counters::default();
+        run(
+            rdr,
+            Vec::new(),
+            2,
+            RowFormat::default(),
+            &RotateRight,
+            &counters,
+            None,
+        )
+        .unwrap();
+
+        let summary = counters.snapshot(std::time::Duration::from_secs(1));
+        assert_eq!((summary.rows, summary.valid), (3, 2));
+        assert_eq!(summary.input_bytes, input.len() as u64);
+    }
+
+    #[test]
+    fn test_pipeline_writes_rows_before_error() {
+        // Input that fails with an I/O error once the first data row has been read
+        struct Failing<'a>(&'a [u8]);
+        impl Read for Failing<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0.is_empty() {
+                    return Err(std::io::Error::other("boom"));
+                }
+                self.0.read(buf)
+            }
+        }
+
+        let rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(Failing(b"id,json\n1,[7]\n"));
+        let mut output = Vec::new();
+        let err = run(
+            rdr,
+            &mut output,
+            2,
+            RowFormat::default(),
+            &RotateRight,
+            &Counters::default(),
+            None,
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("boom"));
+        assert_eq!(output, b"1,[7],true\n");
+    }
+}
@@ -0,0 +1,164 @@
+//! [Polars](::polars) integration (`polars` feature).
+//!
+//! [`rotate_column`] rotates every table of a `DataFrame` column in place, so the CSV can be
+//! loaded with Polars' multithreaded reader and benchmarked against the csv-crate pipeline
+//! (`rotate_cli --engine polars`).
+
+use crate::json::write_array;
+use crate::{parse, rotate_right};
+use ::polars::prelude::*;
+
+/// Name of the Boolean column added by [`rotate_column`].
+pub const IS_VALID: &str = "is_valid";
+
+/// Rotates the table in every row of `column` and adds or replaces an `is_valid` column.
+///
+/// The column is either
+/// - a `List` of integers, rewritten as `List(Int64)`; rows that are null, contain nulls or
+///   are not a non-empty square become empty lists, or
+/// - a `String` of JSON arrays as in the CSV format, rewritten as compact JSON; invalid rows
+///   become `"[]"`, exactly like the CLI output.
+///
+/// # Examples
+///
+/// ```
+/// use polars::prelude::*;
+/// use rotate_cli::polars::rotate_column;
+///
+/// let mut df = df!("json" => ["[1, 2, 3, 4]", "[1, 2, 3]"]).unwrap();
+/// rotate_column(&mut df, "json").unwrap();
+/// assert_eq!(df.column("json").unwrap().str().unwrap().get(0), Some("[3,1,4,2]"));
+/// assert_eq!(df.column("is_valid").unwrap().bool().unwrap().get(1), Some(false));
+/// ```
+pub fn rotate_column(df: &mut DataFrame, column: &str) -> PolarsResult<()> {
+    let values = df.column(column)?;
+    let (rotated, is_valid) = match values.dtype() {
+        DataType::String => rotate_json(values.str()?),
+        DataType::List(inner) if inner.is_integer() => rotate_lists(values.list()?)?,
+        dtype => polars_bail!(
+            InvalidOperation: "cannot rotate column '{column}' of type {dtype}; expected a String or List of integers"
+        ),
+    };
+    df.with_column(rotated)?;
+    df.with_column(is_valid)?;
+    Ok(())
+}
+
+/// Parses, rotates and re-serializes JSON arrays, reusing the scratch buffers across rows.
+fn rotate_json(values: &StringChunked) -> (Series, Series) {
+    let mut rotated = StringChunkedBuilder::new(values.name().clone(), values.len());
+    let mut is_valid = Vec::with_capacity(values.len());
+    let mut numbers = Vec::new();
+    let mut json = Vec::new();
+
+    for text in values {
+        let valid = text.is_some_and(|text| parse::parse_numbers(text, &mut numbers))
+            && rotate_right(&mut numbers).is_ok();
+        json.clear();
+        if valid {
+            write_array(&mut json, &numbers);
+        } else {
+            json.extend_from_slice(b"[]");
+        }
+        // Only ASCII digits, signs, commas and brackets were written
+        rotated.append_value(std::str::from_utf8(&json).expect("JSON output is ASCII"));
+        is_valid.push(valid);
+    }
+    (
+        rotated.finish().into_series(),
+        Series::new(IS_VALID.into(), is_valid),
+    )
+}
+
+fn rotate_lists(values: &ListChunked) -> PolarsResult<(Series, Series)> {
+    let mut rotated = ListPrimitiveChunkedBuilder::<Int64Type>::new(
+        values.name().clone(),
+        values.len(),
+        values.get_inner().len(),
+        DataType::Int64,
+    );
+    let mut is_valid = Vec::with_capacity(values.len());
+    let mut numbers: Vec<i64> = Vec::new();
+
+    for row in values {
+        numbers.clear();
+        let mut valid = false;
+        if let Some(row) = row {
+            let row = row.cast(&DataType::Int64)?;
+            let row = row.i64()?;
+            if row.null_count() == 0 {
+                numbers.extend(row.into_no_null_iter());
+                valid = rotate_right(&mut numbers).is_ok();
+            }
+        }
+        rotated.append_slice(if valid { &numbers } else { &[] });
+        is_valid.push(valid);
+    }
+    Ok((
+        rotated.finish().into_series(),
+        Series::new(IS_VALID.into(), is_valid),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotates_json_column() {
+        let mut df = df!(
+            "id" => ["1", "2", "3", "4"],
+            "json" => [Some("[1, 2, 3, 4]"), Some("[1, 2, 3]"), Some("oops"), None],
+        )
+        .unwrap();
+        rotate_column(&mut df, "json").unwrap();
+
+        let json: Vec<_> = df.column("json").unwrap().str().unwrap().iter().collect();
+        assert_eq!(
+            json,
+            [Some("[3,1,4,2]"), Some("[]"), Some("[]"), Some("[]")]
+        );
+        let valid: Vec<_> = df
+            .column(IS_VALID)
+            .unwrap()
+            .bool()
+            .unwrap()
+            .iter()
+            .collect();
+        assert_eq!(valid, [Some(true), Some(false), Some(false), Some(false)]);
+    }
+
+    #[test]
+    fn test_rotates_list_column() {
+        let rows = [
+            Series::new("".into(), [1i32, 2, 3, 4]),
+            Series::new("".into(), [1i32, 2, 3]),
+            Series::new("".into(), [Some(1i32), None, Some(3), Some(4)]),
+        ];
+        let mut df = df!("table" => rows).unwrap();
+        rotate_column(&mut df, "table").unwrap();
+
+        let table = df.column("table").unwrap().list().unwrap();
+        assert_eq!(table.inner_dtype(), &DataType::Int64);
+        let rows: Vec<Vec<i64>> = table
+            .into_iter()
+            .map(|row| row.unwrap().i64().unwrap().into_no_null_iter().collect())
+            .collect();
+        assert_eq!(rows, [vec![3, 1, 4, 2], vec![], vec![]]);
+        let valid: Vec<_> = df
+            .column(IS_VALID)
+            .unwrap()
+            .bool()
+            .unwrap()
+            .iter()
+            .collect();
+        assert_eq!(valid, [Some(true), Some(false), Some(false)]);
+    }
+
+    #[test]
+    fn test_rejects_other_types() {
+        let mut df = df!("json" => [1.5f64]).unwrap();
+        assert!(rotate_column(&mut df, "json").is_err());
+        assert!(rotate_column(&mut df, "missing").is_err());
+    }
+}
@@ -0,0 +1,228 @@
+//! PostgreSQL input and output through the COPY protocol.
+//!
+//! With the `postgres` feature, `--from-postgres DSN --query SQL` streams the query result
+//! as CSV (`COPY (...) TO STDOUT`) into the pipeline and `--to-table TABLE` streams the
+//! output rows back with `COPY ... FROM STDIN`, so large datasets never need a CSV file on
+//! disk.
+//!
+//! The synchronous client borrows its connection for the duration of a COPY, so each
+//! direction runs on its own connection in a background thread and exchanges chunks with
+//! the pipeline over a bounded channel.
+
+#[cfg(feature = "postgres")]
+pub use imp::{CopyWriter, create_writer, open_reader};
+
+#[cfg(feature = "postgres")]
+mod imp {
+    use ::postgres::{Client, NoTls};
+    use crossbeam_channel::{Receiver, Sender, bounded};
+    use std::io::{self, Read, Write};
+    use std::thread::{self, JoinHandle};
+
+    /// Bytes moved between the pipeline and the connection thread at once.
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Chunks in flight before the producing side blocks.
+    const QUEUE_DEPTH: usize = 8;
+
+    /// Streams the result of `query` as CSV with a header row.
+    pub fn open_reader(dsn: &str, query: &str) -> io::Result<Box<dyn Read + Send>> {
+        let mut client = connect(dsn)?;
+        let sql = format!("COPY ({query}) TO STDOUT WITH (FORMAT csv, HEADER true)");
+        let (tx, rx) = bounded::<io::Result<Vec<u8>>>(QUEUE_DEPTH);
+
+        thread::spawn(move || {
+            let mut reader = match client.copy_out(sql.as_str()) {
+                Ok(reader) => reader,
+                Err(e) => {
+                    let _ = tx.send(Err(db_error(e)));
+                    return;
+                }
+            };
+            loop {
+                let mut chunk = vec![0; CHUNK_SIZE];
+                let result = reader.read(&mut chunk).map(|read| {
+                    chunk.truncate(read);
+                    chunk
+                });
+                let done = !matches!(&result, Ok(chunk) if !chunk.is_empty());
+                // The pipeline hanging up early is not an error here
+                if tx.send(result).is_err() || done {
+                    return;
+                }
+            }
+        });
+
+        Ok(Box::new(ChannelReader {
+            rx,
+            chunk: Vec::new(),
+            pos: 0,
+        }))
+    }
+
+    /// Reading end of [`open_reader`].
+    struct ChannelReader {
+        rx: Receiver<io::Result<Vec<u8>>>,
+        chunk: Vec<u8>,
+        pos: usize,
+    }
+
+    impl Read for ChannelReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pos == self.chunk.len() {
+                match self.rx.recv() {
+                    Ok(chunk) => self.chunk = chunk?,
+                    Err(_) => return Ok(0),
+                }
+                self.pos = 0;
+            }
+            let read = buf.len().min(self.chunk.len() - self.pos);
+            buf[..read].copy_from_slice(&self.chunk[self.pos..self.pos + read]);
+            self.pos += read;
+            Ok(read)
+        }
+    }
+
+    enum Message {
+        Data(Vec<u8>),
+        Finish,
+    }
+
+    /// Starts `COPY table (columns) FROM STDIN` and returns a writer for the CSV rows.
+    ///
+    /// The first row written is the header and is skipped by the server. Nothing becomes
+    /// visible in the table until [`CopyWriter::finish`]; dropping the writer aborts the COPY.
+    pub fn create_writer(dsn: &str, table: &str, columns: &[&str]) -> io::Result<CopyWriter> {
+        let mut client = connect(dsn)?;
+        let sql = format!(
+            "COPY {table} ({}) FROM STDIN WITH (FORMAT csv, HEADER true)",
+            columns.join(", ")
+        );
+        let (tx, rx) = bounded::<Message>(QUEUE_DEPTH);
+
+        let handle = thread::spawn(move || -> io::Result<u64> {
+            let mut writer = client.copy_in(sql.as_str()).map_err(db_error)?;
+            loop {
+                match rx.recv() {
+                    Ok(Message::Data(chunk)) => writer.write_all(&chunk)?,
+                    Ok(Message::Finish) => return writer.finish().map_err(db_error),
+                    // Dropping `writer` without finishing rolls the COPY back
+                    Err(_) => return Err(io::Error::other("COPY aborted")),
+                }
+            }
+        });
+
+        Ok(CopyWriter {
+            tx,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+            handle: Some(handle),
+        })
+    }
+
+    /// Synchronous handle to an in-progress `COPY ... FROM STDIN`.
+    pub struct CopyWriter {
+        tx: Sender<Message>,
+        buffer: Vec<u8>,
+        handle: Option<JoinHandle<io::Result<u64>>>,
+    }
+
+    impl CopyWriter {
+        /// Sends the remaining rows and commits the COPY.
+        pub fn finish(mut self) -> io::Result<()> {
+            self.send_buffer()?;
+            self.send(Message::Finish)?;
+            let rows = self.join()?;
+            log::info!("Copied {rows} rows into PostgreSQL");
+            Ok(())
+        }
+
+        fn send_buffer(&mut self) -> io::Result<()> {
+            if self.buffer.is_empty() {
+                return Ok(());
+            }
+            let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+            self.send(Message::Data(chunk))
+        }
+
+        fn send(&mut self, message: Message) -> io::Result<()> {
+            match self.tx.send(message) {
+                Ok(()) => Ok(()),
+                // The connection thread only hangs up after a failure; report that instead
+                Err(_) => Err(self
+                    .join()
+                    .err()
+                    .unwrap_or_else(|| io::Error::other("COPY ended early"))),
+            }
+        }
+
+        fn join(&mut self) -> io::Result<u64> {
+            match self.handle.take() {
+                Some(handle) => handle.join().expect("COPY thread panicked"),
+                None => Err(io::Error::other("COPY already ended")),
+            }
+        }
+    }
+
+    impl Write for CopyWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buffer.extend_from_slice(buf);
+            if self.buffer.len() >= CHUNK_SIZE {
+                self.send_buffer()?;
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.send_buffer()
+        }
+    }
+
+    fn connect(dsn: &str) -> io::Result<Client> {
+        Client::connect(dsn, NoTls).map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e))
+    }
+
+    /// Includes the server's message, which `postgres::Error` only exposes as its source.
+    fn db_error(e: ::postgres::Error) -> io::Error {
+        match e.as_db_error() {
+            Some(db) => io::Error::other(format!("{}: {}", db.severity(), db.message())),
+            None => io::Error::other(e),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_channel_reader_reassembles_chunks() {
+            let (tx, rx) = bounded(4);
+            tx.send(Ok(b"id,json\n".to_vec())).unwrap();
+            tx.send(Ok(b"1,[1]\n".to_vec())).unwrap();
+            tx.send(Ok(Vec::new())).unwrap();
+            drop(tx);
+
+            let mut reader = ChannelReader {
+                rx,
+                chunk: Vec::new(),
+                pos: 0,
+            };
+            let mut text = String::new();
+            reader.read_to_string(&mut text).unwrap();
+            assert_eq!(text, "id,json\n1,[1]\n");
+        }
+
+        #[test]
+        fn test_channel_reader_surfaces_errors() {
+            let (tx, rx) = bounded(1);
+            tx.send(Err(io::Error::other("relation \"t\" does not exist")))
+                .unwrap();
+            let mut reader = ChannelReader {
+                rx,
+                chunk: Vec::new(),
+                pos: 0,
+            };
+            let err = reader.read(&mut [0; 8]).unwrap_err();
+            assert!(err.to_string().contains("does not exist"));
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! What happens to each table on its way through the [`pipeline`](crate::pipeline).
+//!
+//! The CSV reading, ordering, error handling, statistics and parallelism are the same for
+//! every run; a [`RowProcessor`] only turns the `json` field of a record into the output
+//! table. [`RotateRight`] is the default and gives the CLI's usual output; [`Transpose`] and
+//! [`Validate`] are the alternatives behind `--processor`, and library users can plug in
+//! their own through [`CsvRotator::with_processor`](crate::CsvRotator::with_processor):
+//!
+//! ```
+//! use rotate_cli::json::{self, JsonStyle};
+//! use rotate_cli::{CsvRotator, RowBuffers, RowProcessor, parse_table};
+//!
+//! /// Doubles every element.
+//! struct Double;
+//!
+//! impl RowProcessor for Double {
+//!     fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+//!         let Some(text) = std::str::from_utf8(json).ok() else {
+//!             return buffers.set_invalid();
+//!         };
+//!         if parse_table(text, buffers).is_none() {
+//!             return buffers.set_invalid();
+//!         }
+//!         buffers.numbers.iter_mut().for_each(|x| *x *= 2);
+//!         buffers.json.clear();
+//!         json::write_array_styled(&mut buffers.json, &buffers.numbers, style);
+//!         true
+//!     }
+//! }
+//!
+//! let mut output = Vec::new();
+//! let rotator = CsvRotator::default().with_processor(Double);
+//! rotator.process("id,json\n1,\"[1,2,3,4]\"\n".as_bytes(), &mut output).unwrap();
+//! assert_eq!(output, b"id,json,is_valid\n1,\"[2,4,6,8]\",true\n");
+//! ```
+
+use crate::json::{self, JsonStyle};
+use crate::row::parse_table_bytes;
+use crate::{Rotatable, RowBuffers, process_json_bytes, rotate_batch};
+use clap::ValueEnum;
+use std::mem;
+
+/// Turns the `json` field of one record into the output table.
+///
+/// Implementations write the `json` column into `buffers.json` (see
+/// [`RowBuffers::set_invalid`] for rejected rows) and return the row's validity. The
+/// pipeline calls one shared processor from all worker threads, each with its own buffers.
+pub trait RowProcessor: Send + Sync {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool;
+
+    /// [`process`](Self::process) with the record's id, which is what the pipeline calls;
+    /// processors that check the id as well, such as
+    /// [`SchemaCheck`](crate::schema::SchemaCheck), override it.
+    fn process_record(
+        &self,
+        _id: &[u8],
+        json: &[u8],
+        style: JsonStyle,
+        buffers: &mut RowBuffers,
+    ) -> bool {
+        self.process(json, style, buffers)
+    }
+
+    /// [`process_record`](Self::process_record) for the `(id, json)` fields of several
+    /// records, each with its own element of `buffers`, pushing their validity to `valid`.
+    ///
+    /// The pipeline's workers call this once per batch. The default processes the rows one
+    /// after the other; [`RotateRight`] rotates all the tables of a batch in one
+    /// [`rotate_batch`] call.
+    fn process_batch<'a>(
+        &self,
+        rows: &mut dyn Iterator<Item = (&'a [u8], &'a [u8])>,
+        style: JsonStyle,
+        buffers: &mut [RowBuffers],
+        valid: &mut Vec<bool>,
+    ) {
+        valid.clear();
+        for ((id, json), buffers) in rows.zip(buffers) {
+            valid.push(self.process_record(id, json, style, buffers));
+        }
+    }
+}
+
+/// One clockwise ring step, as in [`process_json_bytes`]; the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RotateRight;
+
+impl RowProcessor for RotateRight {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        process_json_bytes(json, style, buffers)
+    }
+
+    fn process_batch<'a>(
+        &self,
+        rows: &mut dyn Iterator<Item = (&'a [u8], &'a [u8])>,
+        style: JsonStyle,
+        buffers: &mut [RowBuffers],
+        valid: &mut Vec<bool>,
+    ) {
+        valid.clear();
+        for ((_, json), buffers) in rows.zip(&mut *buffers) {
+            let is_valid = parse_table_bytes(json, buffers).is_some() || buffers.set_invalid();
+            valid.push(is_valid);
+        }
+
+        // The parsed tables are moved out for the rotation and back in afterwards
+        let mut tables: Vec<Vec<i64>> = (buffers.iter_mut().zip(valid.iter()))
+            .filter_map(|(buffers, &is_valid)| is_valid.then(|| mem::take(&mut buffers.numbers)))
+            .collect();
+        for result in rotate_batch(&mut tables) {
+            result.expect("parse_table checked the table");
+        }
+        let mut tables = tables.into_iter();
+        for (buffers, _) in buffers.iter_mut().zip(valid.iter()).filter(|(_, v)| **v) {
+            buffers.numbers = tables.next().expect("one table per valid row");
+            write(style, buffers);
+        }
+    }
+}
+
+/// Mirrors the table along its main diagonal.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Transpose;
+
+impl RowProcessor for Transpose {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        if parse_table_bytes(json, buffers).is_none() {
+            return buffers.set_invalid();
+        }
+        Rotatable::transpose(buffers.numbers.as_mut_slice())
+            .expect("parse_table checked the table");
+        write(style, buffers)
+    }
+}
+
+/// Checks the table without changing it; valid tables are written back in `style`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Validate;
+
+impl RowProcessor for Validate {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        if parse_table_bytes(json, buffers).is_none() {
+            return buffers.set_invalid();
+        }
+        write(style, buffers)
+    }
+}
+
+fn write(style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+    buffers.json.clear();
+    json::write_array_styled(&mut buffers.json, &buffers.numbers, style);
+    true
+}
+
+/// The built-in processors, by name (`--processor`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Processor {
+    /// Shift every element one position clockwise around its ring
+    #[default]
+    Rotate,
+    /// Mirror the table along its main diagonal
+    Transpose,
+    /// Only check the table, writing valid ones back unchanged
+    Validate,
+}
+
+impl Processor {
+    pub fn get(self) -> &'static dyn RowProcessor {
+        match self {
+            Processor::Rotate => &RotateRight,
+            Processor::Transpose => &Transpose,
+            Processor::Validate => &Validate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(processor: Processor, json: &str) -> (String, bool) {
+        let mut buffers = RowBuffers::new();
+        let valid = processor
+            .get()
+            .process(json.as_bytes(), JsonStyle::Compact, &mut buffers);
+        (String::from_utf8(buffers.json).unwrap(), valid)
+    }
+
+    #[test]
+    fn test_builtin_processors() {
+        let table = "[1, 2, 3, 4, 5, 6, 7, 8, 9]";
+        assert_eq!(
+            process(Processor::Rotate, table),
+            ("[4,1,2,7,5,3,8,9,6]".to_string(), true)
+        );
+        assert_eq!(
+            process(Processor::Transpose, table),
+            ("[1,4,7,2,5,8,3,6,9]".to_string(), true)
+        );
+        assert_eq!(
+            process(Processor::Validate, table),
+            ("[1,2,3,4,5,6,7,8,9]".to_string(), true)
+        );
+        for processor in [Processor::Rotate, Processor::Transpose, Processor::Validate] {
+            assert_eq!(process(processor, "[1, 2, 3]"), ("[]".to_string(), false));
+            assert_eq!(process(processor, "[]"), ("[]".to_string(), false));
+        }
+    }
+
+    #[test]
+    fn test_process_batch_matches_rows() {
+        let tables = [
+            "[1, 2, 3, 4]",
+            "[1, 2, 3]",
+            "[42]",
+            "nope",
+            "[1, 2, 3, 4, 5, 6, 7, 8, 9]",
+        ];
+        for processor in [Processor::Rotate, Processor::Transpose, Processor::Validate] {
+            let mut buffers: Vec<RowBuffers> = tables.iter().map(|_| RowBuffers::new()).collect();
+            let mut valid = Vec::new();
+            let mut rows = tables.iter().map(|json| (&b"1"[..], json.as_bytes()));
+            processor
+                .get()
+                .process_batch(&mut rows, JsonStyle::Compact, &mut buffers, &mut valid);
+
+            let expected: Vec<_> = tables.iter().map(|json| process(processor, json)).collect();
+            let batched: Vec<_> = (buffers.into_iter().zip(valid))
+                .map(|(buffers, valid)| (String::from_utf8(buffers.json).unwrap(), valid))
+                .collect();
+            assert_eq!(batched, expected, "{processor:?}");
+        }
+    }
+}
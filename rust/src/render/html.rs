@@ -0,0 +1,202 @@
+//! `--output-format html`: a single self-contained page for sharing results.
+//!
+//! Every record gets a section with its input and rotated table side by side; invalid
+//! records are highlighted and show the reason and their `json` field instead. A count of
+//! valid and invalid rows closes the page. The styles are inline, so the file can be
+//! mailed or attached as is. With `--diff`, the cells the rotation changed are shaded by
+//! ring.
+
+use super::{Render, Rotation, Table};
+use crate::report::escape;
+use std::io::{self, Write};
+
+/// `json` fields of invalid rows are cut to this many characters.
+const MAX_JSON_CHARS: usize = 200;
+
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+section { margin-bottom: 1.5em; padding: 0.5em 1em; border-left: 4px solid #4a8; }
+section.invalid { border-color: #d44; background: #fdf0f0; }
+h2 { font-size: 1.1em; margin: 0.3em 0; }
+.pair { display: flex; align-items: center; gap: 1em; flex-wrap: wrap; }
+.arrow { font-size: 1.5em; color: #888; }
+table { border-collapse: collapse; }
+caption { font-size: 0.8em; color: #666; }
+td { border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: right; font-variant-numeric: tabular-nums; }
+.reason { color: #b22; font-weight: bold; }
+pre { white-space: pre-wrap; word-break: break-all; }
+td.r0 { background: #fbd5d5; }
+td.r1 { background: #d5f0d5; }
+td.r2 { background: #fbefc8; }
+td.r3 { background: #d5e3fb; }
+td.r4 { background: #ecd5fb; }
+td.r5 { background: #d0f3f3; }
+";
+
+/// Ring shades in [`STYLE`], from the outermost ring inwards and then repeating.
+const RING_CLASSES: usize = 6;
+
+#[derive(Default)]
+pub struct Html {
+    /// Shade moved cells by ring (`--diff`)
+    rings: bool,
+    rows: u64,
+    invalid: u64,
+}
+
+impl Html {
+    pub fn new(rings: bool) -> Self {
+        Html {
+            rings,
+            ..Self::default()
+        }
+    }
+}
+
+impl Render for Html {
+    fn begin(&mut self, output: &mut dyn Write, title: &str) -> io::Result<()> {
+        let title = escape(title);
+        write!(
+            output,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+        )
+    }
+
+    fn table(&mut self, output: &mut dyn Write, table: &Table) -> io::Result<()> {
+        self.rows += 1;
+        let id = escape(table.id);
+        match &table.result {
+            Ok(rotation) => {
+                writeln!(output, "<section>\n<h2>{id}</h2>\n<div class=\"pair\">")?;
+                write_table(output, "input", rotation.original, rotation, self.rings)?;
+                writeln!(output, "<span class=\"arrow\">&rarr;</span>")?;
+                write_table(output, "rotated", rotation.rotated, rotation, self.rings)?;
+                writeln!(output, "</div>\n</section>")
+            }
+            Err(reason) => {
+                self.invalid += 1;
+                let mut json: String = table.json.chars().take(MAX_JSON_CHARS).collect();
+                if json.len() < table.json.len() {
+                    json.push('…');
+                }
+                writeln!(
+                    output,
+                    "<section class=\"invalid\">\n<h2>{id} <span class=\"reason\">invalid: \
+                     {reason}</span></h2>\n<pre>{}</pre>\n</section>",
+                    escape(&json)
+                )
+            }
+        }
+    }
+
+    fn end(&mut self, output: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            output,
+            "<p>{} rows: {} valid, {} invalid</p>\n</body>\n</html>",
+            self.rows,
+            self.rows - self.invalid,
+            self.invalid
+        )
+    }
+}
+
+fn write_table(
+    output: &mut dyn Write,
+    caption: &str,
+    cells: &[i64],
+    rotation: &Rotation,
+    rings: bool,
+) -> io::Result<()> {
+    writeln!(output, "<table>\n<caption>{caption}</caption>")?;
+    let mut buffer = itoa::Buffer::new();
+    for (i, row) in cells.chunks(rotation.n).enumerate() {
+        write!(output, "<tr>")?;
+        for (j, &x) in row.iter().enumerate() {
+            let x = buffer.format(x);
+            match rings
+                .then(|| rotation.moved_ring(i * rotation.n + j))
+                .flatten()
+            {
+                Some(ring) => write!(output, "<td class=\"r{}\">{x}</td>", ring % RING_CLASSES)?,
+                None => write!(output, "<td>{x}</td>")?,
+            }
+        }
+        writeln!(output, "</tr>")?;
+    }
+    writeln!(output, "</table>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rotate_cli::InvalidReason;
+
+    #[test]
+    fn test_html() {
+        let mut html = Html::new(false);
+        let mut output = Vec::new();
+        html.begin(&mut output, "in <1>.csv").unwrap();
+        html.table(
+            &mut output,
+            &Table {
+                id: "1",
+                json: "[1, 2, 3, 4]",
+                result: Ok(Rotation {
+                    n: 2,
+                    original: &[1, 2, 3, 4],
+                    rotated: &[3, 1, 4, 2],
+                }),
+            },
+        )
+        .unwrap();
+        let long = format!("[{}\"]", "<".repeat(300));
+        html.table(
+            &mut output,
+            &Table {
+                id: "b&d",
+                json: &long,
+                result: Err(InvalidReason::Malformed),
+            },
+        )
+        .unwrap();
+        html.end(&mut output).unwrap();
+        let page = String::from_utf8(output).unwrap();
+
+        assert!(page.contains("<title>in &lt;1&gt;.csv</title>"));
+        assert!(page.contains(
+            "<caption>input</caption>\n<tr><td>1</td><td>2</td></tr>\n<tr><td>3</td><td>4</td></tr>"
+        ));
+        assert!(page.contains(
+            "<caption>rotated</caption>\n<tr><td>3</td><td>1</td></tr>\n<tr><td>4</td><td>2</td></tr>"
+        ));
+        assert!(page.contains(
+            "<section class=\"invalid\">\n<h2>b&amp;d <span class=\"reason\">invalid: malformed"
+        ));
+        // The field is cut, and escaped
+        assert!(page.contains(&format!("<pre>[{}</pre>", "&lt;".repeat(199) + "…")));
+        assert!(page.ends_with("<p>2 rows: 1 valid, 1 invalid</p>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn test_html_rings() {
+        let mut html = Html::new(true);
+        let mut output = Vec::new();
+        let table = Table {
+            id: "1",
+            json: "[1, 1, 3, 1]",
+            result: Ok(Rotation {
+                n: 2,
+                original: &[1, 1, 3, 1],
+                rotated: &[3, 1, 1, 1],
+            }),
+        };
+        html.table(&mut output, &table).unwrap();
+        let page = String::from_utf8(output).unwrap();
+        // Cells holding the same value before and after are not shaded
+        assert!(page.contains(
+            "<caption>rotated</caption>\n<tr><td class=\"r0\">3</td><td>1</td></tr>\n\
+             <tr><td class=\"r0\">1</td><td>1</td></tr>"
+        ));
+    }
+}
@@ -0,0 +1,88 @@
+//! `--output-format markdown`: a `### ID` heading and a GitHub-flavored Markdown table per
+//! record.
+//!
+//! GFM tables need a header row, so it is left empty and the table's rows follow the
+//! delimiter row. Invalid records get an italic note with the reason instead of a table.
+
+use super::{Render, Rotation, Table};
+use std::io::{self, Write};
+
+pub struct Markdown;
+
+impl Render for Markdown {
+    fn table(&mut self, output: &mut dyn Write, table: &Table) -> io::Result<()> {
+        writeln!(output, "### {}", heading(table.id))?;
+        writeln!(output)?;
+        match &table.result {
+            Ok(Rotation { n, rotated, .. }) => {
+                writeln!(output, "|{}", "   |".repeat(*n))?;
+                writeln!(output, "|{}", "--:|".repeat(*n))?;
+                let mut buffer = itoa::Buffer::new();
+                for row in rotated.chunks(*n) {
+                    write!(output, "|")?;
+                    for &x in row {
+                        write!(output, " {} |", buffer.format(x))?;
+                    }
+                    writeln!(output)?;
+                }
+            }
+            Err(reason) => writeln!(output, "*invalid: {reason}*")?,
+        }
+        writeln!(output)
+    }
+}
+
+/// The id on one line, with the characters Markdown would interpret escaped.
+fn heading(id: &str) -> String {
+    let mut heading = String::with_capacity(id.len());
+    for c in id.chars() {
+        match c {
+            '\r' | '\n' => heading.push(' '),
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' => {
+                heading.push('\\');
+                heading.push(c);
+            }
+            _ => heading.push(c),
+        }
+    }
+    heading
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rotate_cli::InvalidReason;
+
+    fn render(tables: &[Table]) -> String {
+        let mut output = Vec::new();
+        for table in tables {
+            Markdown.table(&mut output, table).unwrap();
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_markdown() {
+        let output = render(&[
+            Table {
+                id: "1",
+                json: "[1, 2, 3, 4]",
+                result: Ok(Rotation {
+                    n: 2,
+                    original: &[1, 2, 3, 4],
+                    rotated: &[3, 1, 4, 2],
+                }),
+            },
+            Table {
+                id: "bad_row",
+                json: "[1, 2, 3]",
+                result: Err(InvalidReason::NotSquare),
+            },
+        ]);
+        assert_eq!(
+            output,
+            "### 1\n\n|   |   |\n|--:|--:|\n| 3 | 1 |\n| 4 | 2 |\n\n\
+             ### bad\\_row\n\n*invalid: not_square*\n\n"
+        );
+    }
+}
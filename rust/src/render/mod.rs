@@ -0,0 +1,191 @@
+//! Human-readable output (`--output-format`, `--preview`, `--diff`).
+//!
+//! The CSV output is made for other programs; these formats show every table for people,
+//! e.g. to paste into an issue. The input is the usual `id,json` CSV, read and rotated
+//! record by record on the main thread, as the files worth rendering are small.
+
+use crate::engine::Engine;
+use crate::formats::{RecordFormat, pipeline_option};
+use crate::output::Output;
+use crate::stats::{self, Counters};
+use crate::{Cli, input, max_n_limit};
+use clap::ValueEnum;
+use csv::{ByteRecord, ReaderBuilder};
+use rotate_cli::errors::{CliError, RecordError};
+use rotate_cli::{InvalidReason, RowBuffers, parse_table, rotate_right};
+use std::error::Error;
+use std::io::{self, BufWriter, Write};
+use std::time::Instant;
+
+mod html;
+mod markdown;
+mod preview;
+mod rows;
+
+#[cfg_attr(not(feature = "tui"), allow(unused_imports))]
+pub(crate) use preview::grid;
+
+/// How the results are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// `id,json,is_valid` rows
+    #[default]
+    Csv,
+    /// A `### ID` heading and a GitHub-flavored Markdown table per rotated table
+    Markdown,
+    /// A self-contained HTML page with every table before and after, invalid rows marked
+    Html,
+}
+
+/// One processed record, as the renderers see it.
+pub struct Table<'a> {
+    pub id: &'a str,
+    /// The `json` field as read
+    pub json: &'a str,
+    /// The table before and after the rotation, or why the record is not a table
+    pub result: Result<Rotation<'a>, InvalidReason>,
+}
+
+/// The cells of a valid table, row by row.
+pub struct Rotation<'a> {
+    pub n: usize,
+    pub original: &'a [i64],
+    pub rotated: &'a [i64],
+}
+
+impl Rotation<'_> {
+    /// The ring of cell `i`, counting from the outermost (0), if the rotation changed it.
+    pub fn moved_ring(&self, i: usize) -> Option<usize> {
+        let (row, col) = (i / self.n, i % self.n);
+        let ring = row.min(col).min(self.n - 1 - row).min(self.n - 1 - col);
+        (self.original[i] != self.rotated[i]).then_some(ring)
+    }
+}
+
+/// Writes the tables in one output format.
+trait Render {
+    /// Called once before the first table, with the input's name.
+    fn begin(&mut self, _output: &mut dyn Write, _title: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn table(&mut self, output: &mut dyn Write, table: &Table) -> io::Result<()>;
+
+    /// Called once after the last table.
+    fn end(&mut self, _output: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Renders the whole input in `cli.output_format`, or its first tables with `--preview`;
+/// `--diff` runs land here in every output format.
+pub fn run(cli: &Cli) -> Result<(), Box<dyn Error + Send + Sync>> {
+    check_options(cli).map_err(CliError::usage)?;
+    let mut renderer: Box<dyn Render> = match (cli.preview, cli.output_format) {
+        (Some(_), _) => Box::new(preview::Preview { rings: cli.diff }),
+        (None, OutputFormat::Csv) => Box::new(rows::Rows::default()),
+        (None, OutputFormat::Markdown) => Box::new(markdown::Markdown),
+        (None, OutputFormat::Html) => Box::new(html::Html::new(cli.diff)),
+    };
+    // `--diff` colors the grids of --preview and the HTML page, and draws its own to
+    // stderr next to the formats without grids
+    let mut diff = (cli.diff && cli.preview.is_none() && cli.output_format != OutputFormat::Html)
+        .then_some(preview::Preview { rings: true });
+    let limit = cli.preview.unwrap_or(u64::MAX);
+
+    let counters = Counters::default();
+    let started = Instant::now();
+    let path = cli.input.as_deref().ok_or("missing input argument")?;
+    let mut rdr = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(input::open(path)?);
+    let mut output = BufWriter::new(Output::create(cli.output.as_deref())?);
+    let mut buffers = RowBuffers::with_max_n(max_n_limit(cli.max_n));
+    buffers.parser = cli.parser;
+    let mut record = ByteRecord::new();
+    let mut original = Vec::new();
+
+    renderer.begin(&mut output, path)?;
+    let mut rendered = 0;
+    while rendered < limit
+        && rdr
+            .read_byte_record(&mut record)
+            .map_err(|e| RecordError::csv(e, rdr.position()))?
+    {
+        if record.len() < 2 {
+            eprintln!(
+                "Warning: {}; skipped",
+                RecordError::too_few_fields(&record, 2)
+            );
+            continue;
+        }
+        let id = String::from_utf8_lossy(&record[0]);
+        let json = String::from_utf8_lossy(&record[1]);
+        let n = std::str::from_utf8(&record[1])
+            .ok()
+            .and_then(|json| parse_table(json, &mut buffers));
+        let result = match n {
+            Some(n) => {
+                original.clone_from(&buffers.numbers);
+                rotate_right(&mut buffers.numbers).expect("parse_table checked the table");
+                Ok(Rotation {
+                    n,
+                    original: &original,
+                    rotated: &buffers.numbers,
+                })
+            }
+            None => Err(buffers
+                .invalid_reason(false)
+                .unwrap_or(InvalidReason::Malformed)),
+        };
+        let is_valid = result.is_ok();
+        let table = Table {
+            id: &id,
+            json: &json,
+            result,
+        };
+        renderer.table(&mut output, &table)?;
+        if let Some(diff) = &mut diff {
+            diff.table(&mut io::stderr().lock(), &table)?;
+        }
+        rendered += 1;
+        counters.add_rows(1, u64::from(is_valid));
+        counters.set_input_bytes(rdr.position().byte());
+    }
+    renderer.end(&mut output)?;
+    output.into_inner().map_err(|e| e.into_error())?.finish()?;
+
+    if cli.stats {
+        stats::print(counters.snapshot(started.elapsed()));
+    }
+    Ok(())
+}
+
+/// Rejects options the renderers do not implement instead of silently ignoring them.
+fn check_options(cli: &Cli) -> Result<(), String> {
+    let unsupported = [
+        ("--format", cli.format != RecordFormat::Csv),
+        ("--emit-dimension", cli.emit_dimension),
+        ("--to-table", cli.to_table.is_some()),
+        ("--from-postgres", cli.from_postgres.is_some()),
+        ("--engine polars", cli.engine == Engine::Polars),
+    ];
+    let option = pipeline_option(cli).or(unsupported
+        .into_iter()
+        .find_map(|(option, used)| used.then_some(option)));
+    let mode = match cli.preview {
+        Some(_) => "--preview".to_string(),
+        None if cli.output_format == OutputFormat::Csv => "--diff".to_string(),
+        None => format!(
+            "--output-format {}",
+            cli.output_format
+                .to_possible_value()
+                .expect("no variant is skipped")
+                .get_name()
+        ),
+    };
+    match option {
+        Some(option) => Err(format!("{option} is not supported with {mode}")),
+        None => Ok(()),
+    }
+}
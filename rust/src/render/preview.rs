@@ -0,0 +1,161 @@
+//! `--preview N`: the first N tables before and after the rotation, drawn as grids.
+//!
+//! Meant for a quick look at a new dataset in the terminal; nothing else is written. With
+//! `--diff`, the elements the rotation moved are colored by ring, and the same grids are
+//! what `--diff` draws to stderr next to the other output formats.
+//!
+//! ```text
+//! 2 (2×2)
+//! ┌───┬───┐     ┌───┬───┐
+//! │ 1 │ 2 │     │ 3 │ 1 │
+//! ├───┼───┤  →  ├───┼───┤
+//! │ 3 │ 4 │     │ 4 │ 2 │
+//! └───┴───┘     └───┴───┘
+//! ```
+
+use super::{Render, Table};
+use std::io::{self, Write};
+
+/// `json` fields of invalid rows are cut to this many characters.
+const MAX_JSON_CHARS: usize = 60;
+
+/// SGR foreground colors of the rings, from the outermost inwards and then repeating.
+const RING_COLORS: [u8; 6] = [31, 32, 33, 34, 35, 36];
+
+#[derive(Default)]
+pub struct Preview {
+    /// Color moved elements by ring (`--diff`)
+    pub rings: bool,
+}
+
+impl Render for Preview {
+    fn table(&mut self, output: &mut dyn Write, table: &Table) -> io::Result<()> {
+        let id = table.id.replace(['\r', '\n'], " ");
+        let rotation = match &table.result {
+            Ok(rotation) => rotation,
+            Err(reason) => {
+                let mut json: String = table.json.chars().take(MAX_JSON_CHARS).collect();
+                if json.len() < table.json.len() {
+                    json.push('…');
+                }
+                return writeln!(output, "{id}: invalid ({reason}) {json}\n");
+            }
+        };
+
+        writeln!(output, "{id} ({n}×{n})", n = rotation.n)?;
+        let width = rotation
+            .original
+            .iter()
+            .map(|&x| itoa::Buffer::new().format(x).len())
+            .max()
+            .unwrap_or(1);
+        let ring = |i| self.rings.then(|| rotation.moved_ring(i)).flatten();
+        let before = grid(rotation.original, rotation.n, width, ring);
+        let after = grid(rotation.rotated, rotation.n, width, ring);
+        let middle = before.len() / 2;
+        for (i, (left, right)) in before.iter().zip(&after).enumerate() {
+            let arrow = if i == middle { "  →  " } else { "     " };
+            writeln!(output, "{left}{arrow}{right}")?;
+        }
+        writeln!(output)
+    }
+}
+
+/// The lines of a box-drawn `n`×`n` grid with cells `width` characters wide; each cell for
+/// which `ring` returns a ring is colored after it.
+pub fn grid(
+    cells: &[i64],
+    n: usize,
+    width: usize,
+    ring: impl Fn(usize) -> Option<usize>,
+) -> Vec<String> {
+    let border = |left: &str, middle: &str, right: &str| {
+        let segment = "─".repeat(width + 2);
+        format!("{left}{}{right}", vec![segment; n].join(middle))
+    };
+    let mut buffer = itoa::Buffer::new();
+    let mut lines = vec![border("┌", "┬", "┐")];
+    for (i, row) in cells.chunks(n).enumerate() {
+        if i > 0 {
+            lines.push(border("├", "┼", "┤"));
+        }
+        let mut line = String::from("│");
+        for (j, &x) in row.iter().enumerate() {
+            let x = buffer.format(x);
+            match ring(i * n + j) {
+                Some(ring) => {
+                    let color = RING_COLORS[ring % RING_COLORS.len()];
+                    line.push_str(&format!(" \x1b[{color}m{x:>width$}\x1b[0m │"));
+                }
+                None => line.push_str(&format!(" {x:>width$} │")),
+            }
+        }
+        lines.push(line);
+    }
+    lines.push(border("└", "┴", "┘"));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Rotation;
+    use rotate_cli::InvalidReason;
+
+    fn render(table: &Table) -> String {
+        let mut output = Vec::new();
+        Preview::default().table(&mut output, table).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_preview() {
+        let table = Table {
+            id: "2",
+            json: "[1, 2, 3, -40]",
+            result: Ok(Rotation {
+                n: 2,
+                original: &[1, 2, 3, -40],
+                rotated: &[3, 1, -40, 2],
+            }),
+        };
+        assert_eq!(
+            render(&table),
+            "2 (2×2)\n\
+             ┌─────┬─────┐     ┌─────┬─────┐\n\
+             │   1 │   2 │     │   3 │   1 │\n\
+             ├─────┼─────┤  →  ├─────┼─────┤\n\
+             │   3 │ -40 │     │ -40 │   2 │\n\
+             └─────┴─────┘     └─────┴─────┘\n\n"
+        );
+
+        let table = Table {
+            id: "bad",
+            json: "[1, 2, 3]",
+            result: Err(InvalidReason::NotSquare),
+        };
+        assert_eq!(render(&table), "bad: invalid (not_square) [1, 2, 3]\n\n");
+    }
+
+    #[test]
+    fn test_rings() {
+        let table = Table {
+            id: "3",
+            json: "[1, 2, 3, 4, 5, 6, 7, 8, 9]",
+            result: Ok(Rotation {
+                n: 3,
+                original: &[1, 2, 3, 4, 5, 6, 7, 8, 9],
+                rotated: &[4, 1, 2, 7, 5, 3, 8, 9, 6],
+            }),
+        };
+        let mut output = Vec::new();
+        Preview { rings: true }.table(&mut output, &table).unwrap();
+        let lines: Vec<_> = std::str::from_utf8(&output).unwrap().lines().collect();
+        // The center did not move and stays uncolored
+        assert_eq!(
+            lines[4],
+            "│ \x1b[31m4\x1b[0m │ 5 │ \x1b[31m6\x1b[0m │  →  \
+             │ \x1b[31m7\x1b[0m │ 5 │ \x1b[31m3\x1b[0m │"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! The usual `id,json,is_valid` rows, written by the renderer for `--diff` runs so the
+//! result file and the grids on stderr come from the same pass over the input.
+//!
+//! Only the default options reach this path, so the rows match the pipeline's output.
+
+use super::{Render, Table};
+use csv::WriterBuilder;
+use rotate_cli::json;
+use std::io::{self, Write};
+
+#[derive(Default)]
+pub struct Rows {
+    json: Vec<u8>,
+}
+
+impl Render for Rows {
+    fn begin(&mut self, output: &mut dyn Write, _title: &str) -> io::Result<()> {
+        output.write_all(b"id,json,is_valid\n")
+    }
+
+    fn table(&mut self, output: &mut dyn Write, table: &Table) -> io::Result<()> {
+        self.json.clear();
+        let is_valid = match &table.result {
+            Ok(rotation) => {
+                json::write_array(&mut self.json, rotation.rotated);
+                "true"
+            }
+            Err(_) => {
+                self.json.extend_from_slice(b"[]");
+                "false"
+            }
+        };
+        let mut wtr = WriterBuilder::new().from_writer(output);
+        wtr.write_record([table.id.as_bytes(), &self.json, is_valid.as_bytes()])?;
+        wtr.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Rotation;
+    use rotate_cli::InvalidReason;
+
+    #[test]
+    fn test_rows() {
+        let mut rows = Rows::default();
+        let mut output = Vec::new();
+        rows.begin(&mut output, "in.csv").unwrap();
+        let table = Table {
+            id: "a,b",
+            json: "[1, 2, 3, 4]",
+            result: Ok(Rotation {
+                n: 2,
+                original: &[1, 2, 3, 4],
+                rotated: &[3, 1, 4, 2],
+            }),
+        };
+        rows.table(&mut output, &table).unwrap();
+        let table = Table {
+            id: "7",
+            json: "[1]]",
+            result: Err(InvalidReason::Malformed),
+        };
+        rows.table(&mut output, &table).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "id,json,is_valid\n\"a,b\",\"[3,1,4,2]\",true\n7,[],false\n"
+        );
+    }
+}
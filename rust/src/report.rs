@@ -0,0 +1,303 @@
+//! Structured benchmark reports for `rotate_cli bench` and `rotate_cli orchestrate`.
+//!
+//! A [`Report`] is a list of named tables, some of which carry a bar chart of one column.
+//! `--report FILE` writes it in the format given by the file extension:
+//!
+//! - `.json`: `{"title", "sections": [{"name", "rows": [{column: value}]}]}`
+//! - `.csv`: every table with its own header line, tables separated by an empty line
+//! - `.html`: a single page with the tables and inline SVG charts, with no external assets,
+//!   so it can be attached to a CI run or pull request as is
+
+use serde_json::{Value, json};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Bar chart of one numeric column, labelled by another.
+pub struct Chart {
+    pub title: String,
+    pub label_column: usize,
+    pub value_column: usize,
+}
+
+/// A named table.
+pub struct Section {
+    pub name: String,
+    pub columns: Vec<String>,
+    /// Cells are strings, numbers or null (not measured)
+    pub rows: Vec<Vec<Value>>,
+    pub chart: Option<Chart>,
+}
+
+impl Section {
+    pub fn new(name: &str, columns: &[&str]) -> Self {
+        Section {
+            name: name.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: Vec::new(),
+            chart: None,
+        }
+    }
+
+    /// Adds a bar chart of `value_column` labelled by `label_column`.
+    pub fn with_chart(mut self, title: &str, label_column: &str, value_column: &str) -> Self {
+        let index = |name| self.columns.iter().position(|c| c == name);
+        self.chart =
+            index(label_column)
+                .zip(index(value_column))
+                .map(|(label_column, value_column)| Chart {
+                    title: title.to_string(),
+                    label_column,
+                    value_column,
+                });
+        self
+    }
+}
+
+pub struct Report {
+    pub title: String,
+    pub sections: Vec<Section>,
+}
+
+impl Report {
+    /// Writes the report to `path` in the format of its extension.
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::to_string_pretty(&self.to_json())? + "\n",
+            Some("csv") => self.to_csv()?,
+            Some("html" | "htm") => self.to_html(),
+            _ => {
+                return Err(format!(
+                    "unknown report format for {} (use .json, .csv or .html)",
+                    path.display()
+                )
+                .into());
+            }
+        };
+        std::fs::write(path, text).map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+        Ok(())
+    }
+
+    fn to_json(&self) -> Value {
+        let sections: Vec<Value> = self
+            .sections
+            .iter()
+            .map(|section| {
+                let rows: Vec<Value> = section
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let fields = section.columns.iter().cloned().zip(row.iter().cloned());
+                        Value::Object(fields.collect())
+                    })
+                    .collect();
+                json!({ "name": section.name, "rows": rows })
+            })
+            .collect();
+        json!({ "title": self.title, "sections": sections })
+    }
+
+    fn to_csv(&self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut out = Vec::new();
+        for (i, section) in self.sections.iter().enumerate() {
+            if i > 0 {
+                out.push(b'\n');
+            }
+            let mut wtr = csv::Writer::from_writer(&mut out);
+            wtr.write_record(&section.columns)?;
+            for row in &section.rows {
+                wtr.write_record(row.iter().map(plain))?;
+            }
+            wtr.flush()?;
+        }
+        Ok(String::from_utf8(out)?)
+    }
+
+    fn to_html(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+            title = escape(&self.title)
+        );
+        for section in &self.sections {
+            let _ = writeln!(out, "<h2>{}</h2>", escape(&section.name));
+            if let Some(chart) = &section.chart {
+                out.push_str(&bar_chart(section, chart));
+            }
+            out.push_str("<table>\n<tr>");
+            for column in &section.columns {
+                let _ = write!(out, "<th>{}</th>", escape(column));
+            }
+            out.push_str("</tr>\n");
+            for row in &section.rows {
+                out.push_str("<tr>");
+                for cell in row {
+                    let class = if cell.is_number() {
+                        " class=\"num\""
+                    } else {
+                        ""
+                    };
+                    let _ = write!(out, "<td{class}>{}</td>", escape(&display(cell)));
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</table>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; }
+th { background: #f3f3f3; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; }
+svg text { font-size: 12px; }
+";
+
+const BAR_HEIGHT: usize = 22;
+const LABEL_WIDTH: usize = 160;
+const PLOT_WIDTH: usize = 480;
+
+/// Horizontal bars scaled to the largest value; rows without a number are left out.
+fn bar_chart(section: &Section, chart: &Chart) -> String {
+    let bars: Vec<(String, f64)> = section
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let value = row.get(chart.value_column)?.as_f64()?;
+            Some((display(row.get(chart.label_column)?), value))
+        })
+        .collect();
+    let max = bars.iter().map(|(_, value)| *value).fold(0.0, f64::max);
+    if bars.is_empty() || max <= 0.0 {
+        return String::new();
+    }
+
+    let height = BAR_HEIGHT * (bars.len() + 1);
+    let mut svg = format!(
+        "<figure>\n<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{height}\">\n",
+        LABEL_WIDTH + PLOT_WIDTH + 100
+    );
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let y = i * BAR_HEIGHT;
+        let width = (value / max * PLOT_WIDTH as f64).max(1.0);
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\
+             <rect x=\"{LABEL_WIDTH}\" y=\"{}\" width=\"{width:.1}\" height=\"{}\" fill=\"#4e79a7\"/>\
+             <text x=\"{:.1}\" y=\"{}\">{}</text>",
+            LABEL_WIDTH - 6,
+            y + 15,
+            escape(label),
+            y + 3,
+            BAR_HEIGHT - 6,
+            LABEL_WIDTH as f64 + width + 4.0,
+            y + 15,
+            format_number(*value),
+        );
+    }
+    let _ = write!(
+        svg,
+        "</svg>\n<figcaption>{}</figcaption>\n</figure>\n",
+        escape(&chart.title)
+    );
+    svg
+}
+
+/// A cell as text: strings without quotes, null as empty.
+fn plain(cell: &Value) -> String {
+    match cell {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A cell as shown in HTML: like [`plain`], with numbers rounded for reading.
+fn display(cell: &Value) -> String {
+    match cell.as_f64() {
+        Some(number) if cell.is_f64() => format_number(number),
+        _ => plain(cell),
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if value.abs() >= 1000.0 || value.fract() == 0.0 {
+        format!("{value:.0}")
+    } else if value.abs() >= 1.0 {
+        format!("{value:.2}")
+    } else {
+        format!("{value:.4}")
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        let mut section = Section::new("implementations", &["name", "mean (s)", "note"])
+            .with_chart("Mean wall time", "name", "mean (s)");
+        section.rows = vec![
+            vec![json!("rust"), json!(0.25), Value::Null],
+            vec![json!("go <1.22>"), json!(0.5), json!("a, b")],
+        ];
+        Report {
+            title: "Comparison".to_string(),
+            sections: vec![section, Section::new("empty", &["x"])],
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            report().to_json(),
+            json!({
+                "title": "Comparison",
+                "sections": [
+                    {"name": "implementations", "rows": [
+                        {"name": "rust", "mean (s)": 0.25, "note": null},
+                        {"name": "go <1.22>", "mean (s)": 0.5, "note": "a, b"},
+                    ]},
+                    {"name": "empty", "rows": []},
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(
+            report().to_csv().unwrap(),
+            "name,mean (s),note\nrust,0.25,\ngo <1.22>,0.5,\"a, b\"\n\nx\n"
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        let html = report().to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<td>go &lt;1.22&gt;</td><td class=\"num\">0.5000</td>"));
+        // The largest value spans the whole plot
+        assert!(html.contains(&format!("width=\"{PLOT_WIDTH}.0\"")));
+        assert!(html.contains("<figcaption>Mean wall time</figcaption>"));
+    }
+
+    #[test]
+    fn test_write_rejects_unknown_extension() {
+        let error = report().write(Path::new("report.txt")).unwrap_err();
+        assert!(error.to_string().starts_with("unknown report format"));
+    }
+}
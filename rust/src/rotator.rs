@@ -0,0 +1,324 @@
+//! [`CsvRotator`]: the whole `rotate_cli INPUT.csv` run as a library call.
+//!
+//! It reads `id,json` records from any reader, runs them through the parallel
+//! [`pipeline`](crate::pipeline) and writes the output rows, header included, to any writer,
+//! exactly as the CLI does. Options cover the header row, which input columns hold the id
+//! and the table, the output layout and the `--on-error` policy; the result is the run's
+//! [`Summary`].
+//!
+//! ```
+//! use rotate_cli::{Columns, CsvRotator, RotatorOptions};
+//!
+//! let input = "json,key\n\"[1, 2, 3, 4]\",a\n[1],b\n";
+//! let rotator = CsvRotator::new(RotatorOptions {
+//!     columns: Columns::named("key", "json"),
+//!     ..RotatorOptions::default()
+//! });
+//! let mut output = Vec::new();
+//! let summary = rotator.process(input.as_bytes(), &mut output).unwrap();
+//! assert_eq!(output, b"id,json,is_valid\na,\"[3,1,4,2]\",true\nb,[1],true\n");
+//! assert_eq!((summary.rows, summary.valid), (2, 2));
+//! ```
+
+use crate::counters::{Counters, Summary};
+use crate::errors::OnError;
+use crate::json::JsonStyle;
+use crate::pipeline::{self, RowFormat, Sample};
+use crate::processor::{RotateRight, RowProcessor};
+use crate::row::DEFAULT_MAX_N;
+use csv::{ByteRecord, ReaderBuilder, WriterBuilder};
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Which input fields hold the id and the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Columns {
+    /// Zero-based field positions
+    Index { id: usize, json: usize },
+    /// Names looked up in the header row
+    Named { id: String, json: String },
+}
+
+impl Columns {
+    pub fn named(id: impl Into<String>, json: impl Into<String>) -> Self {
+        Columns::Named {
+            id: id.into(),
+            json: json.into(),
+        }
+    }
+
+    /// Field positions of the id and the table, given the input's header row.
+    pub fn resolve(&self, headers: Option<&ByteRecord>) -> Result<(usize, usize), String> {
+        let (id, json) = match self {
+            Columns::Index { id, json } => return Ok((*id, *json)),
+            Columns::Named { id, json } => (id, json),
+        };
+        let headers = headers.ok_or("columns can only be selected by name with a header row")?;
+        let find = |name: &str| {
+            headers
+                .iter()
+                .position(|field| field == name.as_bytes())
+                .ok_or_else(|| format!("the header row has no '{name}' column"))
+        };
+        Ok((find(id)?, find(json)?))
+    }
+}
+
+impl Default for Columns {
+    /// `id` first, `json` second, as in the CLI's input.
+    fn default() -> Self {
+        Columns::Index { id: 0, json: 1 }
+    }
+}
+
+/// Settings for a [`CsvRotator`]; the defaults are those of the CLI.
+#[derive(Debug, Clone)]
+pub struct RotatorOptions {
+    /// Worker threads; 0 uses one per available core
+    pub threads: usize,
+    /// The first input record is a header row, not data
+    pub has_headers: bool,
+    /// Start the output with the `id,json,is_valid` header row
+    pub write_header: bool,
+    pub columns: Columns,
+    /// Serialization of the `json` column
+    pub json_style: JsonStyle,
+    /// Append the `n` column
+    pub emit_dimension: bool,
+    /// Write the input's `json` field for invalid rows instead of `[]`
+    pub keep_original: bool,
+    /// Handling of records that cannot be processed
+    pub on_error: OnError,
+    /// Run `verify_rotation_identity` on one in this many valid rows
+    pub self_check: Option<u64>,
+    /// Largest accepted table side; bigger tables become invalid rows
+    pub max_n: Option<usize>,
+    /// Approximate memory budget of the run, in bytes (see [`MemoryBudget`](crate::budget::MemoryBudget))
+    pub max_memory: Option<usize>,
+    /// Process only a random subset of the records
+    pub sample: Option<Sample>,
+}
+
+impl Default for RotatorOptions {
+    fn default() -> Self {
+        RotatorOptions {
+            threads: 0,
+            has_headers: true,
+            write_header: true,
+            columns: Columns::default(),
+            json_style: JsonStyle::default(),
+            emit_dimension: false,
+            keep_original: false,
+            on_error: OnError::default(),
+            self_check: None,
+            max_n: Some(DEFAULT_MAX_N),
+            max_memory: None,
+            sample: None,
+        }
+    }
+}
+
+/// Rotates every table of a CSV stream, as `rotate_cli INPUT.csv` does.
+#[derive(Clone)]
+pub struct CsvRotator {
+    options: RotatorOptions,
+    processor: Arc<dyn RowProcessor>,
+}
+
+impl Default for CsvRotator {
+    fn default() -> Self {
+        CsvRotator::new(RotatorOptions::default())
+    }
+}
+
+impl fmt::Debug for CsvRotator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CsvRotator")
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CsvRotator {
+    pub fn new(options: RotatorOptions) -> Self {
+        CsvRotator {
+            options,
+            processor: Arc::new(RotateRight),
+        }
+    }
+
+    /// Applies `processor` to every table instead of rotating it.
+    pub fn with_processor(mut self, processor: impl RowProcessor + 'static) -> Self {
+        self.processor = Arc::new(processor);
+        self
+    }
+
+    /// Reads all records from `reader` and writes the output rows to `writer`, then flushes it.
+    ///
+    /// Rows read before an error are written before it is returned; which errors end the run
+    /// is decided by [`RotatorOptions::on_error`].
+    pub fn process<R, W>(
+        &self,
+        reader: R,
+        writer: W,
+    ) -> Result<Summary, Box<dyn Error + Send + Sync>>
+    where
+        R: Read + Send,
+        W: Write + Send,
+    {
+        let options = &self.options;
+        let started = Instant::now();
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(options.has_headers)
+            .flexible(true)
+            .from_reader(reader);
+        let headers = if options.has_headers {
+            Some(rdr.byte_headers()?)
+        } else {
+            None
+        };
+        let (id_column, json_column) = options.columns.resolve(headers)?;
+        let format = RowFormat {
+            emit_dimension: options.emit_dimension,
+            json_style: options.json_style,
+            keep_original: options.keep_original,
+            self_check: options.self_check,
+            on_error: options.on_error,
+            id_column,
+            json_column,
+            max_n: options.max_n,
+            max_memory: options.max_memory,
+            sample: options.sample,
+            ..RowFormat::default()
+        };
+
+        let mut wtr = WriterBuilder::new().has_headers(false).from_writer(writer);
+        if options.write_header {
+            wtr.write_record(format.header())?;
+        }
+        let output = wtr.into_inner().map_err(|e| e.into_error())?;
+
+        let workers = match options.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            threads => threads,
+        };
+        let counters = Counters::default();
+        pipeline::run(
+            rdr,
+            output,
+            workers,
+            format,
+            self.processor.as_ref(),
+            &counters,
+            None,
+        )?;
+        Ok(counters.snapshot(started.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(options: RotatorOptions, input: &str) -> Result<String, String> {
+        let mut output = Vec::new();
+        CsvRotator::new(options)
+            .process(input.as_bytes(), &mut output)
+            .map_err(|e| e.to_string())?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_default_matches_cli() {
+        let input = "id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2, 3]\"\nshort\n3,[42]\n";
+        assert_eq!(
+            process(RotatorOptions::default(), input).unwrap(),
+            "id,json,is_valid\n1,\"[3,1,4,2]\",true\n2,[],false\n3,[42],true\n"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let input = "id,json\n1,[1]\n2,\"[1, 2]\"\n";
+        let summary = CsvRotator::default()
+            .process(input.as_bytes(), Vec::new())
+            .unwrap();
+        assert_eq!((summary.rows, summary.valid), (2, 1));
+        assert_eq!(summary.input_bytes, input.len() as u64);
+    }
+
+    #[test]
+    fn test_columns_by_index_without_headers() {
+        let options = RotatorOptions {
+            threads: 2,
+            has_headers: false,
+            write_header: false,
+            columns: Columns::Index { id: 2, json: 0 },
+            emit_dimension: true,
+            ..RotatorOptions::default()
+        };
+        assert_eq!(
+            process(options, "\"[1, 2, 3, 4]\",x,a\n[5],y\n").unwrap(),
+            "a,\"[3,1,4,2]\",true,2\n"
+        );
+    }
+
+    #[test]
+    fn test_max_n() {
+        let input = "id,json\n1,\"[1, 2, 3, 4]\"\n2,[5]\n";
+        let options = RotatorOptions {
+            max_n: Some(1),
+            ..RotatorOptions::default()
+        };
+        assert_eq!(
+            process(options, input).unwrap(),
+            "id,json,is_valid\n1,[],false\n2,[5],true\n"
+        );
+    }
+
+    #[test]
+    fn test_with_processor() {
+        let input = "id,json\n1,\"[1, 2, 3, 4]\"\n2,\"[1, 2, 3]\"\n";
+        let mut output = Vec::new();
+        CsvRotator::default()
+            .with_processor(crate::processor::Transpose)
+            .process(input.as_bytes(), &mut output)
+            .unwrap();
+        assert_eq!(
+            output,
+            b"id,json,is_valid\n1,\"[1,3,2,4]\",true\n2,[],false\n"
+        );
+    }
+
+    #[test]
+    fn test_named_columns() {
+        let options = RotatorOptions {
+            columns: Columns::named("key", "table"),
+            on_error: OnError::Fail,
+            ..RotatorOptions::default()
+        };
+        assert_eq!(
+            process(options.clone(), "table,key\n[7],k\n").unwrap(),
+            "id,json,is_valid\nk,[7],true\n"
+        );
+        assert_eq!(
+            process(options.clone(), "table,key\n[7]\n").unwrap_err(),
+            "record 1 (line 2, byte 10) has 1 field, expected at least 2 (id, json)"
+        );
+        assert_eq!(
+            process(options.clone(), "id,json\n1,[7]\n").unwrap_err(),
+            "the header row has no 'key' column"
+        );
+        let options = RotatorOptions {
+            has_headers: false,
+            ..options
+        };
+        assert_eq!(
+            process(options, "1,[7]\n").unwrap_err(),
+            "columns can only be selected by name with a header row"
+        );
+    }
+}
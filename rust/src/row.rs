@@ -0,0 +1,573 @@
+//! One `id,json` row in, one `id,json,is_valid` row out: the CLI's row semantics.
+//!
+//! A row is valid when its `json` cell is an array of integers (whole-number floats
+//! included) with a non-zero square length. Valid tables are rotated one step clockwise
+//! and written back in the requested [`JsonStyle`]; anything else becomes `[]` with
+//! `is_valid = false`. The CSV, RPC, HTTP, Kafka and Redis modes of `rotate_cli` all go
+//! through [`process_json_array`], so embedding it gives exactly the same output.
+//!
+//! [`RowBuffers::max_n`] caps the table size: parsing stops as soon as an array has more
+//! than `max_n * max_n` elements, so one corrupt or hostile row cannot exhaust the memory
+//! of a long-running server. [`RowBuffers::invalid_reason`] tells such rows apart from
+//! the other invalid ones. [`RowBuffers::timeout`] likewise abandons a table whose parsing
+//! takes too long, so a pathological payload cannot wedge a server's worker.
+//!
+//! ```
+//! use rotate_cli::json::JsonStyle;
+//! use rotate_cli::rotate_row;
+//!
+//! let row = rotate_row("7", "[1, 2, 3, 4]", JsonStyle::Compact);
+//! assert_eq!((row.json.as_str(), row.is_valid, row.n), ("[3,1,4,2]", true, Some(2)));
+//!
+//! let row = rotate_row("8", "[1, 2, 3]", JsonStyle::Compact);
+//! assert_eq!((row.json.as_str(), row.is_valid, row.n), ("[]", false, None));
+//! ```
+
+use crate::json::{self, JsonStyle};
+use crate::parse::{self, JsonParser, Limits, ParseError};
+use crate::{ShapeError, rotate_right_unchecked, square_len, validate_table};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The CLI's default `--max-n`: a 10,000×10,000 table already takes 800 MB as `i64`s.
+pub const DEFAULT_MAX_N: usize = 10_000;
+
+/// Why a row is invalid, as reported in the `error_reason` field of the server modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidReason {
+    /// Not a JSON array of integers
+    Malformed,
+    /// An empty array
+    Empty,
+    /// The element count is not a perfect square
+    NotSquare,
+    /// More than `max_n * max_n` elements
+    TooLarge,
+    /// Parsing took longer than the row timeout
+    Timeout,
+    /// A valid table the row processor refused, such as one a `--script` failed on
+    Rejected,
+    /// The record violates the `--schema`
+    Schema,
+    /// The id is not of the `--id-type`
+    Id,
+}
+
+impl InvalidReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InvalidReason::Malformed => "malformed",
+            InvalidReason::Empty => "empty",
+            InvalidReason::NotSquare => "not_square",
+            InvalidReason::TooLarge => "too_large",
+            InvalidReason::Timeout => "timeout",
+            InvalidReason::Rejected => "rejected",
+            InvalidReason::Schema => "schema",
+            InvalidReason::Id => "id",
+        }
+    }
+}
+
+impl From<ShapeError> for InvalidReason {
+    fn from(shape: ShapeError) -> Self {
+        match shape.len {
+            0 => InvalidReason::Empty,
+            _ => InvalidReason::NotSquare,
+        }
+    }
+}
+
+impl fmt::Display for InvalidReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Scratch buffers reused across rows so the hot loop does not allocate per record.
+#[derive(Debug, Default)]
+pub struct RowBuffers {
+    /// Parsed table elements; after a valid row, the rotated table
+    pub numbers: Vec<i64>,
+    /// Serialized output for the `json` column
+    pub json: Vec<u8>,
+    /// Largest accepted table side; `None` accepts any size
+    pub max_n: Option<usize>,
+    /// Time one table may take to parse before it is given up on
+    pub timeout: Option<Duration>,
+    /// Parser for the `json` text
+    pub parser: JsonParser,
+    /// State of [`JsonParser::Simd`], reused across rows
+    #[cfg(feature = "simd-json")]
+    simd: parse::SimdParser,
+    /// Why [`parse_table`] last rejected a table
+    reason: Option<InvalidReason>,
+    /// What exactly was wrong with the last rejected row, if known
+    detail: String,
+    /// The length of the last rejected table, if it was empty or not square
+    shape: Option<ShapeError>,
+}
+
+impl RowBuffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Buffers that reject tables with a side longer than `max_n`.
+    pub fn with_max_n(max_n: Option<usize>) -> Self {
+        RowBuffers {
+            max_n,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the output to the empty array used for invalid rows and returns `false`.
+    pub fn set_invalid(&mut self) -> bool {
+        self.json.clear();
+        self.json.extend_from_slice(b"[]");
+        false
+    }
+
+    /// [`set_invalid`](Self::set_invalid), recording `reason` for [`invalid_reason`](Self::invalid_reason).
+    pub fn reject(&mut self, reason: InvalidReason) -> bool {
+        self.reason = Some(reason);
+        self.detail.clear();
+        self.shape = None;
+        self.set_invalid()
+    }
+
+    /// [`reject`](Self::reject) for a table that is empty or not square, recording its
+    /// length for [`explanation`](Self::explanation).
+    pub fn reject_shape(&mut self, shape: ShapeError) -> bool {
+        self.reject(InvalidReason::from(shape));
+        self.shape = Some(shape);
+        false
+    }
+
+    /// [`reject`](Self::reject), with a description of the problem for
+    /// [`error_reason`](Self::error_reason).
+    pub fn reject_with(&mut self, reason: InvalidReason, detail: impl fmt::Display) -> bool {
+        self.reject(reason);
+        use std::fmt::Write;
+        write!(self.detail, "{detail}").expect("writing to a String cannot fail");
+        false
+    }
+
+    /// Side length of the table of the last row, if it was valid.
+    pub fn dimension(&self, is_valid: bool) -> Option<usize> {
+        is_valid.then(|| square_len(self.numbers.len())).flatten()
+    }
+
+    /// Why the last row was invalid; `None` if it was valid.
+    ///
+    /// Rows rejected after [`parse_table`] accepted them are reported as
+    /// [`InvalidReason::Rejected`].
+    pub fn invalid_reason(&self, is_valid: bool) -> Option<InvalidReason> {
+        (!is_valid).then(|| self.reason.unwrap_or(InvalidReason::Rejected))
+    }
+
+    /// [`invalid_reason`](Self::invalid_reason) as text, followed by the detail given to
+    /// [`reject_with`](Self::reject_with): `schema: /json/3: 15 is greater than the maximum 10`.
+    pub fn error_reason(&self, is_valid: bool) -> Option<String> {
+        let reason = self.invalid_reason(is_valid)?;
+        Some(match self.detail.is_empty() {
+            true => reason.to_string(),
+            false => format!("{reason}: {}", self.detail),
+        })
+    }
+
+    /// How far the last table was from square, if it was rejected as empty or not square.
+    pub fn shape(&self, is_valid: bool) -> Option<ShapeError> {
+        self.shape.filter(|_| !is_valid)
+    }
+
+    /// What is wrong with the last row, for people (`--explain`): for tables that are empty
+    /// or not square, how far they are from the nearest squares (see [`validate_table`]),
+    /// such as `15 elements: remove 6 for 3×3 or add 1 for 4×4`; otherwise the
+    /// [`error_reason`](Self::error_reason). `None` if the row was valid.
+    pub fn explanation(&self, is_valid: bool) -> Option<String> {
+        match self.shape(is_valid) {
+            Some(shape) => Some(shape.to_string()),
+            None => self.error_reason(is_valid),
+        }
+    }
+}
+
+/// Process a JSON string containing an array of numbers.
+/// Writes either the rotated array (in `style`) or an empty array into `buffers.json` and
+/// returns validity.
+///
+/// # Examples
+///
+/// ```
+/// use rotate_cli::json::JsonStyle;
+/// use rotate_cli::{RowBuffers, process_json_array};
+///
+/// let mut buffers = RowBuffers::new();
+/// assert!(process_json_array("[1, 2, 3, 4]", JsonStyle::Spaced, &mut buffers));
+/// assert_eq!(buffers.json, b"[3, 1, 4, 2]");
+/// ```
+pub fn process_json_array(json_text: &str, style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+    let Some(n) = parse_table(json_text, buffers) else {
+        return buffers.set_invalid();
+    };
+
+    // Rotate the table; `parse_table` already made the checks `rotate_right` would repeat
+    // SAFETY: parse_table returned n, so the table has n * n elements
+    unsafe { rotate_right_unchecked(&mut buffers.numbers, n) };
+
+    // Convert back to JSON, formatting digits straight into the reused buffer
+    buffers.json.clear();
+    json::write_array_styled(&mut buffers.json, &buffers.numbers, style);
+    true
+}
+
+/// Parses a table into `buffers.numbers` and returns its side length, or `None` if the text
+/// is not a non-empty square array of integers no larger than `buffers.max_n` allows.
+pub fn parse_table(json_text: &str, buffers: &mut RowBuffers) -> Option<usize> {
+    buffers.shape = None;
+    let (n, reason) = match check_table(json_text, buffers) {
+        Ok(n) => (Some(n), None),
+        Err(reason) => (None, Some(reason)),
+    };
+    buffers.reason = reason;
+    buffers.detail.clear();
+    n
+}
+
+fn check_table(json_text: &str, buffers: &mut RowBuffers) -> Result<usize, InvalidReason> {
+    let limits = Limits {
+        max_len: buffers.max_n.map(|max_n| max_n.saturating_mul(max_n)),
+        deadline: buffers.timeout.map(|timeout| Instant::now() + timeout),
+    };
+    // Parse straight into integers; any non-integral element makes the row invalid
+    let parsed = match buffers.parser {
+        #[cfg(feature = "simd-json")]
+        JsonParser::Simd => {
+            buffers
+                .simd
+                .parse_numbers_limited(json_text, &mut buffers.numbers, limits)
+        }
+        JsonParser::Fast => parse::parse_numbers_fast(json_text, &mut buffers.numbers, limits),
+        _ => parse::parse_numbers_limited(json_text, &mut buffers.numbers, limits),
+    };
+    parsed.map_err(|e| match e {
+        ParseError::Invalid => InvalidReason::Malformed,
+        ParseError::TooLong => InvalidReason::TooLarge,
+        ParseError::TimedOut => InvalidReason::Timeout,
+    })?;
+
+    // Check if it can form a square table; an empty array is technically a 0x0 square but
+    // we treat it as invalid per spec
+    validate_table(buffers.numbers.len()).map_err(|shape| {
+        buffers.shape = Some(shape);
+        InvalidReason::from(shape)
+    })
+}
+
+/// [`parse_table`] for a raw field; text that is not UTF-8 is not a table.
+pub(crate) fn parse_table_bytes(json: &[u8], buffers: &mut RowBuffers) -> Option<usize> {
+    match std::str::from_utf8(json) {
+        Ok(json_text) => parse_table(json_text, buffers),
+        Err(_) => {
+            buffers.reject(InvalidReason::Malformed);
+            None
+        }
+    }
+}
+
+/// [`process_json_array`] for a raw field, as read from CSV or a message payload;
+/// text that is not UTF-8 cannot be JSON and makes the row invalid.
+pub fn process_json_bytes(json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+    match std::str::from_utf8(json) {
+        Ok(json_text) => process_json_array(json_text, style, buffers),
+        Err(_) => buffers.reject(InvalidReason::Malformed),
+    }
+}
+
+/// An output row, as `rotate_cli` writes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotatedRow {
+    pub id: String,
+    /// The rotated table, or `[]` for an invalid row
+    pub json: String,
+    pub is_valid: bool,
+    /// Side length of the table (the `--emit-dimension` column); `None` for invalid rows
+    pub n: Option<usize>,
+}
+
+/// Processes a single row.
+///
+/// This allocates the output strings; to process many rows, reuse one [`RowBuffers`] with
+/// [`process_json_array`] instead.
+pub fn rotate_row(id: &str, json_text: &str, style: JsonStyle) -> RotatedRow {
+    let mut buffers = RowBuffers::new();
+    let is_valid = process_json_array(json_text, style, &mut buffers);
+    let n = buffers.dimension(is_valid);
+    RotatedRow {
+        id: id.to_string(),
+        // Only ASCII digits, signs and punctuation are ever written
+        json: String::from_utf8(buffers.json).expect("serialized JSON is ASCII"),
+        is_valid,
+        n,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(json_text: &str) -> (String, bool) {
+        let mut buffers = RowBuffers::default();
+        let valid = process_json_array(json_text, JsonStyle::Compact, &mut buffers);
+        (String::from_utf8(buffers.json).unwrap(), valid)
+    }
+
+    #[test]
+    fn test_process_valid_2x2() {
+        // Original:        After 1-step clockwise:
+        // [1, 2]       →   [3, 1]
+        // [3, 4]           [4, 2]
+        // Ring: 1→2→4→3 becomes 3→1→2→4
+        // Expected JSON: "[3,1,4,2]"
+        let (result, valid) = process("[1, 2, 3, 4]");
+        assert!(valid);
+        assert_eq!(result, "[3,1,4,2]");
+    }
+
+    #[test]
+    fn test_process_valid_3x3() {
+        // Original:           After 1-step clockwise:
+        // [1, 2, 3]       →   [4, 1, 2]
+        // [4, 5, 6]           [7, 5, 3]
+        // [7, 8, 9]           [8, 9, 6]
+        // Ring: 1→2→3→6→9→8→7→4 becomes 4→1→2→3→6→9→8→7, center 5 unchanged
+        // Expected JSON: "[4,1,2,7,5,3,8,9,6]"
+        let (result, valid) = process("[1, 2, 3, 4, 5, 6, 7, 8, 9]");
+        assert!(valid);
+        assert_eq!(result, "[4,1,2,7,5,3,8,9,6]");
+    }
+
+    #[test]
+    fn test_process_valid_1x1() {
+        // Original: [42]  →  After: [42] (single element unchanged)
+        // Expected JSON: "[42]"
+        let (result, valid) = process("[42]");
+        assert!(valid);
+        assert_eq!(result, "[42]");
+    }
+
+    #[test]
+    fn test_process_invalid_non_square() {
+        let (result, valid) = process("[1, 2, 3]");
+        assert!(!valid);
+        assert_eq!(result, "[]");
+    }
+
+    #[test]
+    fn test_process_invalid_empty() {
+        let (result, valid) = process("[]");
+        assert!(!valid);
+        assert_eq!(result, "[]");
+    }
+
+    #[test]
+    fn test_process_invalid_non_array() {
+        let (result, valid) = process("42");
+        assert!(!valid);
+        assert_eq!(result, "[]");
+    }
+
+    #[test]
+    fn test_process_invalid_non_numeric() {
+        let (result, valid) = process("[1, \"hello\", 3]");
+        assert!(!valid);
+        assert_eq!(result, "[]");
+    }
+
+    #[test]
+    fn test_process_malformed_json() {
+        let (result, valid) = process("[1, 2,");
+        assert!(!valid);
+        assert_eq!(result, "[]");
+    }
+
+    #[test]
+    fn test_process_with_negative_numbers() {
+        // Original:         After 1-step clockwise:
+        // [-1, -2]      →   [-3, -1]
+        // [-3, -4]          [-4, -2]
+        // Ring: -1→-2→-4→-3 becomes -3→-1→-2→-4
+        // Expected JSON: "[-3,-1,-4,-2]"
+        let (result, valid) = process("[-1, -2, -3, -4]");
+        assert!(valid);
+        assert_eq!(result, "[-3,-1,-4,-2]");
+    }
+
+    #[test]
+    fn test_process_reuses_buffers() {
+        let mut buffers = RowBuffers::default();
+        assert!(process_json_array(
+            "[1, 2, 3, 4, 5, 6, 7, 8, 9]",
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        assert!(!process_json_array(
+            "[1, 2, 3]",
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        assert_eq!(buffers.json, b"[]");
+        assert!(process_json_array(
+            "[1, 2, 3, 4]",
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        assert_eq!(buffers.json, b"[3,1,4,2]");
+    }
+
+    #[test]
+    fn test_process_json_bytes() {
+        let mut buffers = RowBuffers::default();
+        assert!(process_json_bytes(b"[5]", JsonStyle::Compact, &mut buffers));
+        assert!(!process_json_bytes(
+            b"[\xff]",
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        assert_eq!(buffers.json, b"[]");
+    }
+
+    #[test]
+    fn test_max_n() {
+        let mut buffers = RowBuffers::with_max_n(Some(2));
+        assert!(process_json_array(
+            "[1, 2, 3, 4]",
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        assert_eq!(buffers.invalid_reason(true), None);
+        assert!(!process_json_array(
+            "[1, 2, 3, 4, 5, 6, 7, 8, 9]",
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        assert_eq!(buffers.json, b"[]");
+        assert_eq!(buffers.invalid_reason(false), Some(InvalidReason::TooLarge));
+        // Parsing stops at the first element past the cap
+        assert_eq!(buffers.numbers.len(), 5);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut buffers = RowBuffers {
+            timeout: Some(Duration::ZERO),
+            ..RowBuffers::default()
+        };
+        let long = format!("[{}]", vec!["1"; 4096].join(","));
+        let is_valid = process_json_array(&long, JsonStyle::Compact, &mut buffers);
+        assert_eq!(
+            buffers.invalid_reason(is_valid),
+            Some(InvalidReason::Timeout)
+        );
+        buffers.timeout = Some(Duration::from_secs(60));
+        assert!(process_json_array(&long, JsonStyle::Compact, &mut buffers));
+    }
+
+    #[test]
+    fn test_invalid_reason() {
+        let mut buffers = RowBuffers::default();
+        for (json_text, reason) in [
+            ("[1, 2,", InvalidReason::Malformed),
+            ("[1, \"2\"]", InvalidReason::Malformed),
+            ("[]", InvalidReason::Empty),
+            ("[1, 2, 3]", InvalidReason::NotSquare),
+        ] {
+            let is_valid = process_json_array(json_text, JsonStyle::Compact, &mut buffers);
+            assert_eq!(
+                buffers.invalid_reason(is_valid),
+                Some(reason),
+                "{json_text}"
+            );
+        }
+        let is_valid = process_json_bytes(b"[\xff]", JsonStyle::Compact, &mut buffers);
+        assert_eq!(
+            buffers.invalid_reason(is_valid),
+            Some(InvalidReason::Malformed)
+        );
+        assert!(parse_table("[1]", &mut buffers).is_some());
+        let is_valid = buffers.set_invalid();
+        assert_eq!(
+            buffers.invalid_reason(is_valid),
+            Some(InvalidReason::Rejected)
+        );
+    }
+
+    #[test]
+    fn test_explanation() {
+        let mut buffers = RowBuffers::default();
+        for (json_text, explanation) in [
+            ("[1, 2, 3]", "3 elements: remove 2 for 1×1 or add 1 for 2×2"),
+            ("[]", "empty: add 1 for 1×1"),
+            ("[1, 2,", "malformed"),
+        ] {
+            let is_valid = process_json_array(json_text, JsonStyle::Compact, &mut buffers);
+            assert_eq!(
+                buffers.explanation(is_valid).as_deref(),
+                Some(explanation),
+                "{json_text}"
+            );
+        }
+        // A later valid or differently invalid row does not keep the shape
+        assert!(!process_json_array(
+            "[1, 2, 3]",
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        let is_valid = process_json_array("[1]", JsonStyle::Compact, &mut buffers);
+        assert_eq!(buffers.explanation(is_valid), None);
+        assert!(!process_json_array(
+            "[1, 2, 3]",
+            JsonStyle::Compact,
+            &mut buffers
+        ));
+        let is_valid = buffers.reject(InvalidReason::Rejected);
+        assert_eq!(buffers.shape(is_valid), None);
+    }
+
+    #[test]
+    fn test_parsers_agree() {
+        for parser in [JsonParser::Serde, JsonParser::Simd, JsonParser::Fast] {
+            let mut buffers = RowBuffers::with_max_n(Some(2));
+            buffers.parser = parser;
+            for (json_text, reason) in [
+                ("[1, 2.0, 3, 4]", None),
+                ("[1, 2, 3]", Some(InvalidReason::NotSquare)),
+                ("[1, [2], 3, 4]", Some(InvalidReason::Malformed)),
+                ("[1, 2, 3, 4, 5, 6, 7, 8, 9]", Some(InvalidReason::TooLarge)),
+            ] {
+                let is_valid = process_json_array(json_text, JsonStyle::Compact, &mut buffers);
+                assert_eq!(
+                    buffers.invalid_reason(is_valid),
+                    reason,
+                    "{parser} {json_text}"
+                );
+            }
+            assert_eq!(buffers.json, b"[]");
+        }
+    }
+
+    #[test]
+    fn test_rotate_row() {
+        assert_eq!(
+            rotate_row("a", "[1, 2, 3, 4]", JsonStyle::Spaced),
+            RotatedRow {
+                id: "a".to_string(),
+                json: "[3, 1, 4, 2]".to_string(),
+                is_valid: true,
+                n: Some(2),
+            }
+        );
+        let invalid = rotate_row("b", "[]", JsonStyle::Compact);
+        assert_eq!((invalid.json.as_str(), invalid.n), ("[]", None));
+    }
+}
@@ -0,0 +1,466 @@
+//! JSON-RPC 2.0 over stdin/stdout (`--rpc`).
+//!
+//! Editors and orchestration scripts can keep one process running and send it tables
+//! instead of writing CSV temp files. Messages are either one JSON document per line or
+//! framed with LSP-style `Content-Length` headers; each response uses the framing of its
+//! request, so language-server clients work unchanged.
+//!
+//! Methods:
+//! - `rotate` `{"json": "[1,2,3,4]"}` or `{"table": [1,2,3,4]}` →
+//!   `{"json": "[3,1,4,2]", "is_valid": true, "n": 2}`
+//! - `validate` (same params) → `{"is_valid": true, "n": 2}`
+//!
+//! Invalid tables also get an `error_reason`: `malformed`, `empty`, `not_square` or
+//! `too_large` (more than `--max-n` rows) or `timeout` (parsing took longer than
+//! `--row-timeout`). `empty` and `not_square` tables also get an `explanation`, such as
+//! `3 elements: remove 2 for 1×1 or add 1 for 2×2`, and the same numbers as a `shape`:
+//! `{"len": 3, "smaller": 1, "remove": 2, "larger": 2, "add": 1}` (see
+//! [`validate_table`](crate::validate_table)).
+//! - `stats` → rows processed so far and throughput since startup
+//!
+//! Invalid rows and failed requests also carry the stable [`ErrorCode`] of the failure, as
+//! `error_code` in the result and in the error's `data` respectively, such as `E002` for a
+//! table that is not square or `E012` for a request without a table.
+
+use crate::metrics::Metrics;
+use crate::stats::Counters;
+use rotate_cli::errors::ErrorCode;
+use rotate_cli::json::JsonStyle;
+use rotate_cli::{RowBuffers, parse_table, process_json_array};
+use serde_json::value::RawValue;
+use serde_json::{Value, json};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use std::time::Instant;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// How a message was delimited on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    Line,
+    ContentLength,
+}
+
+/// Serves requests from `input` until it is closed, updating `metrics` when given; `buffers`
+/// carry the per-row limits.
+pub fn serve<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    buffers: RowBuffers,
+    metrics: Option<Arc<Metrics>>,
+) -> io::Result<()> {
+    let mut server = Server::new();
+    server.buffers = buffers;
+    server.metrics = metrics;
+    while let Some((message, framing)) = read_message(&mut input)? {
+        if let Some(response) = server.handle_message(&message) {
+            write_message(&mut output, &response, framing)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the next message, skipping blank lines between line-delimited messages.
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<(String, Framing)>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if !trimmed.to_ascii_lowercase().starts_with("content-length:") {
+            return Ok(Some((trimmed.to_string(), Framing::Line)));
+        }
+
+        // Header block: Content-Length (plus optional others) up to an empty line
+        let mut length = parse_content_length(trimmed)?;
+        loop {
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let header = line.trim();
+            if header.is_empty() {
+                break;
+            }
+            if header.to_ascii_lowercase().starts_with("content-length:") {
+                length = parse_content_length(header)?;
+            }
+        }
+        let mut body = vec![0; length];
+        input.read_exact(&mut body)?;
+        let body =
+            String::from_utf8(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        return Ok(Some((body, Framing::ContentLength)));
+    }
+}
+
+fn parse_content_length(header: &str) -> io::Result<usize> {
+    header
+        .split_once(':')
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid header '{header}'"),
+            )
+        })
+}
+
+fn write_message<W: Write>(output: &mut W, message: &Value, framing: Framing) -> io::Result<()> {
+    let body = message.to_string();
+    match framing {
+        Framing::Line => writeln!(output, "{body}")?,
+        Framing::ContentLength => write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?,
+    }
+    output.flush()
+}
+
+/// Request dispatcher holding the counters reported by `stats`.
+struct Server {
+    counters: Counters,
+    started: Instant,
+    buffers: RowBuffers,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl Server {
+    fn new() -> Self {
+        Self {
+            counters: Counters::default(),
+            started: Instant::now(),
+            buffers: RowBuffers::default(),
+            metrics: None,
+        }
+    }
+
+    /// Handles a raw message, which may be a single request or a batch.
+    ///
+    /// Returns `None` when nothing should be sent back (notifications only).
+    fn handle_message(&mut self, message: &str) -> Option<Value> {
+        let metrics = self.metrics.clone();
+        let _in_flight = metrics.as_deref().map(|metrics| metrics.in_flight(1));
+        // Requests are only split up here; see `RawObject`
+        if !message.trim_start().starts_with('[') {
+            return match serde_json::from_str::<&RawValue>(message) {
+                Ok(request) => self.handle_request(request),
+                Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+            };
+        }
+        let batch: Vec<&RawValue> = match serde_json::from_str(message) {
+            Ok(batch) => batch,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        if batch.is_empty() {
+            return Some(error_response(Value::Null, INVALID_REQUEST, "empty batch"));
+        }
+        let responses: Vec<Value> = batch
+            .into_iter()
+            .filter_map(|request| self.handle_request(request))
+            .collect();
+        (!responses.is_empty()).then_some(Value::Array(responses))
+    }
+
+    fn handle_request(&mut self, request: &RawValue) -> Option<Value> {
+        let Ok(mut request) = serde_json::from_str::<RawObject>(request.get()) else {
+            return Some(error_response(
+                Value::Null,
+                INVALID_REQUEST,
+                "request must be an object",
+            ));
+        };
+        // Requests without an id are notifications and get no response
+        let id = request.remove("id").map(|id| to_value(&id));
+        let jsonrpc = request.get("jsonrpc").map(|jsonrpc| to_value(jsonrpc));
+        let method = match request.get("method").map(|method| to_value(method)) {
+            Some(Value::String(method)) if jsonrpc == Some(json!("2.0")) => method,
+            _ => {
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    INVALID_REQUEST,
+                    "expected \"jsonrpc\": \"2.0\" and a string \"method\"",
+                ));
+            }
+        };
+        let params = request
+            .remove("params")
+            .map(|params| raw_object(&params))
+            .unwrap_or_default();
+
+        let result = match method.as_str() {
+            "rotate" => self.rotate(&params),
+            "validate" => self.validate(&params),
+            "stats" => Ok(self.stats()),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method '{method}'"))),
+        };
+
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn rotate(&mut self, params: &RawObject) -> Result<Value, (i64, String)> {
+        let json_text = table_param(params)?;
+        let started = Instant::now();
+        let is_valid = process_json_array(&json_text, JsonStyle::Compact, &mut self.buffers);
+        self.counters.add_rows(1, u64::from(is_valid));
+        if let Some(metrics) = &self.metrics {
+            metrics.record_row(is_valid, started.elapsed());
+        }
+
+        let mut result = json!({"json": String::from_utf8_lossy(&self.buffers.json)});
+        add_validity(&mut result, &self.buffers, is_valid);
+        Ok(result)
+    }
+
+    fn validate(&mut self, params: &RawObject) -> Result<Value, (i64, String)> {
+        let json_text = table_param(params)?;
+        let is_valid = parse_table(&json_text, &mut self.buffers).is_some();
+        let mut result = json!({});
+        add_validity(&mut result, &self.buffers, is_valid);
+        Ok(result)
+    }
+
+    fn stats(&self) -> Value {
+        let summary = self.counters.snapshot(self.started.elapsed());
+        json!({
+            "rows": summary.rows,
+            "valid": summary.valid,
+            "invalid": summary.invalid(),
+            "uptime_secs": summary.elapsed.as_secs_f64(),
+            "rows_per_sec": summary.rows_per_sec(),
+        })
+    }
+}
+
+/// A JSON object whose members are left as unparsed JSON text.
+///
+/// Requests are read this way so that a table is parsed only once, by
+/// [`process_json_array`] under the `--max-n` and `--row-timeout` limits, rather than into
+/// a [`Value`] tree of any size first.
+pub(crate) type RawObject = HashMap<String, Box<RawValue>>;
+
+/// The members of `value`, or none if it is not an object.
+pub(crate) fn raw_object(value: &RawValue) -> RawObject {
+    serde_json::from_str(value.get()).unwrap_or_default()
+}
+
+/// Parses a member of a [`RawObject`], such as an `id` to echo back.
+pub(crate) fn to_value(member: &RawValue) -> Value {
+    // Already checked to be JSON; only nesting deeper than serde_json allows can fail
+    serde_json::from_str(member.get()).unwrap_or(Value::Null)
+}
+
+/// Extracts the table as JSON text from `{"json": "..."}` or `{"table": [...]}`.
+pub(crate) fn table_param(params: &RawObject) -> Result<Cow<'_, str>, (i64, String)> {
+    let text = match (params.get("json"), params.get("table")) {
+        (Some(json), None) => serde_json::from_str::<String>(json.get())
+            .ok()
+            .map(Cow::Owned),
+        (None, Some(table)) => Some(Cow::Borrowed(table.get())),
+        _ => None,
+    };
+    text.ok_or_else(|| {
+        (
+            INVALID_PARAMS,
+            "expected params {\"json\": string} or {\"table\": array}".to_string(),
+        )
+    })
+}
+
+/// Adds `is_valid`, `n` and, for invalid rows, `error_reason` and `error_code` to a result
+/// object.
+pub(crate) fn add_validity(result: &mut Value, buffers: &RowBuffers, is_valid: bool) {
+    result["is_valid"] = is_valid.into();
+    result["n"] = buffers.dimension(is_valid).into();
+    if let Some(reason) = buffers.invalid_reason(is_valid) {
+        result["error_reason"] = reason.as_str().into();
+        result["error_code"] = ErrorCode::from(reason).as_str().into();
+    }
+    if let Some(shape) = buffers.shape(is_valid) {
+        result["explanation"] = shape.to_string().into();
+        result["shape"] = json!({
+            "len": shape.len,
+            "smaller": shape.smaller,
+            "remove": shape.to_remove(),
+            "larger": shape.larger,
+            "add": shape.to_add(),
+        });
+    }
+}
+
+/// The [`ErrorCode`] of a failed request: a parse error or a request that cannot be served
+/// as given.
+fn error_code(code: i64) -> ErrorCode {
+    match code {
+        PARSE_ERROR => ErrorCode::Parse,
+        _ => ErrorCode::Usage,
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": code,
+            "message": message,
+            "data": {"error_code": error_code(code).as_str()},
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(server: &mut Server, message: &str) -> Value {
+        server.handle_message(message).expect("expected a response")
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut server = Server::new();
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "rotate", "params": {"json": "[1, 2, 3, 4]"}}"#,
+        );
+        assert_eq!(
+            response,
+            json!({"jsonrpc": "2.0", "id": 1, "result": {"json": "[3,1,4,2]", "is_valid": true, "n": 2}})
+        );
+
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": "b", "method": "rotate", "params": {"table": [1, 2, 3]}}"#,
+        );
+        assert_eq!(
+            response["result"],
+            json!({
+                "json": "[]",
+                "is_valid": false,
+                "n": null,
+                "error_reason": "not_square",
+                "error_code": "E002",
+                "explanation": "3 elements: remove 2 for 1×1 or add 1 for 2×2",
+                "shape": {"len": 3, "smaller": 1, "remove": 2, "larger": 2, "add": 1},
+            })
+        );
+    }
+
+    #[test]
+    fn test_row_limits() {
+        let mut server = Server::new();
+        server.buffers.max_n = Some(2);
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "validate", "params": {"table": [1, 2, 3, 4, 5, 6, 7, 8, 9]}}"#,
+        );
+        assert_eq!(
+            response["result"],
+            json!({"is_valid": false, "n": null, "error_reason": "too_large", "error_code": "E004"})
+        );
+
+        server.buffers.max_n = None;
+        server.buffers.timeout = Some(std::time::Duration::ZERO);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "rotate",
+            "params": {"table": vec![1; 64 * 64]},
+        });
+        let response = call(&mut server, &request.to_string());
+        assert_eq!(response["result"]["error_reason"], "timeout");
+    }
+
+    #[test]
+    fn test_validate_and_stats() {
+        let mut server = Server::new();
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "validate", "params": {"table": [1, 2, 3, 4, 5, 6, 7, 8, 9]}}"#,
+        );
+        assert_eq!(response["result"], json!({"is_valid": true, "n": 3}));
+
+        call(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "rotate", "params": {"table": [5]}}"#,
+        );
+        let response = call(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "stats"}"#,
+        );
+        assert_eq!(response["result"]["rows"], 1);
+        assert_eq!(response["result"]["valid"], 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut server = Server::new();
+        let response = call(&mut server, "{oops");
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["error"]["data"]["error_code"], "E001");
+        assert_eq!(
+            call(
+                &mut server,
+                r#"{"jsonrpc": "2.0", "id": 1, "method": "nope"}"#
+            )["error"]["code"],
+            METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            call(
+                &mut server,
+                r#"{"jsonrpc": "2.0", "id": 1, "method": "rotate", "params": {}}"#
+            )["error"]["code"],
+            INVALID_PARAMS
+        );
+        let response = call(&mut server, r#"{"id": 1, "method": "rotate"}"#);
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        assert_eq!(response["error"]["data"]["error_code"], "E012");
+    }
+
+    #[test]
+    fn test_notifications_and_batches() {
+        let mut server = Server::new();
+        let notification = r#"{"jsonrpc": "2.0", "method": "rotate", "params": {"table": [1]}}"#;
+        assert_eq!(server.handle_message(notification), None);
+
+        let batch =
+            format!(r#"[{notification}, {{"jsonrpc": "2.0", "id": 7, "method": "stats"}}]"#);
+        let response = call(&mut server, &batch);
+        assert_eq!(response.as_array().unwrap().len(), 1);
+        assert_eq!(response[0]["result"]["rows"], 2);
+    }
+
+    #[test]
+    fn test_serve_framings() {
+        let body = r#"{"jsonrpc":"2.0","id":2,"method":"validate","params":{"json":"[1]"}}"#;
+        let input = format!(
+            "{{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"stats\"}}\n\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output, RowBuffers::default(), None).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let (line, framed) = output.split_once('\n').unwrap();
+        assert!(line.contains(r#""id":1"#));
+        let expected = r#"{"id":2,"jsonrpc":"2.0","result":{"is_valid":true,"n":1}}"#;
+        assert_eq!(
+            framed,
+            format!("Content-Length: {}\r\n\r\n{expected}", expected.len())
+        );
+    }
+}
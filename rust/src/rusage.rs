@@ -0,0 +1,127 @@
+//! CPU time and peak memory of this process or of child processes (`getrusage`).
+//!
+//! Only Unix reports them; elsewhere the wall time is all that is measured.
+
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+
+/// Resources used by a process.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Usage {
+    pub cpu: Option<CpuTime>,
+    /// Peak resident set size in bytes
+    pub max_rss: Option<u64>,
+}
+
+/// CPU time spent in user and in kernel mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CpuTime {
+    pub user: Duration,
+    pub system: Duration,
+}
+
+impl CpuTime {
+    pub fn total(&self) -> Duration {
+        self.user + self.system
+    }
+
+    /// Time spent since the `earlier` reading of the same process.
+    pub fn since(&self, earlier: &CpuTime) -> CpuTime {
+        CpuTime {
+            user: self.user.saturating_sub(earlier.user),
+            system: self.system.saturating_sub(earlier.system),
+        }
+    }
+}
+
+/// Waits for `child` to exit and returns its status with the resources it used.
+#[cfg(unix)]
+pub fn wait(child: Child) -> io::Result<(ExitStatus, Usage)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let pid = child.id() as libc::pid_t;
+    let mut status = 0;
+    // SAFETY: `rusage` is plain old data, for which all zeroes is a valid value
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: `pid` is our child and has not been reaped; both pointers are valid
+        if unsafe { libc::wait4(pid, &mut status, 0, &mut usage) } >= 0 {
+            break;
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+
+    Ok((ExitStatus::from_raw(status), from_rusage(&usage)))
+}
+
+#[cfg(not(unix))]
+pub fn wait(mut child: Child) -> io::Result<(ExitStatus, Usage)> {
+    Ok((child.wait()?, Usage::default()))
+}
+
+/// Resources used by this process so far.
+#[cfg(unix)]
+pub fn own() -> Usage {
+    // SAFETY: `rusage` is plain old data, for which all zeroes is a valid value
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    // SAFETY: the pointer is valid; RUSAGE_SELF cannot fail
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return Usage::default();
+    }
+    from_rusage(&usage)
+}
+
+#[cfg(not(unix))]
+pub fn own() -> Usage {
+    Usage::default()
+}
+
+#[cfg(unix)]
+fn from_rusage(usage: &libc::rusage) -> Usage {
+    let cpu = CpuTime {
+        user: timeval(usage.ru_utime),
+        system: timeval(usage.ru_stime),
+    };
+    // Linux reports kilobytes, macOS bytes
+    let max_rss = usage.ru_maxrss.max(0) as u64;
+    let max_rss = if cfg!(target_os = "macos") {
+        max_rss
+    } else {
+        max_rss * 1024
+    };
+    Usage {
+        cpu: Some(cpu),
+        max_rss: Some(max_rss),
+    }
+}
+
+#[cfg(unix)]
+fn timeval(tv: libc::timeval) -> Duration {
+    Duration::from_secs(tv.tv_sec.max(0) as u64) + Duration::from_micros(tv.tv_usec.max(0) as u64)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_wait_reports_usage() {
+        let child = Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap();
+        let (status, usage) = wait(child).unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert!(usage.cpu.is_some());
+        assert!(usage.max_rss.unwrap() > 0);
+    }
+
+    #[test]
+    fn test_own_usage() {
+        let usage = own();
+        assert!(usage.max_rss.unwrap() > 1024 * 1024);
+        assert!(usage.cpu.unwrap().total() > Duration::ZERO);
+    }
+}
@@ -0,0 +1,622 @@
+//! Checking input records against a JSON Schema before rotation (`--schema schema.json`).
+//!
+//! Each record is validated as the object `{"id": ..., "json": ...}`, where `json` is the
+//! parsed `json` field and `id` is a number if the field reads as one and a string
+//! otherwise. The schemas written for producers that emit `{"id": 7, "json": [...]}`
+//! therefore apply as they are:
+//!
+//! ```json
+//! {
+//!   "type": "object",
+//!   "properties": {
+//!     "id": {"type": "integer", "minimum": 1},
+//!     "json": {"type": "array", "maxItems": 2500, "items": {"type": "integer", "maximum": 1000}}
+//!   }
+//! }
+//! ```
+//!
+//! A record that violates the schema becomes an invalid row with the reason
+//! [`InvalidReason::Schema`] and the first violation as detail, such as
+//! `/json/3: 1500 is greater than the maximum 1000`. A `json` field that is not JSON at all
+//! is left to the processor, which reports it as `malformed`.
+//!
+//! The validation keywords that describe such records are supported: `type`, `enum`,
+//! `const`, `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`, `multipleOf`,
+//! `minLength`, `maxLength`, `items`, `minItems`, `maxItems`, `uniqueItems`, `properties`,
+//! `required` and `additionalProperties`, plus `true`/`false` schemas. Annotations such as
+//! `title` are ignored, and any other keyword fails [`Schema::from_value`] rather than being
+//! silently skipped.
+
+use crate::json::JsonStyle;
+use crate::processor::RowProcessor;
+use crate::row::{InvalidReason, RowBuffers};
+use serde_json::{Map, Number, Value};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Keywords that carry no constraint.
+const ANNOTATIONS: [&str; 8] = [
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+];
+
+/// A compiled schema for `{"id", "json"}` records.
+#[derive(Debug)]
+pub struct Schema {
+    root: Node,
+}
+
+/// Where in the record a schema was violated, and how.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON Pointer to the offending value, such as `/json/3`; empty for the record itself
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl Error for Violation {}
+
+impl Schema {
+    /// Reads and compiles the schema at `path`.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        let value = serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::from_value(&value).map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    /// Compiles a parsed schema, rejecting keywords that are not supported.
+    pub fn from_value(value: &Value) -> Result<Self, String> {
+        Ok(Schema {
+            root: Node::compile(value, "")?,
+        })
+    }
+
+    /// Validates one record; without an `id`, only the `json` field is checked.
+    pub fn validate(&self, id: Option<&[u8]>, json: &[u8]) -> Result<(), Violation> {
+        // Not JSON: the processor reports the row as malformed
+        let Ok(json) = serde_json::from_slice::<Value>(json) else {
+            return Ok(());
+        };
+        let mut record = Map::new();
+        if let Some(id) = id {
+            let id = String::from_utf8_lossy(id);
+            let id = match serde_json::from_str::<Number>(&id) {
+                Ok(number) => Value::Number(number),
+                Err(_) => Value::String(id.into_owned()),
+            };
+            record.insert("id".to_string(), id);
+        }
+        record.insert("json".to_string(), json);
+        self.root.check(&Value::Object(record), &Pointer::Root)
+    }
+}
+
+/// Rejects the records that violate a schema and hands the others to another processor.
+pub struct SchemaCheck<'a> {
+    schema: Schema,
+    inner: &'a dyn RowProcessor,
+}
+
+impl<'a> SchemaCheck<'a> {
+    pub fn new(schema: Schema, inner: &'a dyn RowProcessor) -> Self {
+        SchemaCheck { schema, inner }
+    }
+
+    fn check(
+        &self,
+        id: Option<&[u8]>,
+        json: &[u8],
+        style: JsonStyle,
+        buffers: &mut RowBuffers,
+    ) -> bool {
+        match self.schema.validate(id, json) {
+            Ok(()) => self.inner.process(json, style, buffers),
+            Err(violation) => buffers.reject_with(InvalidReason::Schema, violation),
+        }
+    }
+}
+
+impl RowProcessor for SchemaCheck<'_> {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        self.check(None, json, style, buffers)
+    }
+
+    fn process_record(
+        &self,
+        id: &[u8],
+        json: &[u8],
+        style: JsonStyle,
+        buffers: &mut RowBuffers,
+    ) -> bool {
+        self.check(Some(id), json, style, buffers)
+    }
+}
+
+/// A JSON value type, as named in `type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl Type {
+    const ALL: [Type; 7] = [
+        Type::Null,
+        Type::Boolean,
+        Type::Integer,
+        Type::Number,
+        Type::String,
+        Type::Array,
+        Type::Object,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Null => "null",
+            Type::Boolean => "boolean",
+            Type::Integer => "integer",
+            Type::Number => "number",
+            Type::String => "string",
+            Type::Array => "array",
+            Type::Object => "object",
+        }
+    }
+
+    /// The most specific type of `value`.
+    fn of(value: &Value) -> Type {
+        match value {
+            Value::Null => Type::Null,
+            Value::Bool(_) => Type::Boolean,
+            Value::Number(n) if is_integer(n) => Type::Integer,
+            Value::Number(_) => Type::Number,
+            Value::String(_) => Type::String,
+            Value::Array(_) => Type::Array,
+            Value::Object(_) => Type::Object,
+        }
+    }
+
+    fn matches(self, value: &Value) -> bool {
+        let actual = Type::of(value);
+        actual == self || (self == Type::Number && actual == Type::Integer)
+    }
+}
+
+/// Whole numbers, including `1.0`, are integers in JSON Schema.
+fn is_integer(n: &Number) -> bool {
+    n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|x| x.fract() == 0.0)
+}
+
+/// What `additionalProperties` allows.
+#[derive(Debug, Default)]
+enum Additional {
+    #[default]
+    Any,
+    Schema(Box<Node>),
+}
+
+/// One compiled (sub)schema.
+#[derive(Debug, Default)]
+struct Node {
+    /// The `false` schema
+    reject_all: bool,
+    types: Option<Vec<Type>>,
+    enumeration: Option<Vec<Value>>,
+    constant: Option<Value>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+    multiple_of: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    items: Option<Box<Node>>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    unique_items: bool,
+    properties: Vec<(String, Node)>,
+    required: Vec<String>,
+    additional: Additional,
+}
+
+impl Node {
+    fn compile(value: &Value, path: &str) -> Result<Node, String> {
+        let schema = match value {
+            Value::Bool(allowed) => {
+                return Ok(Node {
+                    reject_all: !allowed,
+                    ..Node::default()
+                });
+            }
+            Value::Object(schema) => schema,
+            _ => return Err(format!("{} must be an object or a boolean", at(path))),
+        };
+        let mut node = Node::default();
+        for (keyword, value) in schema {
+            let path = format!("{path}/{keyword}");
+            let number = || value.as_f64().ok_or_else(|| expected(&path, "a number"));
+            let count = || {
+                value
+                    .as_u64()
+                    .map(|n| n as usize)
+                    .ok_or_else(|| expected(&path, "a non-negative integer"))
+            };
+            match keyword.as_str() {
+                "type" => {
+                    let names = match value {
+                        Value::String(name) => vec![name.as_str()],
+                        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                        _ => Vec::new(),
+                    };
+                    let types = names
+                        .iter()
+                        .map(|name| Type::ALL.into_iter().find(|t| t.name() == *name))
+                        .collect::<Option<Vec<_>>>()
+                        .filter(|types| !types.is_empty())
+                        .ok_or_else(|| expected(&path, "a type name or an array of them"))?;
+                    node.types = Some(types);
+                }
+                "enum" => {
+                    let values = value
+                        .as_array()
+                        .ok_or_else(|| expected(&path, "an array"))?;
+                    node.enumeration = Some(values.clone());
+                }
+                "const" => node.constant = Some(value.clone()),
+                "minimum" => node.minimum = Some(number()?),
+                "maximum" => node.maximum = Some(number()?),
+                "exclusiveMinimum" => node.exclusive_minimum = Some(number()?),
+                "exclusiveMaximum" => node.exclusive_maximum = Some(number()?),
+                "multipleOf" => {
+                    let m = number()?;
+                    if m <= 0.0 {
+                        return Err(expected(&path, "greater than 0"));
+                    }
+                    node.multiple_of = Some(m);
+                }
+                "minLength" => node.min_length = Some(count()?),
+                "maxLength" => node.max_length = Some(count()?),
+                "items" => node.items = Some(Box::new(Node::compile(value, &path)?)),
+                "minItems" => node.min_items = Some(count()?),
+                "maxItems" => node.max_items = Some(count()?),
+                "uniqueItems" => {
+                    node.unique_items = value
+                        .as_bool()
+                        .ok_or_else(|| expected(&path, "a boolean"))?
+                }
+                "properties" => {
+                    let properties = value
+                        .as_object()
+                        .ok_or_else(|| expected(&path, "an object"))?;
+                    for (name, schema) in properties {
+                        let schema = Node::compile(schema, &format!("{path}/{name}"))?;
+                        node.properties.push((name.clone(), schema));
+                    }
+                }
+                "required" => {
+                    node.required = value
+                        .as_array()
+                        .and_then(|names| {
+                            names
+                                .iter()
+                                .map(|name| name.as_str().map(String::from))
+                                .collect()
+                        })
+                        .ok_or_else(|| expected(&path, "an array of strings"))?;
+                }
+                "additionalProperties" => {
+                    node.additional = Additional::Schema(Box::new(Node::compile(value, &path)?));
+                }
+                keyword if ANNOTATIONS.contains(&keyword) => {}
+                _ => return Err(format!("unsupported keyword at {path}")),
+            }
+        }
+        Ok(node)
+    }
+
+    fn check(&self, value: &Value, pointer: &Pointer) -> Result<(), Violation> {
+        let fail = |message: String| {
+            Err(Violation {
+                path: pointer.to_string(),
+                message,
+            })
+        };
+        if self.reject_all {
+            return fail("no value is allowed here".to_string());
+        }
+        if let Some(types) = &self.types
+            && !types.iter().any(|t| t.matches(value))
+        {
+            let names: Vec<_> = types.iter().map(|t| t.name()).collect();
+            return fail(format!(
+                "expected {}, found {}",
+                names.join(" or "),
+                Type::of(value).name()
+            ));
+        }
+        if let Some(values) = &self.enumeration
+            && !values.iter().any(|allowed| equal(allowed, value))
+        {
+            return fail(format!("{value} is not one of the allowed values"));
+        }
+        if let Some(constant) = &self.constant
+            && !equal(constant, value)
+        {
+            return fail(format!("{value} is not {constant}"));
+        }
+
+        match value {
+            Value::Number(n) => {
+                let x = n.as_f64().unwrap_or_default();
+                if let Some(m) = self.minimum.filter(|&m| x < m) {
+                    return fail(format!("{n} is less than the minimum {m}"));
+                }
+                if let Some(m) = self.maximum.filter(|&m| x > m) {
+                    return fail(format!("{n} is greater than the maximum {m}"));
+                }
+                if let Some(m) = self.exclusive_minimum.filter(|&m| x <= m) {
+                    return fail(format!("{n} is not greater than {m}"));
+                }
+                if let Some(m) = self.exclusive_maximum.filter(|&m| x >= m) {
+                    return fail(format!("{n} is not less than {m}"));
+                }
+                if let Some(m) = self.multiple_of.filter(|&m| (x / m).fract() != 0.0) {
+                    return fail(format!("{n} is not a multiple of {m}"));
+                }
+            }
+            Value::String(s) => {
+                let len = s.chars().count();
+                if let Some(m) = self.min_length.filter(|&m| len < m) {
+                    return fail(format!("{value} is shorter than {m} characters"));
+                }
+                if let Some(m) = self.max_length.filter(|&m| len > m) {
+                    return fail(format!("{value} is longer than {m} characters"));
+                }
+            }
+            Value::Array(items) => {
+                if let Some(m) = self.min_items.filter(|&m| items.len() < m) {
+                    return fail(format!("{} items, expected at least {m}", items.len()));
+                }
+                if let Some(m) = self.max_items.filter(|&m| items.len() > m) {
+                    return fail(format!("{} items, expected at most {m}", items.len()));
+                }
+                if let Some(schema) = &self.items {
+                    for (index, item) in items.iter().enumerate() {
+                        schema.check(item, &Pointer::Index(pointer, index))?;
+                    }
+                }
+                if self.unique_items {
+                    for (j, item) in items.iter().enumerate() {
+                        if let Some(i) = items[..j].iter().position(|other| equal(other, item)) {
+                            return fail(format!("items {i} and {j} are equal"));
+                        }
+                    }
+                }
+            }
+            Value::Object(object) => {
+                if let Some(name) = self
+                    .required
+                    .iter()
+                    .find(|name| !object.contains_key(*name))
+                {
+                    return fail(format!("missing property '{name}'"));
+                }
+                for (name, value) in object {
+                    let pointer = Pointer::Key(pointer, name);
+                    match self.properties.iter().find(|(key, _)| key == name) {
+                        Some((_, schema)) => schema.check(value, &pointer)?,
+                        None => match &self.additional {
+                            Additional::Any => {}
+                            Additional::Schema(schema) if schema.reject_all => {
+                                return fail(format!("unexpected property '{name}'"));
+                            }
+                            Additional::Schema(schema) => schema.check(value, &pointer)?,
+                        },
+                    }
+                }
+            }
+            Value::Null | Value::Bool(_) => {}
+        }
+        Ok(())
+    }
+}
+
+/// JSON equality, under which `1` and `1.0` are the same number.
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a == b || a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| equal(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+fn at(path: &str) -> String {
+    if path.is_empty() {
+        "the schema".to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+fn expected(path: &str, what: &str) -> String {
+    format!("{} must be {what}", at(path))
+}
+
+/// The JSON Pointer to the value being checked, built only when a check fails.
+enum Pointer<'a> {
+    Root,
+    Key(&'a Pointer<'a>, &'a str),
+    Index(&'a Pointer<'a>, usize),
+}
+
+impl fmt::Display for Pointer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pointer::Root => Ok(()),
+            Pointer::Key(parent, key) => {
+                write!(f, "{parent}/{}", key.replace('~', "~0").replace('/', "~1"))
+            }
+            Pointer::Index(parent, index) => write!(f, "{parent}/{index}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(value: Value) -> Schema {
+        Schema::from_value(&value).unwrap()
+    }
+
+    fn violation(schema: &Schema, id: &str, json: &str) -> String {
+        match schema.validate(Some(id.as_bytes()), json.as_bytes()) {
+            Ok(()) => String::new(),
+            Err(violation) => violation.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_record_schema() {
+        let schema = schema(json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer", "minimum": 1},
+                "json": {
+                    "type": "array",
+                    "maxItems": 4,
+                    "items": {"type": "integer", "minimum": -10, "maximum": 10}
+                }
+            },
+            "required": ["id", "json"]
+        }));
+        assert_eq!(violation(&schema, "7", "[1, 2.0, 3, -10]"), "");
+        assert_eq!(
+            violation(&schema, "0", "[1]"),
+            "/id: 0 is less than the minimum 1"
+        );
+        assert_eq!(
+            violation(&schema, "x7", "[1]"),
+            "/id: expected integer, found string"
+        );
+        assert_eq!(
+            violation(&schema, "7", "[1, 2, 15]"),
+            "/json/2: 15 is greater than the maximum 10"
+        );
+        assert_eq!(
+            violation(&schema, "7", "[1, 2.5]"),
+            "/json/1: expected integer, found number"
+        );
+        assert_eq!(
+            violation(&schema, "7", "[1, 2, 3, 4, 5]"),
+            "/json: 5 items, expected at most 4"
+        );
+        // Left to the processor
+        assert_eq!(violation(&schema, "7", "[1, 2"), "");
+        // Without an id, only the table is checked
+        assert_eq!(
+            schema.validate(None, b"[1]").unwrap_err().to_string(),
+            "missing property 'id'"
+        );
+    }
+
+    #[test]
+    fn test_keywords() {
+        let schema = schema(json!({
+            "properties": {
+                "id": {"type": "string", "minLength": 2, "enum": ["a", "ab", "abc"]},
+                "json": {"uniqueItems": true, "items": {"multipleOf": 2, "exclusiveMaximum": 8}}
+            },
+            "additionalProperties": false
+        }));
+        assert_eq!(violation(&schema, "ab", "[2, 4, 6]"), "");
+        assert_eq!(
+            violation(&schema, "a", "[2]"),
+            "/id: \"a\" is shorter than 2 characters"
+        );
+        assert_eq!(
+            violation(&schema, "abcd", "[2]"),
+            "/id: \"abcd\" is not one of the allowed values"
+        );
+        assert_eq!(
+            violation(&schema, "ab", "[2, 3]"),
+            "/json/1: 3 is not a multiple of 2"
+        );
+        assert_eq!(
+            violation(&schema, "ab", "[8]"),
+            "/json/0: 8 is not less than 8"
+        );
+        assert_eq!(
+            violation(&schema, "ab", "[2, 4, 2.0]"),
+            "/json: items 0 and 2 are equal"
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let error = Schema::from_value(&json!({"properties": {"id": {"pattern": "^a"}}}));
+        assert_eq!(
+            error.unwrap_err(),
+            "unsupported keyword at /properties/id/pattern"
+        );
+        let error = Schema::from_value(&json!({"minimum": "1"}));
+        assert_eq!(error.unwrap_err(), "/minimum must be a number");
+        let error = Schema::from_value(&json!({"type": "table"}));
+        assert_eq!(
+            error.unwrap_err(),
+            "/type must be a type name or an array of them"
+        );
+    }
+
+    #[test]
+    fn test_schema_check() {
+        let check = SchemaCheck::new(
+            schema(json!({"properties": {"json": {"items": {"minimum": 0}}}})),
+            &crate::processor::RotateRight,
+        );
+        let mut buffers = RowBuffers::default();
+        assert!(check.process_record(b"1", b"[1, 2, 3, 4]", JsonStyle::Compact, &mut buffers));
+        assert_eq!(buffers.json, b"[3,1,4,2]");
+        assert!(!check.process_record(b"1", b"[1, -2, 3, 4]", JsonStyle::Compact, &mut buffers));
+        assert_eq!(buffers.json, b"[]");
+        assert_eq!(
+            buffers.error_reason(false).as_deref(),
+            Some("schema: /json/1: -2 is less than the minimum 0")
+        );
+        // Tables the schema accepts can still be invalid
+        assert!(!check.process_record(b"1", b"[1, 2, 3]", JsonStyle::Compact, &mut buffers));
+        assert_eq!(buffers.error_reason(false).as_deref(), Some("not_square"));
+    }
+}
@@ -0,0 +1,190 @@
+//! `--script transform.rhai`: a [Rhai](https://rhai.rs) script applied to every table after
+//! rotation, for bespoke tweaks such as scaling or masking without recompiling.
+//!
+//! The script defines `fn transform(table, n)`, which receives the rotated table as a flat,
+//! row-major array of `n * n` integers and returns the array to write:
+//!
+//! ```rhai
+//! // Scale every element and zero the main diagonal
+//! fn transform(table, n) {
+//!     let out = table.map(|x| x * 10);
+//!     for i in 0..n {
+//!         out[i * n + i] = 0;
+//!     }
+//!     out
+//! }
+//! ```
+//!
+//! Only `transform` is called; top-level statements never run. A script that fails, or
+//! returns anything but `n * n` integers, turns that row into an invalid one and logs a
+//! warning, so one odd table does not stop a long run.
+//!
+//! Scripts are sandboxed: Rhai has no file, network or process access, `eval` is disabled,
+//! `print` and `debug` go to stderr so they cannot corrupt the CSV on stdout, and each call
+//! is limited to [`MAX_OPERATIONS`] operations, [`MAX_CALL_LEVELS`] nested calls and arrays
+//! of [`MAX_ARRAY_SIZE`] elements, so a runaway loop fails its row instead of hanging the
+//! run. The `script` group of `cargo bench --features script` measures the per-row cost.
+
+use crate::json::{self, JsonStyle};
+use crate::processor::RowProcessor;
+use crate::row::{InvalidReason, parse_table_bytes};
+use crate::{RowBuffers, rotate_right};
+use rhai::{AST, Array, CallFnOptions, Dynamic, Engine, Scope};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Name of the function the script must define.
+const FUNCTION: &str = "transform";
+
+/// Operations one `transform` call may run before it is aborted.
+pub const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// Deepest nesting of function calls inside a script.
+pub const MAX_CALL_LEVELS: usize = 32;
+
+/// Largest array a script may build; a 4096×4096 table.
+pub const MAX_ARRAY_SIZE: usize = 1 << 24;
+
+/// A compiled script, shared by all worker threads.
+pub struct ScriptProcessor {
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptProcessor {
+    /// Compiles the script at `path`.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let source =
+            fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        Self::compile(&source).map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    /// Compiles a script from its source text.
+    pub fn compile(source: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let engine = sandboxed_engine();
+        let ast = engine.compile(source)?;
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == FUNCTION && f.params.len() == 2)
+        {
+            return Err(format!("the script must define `fn {FUNCTION}(table, n)`").into());
+        }
+        Ok(ScriptProcessor { engine, ast })
+    }
+
+    /// Replaces the `n`×`n` `table` with the script's result for it.
+    pub fn transform(
+        &self,
+        table: &mut [i64],
+        n: usize,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let input: Array = table.iter().map(|&x| Dynamic::from_int(x)).collect();
+        // Only the function runs; top-level statements are skipped
+        let options = CallFnOptions::new().eval_ast(false);
+        let result: Dynamic = self.engine.call_fn_with_options(
+            options,
+            &mut Scope::new(),
+            &self.ast,
+            FUNCTION,
+            (input, n as i64),
+        )?;
+        let output = result
+            .into_typed_array::<i64>()
+            .map_err(|_| format!("`{FUNCTION}` must return an array of integers"))?;
+        if output.len() != table.len() {
+            return Err(format!(
+                "`{FUNCTION}` returned {} elements for a {n}×{n} table",
+                output.len()
+            )
+            .into());
+        }
+        table.copy_from_slice(&output);
+        Ok(())
+    }
+}
+
+impl RowProcessor for ScriptProcessor {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        let Some(n) = parse_table_bytes(json, buffers) else {
+            return buffers.set_invalid();
+        };
+        rotate_right(&mut buffers.numbers).expect("parse_table accepts only square tables");
+        if let Err(e) = self.transform(&mut buffers.numbers, n) {
+            log::warn!("Script failed, writing an invalid row: {e}");
+            return buffers.reject(InvalidReason::Rejected);
+        }
+        buffers.json.clear();
+        json::write_array_styled(&mut buffers.json, &buffers.numbers, style);
+        true
+    }
+}
+
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_array_size(MAX_ARRAY_SIZE)
+        .set_max_string_size(1 << 20)
+        .set_max_map_size(1 << 16)
+        .disable_symbol("eval")
+        .on_print(|text| eprintln!("{text}"))
+        .on_debug(|text, _, position| eprintln!("{position:?} | {text}"));
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(script: &str, json: &str) -> (String, bool) {
+        let processor = ScriptProcessor::compile(script).unwrap();
+        let mut buffers = RowBuffers::new();
+        let valid = processor.process(json.as_bytes(), JsonStyle::Compact, &mut buffers);
+        (String::from_utf8(buffers.json).unwrap(), valid)
+    }
+
+    #[test]
+    fn test_transform_after_rotation() {
+        let script = "fn transform(table, n) {
+            let out = table.map(|x| x * 10);
+            for i in 0..n { out[i * n + i] = 0; }
+            out
+        }";
+        // [1, 2, 3, 4] rotates to [3, 1, 4, 2]
+        assert_eq!(
+            process(script, "[1, 2, 3, 4]"),
+            ("[0,10,40,0]".to_string(), true)
+        );
+        assert_eq!(process(script, "[1, 2, 3]"), ("[]".to_string(), false));
+    }
+
+    #[test]
+    fn test_script_errors_invalidate_the_row() {
+        let cases = [
+            "fn transform(table, n) { [1] }",
+            "fn transform(table, n) { table.map(|x| x / 2.0) }",
+            "fn transform(table, n) { loop {} }",
+        ];
+        for script in cases {
+            assert_eq!(
+                process(script, "[1, 2, 3, 4]"),
+                ("[]".to_string(), false),
+                "{script}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let error = ScriptProcessor::compile("fn scale(x) { x }").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "the script must define `fn transform(table, n)`"
+        );
+        assert!(ScriptProcessor::compile("fn transform(table, n) {").is_err());
+        assert!(ScriptProcessor::compile("fn transform(table, n) { eval(\"table\") }").is_err());
+        assert!(ScriptProcessor::load(Path::new("missing.rhai")).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! Graceful shutdown of the long-running modes (`kafka`, `redis`, `serve`).
+//!
+//! The first SIGTERM or SIGINT only sets a flag: the mode stops taking new work, finishes
+//! the rows it already has, commits or flushes them and prints its `--stats` summary before
+//! exiting normally. A second signal ends the process right away, for when draining hangs.
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::flag;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether a shutdown was requested.
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    /// Handles SIGTERM and SIGINT from now on.
+    pub fn install() -> io::Result<Self> {
+        let requested = Arc::new(AtomicBool::new(false));
+        for signal in [SIGTERM, SIGINT] {
+            // Registered first, so it sees the flag as the previous signal left it
+            flag::register_conditional_shutdown(signal, 128 + signal, Arc::clone(&requested))?;
+            flag::register(signal, Arc::clone(&requested))?;
+        }
+        Ok(Shutdown { requested })
+    }
+
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Completes once a shutdown is requested.
+    #[cfg(feature = "serve")]
+    pub async fn wait(self) {
+        const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+        while !self.requested() {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        log::info!("Shutting down: finishing the requests in progress");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_signal_sets_flag() {
+        let shutdown = Shutdown::install().unwrap();
+        assert!(!shutdown.requested());
+        signal_hook::low_level::raise(SIGTERM).unwrap();
+        assert!(shutdown.requested());
+    }
+}
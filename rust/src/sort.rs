@@ -0,0 +1,353 @@
+//! Sorting result rows by id with bounded memory.
+//!
+//! Rows are buffered until the memory budget is reached, then sorted and spilled to a
+//! temporary file as a "run". At the end all runs are k-way merged into the output. The
+//! sort is stable: rows with equal ids keep their input order.
+
+use clap::ValueEnum;
+use csv::{ByteRecord, ReaderBuilder, Writer};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+
+/// Maximum number of runs merged at once; more runs are first merged in extra passes.
+const MAX_FAN_IN: usize = 64;
+
+/// Approximate per-record bookkeeping overhead on top of the field bytes.
+const RECORD_OVERHEAD: usize = 64;
+
+/// How ids are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IdOrder {
+    /// Integer ids in numeric order (`2` before `10`); other ids after them, lexicographically
+    Numeric,
+    /// Plain byte-wise comparison (`10` before `2`)
+    Lexicographic,
+}
+
+impl IdOrder {
+    /// Compares two ids.
+    pub fn compare(self, a: &[u8], b: &[u8]) -> Ordering {
+        match self {
+            IdOrder::Lexicographic => a.cmp(b),
+            IdOrder::Numeric => compare_numeric(a, b),
+        }
+    }
+}
+
+/// Natural ordering for integer ids of any length; non-integers sort after all integers.
+fn compare_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    match (parse_integer(a), parse_integer(b)) {
+        (Some(x), Some(y)) => x.cmp(&y).then_with(|| a.cmp(b)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+/// Integer id split into sign and significant digits, ordered numerically.
+#[derive(PartialEq, Eq)]
+struct Integer<'a> {
+    negative: bool,
+    digits: &'a [u8],
+}
+
+impl Ord for Integer<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let magnitude = self
+            .digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.cmp(other.digits));
+        match (self.negative, other.negative) {
+            (false, false) => magnitude,
+            (true, true) => magnitude.reverse(),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for Integer<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn parse_integer(id: &[u8]) -> Option<Integer<'_>> {
+    let id = id.trim_ascii();
+    let (negative, digits) = match id.first()? {
+        b'-' => (true, &id[1..]),
+        b'+' => (false, &id[1..]),
+        _ => (false, id),
+    };
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    // Leading zeros don't change the value; keep a single digit for zero itself
+    let first = digits
+        .iter()
+        .position(|&d| d != b'0')
+        .unwrap_or(digits.len() - 1);
+    let digits = &digits[first..];
+    let negative = negative && digits != b"0";
+    Some(Integer { negative, digits })
+}
+
+/// Stable external sorter for CSV records keyed by their first field.
+pub struct ExternalSorter {
+    order: IdOrder,
+    memory_limit: usize,
+    buffered: Vec<ByteRecord>,
+    buffered_bytes: usize,
+    runs: Vec<File>,
+}
+
+impl ExternalSorter {
+    /// Creates a sorter that spills to disk once roughly `memory_limit` bytes are buffered.
+    pub fn new(order: IdOrder, memory_limit: usize) -> Self {
+        Self {
+            order,
+            memory_limit,
+            buffered: Vec::new(),
+            buffered_bytes: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Adds a record, spilling a sorted run if the memory budget is exceeded.
+    pub fn push(&mut self, record: ByteRecord) -> csv::Result<()> {
+        self.buffered_bytes += record.as_slice().len() + RECORD_OVERHEAD;
+        self.buffered.push(record);
+        if self.buffered_bytes >= self.memory_limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Sorts all records from `rdr` (which must not yield a header) into `wtr`.
+    pub fn sort<R: Read, W: Write>(
+        mut self,
+        rdr: &mut csv::Reader<R>,
+        wtr: &mut Writer<W>,
+    ) -> csv::Result<()> {
+        let mut record = ByteRecord::new();
+        while rdr.read_byte_record(&mut record)? {
+            self.push(record.clone())?;
+        }
+        self.finish(wtr)
+    }
+
+    /// Writes all pushed records to `wtr` in sorted order.
+    pub fn finish<W: Write>(mut self, wtr: &mut Writer<W>) -> csv::Result<()> {
+        if self.runs.is_empty() {
+            self.sort_buffered();
+            for record in &self.buffered {
+                wtr.write_byte_record(record)?;
+            }
+            return Ok(());
+        }
+
+        self.spill()?;
+        let order = self.order;
+        let mut runs = self.runs;
+        // Merge in passes until a single final merge can read every run at once
+        while runs.len() > MAX_FAN_IN {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(MAX_FAN_IN));
+            let mut rest = runs.into_iter();
+            loop {
+                let group: Vec<File> = rest.by_ref().take(MAX_FAN_IN).collect();
+                if group.is_empty() {
+                    break;
+                }
+                let mut run = Writer::from_writer(BufWriter::new(tempfile::tempfile()?));
+                merge(order, group, &mut run)?;
+                merged.push(finish_run(run)?);
+            }
+            runs = merged;
+        }
+        merge(order, runs, wtr)
+    }
+
+    fn sort_buffered(&mut self) {
+        let order = self.order;
+        self.buffered.sort_by(|a, b| order.compare(id(a), id(b)));
+    }
+
+    /// Sorts the buffered records and writes them to a new temporary run file.
+    fn spill(&mut self) -> csv::Result<()> {
+        if self.buffered.is_empty() {
+            return Ok(());
+        }
+        self.sort_buffered();
+
+        let mut run = Writer::from_writer(BufWriter::new(tempfile::tempfile()?));
+        for record in self.buffered.drain(..) {
+            run.write_byte_record(&record)?;
+        }
+        self.runs.push(finish_run(run)?);
+        self.buffered_bytes = 0;
+        Ok(())
+    }
+}
+
+/// Sort key of a record: its first field.
+fn id(record: &ByteRecord) -> &[u8] {
+    record.get(0).unwrap_or_default()
+}
+
+/// Flushes a run and rewinds it for reading.
+fn finish_run(run: Writer<BufWriter<File>>) -> io::Result<File> {
+    let mut file = run
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .into_inner()
+        .map_err(|e| e.into_error())?;
+    file.rewind()?;
+    Ok(file)
+}
+
+/// Head record of one run during a k-way merge.
+struct Head {
+    record: ByteRecord,
+    run: usize,
+    order: IdOrder,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap: reverse so the smallest id (then earliest run) pops first
+        self.order
+            .compare(id(&self.record), id(&other.record))
+            .then(self.run.cmp(&other.run))
+            .reverse()
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+/// Merges sorted runs into `wtr`.
+fn merge<W: Write>(order: IdOrder, runs: Vec<File>, wtr: &mut Writer<W>) -> csv::Result<()> {
+    let mut readers: Vec<_> = runs
+        .into_iter()
+        .map(|file| {
+            ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(BufReader::new(file))
+        })
+        .collect();
+
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (run, rdr) in readers.iter_mut().enumerate() {
+        let mut record = ByteRecord::new();
+        if rdr.read_byte_record(&mut record)? {
+            heap.push(Head { record, run, order });
+        }
+    }
+
+    while let Some(mut head) = heap.pop() {
+        wtr.write_byte_record(&head.record)?;
+        if readers[head.run].read_byte_record(&mut head.record)? {
+            heap.push(head);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort_ids(ids: &[&str], order: IdOrder, memory_limit: usize) -> Vec<String> {
+        let mut input = String::new();
+        for (i, id) in ids.iter().enumerate() {
+            input.push_str(&format!("{id},{i}\n"));
+        }
+
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(input.as_bytes());
+        let mut wtr = Writer::from_writer(Vec::new());
+        ExternalSorter::new(order, memory_limit)
+            .sort(&mut rdr, &mut wtr)
+            .unwrap();
+
+        String::from_utf8(wtr.into_inner().unwrap())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_numeric_order() {
+        let sorted = sort_ids(
+            &["10", "2", "-3", "abc", "007", "1"],
+            IdOrder::Numeric,
+            1 << 20,
+        );
+        assert_eq!(sorted, ["-3,2", "1,5", "2,1", "007,4", "10,0", "abc,3"]);
+    }
+
+    #[test]
+    fn test_lexicographic_order() {
+        let sorted = sort_ids(&["10", "2", "1"], IdOrder::Lexicographic, 1 << 20);
+        assert_eq!(sorted, ["1,2", "10,0", "2,1"]);
+    }
+
+    #[test]
+    fn test_numeric_handles_huge_ids() {
+        let sorted = sort_ids(
+            &[
+                "123456789012345678901234567890",
+                "99999999999999999999",
+                "-0",
+            ],
+            IdOrder::Numeric,
+            1 << 20,
+        );
+        assert_eq!(
+            sorted,
+            [
+                "-0,2",
+                "99999999999999999999,1",
+                "123456789012345678901234567890,0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spilled_sort_is_stable() {
+        // Tiny budget: every record becomes its own run, forcing multi-pass merges
+        let ids: Vec<String> = (0..200).map(|i| (i % 7).to_string()).collect();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+
+        let sorted = sort_ids(&ids, IdOrder::Numeric, 1);
+        let expected = sort_ids(&ids, IdOrder::Numeric, usize::MAX);
+        assert_eq!(sorted, expected);
+
+        let mut keyed: Vec<(u32, usize)> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.parse().unwrap(), i))
+            .collect();
+        keyed.sort();
+        let keyed: Vec<String> = keyed.iter().map(|(id, i)| format!("{id},{i}")).collect();
+        assert_eq!(sorted, keyed);
+    }
+}
@@ -0,0 +1,333 @@
+//! `--split-rows`, `--split-size` and `--split-by-validity`: shard the output into numbered
+//! files.
+//!
+//! The CSV bytes written to a [`SplitOutput`] are cut at record boundaries (newlines outside
+//! quoted fields). The first record is the header and starts every file. `out.csv` becomes
+//! `out-00001.csv`, `out-00002.csv`, ..., or `out-valid-00001.csv` and
+//! `out-invalid-00001.csv` when split by validity. A file is closed before the row that
+//! would take it past `--split-rows` or `--split-size`. A single row that is larger than
+//! `--split-size` gets a file of its own.
+
+use crate::output::Output;
+use std::io::{self, Write};
+
+/// When to start a new file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SplitOptions {
+    /// Most data rows per file
+    pub rows: Option<u64>,
+    /// Most bytes per file, header included
+    pub bytes: Option<u64>,
+    /// Separate files for valid and invalid rows
+    pub by_validity: bool,
+}
+
+impl SplitOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.rows.is_some() || self.bytes.is_some() || self.by_validity
+    }
+}
+
+/// Position of the `is_valid` column in an output row.
+const IS_VALID_FIELD: usize = 2;
+
+/// Writer that spreads output rows over numbered files next to `location`.
+pub struct SplitOutput {
+    location: String,
+    options: SplitOptions,
+    header: Option<Vec<u8>>,
+    /// The record being received, up to its newline
+    record: Vec<u8>,
+    in_quotes: bool,
+    /// Open files: all rows (or the valid ones) and the invalid ones
+    shards: [Shard; 2],
+}
+
+/// The current file of one sequence of numbered files.
+#[derive(Default)]
+struct Shard {
+    output: Option<Output>,
+    /// Number of the current (or last) file
+    index: u32,
+    rows: u64,
+    bytes: u64,
+}
+
+impl SplitOutput {
+    pub fn new(location: &str, options: SplitOptions) -> Self {
+        SplitOutput {
+            location: location.to_string(),
+            options,
+            header: None,
+            record: Vec::new(),
+            in_quotes: false,
+            shards: Default::default(),
+        }
+    }
+
+    /// Writes any unterminated last record and completes every file.
+    ///
+    /// An output without data rows still gets its first file, holding just the header.
+    pub fn finish(mut self) -> io::Result<()> {
+        if !self.record.is_empty() {
+            self.end_record()?;
+        }
+        if self.shards.iter().all(|shard| shard.index == 0) {
+            self.open(0)?;
+        }
+        for shard in &mut self.shards {
+            if let Some(output) = shard.output.take() {
+                output.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn end_record(&mut self) -> io::Result<()> {
+        let record = std::mem::take(&mut self.record);
+        if self.header.is_none() {
+            self.header = Some(record);
+            return Ok(());
+        }
+
+        let kind = usize::from(self.options.by_validity && !is_valid(&record));
+        let shard = &self.shards[kind];
+        let full = shard.rows > 0
+            && (self.options.rows.is_some_and(|rows| shard.rows >= rows)
+                || self
+                    .options
+                    .bytes
+                    .is_some_and(|bytes| shard.bytes + record.len() as u64 > bytes));
+        if shard.output.is_none() || full {
+            self.open(kind)?;
+        }
+
+        let shard = &mut self.shards[kind];
+        let output = shard.output.as_mut().expect("the shard was just opened");
+        output.write_all(&record)?;
+        shard.rows += 1;
+        shard.bytes += record.len() as u64;
+        self.record = record;
+        self.record.clear();
+        Ok(())
+    }
+
+    /// Finishes the current file of `kind` and starts the next one with the header.
+    fn open(&mut self, kind: usize) -> io::Result<()> {
+        let shard = &mut self.shards[kind];
+        if let Some(output) = shard.output.take() {
+            output.finish()?;
+        }
+        shard.index += 1;
+        let name = match (self.options.by_validity, kind) {
+            (false, _) => None,
+            (true, 0) => Some("valid"),
+            (true, _) => Some("invalid"),
+        };
+        let path = shard_path(&self.location, name, shard.index);
+        log::info!("Writing {path}");
+        let mut output = Output::create(Some(&path))?;
+        let header = self.header.as_deref().unwrap_or_default();
+        output.write_all(header)?;
+        shard.output = Some(output);
+        shard.rows = 0;
+        shard.bytes = header.len() as u64;
+        Ok(())
+    }
+}
+
+impl Write for SplitOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut start = 0;
+        for (i, &byte) in buf.iter().enumerate() {
+            match byte {
+                // An escaped quote toggles twice, leaving the state unchanged
+                b'"' => self.in_quotes = !self.in_quotes,
+                b'\n' if !self.in_quotes => {
+                    self.record.extend_from_slice(&buf[start..=i]);
+                    start = i + 1;
+                    self.end_record()?;
+                }
+                _ => {}
+            }
+        }
+        self.record.extend_from_slice(&buf[start..]);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for output in self
+            .shards
+            .iter_mut()
+            .filter_map(|shard| shard.output.as_mut())
+        {
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the `is_valid` field of an output record is `true`.
+fn is_valid(record: &[u8]) -> bool {
+    let mut in_quotes = false;
+    let mut field = 0;
+    let mut start = 0;
+    for (i, &byte) in record.iter().enumerate() {
+        match byte {
+            b'"' => in_quotes = !in_quotes,
+            b',' | b'\r' | b'\n' if !in_quotes => {
+                if field == IS_VALID_FIELD {
+                    return &record[start..i] == b"true";
+                }
+                field += 1;
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    field == IS_VALID_FIELD && &record[start..] == b"true"
+}
+
+/// `out.csv` → `out-00001.csv`, or `out-valid-00001.csv` for a named sequence.
+fn shard_path(location: &str, name: Option<&str>, index: u32) -> String {
+    let file_start = location.rfind('/').map_or(0, |i| i + 1);
+    let (stem, extension) = match location[file_start..].rfind('.') {
+        Some(dot) if dot > 0 => location.split_at(file_start + dot),
+        _ => (location, ""),
+    };
+    match name {
+        Some(name) => format!("{stem}-{name}-{index:05}{extension}"),
+        None => format!("{stem}-{index:05}{extension}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const HEADER: &str = "id,json,is_valid\n";
+
+    fn split(input: &str, options: SplitOptions) -> (tempfile::TempDir, Vec<(String, String)>) {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("out.csv");
+        let mut output = SplitOutput::new(location.to_str().unwrap(), options);
+        // Write in small pieces so records straddle the calls
+        for piece in input.as_bytes().chunks(5) {
+            output.write_all(piece).unwrap();
+        }
+        output.finish().unwrap();
+
+        let mut files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let name = path.file_name().unwrap().to_str().unwrap().to_string();
+                (name, fs::read_to_string(&path).unwrap())
+            })
+            .collect();
+        files.sort();
+        (dir, files)
+    }
+
+    #[test]
+    fn test_split_rows() {
+        let input = format!("{HEADER}1,[1],true\n2,[],false\n3,\"[3,1,4,2]\",true\n");
+        let options = SplitOptions {
+            rows: Some(2),
+            ..SplitOptions::default()
+        };
+        let (_dir, files) = split(&input, options);
+        assert_eq!(
+            files,
+            [
+                (
+                    "out-00001.csv".to_string(),
+                    format!("{HEADER}1,[1],true\n2,[],false\n")
+                ),
+                (
+                    "out-00002.csv".to_string(),
+                    format!("{HEADER}3,\"[3,1,4,2]\",true\n")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_size() {
+        // Each row is 11 bytes; the header 17
+        let input = format!("{HEADER}1,[1],true\n2,[2],true\n3,[3],true\n");
+        let options = SplitOptions {
+            bytes: Some(40),
+            ..SplitOptions::default()
+        };
+        let (_dir, files) = split(&input, options);
+        let rows: Vec<usize> = files
+            .iter()
+            .map(|(_, text)| text.lines().count() - 1)
+            .collect();
+        assert_eq!(rows, [2, 1]);
+        assert!(files.iter().all(|(_, text)| text.len() <= 40));
+
+        // A row larger than the limit still gets written, alone
+        let options = SplitOptions {
+            bytes: Some(5),
+            ..SplitOptions::default()
+        };
+        assert_eq!(split(&input, options).1.len(), 3);
+    }
+
+    #[test]
+    fn test_split_by_validity() {
+        let input =
+            format!("{HEADER}\"a,\"\"b\"\"\",[1],true\n2,[],false\n\"multi\nline\",[],false\n");
+        let options = SplitOptions {
+            by_validity: true,
+            ..SplitOptions::default()
+        };
+        let (_dir, files) = split(&input, options);
+        assert_eq!(
+            files,
+            [
+                (
+                    "out-invalid-00001.csv".to_string(),
+                    format!("{HEADER}2,[],false\n\"multi\nline\",[],false\n")
+                ),
+                (
+                    "out-valid-00001.csv".to_string(),
+                    format!("{HEADER}\"a,\"\"b\"\"\",[1],true\n")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_output_keeps_the_header() {
+        let options = SplitOptions {
+            rows: Some(10),
+            ..SplitOptions::default()
+        };
+        let (_dir, files) = split(HEADER, options);
+        assert_eq!(files, [("out-00001.csv".to_string(), HEADER.to_string())]);
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(is_valid(b"1,[1],true\n"));
+        assert!(is_valid(b"1,[1],true,1\n"));
+        assert!(is_valid(b"\"a,b\",\"[1,2,3,4]\",true"));
+        assert!(!is_valid(b"1,[],false,\n"));
+        assert!(!is_valid(b"\"x,true\",[],false\n"));
+    }
+
+    #[test]
+    fn test_shard_path() {
+        assert_eq!(shard_path("out.csv", None, 1), "out-00001.csv");
+        assert_eq!(
+            shard_path("s3://bucket/run.1/out.csv", Some("valid"), 12),
+            "s3://bucket/run.1/out-valid-00012.csv"
+        );
+        assert_eq!(shard_path("dir.d/out", None, 3), "dir.d/out-00003");
+        assert_eq!(shard_path(".hidden", None, 3), ".hidden-00003");
+    }
+}
@@ -0,0 +1,134 @@
+//! Run statistics and throughput reporting.
+//!
+//! The summary printed by `--stats` and the periodic `--progress-interval` lines are derived
+//! from snapshots of the pipeline's [`Counters`].
+//! `--stats` also reports the CPU time and peak RSS of the process and, when built with the
+//! `count-allocs` feature, its heap allocations.
+
+use crate::rusage::{self, Usage};
+use rotate_cli::counting_alloc::{self, AllocStats};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+pub use rotate_cli::counters::{Counters, Summary};
+
+/// CPU time, peak memory and heap allocations of this process.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Resources {
+    pub usage: Usage,
+    /// Only counted with the `count-allocs` feature
+    pub allocs: Option<AllocStats>,
+}
+
+impl Resources {
+    /// Totals since the process started.
+    pub fn now() -> Self {
+        Resources {
+            usage: rusage::own(),
+            allocs: cfg!(feature = "count-allocs").then(counting_alloc::stats),
+        }
+    }
+
+    /// Resources used since `earlier`; the peak RSS stays the peak of the whole process.
+    pub fn since(&self, earlier: &Resources) -> Resources {
+        let cpu = match (self.usage.cpu, earlier.usage.cpu) {
+            (Some(now), Some(before)) => Some(now.since(&before)),
+            _ => None,
+        };
+        let allocs = match (self.allocs, earlier.allocs) {
+            (Some(now), Some(before)) => Some(now - before),
+            _ => None,
+        };
+        Resources {
+            usage: Usage {
+                cpu,
+                max_rss: self.usage.max_rss,
+            },
+            allocs,
+        }
+    }
+}
+
+impl fmt::Display for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.usage.cpu {
+            Some(cpu) => write!(f, "{:.3}s CPU", cpu.total().as_secs_f64())?,
+            None => write!(f, "CPU time unavailable")?,
+        }
+        if let Some(max_rss) = self.usage.max_rss {
+            write!(
+                f,
+                ", peak RSS {:.1} MiB",
+                max_rss as f64 / (1024.0 * 1024.0)
+            )?;
+        }
+        if let Some(allocs) = self.allocs {
+            write!(
+                f,
+                ", {} allocations ({:.2} MB)",
+                allocs.allocations,
+                allocs.bytes as f64 / 1e6
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the `--stats` report to stderr.
+pub fn print(summary: Summary) {
+    eprintln!("Stats: {summary}");
+    eprintln!("Resources: {}", Resources::now());
+}
+
+/// Prints a progress line to stderr every `interval` until `stop` is disconnected.
+pub fn report_progress(
+    counters: &Counters,
+    started: Instant,
+    interval: Duration,
+    stop: crossbeam_channel::Receiver<()>,
+) {
+    while let Err(crossbeam_channel::RecvTimeoutError::Timeout) = stop.recv_timeout(interval) {
+        eprintln!("Progress: {}", counters.snapshot(started.elapsed()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusage::CpuTime;
+
+    #[test]
+    fn test_resources_since() {
+        let earlier = Resources {
+            usage: Usage {
+                cpu: Some(CpuTime {
+                    user: Duration::from_millis(400),
+                    system: Duration::from_millis(100),
+                }),
+                max_rss: Some(8 << 20),
+            },
+            allocs: Some(AllocStats {
+                allocations: 10,
+                bytes: 1_000,
+            }),
+        };
+        let now = Resources {
+            usage: Usage {
+                cpu: Some(CpuTime {
+                    user: Duration::from_millis(1500),
+                    system: Duration::from_millis(250),
+                }),
+                max_rss: Some(24 << 20),
+            },
+            allocs: Some(AllocStats {
+                allocations: 110,
+                bytes: 2_501_000,
+            }),
+        };
+        assert_eq!(
+            now.since(&earlier).to_string(),
+            "1.250s CPU, peak RSS 24.0 MiB, 100 allocations (2.50 MB)"
+        );
+        assert_eq!(Resources::default().to_string(), "CPU time unavailable");
+    }
+}
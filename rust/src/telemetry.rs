@@ -0,0 +1,480 @@
+//! OpenTelemetry export of the `tracing` spans (the `otel` feature).
+//!
+//! The pipeline and `serve` record `tracing` spans: `file` for the input of a run, `pipeline`
+//! below it with one `batch` per batch of records, and `request` for every HTTP request. With
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` set (such as `http://localhost:4318`) they are sent to
+//! `{endpoint}/v1/traces` as OTLP/HTTP JSON, a batch at a time, under the service name in
+//! `OTEL_SERVICE_NAME` (`rotate_cli` by default). A `traceparent` header on a request, or the
+//! `TRACEPARENT` environment variable for a run, makes its spans part of the caller's trace,
+//! so one trace covers a whole benchmark harness run. Spans are only dropped, with a warning,
+//! when the collector falls far behind; log lines still go to stderr.
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TrySendError};
+use serde_json::{Value, json};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+/// Most spans sent in one request to the collector.
+const MAX_BATCH: usize = 512;
+
+/// Longest a finished span waits before it is sent.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Finished spans queued for the exporter before new ones are dropped.
+const QUEUED_SPANS: usize = 8192;
+
+/// Longest a request to the collector may take.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// OTLP `SpanKind`s.
+const KIND_INTERNAL: u8 = 1;
+const KIND_SERVER: u8 = 2;
+
+/// Sends the spans to the collector until dropped, then sends the ones still queued.
+pub struct Exporter {
+    tx: Sender<Message>,
+    thread: Option<JoinHandle<()>>,
+}
+
+enum Message {
+    Span(FinishedSpan),
+    /// Send what is queued and stop
+    Flush,
+}
+
+/// Starts exporting when `OTEL_EXPORTER_OTLP_ENDPOINT` is set.
+pub fn init() -> Option<Exporter> {
+    let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok()?;
+    let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+    let service = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "rotate_cli".into());
+    let (tx, rx) = crossbeam_channel::bounded(QUEUED_SPANS);
+    if let Err(e) = tracing::subscriber::set_global_default(SpanCollector::new(tx.clone())) {
+        log::warn!("Cannot export spans: {e}");
+        return None;
+    }
+    log::info!("Exporting spans to {url}");
+    let thread = thread::spawn(move || export(rx, &url, &service));
+    Some(Exporter {
+        tx,
+        thread: Some(thread),
+    })
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        // The global subscriber keeps its sender, so the thread has to be told to stop
+        if self.tx.send_timeout(Message::Flush, EXPORT_TIMEOUT).is_ok()
+            && let Some(thread) = self.thread.take()
+        {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Exporter thread: sends the spans in batches of up to `MAX_BATCH`, at least every
+/// `EXPORT_INTERVAL`.
+fn export(rx: Receiver<Message>, url: &str, service: &str) {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(EXPORT_TIMEOUT))
+        .build()
+        .into();
+    let mut spans = Vec::new();
+    let mut deadline = Instant::now() + EXPORT_INTERVAL;
+    loop {
+        let message = rx.recv_deadline(deadline);
+        let done = matches!(
+            message,
+            Ok(Message::Flush) | Err(RecvTimeoutError::Disconnected)
+        );
+        if let Ok(Message::Span(span)) = message {
+            spans.push(span);
+        }
+        if done || spans.len() >= MAX_BATCH || Instant::now() >= deadline {
+            if !spans.is_empty() {
+                let body = payload(service, &spans).to_string();
+                let sent = agent
+                    .post(url)
+                    .header("Content-Type", "application/json")
+                    .send(&body);
+                if let Err(e) = sent {
+                    log::warn!("Exporting {} spans to {url} failed: {e}", spans.len());
+                }
+                spans.clear();
+            }
+            deadline = Instant::now() + EXPORT_INTERVAL;
+        }
+        if done {
+            return;
+        }
+    }
+}
+
+/// An `ExportTraceServiceRequest` in the OTLP JSON encoding.
+fn payload(service: &str, spans: &[FinishedSpan]) -> Value {
+    let spans: Vec<Value> = spans.iter().map(FinishedSpan::to_json).collect();
+    json!({"resourceSpans": [{
+        "resource": {"attributes": [{"key": "service.name", "value": {"stringValue": service}}]},
+        "scopeSpans": [{
+            "scope": {"name": "rotate_cli", "version": env!("CARGO_PKG_VERSION")},
+            "spans": spans,
+        }],
+    }]})
+}
+
+/// A [`Subscriber`] that keeps the spans until they close and then queues them for export.
+struct SpanCollector {
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, OpenSpan>>,
+    tx: Sender<Message>,
+    /// Whether dropped spans were already reported
+    dropped: AtomicBool,
+}
+
+struct OpenSpan {
+    metadata: &'static Metadata<'static>,
+    trace_id: u128,
+    span_id: u64,
+    parent_span_id: Option<u64>,
+    kind: u8,
+    start: SystemTime,
+    attributes: Vec<(&'static str, Value)>,
+    /// Handles to the span still alive
+    refs: usize,
+}
+
+struct FinishedSpan {
+    span: OpenSpan,
+    end: SystemTime,
+}
+
+thread_local! {
+    /// The spans entered on this thread, innermost last.
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+impl SpanCollector {
+    fn new(tx: Sender<Message>) -> Self {
+        SpanCollector {
+            next_id: AtomicU64::new(1),
+            spans: Mutex::default(),
+            tx,
+            dropped: AtomicBool::new(false),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, OpenSpan>> {
+        self.spans.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Subscriber for SpanCollector {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.is_span()
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let parent = match attributes.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if attributes.is_contextual() => {
+                ENTERED.with(|entered| entered.borrow().last().copied())
+            }
+            None => None,
+        };
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+
+        let mut spans = self.lock();
+        let parent = parent
+            .and_then(|parent| spans.get(&parent))
+            .map(|parent| (parent.trace_id, parent.span_id))
+            .or(fields.traceparent);
+        let span = OpenSpan {
+            metadata: attributes.metadata(),
+            trace_id: parent.map_or_else(
+                || u128::from(random()) << 64 | u128::from(random()),
+                |(trace_id, _)| trace_id,
+            ),
+            span_id: random(),
+            parent_span_id: parent.map(|(_, span_id)| span_id),
+            kind: fields.kind.unwrap_or(KIND_INTERNAL),
+            start: SystemTime::now(),
+            attributes: fields.attributes,
+            refs: 1,
+        };
+        spans.insert(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        if let Some(span) = self.lock().get_mut(&span.into_u64()) {
+            for (key, value) in fields.attributes {
+                set_attribute(&mut span.attributes, key, value);
+            }
+            span.kind = fields.kind.unwrap_or(span.kind);
+        }
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(i) = entered.iter().rposition(|&id| id == span.into_u64()) {
+                entered.remove(i);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(span) = self.lock().get_mut(&span.into_u64()) {
+            span.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.lock();
+        let Some(open) = spans.get_mut(&span.into_u64()) else {
+            return false;
+        };
+        open.refs -= 1;
+        if open.refs > 0 {
+            return false;
+        }
+        let span = spans.remove(&span.into_u64()).expect("the span is open");
+        drop(spans);
+        let finished = FinishedSpan {
+            span,
+            end: SystemTime::now(),
+        };
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(Message::Span(finished))
+            && !self.dropped.swap(true, Ordering::Relaxed)
+        {
+            log::warn!("The span exporter is falling behind; dropping spans");
+        }
+        true
+    }
+
+    fn current_span(&self) -> Current {
+        let Some(id) = ENTERED.with(|entered| entered.borrow().last().copied()) else {
+            return Current::none();
+        };
+        match self.lock().get(&id) {
+            Some(span) => Current::new(Id::from_u64(id), span.metadata),
+            None => Current::none(),
+        }
+    }
+}
+
+impl FinishedSpan {
+    fn to_json(&self) -> Value {
+        let span = &self.span;
+        let attributes: Vec<Value> = span
+            .attributes
+            .iter()
+            .map(|(key, value)| json!({"key": key, "value": value}))
+            .collect();
+        let mut json = json!({
+            "traceId": format!("{:032x}", span.trace_id),
+            "spanId": format!("{:016x}", span.span_id),
+            "name": span.metadata.name(),
+            "kind": span.kind,
+            "startTimeUnixNano": unix_nanos(span.start).to_string(),
+            "endTimeUnixNano": unix_nanos(self.end).to_string(),
+            "attributes": attributes,
+        });
+        if let Some(parent) = span.parent_span_id {
+            json["parentSpanId"] = format!("{parent:016x}").into();
+        }
+        json
+    }
+}
+
+/// The fields of a span as OTLP attributes, less the ones that steer the export.
+#[derive(Default)]
+struct Fields {
+    attributes: Vec<(&'static str, Value)>,
+    /// From an `otel.kind` field
+    kind: Option<u8>,
+    /// Trace and parent span ids from a `traceparent` field
+    traceparent: Option<(u128, u64)>,
+}
+
+impl Visit for Fields {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        set_attribute(
+            &mut self.attributes,
+            field.name(),
+            json!({"intValue": value.to_string()}),
+        );
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        set_attribute(
+            &mut self.attributes,
+            field.name(),
+            json!({"intValue": value.to_string()}),
+        );
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        set_attribute(
+            &mut self.attributes,
+            field.name(),
+            json!({"doubleValue": value}),
+        );
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        set_attribute(
+            &mut self.attributes,
+            field.name(),
+            json!({"boolValue": value}),
+        );
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "otel.kind" => {
+                self.kind = Some(if value == "server" {
+                    KIND_SERVER
+                } else {
+                    KIND_INTERNAL
+                })
+            }
+            "traceparent" => self.traceparent = parse_traceparent(value),
+            name => set_attribute(&mut self.attributes, name, json!({"stringValue": value})),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+/// Sets `key`, replacing a value recorded before.
+fn set_attribute(attributes: &mut Vec<(&'static str, Value)>, key: &'static str, value: Value) {
+    match attributes.iter_mut().find(|(name, _)| *name == key) {
+        Some(attribute) => attribute.1 = value,
+        None => attributes.push((key, value)),
+    }
+}
+
+/// The trace and parent span ids of a W3C `traceparent` such as
+/// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+fn parse_traceparent(value: &str) -> Option<(u128, u64)> {
+    let mut parts = value.trim().split('-');
+    let (version, trace_id, span_id, flags) =
+        (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    let is_hex = |part: &str, len| part.len() == len && part.bytes().all(|b| b.is_ascii_hexdigit());
+    if !is_hex(version, 2)
+        || version == "ff"
+        || !is_hex(trace_id, 32)
+        || !is_hex(span_id, 16)
+        || !is_hex(flags, 2)
+    {
+        return None;
+    }
+    let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+    let span_id = u64::from_str_radix(span_id, 16).ok()?;
+    (trace_id != 0 && span_id != 0).then_some((trace_id, span_id))
+}
+
+/// A random non-zero id.
+fn random() -> u64 {
+    // Every `RandomState` is seeded differently
+    RandomState::new().build_hasher().finish().max(1)
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent() {
+        assert_eq!(
+            parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+            Some((0x4bf92f3577b34da6a3ce929d0e0e4736, 0x00f067aa0ba902b7))
+        );
+        assert_eq!(parse_traceparent(""), None);
+        assert_eq!(
+            parse_traceparent("00-00000000000000000000000000000000-00f067aa0ba902b7-01"),
+            None
+        );
+        assert_eq!(
+            parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b-01"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_collect_spans() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        tracing::subscriber::with_default(SpanCollector::new(tx), || {
+            let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+            let file = tracing::info_span!("file", path = "in.csv", traceparent);
+            let _entered = file.enter();
+            let batch = tracing::info_span!("batch", rows = 3u64, valid = tracing::field::Empty);
+            batch.record("valid", 2u64);
+        });
+        let spans: Vec<FinishedSpan> = rx
+            .try_iter()
+            .map(|message| match message {
+                Message::Span(span) => span,
+                Message::Flush => unreachable!(),
+            })
+            .collect();
+        let json = payload("bench", &spans);
+        let resource = &json["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "bench"
+        );
+        let [batch, file] = &resource["scopeSpans"][0]["spans"].as_array().unwrap()[..] else {
+            panic!("expected two spans: {json}");
+        };
+        assert_eq!(batch["name"], "batch");
+        assert_eq!(file["name"], "file");
+        assert_eq!(file["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(file["parentSpanId"], "00f067aa0ba902b7");
+        assert_eq!(batch["traceId"], file["traceId"]);
+        assert_eq!(batch["parentSpanId"], file["spanId"]);
+        assert_eq!(
+            file["attributes"],
+            json!([{"key": "path", "value": {"stringValue": "in.csv"}}])
+        );
+        assert_eq!(
+            batch["attributes"],
+            json!([
+                {"key": "rows", "value": {"intValue": "3"}},
+                {"key": "valid", "value": {"intValue": "2"}},
+            ])
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Tables read as the raw JSON number tokens, borrowed from the input.
+//!
+//! [`RawNumbers`] (`--preserve-numbers`) moves the tokens themselves, so every number is
+//! written byte for byte as it was read, and [`JsNumbers`] (`--profile node`) reads them as
+//! JavaScript numbers. The element modes (`--bigint`, `--decimal`) parse
+//! each token into their own type instead. Either way the values never move: the
+//! [`Processor`] runs on the table of cell indices `0..n * n`, which is left in
+//! [`RowBuffers::numbers`] for `--emit-dimension` and `--self-check`, and the values are
+//! written out in that order.
+
+use crate::json::{JsNumber, JsonStyle};
+use crate::processor::{Processor, RowProcessor};
+use crate::row::InvalidReason;
+use crate::{Rotatable, RowBuffers, rotate_right, validate_table};
+use serde_json::value::RawValue;
+use std::fmt::Display;
+use std::io::Write;
+
+/// A built-in [`Processor`] applied to the number tokens as written: `1.50`, `1e3` and `-0`
+/// come out exactly so, only reordered, for downstream checks that compare bytes or
+/// checksums. Any JSON number is accepted, fractions included; `--json-style` still sets
+/// the separators between them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RawNumbers {
+    processor: Processor,
+}
+
+impl RawNumbers {
+    pub fn new(processor: Processor) -> Self {
+        RawNumbers { processor }
+    }
+}
+
+impl RowProcessor for RawNumbers {
+    fn process(&self, json: &[u8], style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        // serde_json has checked the grammar, so a value starting like a number is one
+        let number = |token: &str| token.starts_with(|c: char| c == '-' || c.is_ascii_digit());
+        match parse_values(json, buffers, |token| number(token).then_some(token)) {
+            Some(values) => write_processed(self.processor, &values, style, buffers),
+            None => false,
+        }
+    }
+}
+
+/// A built-in [`Processor`] applied to tables read as the JavaScript reference implementation
+/// reads them (`--profile node`): every element is a finite `f64`, fractions included, and is
+/// written back as `JSON.stringify` writes it ([`JsNumber`]), so `1.50` comes out as `1.5`
+/// and `1e21` as `1e+21`. The `style` is ignored; JavaScript writes compact arrays.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsNumbers {
+    processor: Processor,
+}
+
+impl JsNumbers {
+    pub fn new(processor: Processor) -> Self {
+        JsNumbers { processor }
+    }
+}
+
+impl RowProcessor for JsNumbers {
+    fn process(&self, json: &[u8], _style: JsonStyle, buffers: &mut RowBuffers) -> bool {
+        // Rust's float parsing rounds correctly, as `JSON.parse` does; `1e400` is infinite and
+        // so invalid, as in the reference
+        let number = |token: &str| token.parse().ok().filter(|x: &f64| x.is_finite());
+        match parse_values(json, buffers, |token| number(token).map(JsNumber)) {
+            Some(values) => write_processed(self.processor, &values, JsonStyle::Compact, buffers),
+            None => false,
+        }
+    }
+}
+
+/// Parses a non-empty square JSON array of at most `buffers.max_n * buffers.max_n` elements,
+/// turning each element's text into a value with `parse`. If it is not one, rejects the row
+/// in `buffers` and returns `None`.
+pub(crate) fn parse_values<'a, T>(
+    json: &'a [u8],
+    buffers: &mut RowBuffers,
+    parse: impl Fn(&'a str) -> Option<T>,
+) -> Option<Vec<T>> {
+    let Ok(tokens) = serde_json::from_slice::<Vec<&RawValue>>(json) else {
+        buffers.reject(InvalidReason::Malformed);
+        return None;
+    };
+    let max_n = buffers.max_n;
+    if max_n.is_some_and(|max_n| tokens.len() > max_n.saturating_mul(max_n)) {
+        buffers.reject(InvalidReason::TooLarge);
+        return None;
+    }
+    if let Err(shape) = validate_table(tokens.len()) {
+        buffers.reject_shape(shape);
+        return None;
+    }
+    let values: Option<Vec<T>> = tokens.iter().map(|token| parse(token.get())).collect();
+    if values.is_none() {
+        buffers.reject(InvalidReason::Malformed);
+    }
+    values
+}
+
+/// Applies `processor` to the square table `values` and writes the result to
+/// `buffers.json` in `style`, every value as its `Display` text. [`JsonStyle::Node`] writes
+/// the values as [`JsonStyle::Compact`] does: a JS number could not hold them anyway.
+pub(crate) fn write_processed<T: Display>(
+    processor: Processor,
+    values: &[T],
+    style: JsonStyle,
+    buffers: &mut RowBuffers,
+) -> bool {
+    buffers.numbers.clear();
+    buffers.numbers.extend(0..values.len() as i64);
+    match processor {
+        Processor::Rotate => {
+            rotate_right(&mut buffers.numbers).expect("parse_values checked the table")
+        }
+        Processor::Transpose => Rotatable::transpose(buffers.numbers.as_mut_slice())
+            .expect("parse_values checked the table"),
+        Processor::Validate => {}
+    }
+
+    let separator: &[u8] = match style {
+        JsonStyle::Spaced => b", ",
+        JsonStyle::Compact | JsonStyle::Node => b",",
+    };
+    buffers.json.clear();
+    buffers.json.push(b'[');
+    for (i, &index) in buffers.numbers.iter().enumerate() {
+        if i > 0 {
+            buffers.json.extend_from_slice(separator);
+        }
+        write!(buffers.json, "{}", values[index as usize]).expect("writing to a Vec cannot fail");
+    }
+    buffers.json.push(b']');
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(processor: Processor, json: &str, style: JsonStyle) -> (String, bool) {
+        let mut buffers = RowBuffers::with_max_n(Some(3));
+        let valid = RawNumbers::new(processor).process(json.as_bytes(), style, &mut buffers);
+        (String::from_utf8(buffers.json).unwrap(), valid)
+    }
+
+    #[test]
+    fn test_raw_numbers() {
+        let table = "[ 1.50 ,1e3,\n-0, 2E-7 ]";
+        assert_eq!(
+            process(Processor::Rotate, table, JsonStyle::Compact),
+            ("[-0,1.50,2E-7,1e3]".to_string(), true)
+        );
+        assert_eq!(
+            process(Processor::Transpose, table, JsonStyle::Spaced),
+            ("[1.50, -0, 1e3, 2E-7]".to_string(), true)
+        );
+        assert_eq!(
+            process(
+                Processor::Validate,
+                "[123456789012345678901234567890]",
+                JsonStyle::Node
+            ),
+            ("[123456789012345678901234567890]".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_js_numbers() {
+        let process = |json: &str| {
+            let mut buffers = RowBuffers::new();
+            let valid =
+                JsNumbers::default().process(json.as_bytes(), JsonStyle::Spaced, &mut buffers);
+            (String::from_utf8(buffers.json).unwrap(), valid)
+        };
+        assert_eq!(
+            process("[1.50, 1e21, -0, 9007199254740993]"),
+            ("[0,1.5,9007199254740992,1e+21]".to_string(), true)
+        );
+        assert_eq!(process("[1, 2, 3, 1e400]"), ("[]".to_string(), false));
+        assert_eq!(process("[1, 2, 3, \"4\"]"), ("[]".to_string(), false));
+        assert_eq!(process("[1, 2, 3]"), ("[]".to_string(), false));
+    }
+
+    #[test]
+    fn test_raw_numbers_invalid_tables() {
+        let cases = [
+            ("[1.5, 2, 3]", InvalidReason::NotSquare),
+            ("[]", InvalidReason::Empty),
+            ("[1, 2, 3, \"4\"]", InvalidReason::Malformed),
+            ("[1, 2, 3, null]", InvalidReason::Malformed),
+            ("[1, 2, 3, [4]]", InvalidReason::Malformed),
+            ("[1, 2, 3, 01]", InvalidReason::Malformed),
+            (
+                "[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16]",
+                InvalidReason::TooLarge,
+            ),
+        ];
+        for (json, reason) in cases {
+            let mut buffers = RowBuffers::with_max_n(Some(3));
+            let valid =
+                RawNumbers::default().process(json.as_bytes(), JsonStyle::Compact, &mut buffers);
+            assert!(!valid, "{json}");
+            assert_eq!(buffers.json, b"[]", "{json}");
+            assert_eq!(buffers.invalid_reason(valid), Some(reason), "{json}");
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! WebAssembly bindings for browsers and Node.js.
+//!
+//! Built with `wasm-pack build --release --features wasm`, which produces an npm package in
+//! `pkg/`. JavaScript numbers are doubles, so tables cross the boundary as `Float64Array`s;
+//! `processRow` applies exactly the CLI's per-row semantics to a `json` cell.
+
+use crate::{json, parse, rotate_batch, rotate_right, square_len};
+use wasm_bindgen::prelude::*;
+
+/// Result of processing one `json` cell, mirroring the CLI's `json` and `is_valid` columns.
+#[wasm_bindgen(getter_with_clone)]
+pub struct RowResult {
+    /// Rotated table as compact JSON, or `[]` for invalid input
+    pub json: String,
+    /// Whether the input was a non-empty square table of integers
+    #[wasm_bindgen(js_name = isValid)]
+    pub is_valid: bool,
+}
+
+/// Rotates a row-major square table one step clockwise in place.
+///
+/// Throws if the table is empty or its length is not a perfect square.
+#[wasm_bindgen(js_name = rotateRight)]
+pub fn rotate_right_js(data: &mut [f64]) -> Result<(), JsError> {
+    rotate_right(data).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Returns `n` for an `n × n` table with `len` elements, or `undefined` if there is none.
+#[wasm_bindgen(js_name = squareLen)]
+pub fn square_len_js(len: usize) -> Option<usize> {
+    square_len(len)
+}
+
+/// Parses, validates and rotates a JSON array the same way the CLI does for each row.
+#[wasm_bindgen(js_name = processRow)]
+pub fn process_row(json_text: &str) -> RowResult {
+    let mut numbers = Vec::new();
+    let is_valid =
+        parse::parse_numbers(json_text, &mut numbers) && rotate_right(&mut numbers).is_ok();
+    row_result(is_valid.then_some(&numbers[..]))
+}
+
+/// [`process_row`] for many cells, whose tables are rotated together in one
+/// [`rotate_batch`] call.
+#[wasm_bindgen(js_name = processRows)]
+pub fn process_rows(json_texts: Vec<String>) -> Vec<RowResult> {
+    // A cell that does not parse gets an empty table, which `rotate_batch` rejects
+    let mut tables: Vec<Vec<i64>> = json_texts
+        .iter()
+        .map(|json_text| {
+            let mut numbers = Vec::new();
+            if !parse::parse_numbers(json_text, &mut numbers) {
+                numbers.clear();
+            }
+            numbers
+        })
+        .collect();
+    let results = rotate_batch(&mut tables);
+    (tables.iter().zip(results))
+        .map(|(numbers, result)| row_result(result.is_ok().then_some(&numbers[..])))
+        .collect()
+}
+
+/// The `json` and `is_valid` columns for a rotated table, or for an invalid row.
+fn row_result(rotated: Option<&[i64]>) -> RowResult {
+    let mut out = Vec::new();
+    match rotated {
+        Some(numbers) => json::write_array(&mut out, numbers),
+        None => out.extend_from_slice(b"[]"),
+    }
+    RowResult {
+        // Digits, minus signs, commas and brackets are always valid UTF-8
+        json: String::from_utf8(out).expect("JSON output is ASCII"),
+        is_valid: rotated.is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_row() {
+        let row = process_row("[1, 2, 3, 4]");
+        assert!(row.is_valid);
+        assert_eq!(row.json, "[3,1,4,2]");
+
+        for invalid in ["[1, 2, 3]", "[]", "[1.5]", "nope"] {
+            let row = process_row(invalid);
+            assert!(!row.is_valid);
+            assert_eq!(row.json, "[]");
+        }
+    }
+
+    #[test]
+    fn test_process_rows() {
+        let cells = ["[1, 2, 3, 4]", "[1, 2, 3]", "[]", "[1.5]", "nope", "[42]"];
+        let rows = process_rows(cells.iter().map(|cell| cell.to_string()).collect());
+        assert_eq!(rows.len(), cells.len());
+        for (row, cell) in rows.iter().zip(cells) {
+            let expected = process_row(cell);
+            assert_eq!(
+                (&row.json, row.is_valid),
+                (&expected.json, expected.is_valid)
+            );
+        }
+        assert!(process_rows(Vec::new()).is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+//! Runs the `rotate_cli` binary against every case in `fixtures/golden/` and compares its
+//! output, warnings and exit code byte for byte (see `rotate_cli::fixtures::golden`).
+//!
+//! `UPDATE_GOLDEN=1 cargo test --test golden` rewrites the expected files from the current
+//! binary instead; review the diff before committing it.
+
+use rotate_cli::fixtures::golden;
+use std::path::Path;
+
+#[test]
+fn golden_cases() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../fixtures/golden");
+    let program = Path::new(env!("CARGO_BIN_EXE_rotate_cli"));
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    let cases = golden::cases(&root).unwrap();
+    assert!(!cases.is_empty(), "no cases in {}", root.display());
+    let mut failures = Vec::new();
+    for case in &cases {
+        let output = case.command(program).output().unwrap();
+        if update {
+            case.update(&output).unwrap();
+        } else if let Err(mismatch) = case.check(&output) {
+            failures.push(mismatch);
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} golden cases failed:\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}